@@ -0,0 +1,173 @@
+//! Captures HCI traffic into btsnoop or pcapng files.
+
+use bluer::{
+    capture::{Capture, FileWriter, Format, Record, RingBuffer, Rotation},
+    hci::DEV_NONE,
+};
+use clap::Parser;
+use std::{io::ErrorKind, path::PathBuf, process::exit, time::Duration};
+use tokio::{
+    select,
+    signal::unix::{signal, SignalKind},
+    time::interval,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Parser)]
+#[clap(
+    name = "hcicap",
+    about = "Captures HCI traffic of Bluetooth controllers into btsnoop or pcapng files.",
+    long_about = "Captures HCI traffic of Bluetooth controllers into btsnoop or pcapng files.\n\n\
+                  btsnoop files can be read by btmon -r, pcapng files by Wireshark.\n\
+                  Capturing requires the CAP_NET_RAW capability.",
+    author = "Sebastian Urban <surban@surban.net>",
+    version = env!("CARGO_PKG_VERSION"),
+)]
+struct Opts {
+    /// File format: btsnoop or pcapng.
+    ///
+    /// By default it is determined from the file name extension,
+    /// falling back to btsnoop.
+    #[clap(long, short, parse(try_from_str=parse_format))]
+    format: Option<Format>,
+    /// Only capture traffic of the controller with the specified index,
+    /// i.e. 0 for hci0.
+    #[clap(long, short)]
+    index: Option<u16>,
+    /// Rotate the capture file when it exceeds the specified size in kilobytes.
+    #[clap(long, short = 's')]
+    rotate_size: Option<u64>,
+    /// Number of rotated capture files to keep.
+    #[clap(long, short = 'n', default_value = "4")]
+    rotate_files: usize,
+    /// Keep the most recent traffic of the specified size in kilobytes in memory
+    /// instead of writing it continuously.
+    ///
+    /// The captured traffic is written to the output file when SIGUSR1 is received
+    /// and on exit.
+    #[clap(long, short)]
+    ring: Option<usize>,
+    /// Print a summary of each captured packet to standard error.
+    #[clap(long, short)]
+    verbose: bool,
+    /// Output file.
+    output: PathBuf,
+}
+
+fn parse_format(s: &str) -> std::result::Result<Format, String> {
+    match s {
+        "btsnoop" => Ok(Format::Btsnoop),
+        "pcapng" => Ok(Format::Pcapng),
+        _ => Err("unknown file format".to_string()),
+    }
+}
+
+enum Sink {
+    File(FileWriter),
+    Ring(RingBuffer),
+}
+
+impl Opts {
+    fn format(&self) -> Format {
+        match self.format {
+            Some(format) => format,
+            None if self.output.extension().map(|ext| ext == "pcapng").unwrap_or_default() => Format::Pcapng,
+            None => Format::Btsnoop,
+        }
+    }
+
+    fn dump(&self, ring: &RingBuffer) -> Result<()> {
+        ring.dump_to_file(&self.output, self.format())?;
+        eprintln!("Wrote {} packets to {}", ring.len(), self.output.display());
+        Ok(())
+    }
+
+    fn is_wanted(&self, record: &Record) -> bool {
+        match self.index {
+            Some(index) => record.index == index || record.index == DEV_NONE,
+            None => true,
+        }
+    }
+
+    pub async fn perform(self) -> Result<()> {
+        let format = self.format();
+        let mut sink = match self.ring {
+            Some(size) => Sink::Ring(RingBuffer::new(size * 1024)),
+            None => {
+                let rotation =
+                    self.rotate_size.map(|size| Rotation { max_size: size * 1024, max_files: self.rotate_files });
+                Sink::File(FileWriter::create(&self.output, format, rotation)?)
+            }
+        };
+
+        let capture = Capture::open().await?;
+        let mut sigint = signal(SignalKind::interrupt())?;
+        let mut sigterm = signal(SignalKind::terminate())?;
+        let mut sigusr1 = signal(SignalKind::user_defined1())?;
+        let mut flush = interval(FLUSH_INTERVAL);
+
+        loop {
+            select! {
+                res = capture.recv() => {
+                    let record = match res {
+                        Ok(record) => record,
+                        Err(err) if err.kind() == ErrorKind::InvalidData => {
+                            eprintln!("Skipping invalid packet: {}", err);
+                            continue;
+                        }
+                        Err(err) => return Err(err.into()),
+                    };
+                    if !self.is_wanted(&record) {
+                        continue;
+                    }
+                    if self.verbose {
+                        match record.to_packet() {
+                            Ok(packet) => eprintln!("hci{}: {:?}", record.index, packet.event),
+                            Err(err) => eprintln!("hci{}: {}", record.index, err),
+                        }
+                    }
+                    match &mut sink {
+                        Sink::File(file) => file.write(&record)?,
+                        Sink::Ring(ring) => ring.push(record),
+                    }
+                }
+                _ = flush.tick() => {
+                    if let Sink::File(file) = &mut sink {
+                        file.flush()?;
+                    }
+                }
+                _ = sigusr1.recv() => {
+                    if let Sink::Ring(ring) = &sink {
+                        self.dump(ring)?;
+                    }
+                }
+                _ = sigint.recv() => break,
+                _ = sigterm.recv() => break,
+            }
+        }
+
+        match &mut sink {
+            Sink::File(file) => file.flush()?,
+            Sink::Ring(ring) => self.dump(ring)?,
+        }
+
+        Ok(())
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    env_logger::init();
+    let opts: Opts = Opts::parse();
+
+    match opts.perform().await {
+        Ok(_) => exit(0),
+        Err(err) => {
+            eprintln!("Error: {}", &err);
+            exit(2);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Passively monitor Bluetooth LE advertisements containing manufacturer specific data.
+
+use bluer::adv_monitor::{Monitor, MonitorEvent, Pattern, RssiSamplingPeriod};
+use futures::StreamExt;
+use std::time::Duration;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> bluer::Result<()> {
+    env_logger::init();
+    let session = bluer::Session::new().await?;
+    let adapter = session.default_adapter().await?;
+    adapter.set_powered(true).await?;
+
+    println!("Monitoring on Bluetooth adapter {} with address {}", adapter.name(), adapter.address().await?);
+    let monitor = Monitor {
+        rssi_low_threshold: Some(-90),
+        rssi_high_threshold: Some(-70),
+        rssi_low_timeout: Some(Duration::from_secs(5)),
+        rssi_high_timeout: Some(Duration::from_secs(1)),
+        rssi_sampling_period: Some(RssiSamplingPeriod::First),
+        patterns: vec![Pattern { data_type: 0xff, start_position: 0, content: vec![0x4c, 0x00] }],
+        ..Default::default()
+    };
+    let mut handle = adapter.register_monitor(monitor).await?;
+
+    while let Some(event) = handle.next().await {
+        match event {
+            MonitorEvent::Activated => println!("Monitor activated"),
+            MonitorEvent::DeviceFound(addr) => println!("Device found: {addr}"),
+            MonitorEvent::DeviceLost(addr) => println!("Device lost: {addr}"),
+            _ => (),
+        }
+    }
+
+    println!("Monitor released");
+    Ok(())
+}
@@ -0,0 +1,697 @@
+//! Advertising data (AD) structures.
+//!
+//! Bluetooth LE advertising data, scan response data and extended inquiry responses
+//! consist of a sequence of AD structures, each made of a length byte, an [AD type](ad_type)
+//! and type-specific data.
+//! An [AdStructure] is the typed form of one such structure.
+//!
+//! Use [parse] to decode raw advertising data, for example from an HCI advertising report,
+//! and [encode] to build it.
+//! [parse_map] decodes the advertising data provided by the Bluetooth daemon,
+//! for example [AdvertisementReport::advertising_data](crate::AdvertisementReport::advertising_data).
+//!
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fmt,
+    io::{Error, ErrorKind, Result},
+};
+
+use crate::{Uuid, UuidExt};
+
+/// Assigned AD type numbers.
+pub mod ad_type {
+    /// Flags.
+    pub const FLAGS: u8 = 0x01;
+    /// Incomplete list of 16-bit service class UUIDs.
+    pub const INCOMPLETE_SERVICE_UUIDS_16: u8 = 0x02;
+    /// Complete list of 16-bit service class UUIDs.
+    pub const COMPLETE_SERVICE_UUIDS_16: u8 = 0x03;
+    /// Incomplete list of 32-bit service class UUIDs.
+    pub const INCOMPLETE_SERVICE_UUIDS_32: u8 = 0x04;
+    /// Complete list of 32-bit service class UUIDs.
+    pub const COMPLETE_SERVICE_UUIDS_32: u8 = 0x05;
+    /// Incomplete list of 128-bit service class UUIDs.
+    pub const INCOMPLETE_SERVICE_UUIDS_128: u8 = 0x06;
+    /// Complete list of 128-bit service class UUIDs.
+    pub const COMPLETE_SERVICE_UUIDS_128: u8 = 0x07;
+    /// Shortened local name.
+    pub const SHORTENED_LOCAL_NAME: u8 = 0x08;
+    /// Complete local name.
+    pub const COMPLETE_LOCAL_NAME: u8 = 0x09;
+    /// TX power level.
+    pub const TX_POWER_LEVEL: u8 = 0x0a;
+    /// Class of device.
+    pub const CLASS_OF_DEVICE: u8 = 0x0d;
+    /// Simple pairing hash C-192.
+    pub const SIMPLE_PAIRING_HASH_C192: u8 = 0x0e;
+    /// Simple pairing randomizer R-192.
+    pub const SIMPLE_PAIRING_RANDOMIZER_R192: u8 = 0x0f;
+    /// Device ID or security manager TK value.
+    pub const DEVICE_ID: u8 = 0x10;
+    /// Security manager out of band flags.
+    pub const SECURITY_MANAGER_OOB_FLAGS: u8 = 0x11;
+    /// Peripheral connection interval range.
+    pub const PERIPHERAL_CONNECTION_INTERVAL_RANGE: u8 = 0x12;
+    /// List of 16-bit service solicitation UUIDs.
+    pub const SOLICIT_UUIDS_16: u8 = 0x14;
+    /// List of 128-bit service solicitation UUIDs.
+    pub const SOLICIT_UUIDS_128: u8 = 0x15;
+    /// Service data with 16-bit UUID.
+    pub const SERVICE_DATA_16: u8 = 0x16;
+    /// Public target address.
+    pub const PUBLIC_TARGET_ADDRESS: u8 = 0x17;
+    /// Random target address.
+    pub const RANDOM_TARGET_ADDRESS: u8 = 0x18;
+    /// Appearance.
+    pub const APPEARANCE: u8 = 0x19;
+    /// Advertising interval.
+    pub const ADVERTISING_INTERVAL: u8 = 0x1a;
+    /// LE Bluetooth device address.
+    pub const LE_DEVICE_ADDRESS: u8 = 0x1b;
+    /// LE role.
+    pub const LE_ROLE: u8 = 0x1c;
+    /// Simple pairing hash C-256.
+    pub const SIMPLE_PAIRING_HASH_C256: u8 = 0x1d;
+    /// Simple pairing randomizer R-256.
+    pub const SIMPLE_PAIRING_RANDOMIZER_R256: u8 = 0x1e;
+    /// List of 32-bit service solicitation UUIDs.
+    pub const SOLICIT_UUIDS_32: u8 = 0x1f;
+    /// Service data with 32-bit UUID.
+    pub const SERVICE_DATA_32: u8 = 0x20;
+    /// Service data with 128-bit UUID.
+    pub const SERVICE_DATA_128: u8 = 0x21;
+    /// LE secure connections confirmation value.
+    pub const LE_SECURE_CONNECTIONS_CONFIRMATION_VALUE: u8 = 0x22;
+    /// LE secure connections random value.
+    pub const LE_SECURE_CONNECTIONS_RANDOM_VALUE: u8 = 0x23;
+    /// URI.
+    pub const URI: u8 = 0x24;
+    /// LE supported features.
+    pub const LE_SUPPORTED_FEATURES: u8 = 0x27;
+    /// Channel map update indication.
+    pub const CHANNEL_MAP_UPDATE_INDICATION: u8 = 0x28;
+    /// Mesh provisioning bearer (PB-ADV).
+    pub const PB_ADV: u8 = 0x29;
+    /// Mesh message.
+    pub const MESH_MESSAGE: u8 = 0x2a;
+    /// Mesh beacon.
+    pub const MESH_BEACON: u8 = 0x2b;
+    /// Broadcast isochronous group info.
+    pub const BIG_INFO: u8 = 0x2c;
+    /// Broadcast code.
+    pub const BROADCAST_CODE: u8 = 0x2d;
+    /// Resolvable set identifier.
+    pub const RESOLVABLE_SET_IDENTIFIER: u8 = 0x2e;
+    /// Advertising interval (long).
+    pub const ADVERTISING_INTERVAL_LONG: u8 = 0x2f;
+    /// Broadcast name.
+    pub const BROADCAST_NAME: u8 = 0x30;
+    /// Encrypted advertising data.
+    pub const ENCRYPTED_ADVERTISING_DATA: u8 = 0x31;
+    /// Periodic advertising response timing information.
+    pub const PERIODIC_ADVERTISING_RESPONSE_TIMING_INFO: u8 = 0x32;
+    /// 3D information data.
+    pub const INFORMATION_DATA_3D: u8 = 0x3d;
+    /// Manufacturer specific data.
+    pub const MANUFACTURER_SPECIFIC_DATA: u8 = 0xff;
+}
+
+/// Maximum length of the data of an AD structure.
+pub const MAX_DATA_LEN: usize = 254;
+
+fn invalid_data(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Contents of the flags AD structure.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flags(pub u8);
+
+impl Flags {
+    /// LE limited discoverable mode.
+    pub const LE_LIMITED_DISCOVERABLE: Self = Self(0x01);
+    /// LE general discoverable mode.
+    pub const LE_GENERAL_DISCOVERABLE: Self = Self(0x02);
+    /// BR/EDR not supported.
+    pub const BR_EDR_NOT_SUPPORTED: Self = Self(0x04);
+    /// Simultaneous LE and BR/EDR to same device capable (controller).
+    pub const LE_BR_EDR_CONTROLLER: Self = Self(0x08);
+
+    /// Whether all flags set in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for Flags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Typed AD structure.
+///
+/// Structures of AD types that are not covered are represented by [Other](Self::Other).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum AdStructure {
+    /// Flags.
+    Flags(Flags),
+    /// Incomplete list of 16-bit service class UUIDs.
+    IncompleteServiceUuids16(Vec<u16>),
+    /// Complete list of 16-bit service class UUIDs.
+    CompleteServiceUuids16(Vec<u16>),
+    /// Incomplete list of 32-bit service class UUIDs.
+    IncompleteServiceUuids32(Vec<u32>),
+    /// Complete list of 32-bit service class UUIDs.
+    CompleteServiceUuids32(Vec<u32>),
+    /// Incomplete list of 128-bit service class UUIDs.
+    IncompleteServiceUuids128(Vec<Uuid>),
+    /// Complete list of 128-bit service class UUIDs.
+    CompleteServiceUuids128(Vec<Uuid>),
+    /// Shortened local name.
+    ShortenedLocalName(String),
+    /// Complete local name.
+    CompleteLocalName(String),
+    /// TX power level in dBm.
+    TxPowerLevel(i8),
+    /// Peripheral connection interval range in units of 1.25 ms.
+    ///
+    /// The value `0xffff` indicates no specific minimum or maximum.
+    PeripheralConnectionIntervalRange {
+        /// Minimum connection interval.
+        min: u16,
+        /// Maximum connection interval.
+        max: u16,
+    },
+    /// List of 16-bit service solicitation UUIDs.
+    SolicitUuids16(Vec<u16>),
+    /// List of 32-bit service solicitation UUIDs.
+    SolicitUuids32(Vec<u32>),
+    /// List of 128-bit service solicitation UUIDs.
+    SolicitUuids128(Vec<Uuid>),
+    /// Service data with 16-bit UUID.
+    ServiceData16 {
+        /// Service UUID.
+        uuid: u16,
+        /// Service data.
+        data: Vec<u8>,
+    },
+    /// Service data with 32-bit UUID.
+    ServiceData32 {
+        /// Service UUID.
+        uuid: u32,
+        /// Service data.
+        data: Vec<u8>,
+    },
+    /// Service data with 128-bit UUID.
+    ServiceData128 {
+        /// Service UUID.
+        uuid: Uuid,
+        /// Service data.
+        data: Vec<u8>,
+    },
+    /// Appearance.
+    Appearance(u16),
+    /// Advertising interval in units of 0.625 ms.
+    AdvertisingInterval(u32),
+    /// URI, including its scheme.
+    Uri(String),
+    /// LE supported features as little-endian bit field.
+    LeSupportedFeatures(Vec<u8>),
+    /// Broadcast name.
+    BroadcastName(String),
+    /// Manufacturer specific data.
+    ManufacturerSpecificData {
+        /// Company identifier.
+        company_id: u16,
+        /// Manufacturer specific data.
+        data: Vec<u8>,
+    },
+    /// AD structure of other type.
+    Other {
+        /// AD type.
+        ad_type: u8,
+        /// Data.
+        data: Vec<u8>,
+    },
+}
+
+/// URI scheme name string codes, as assigned by the Bluetooth SIG.
+const URI_SCHEMES: &[(u8, &str)] =
+    &[(0x01, ""), (0x16, "http:"), (0x17, "https:"), (0x1e, "mailto:"), (0x22, "tel:"), (0x2d, "ftp:")];
+
+fn uuids16(data: &[u8]) -> Result<Vec<u16>> {
+    if data.len() % 2 != 0 {
+        return Err(invalid_data("invalid length of 16-bit UUID list"));
+    }
+    Ok(data.chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect())
+}
+
+fn uuids32(data: &[u8]) -> Result<Vec<u32>> {
+    if data.len() % 4 != 0 {
+        return Err(invalid_data("invalid length of 32-bit UUID list"));
+    }
+    Ok(data.chunks(4).map(|c| u32::from_le_bytes(c.try_into().unwrap())).collect())
+}
+
+fn uuids128(data: &[u8]) -> Result<Vec<Uuid>> {
+    if data.len() % 16 != 0 {
+        return Err(invalid_data("invalid length of 128-bit UUID list"));
+    }
+    Ok(data.chunks(16).map(|c| Uuid::from_u128(u128::from_le_bytes(c.try_into().unwrap()))).collect())
+}
+
+fn string(data: &[u8]) -> Result<String> {
+    String::from_utf8(data.to_vec()).map_err(|_| invalid_data("invalid UTF-8 in AD structure"))
+}
+
+fn fixed<const N: usize>(data: &[u8]) -> Result<[u8; N]> {
+    data.try_into().map_err(|_| invalid_data("invalid length of AD structure"))
+}
+
+fn prefixed<const N: usize>(data: &[u8]) -> Result<([u8; N], Vec<u8>)> {
+    if data.len() < N {
+        return Err(invalid_data("AD structure too short"));
+    }
+    Ok((data[..N].try_into().unwrap(), data[N..].to_vec()))
+}
+
+impl AdStructure {
+    /// Decodes an AD structure from its AD type and data.
+    ///
+    /// Fails if the data is malformed for the AD type.
+    pub fn decode(ad_type: u8, data: &[u8]) -> Result<Self> {
+        Ok(match ad_type {
+            ad_type::FLAGS => match data.first() {
+                Some(&flags) => Self::Flags(Flags(flags)),
+                None => return Err(invalid_data("empty flags AD structure")),
+            },
+            ad_type::INCOMPLETE_SERVICE_UUIDS_16 => Self::IncompleteServiceUuids16(uuids16(data)?),
+            ad_type::COMPLETE_SERVICE_UUIDS_16 => Self::CompleteServiceUuids16(uuids16(data)?),
+            ad_type::INCOMPLETE_SERVICE_UUIDS_32 => Self::IncompleteServiceUuids32(uuids32(data)?),
+            ad_type::COMPLETE_SERVICE_UUIDS_32 => Self::CompleteServiceUuids32(uuids32(data)?),
+            ad_type::INCOMPLETE_SERVICE_UUIDS_128 => Self::IncompleteServiceUuids128(uuids128(data)?),
+            ad_type::COMPLETE_SERVICE_UUIDS_128 => Self::CompleteServiceUuids128(uuids128(data)?),
+            ad_type::SHORTENED_LOCAL_NAME => Self::ShortenedLocalName(string(data)?),
+            ad_type::COMPLETE_LOCAL_NAME => Self::CompleteLocalName(string(data)?),
+            ad_type::TX_POWER_LEVEL => Self::TxPowerLevel(i8::from_le_bytes(fixed(data)?)),
+            ad_type::PERIPHERAL_CONNECTION_INTERVAL_RANGE => {
+                let v: [u8; 4] = fixed(data)?;
+                Self::PeripheralConnectionIntervalRange {
+                    min: u16::from_le_bytes([v[0], v[1]]),
+                    max: u16::from_le_bytes([v[2], v[3]]),
+                }
+            }
+            ad_type::SOLICIT_UUIDS_16 => Self::SolicitUuids16(uuids16(data)?),
+            ad_type::SOLICIT_UUIDS_32 => Self::SolicitUuids32(uuids32(data)?),
+            ad_type::SOLICIT_UUIDS_128 => Self::SolicitUuids128(uuids128(data)?),
+            ad_type::SERVICE_DATA_16 => {
+                let (uuid, data) = prefixed(data)?;
+                Self::ServiceData16 { uuid: u16::from_le_bytes(uuid), data }
+            }
+            ad_type::SERVICE_DATA_32 => {
+                let (uuid, data) = prefixed(data)?;
+                Self::ServiceData32 { uuid: u32::from_le_bytes(uuid), data }
+            }
+            ad_type::SERVICE_DATA_128 => {
+                let (uuid, data) = prefixed(data)?;
+                Self::ServiceData128 { uuid: Uuid::from_u128(u128::from_le_bytes(uuid)), data }
+            }
+            ad_type::APPEARANCE => Self::Appearance(u16::from_le_bytes(fixed(data)?)),
+            ad_type::ADVERTISING_INTERVAL => Self::AdvertisingInterval(u16::from_le_bytes(fixed(data)?).into()),
+            ad_type::ADVERTISING_INTERVAL_LONG => match data.len() {
+                3 => Self::AdvertisingInterval(u32::from_le_bytes([data[0], data[1], data[2], 0])),
+                4 => Self::AdvertisingInterval(u32::from_le_bytes(fixed(data)?)),
+                _ => return Err(invalid_data("invalid length of advertising interval")),
+            },
+            ad_type::URI => {
+                let (code, rest) = prefixed::<1>(data)?;
+                match URI_SCHEMES.iter().find(|(c, _)| *c == code[0]) {
+                    Some((_, scheme)) => Self::Uri(format!("{}{}", scheme, string(&rest)?)),
+                    None => Self::Other { ad_type, data: data.to_vec() },
+                }
+            }
+            ad_type::LE_SUPPORTED_FEATURES => Self::LeSupportedFeatures(data.to_vec()),
+            ad_type::BROADCAST_NAME => Self::BroadcastName(string(data)?),
+            ad_type::MANUFACTURER_SPECIFIC_DATA => {
+                let (company_id, data) = prefixed(data)?;
+                Self::ManufacturerSpecificData { company_id: u16::from_le_bytes(company_id), data }
+            }
+            _ => Self::Other { ad_type, data: data.to_vec() },
+        })
+    }
+
+    /// AD type.
+    pub fn ad_type(&self) -> u8 {
+        match self {
+            Self::Flags(_) => ad_type::FLAGS,
+            Self::IncompleteServiceUuids16(_) => ad_type::INCOMPLETE_SERVICE_UUIDS_16,
+            Self::CompleteServiceUuids16(_) => ad_type::COMPLETE_SERVICE_UUIDS_16,
+            Self::IncompleteServiceUuids32(_) => ad_type::INCOMPLETE_SERVICE_UUIDS_32,
+            Self::CompleteServiceUuids32(_) => ad_type::COMPLETE_SERVICE_UUIDS_32,
+            Self::IncompleteServiceUuids128(_) => ad_type::INCOMPLETE_SERVICE_UUIDS_128,
+            Self::CompleteServiceUuids128(_) => ad_type::COMPLETE_SERVICE_UUIDS_128,
+            Self::ShortenedLocalName(_) => ad_type::SHORTENED_LOCAL_NAME,
+            Self::CompleteLocalName(_) => ad_type::COMPLETE_LOCAL_NAME,
+            Self::TxPowerLevel(_) => ad_type::TX_POWER_LEVEL,
+            Self::PeripheralConnectionIntervalRange { .. } => ad_type::PERIPHERAL_CONNECTION_INTERVAL_RANGE,
+            Self::SolicitUuids16(_) => ad_type::SOLICIT_UUIDS_16,
+            Self::SolicitUuids32(_) => ad_type::SOLICIT_UUIDS_32,
+            Self::SolicitUuids128(_) => ad_type::SOLICIT_UUIDS_128,
+            Self::ServiceData16 { .. } => ad_type::SERVICE_DATA_16,
+            Self::ServiceData32 { .. } => ad_type::SERVICE_DATA_32,
+            Self::ServiceData128 { .. } => ad_type::SERVICE_DATA_128,
+            Self::Appearance(_) => ad_type::APPEARANCE,
+            Self::AdvertisingInterval(v) if *v <= u16::MAX as u32 => ad_type::ADVERTISING_INTERVAL,
+            Self::AdvertisingInterval(_) => ad_type::ADVERTISING_INTERVAL_LONG,
+            Self::Uri(_) => ad_type::URI,
+            Self::LeSupportedFeatures(_) => ad_type::LE_SUPPORTED_FEATURES,
+            Self::BroadcastName(_) => ad_type::BROADCAST_NAME,
+            Self::ManufacturerSpecificData { .. } => ad_type::MANUFACTURER_SPECIFIC_DATA,
+            Self::Other { ad_type, .. } => *ad_type,
+        }
+    }
+
+    /// Encodes the data of the AD structure, excluding length and AD type.
+    pub fn data(&self) -> Vec<u8> {
+        fn concat<T>(items: &[T], f: impl Fn(&T) -> Vec<u8>) -> Vec<u8> {
+            items.iter().flat_map(f).collect()
+        }
+        fn with_prefix(prefix: &[u8], data: &[u8]) -> Vec<u8> {
+            prefix.iter().chain(data).copied().collect()
+        }
+
+        match self {
+            Self::Flags(flags) => vec![flags.0],
+            Self::IncompleteServiceUuids16(uuids)
+            | Self::CompleteServiceUuids16(uuids)
+            | Self::SolicitUuids16(uuids) => concat(uuids, |v| v.to_le_bytes().to_vec()),
+            Self::IncompleteServiceUuids32(uuids)
+            | Self::CompleteServiceUuids32(uuids)
+            | Self::SolicitUuids32(uuids) => concat(uuids, |v| v.to_le_bytes().to_vec()),
+            Self::IncompleteServiceUuids128(uuids)
+            | Self::CompleteServiceUuids128(uuids)
+            | Self::SolicitUuids128(uuids) => concat(uuids, |v| v.as_u128().to_le_bytes().to_vec()),
+            Self::ShortenedLocalName(name) | Self::CompleteLocalName(name) | Self::BroadcastName(name) => {
+                name.as_bytes().to_vec()
+            }
+            Self::TxPowerLevel(power) => power.to_le_bytes().to_vec(),
+            Self::PeripheralConnectionIntervalRange { min, max } => {
+                with_prefix(&min.to_le_bytes(), &max.to_le_bytes())
+            }
+            Self::ServiceData16 { uuid, data } => with_prefix(&uuid.to_le_bytes(), data),
+            Self::ServiceData32 { uuid, data } => with_prefix(&uuid.to_le_bytes(), data),
+            Self::ServiceData128 { uuid, data } => with_prefix(&uuid.as_u128().to_le_bytes(), data),
+            Self::Appearance(appearance) => appearance.to_le_bytes().to_vec(),
+            Self::AdvertisingInterval(v) if *v <= u16::MAX as u32 => (*v as u16).to_le_bytes().to_vec(),
+            Self::AdvertisingInterval(v) if *v <= 0xff_ffff => v.to_le_bytes()[..3].to_vec(),
+            Self::AdvertisingInterval(v) => v.to_le_bytes().to_vec(),
+            Self::Uri(uri) => {
+                let (code, scheme) = URI_SCHEMES
+                    .iter()
+                    .rev()
+                    .find(|(_, scheme)| uri.starts_with(scheme))
+                    .copied()
+                    .unwrap_or(URI_SCHEMES[0]);
+                with_prefix(&[code], &uri.as_bytes()[scheme.len()..])
+            }
+            Self::LeSupportedFeatures(features) => features.clone(),
+            Self::ManufacturerSpecificData { company_id, data } => with_prefix(&company_id.to_le_bytes(), data),
+            Self::Other { data, .. } => data.clone(),
+        }
+    }
+
+    /// Length of the encoded AD structure, including length and AD type.
+    pub fn encoded_len(&self) -> usize {
+        2 + self.data().len()
+    }
+
+    /// Appends the encoded AD structure to `buf`.
+    ///
+    /// Fails if the data exceeds [MAX_DATA_LEN].
+    pub fn write(&self, buf: &mut Vec<u8>) -> Result<()> {
+        let data = self.data();
+        if data.len() > MAX_DATA_LEN {
+            return Err(Error::new(ErrorKind::InvalidInput, "AD structure data too long"));
+        }
+        buf.push(data.len() as u8 + 1);
+        buf.push(self.ad_type());
+        buf.extend(data);
+        Ok(())
+    }
+
+    /// Service UUIDs contained in a list of service class UUIDs.
+    ///
+    /// Returns an empty list for other AD structures.
+    pub fn service_uuids(&self) -> Vec<Uuid> {
+        match self {
+            Self::IncompleteServiceUuids16(uuids) | Self::CompleteServiceUuids16(uuids) => {
+                uuids.iter().map(|v| Uuid::from_u16(*v)).collect()
+            }
+            Self::IncompleteServiceUuids32(uuids) | Self::CompleteServiceUuids32(uuids) => {
+                uuids.iter().map(|v| Uuid::from_u32(*v)).collect()
+            }
+            Self::IncompleteServiceUuids128(uuids) | Self::CompleteServiceUuids128(uuids) => uuids.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Service data AD structure for the specified service UUID.
+    ///
+    /// The shortest form of the UUID is used.
+    pub fn service_data(uuid: Uuid, data: Vec<u8>) -> Self {
+        match (uuid.as_u16(), uuid.as_u32()) {
+            (Some(uuid), _) => Self::ServiceData16 { uuid, data },
+            (None, Some(uuid)) => Self::ServiceData32 { uuid, data },
+            (None, None) => Self::ServiceData128 { uuid, data },
+        }
+    }
+
+    /// Complete lists of service class UUIDs for the specified UUIDs.
+    ///
+    /// Each UUID is placed in the list of its shortest form and empty lists are omitted.
+    pub fn service_uuid_lists(uuids: impl IntoIterator<Item = Uuid>) -> Vec<Self> {
+        let (uuids16, uuids32, uuids128) = split_uuids(uuids);
+        let mut structures = Vec::new();
+        if !uuids16.is_empty() {
+            structures.push(Self::CompleteServiceUuids16(uuids16));
+        }
+        if !uuids32.is_empty() {
+            structures.push(Self::CompleteServiceUuids32(uuids32));
+        }
+        if !uuids128.is_empty() {
+            structures.push(Self::CompleteServiceUuids128(uuids128));
+        }
+        structures
+    }
+
+    /// Lists of service solicitation UUIDs for the specified UUIDs.
+    ///
+    /// Each UUID is placed in the list of its shortest form and empty lists are omitted.
+    pub fn solicit_uuid_lists(uuids: impl IntoIterator<Item = Uuid>) -> Vec<Self> {
+        let (uuids16, uuids32, uuids128) = split_uuids(uuids);
+        let mut structures = Vec::new();
+        if !uuids16.is_empty() {
+            structures.push(Self::SolicitUuids16(uuids16));
+        }
+        if !uuids32.is_empty() {
+            structures.push(Self::SolicitUuids32(uuids32));
+        }
+        if !uuids128.is_empty() {
+            structures.push(Self::SolicitUuids128(uuids128));
+        }
+        structures
+    }
+}
+
+fn split_uuids(uuids: impl IntoIterator<Item = Uuid>) -> (Vec<u16>, Vec<u32>, Vec<Uuid>) {
+    let (mut uuids16, mut uuids32, mut uuids128) = (Vec::new(), Vec::new(), Vec::new());
+    for uuid in uuids {
+        match (uuid.as_u16(), uuid.as_u32()) {
+            (Some(v), _) => uuids16.push(v),
+            (None, Some(v)) => uuids32.push(v),
+            (None, None) => uuids128.push(uuid),
+        }
+    }
+    (uuids16, uuids32, uuids128)
+}
+
+impl fmt::Display for AdStructure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AD type 0x{:02x}: {}", self.ad_type(), hex::encode(self.data()))
+    }
+}
+
+/// Parses advertising data into AD structures.
+///
+/// Parsing stops at the first zero length byte, which marks the start of
+/// padding.
+/// Fails if a structure exceeds the data or is malformed for its AD type.
+pub fn parse(mut data: &[u8]) -> Result<Vec<AdStructure>> {
+    let mut structures = Vec::new();
+    while let Some((&len, rest)) = data.split_first() {
+        let len = len as usize;
+        if len == 0 {
+            break;
+        }
+        if rest.len() < len {
+            return Err(invalid_data("AD structure exceeds advertising data"));
+        }
+        let (structure, rest) = rest.split_at(len);
+        structures.push(AdStructure::decode(structure[0], &structure[1..])?);
+        data = rest;
+    }
+    Ok(structures)
+}
+
+/// Parses advertising data given as map from AD type to data.
+///
+/// This is the form used by the Bluetooth daemon.
+/// Structures are ordered by AD type.
+pub fn parse_map(data: &HashMap<u8, Vec<u8>>) -> Result<Vec<AdStructure>> {
+    let mut types: Vec<_> = data.keys().copied().collect();
+    types.sort_unstable();
+    types.into_iter().map(|ad_type| AdStructure::decode(ad_type, &data[&ad_type])).collect()
+}
+
+/// Encodes AD structures into advertising data.
+///
+/// Fails if the data of a structure exceeds [MAX_DATA_LEN].
+pub fn encode(structures: &[AdStructure]) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(encoded_len(structures));
+    for structure in structures {
+        structure.write(&mut buf)?;
+    }
+    Ok(buf)
+}
+
+/// Length of the encoded AD structures.
+pub fn encoded_len(structures: &[AdStructure]) -> usize {
+    structures.iter().map(|s| s.encoded_len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, encoded_len, parse, parse_map, AdStructure, Flags, MAX_DATA_LEN};
+    use crate::Uuid;
+    use std::collections::HashMap;
+
+    #[test]
+    fn known_vectors() {
+        let vectors: Vec<(AdStructure, Vec<u8>)> = vec![
+            (
+                AdStructure::Flags(Flags::LE_GENERAL_DISCOVERABLE | Flags::BR_EDR_NOT_SUPPORTED),
+                vec![0x02, 0x01, 0x06],
+            ),
+            (AdStructure::CompleteServiceUuids16(vec![0x180d, 0x180f]), vec![0x05, 0x03, 0x0d, 0x18, 0x0f, 0x18]),
+            (AdStructure::CompleteLocalName("abc".into()), vec![0x04, 0x09, b'a', b'b', b'c']),
+            (AdStructure::TxPowerLevel(-4), vec![0x02, 0x0a, 0xfc]),
+            (AdStructure::Appearance(0x03c1), vec![0x03, 0x19, 0xc1, 0x03]),
+            (
+                AdStructure::PeripheralConnectionIntervalRange { min: 0x0006, max: 0xffff },
+                vec![0x05, 0x12, 0x06, 0x00, 0xff, 0xff],
+            ),
+            (AdStructure::ServiceData16 { uuid: 0xfeaa, data: vec![0x10] }, vec![0x04, 0x16, 0xaa, 0xfe, 0x10]),
+            (AdStructure::AdvertisingInterval(0x0800), vec![0x03, 0x1a, 0x00, 0x08]),
+            (AdStructure::AdvertisingInterval(0x012345), vec![0x04, 0x2f, 0x45, 0x23, 0x01]),
+            (AdStructure::Uri("https:x".into()), vec![0x03, 0x24, 0x17, b'x']),
+            (
+                AdStructure::ManufacturerSpecificData { company_id: 0x004c, data: vec![0x02, 0x15] },
+                vec![0x05, 0xff, 0x4c, 0x00, 0x02, 0x15],
+            ),
+            (AdStructure::Other { ad_type: 0x0c, data: vec![0x01] }, vec![0x02, 0x0c, 0x01]),
+        ];
+        for (structure, bytes) in vectors {
+            let encoded = encode(std::slice::from_ref(&structure)).unwrap();
+            assert_eq!(encoded, bytes, "{:?}", structure);
+            assert_eq!(structure.encoded_len(), bytes.len());
+            assert_eq!(parse(&bytes).unwrap(), [structure]);
+        }
+    }
+
+    #[test]
+    fn uuid128() {
+        let uuid = Uuid::from_u128(0x0000fe95_0000_1000_8000_00805f9b34fb + 1);
+        let bytes = encode(&[AdStructure::CompleteServiceUuids128(vec![uuid])]).unwrap();
+        assert_eq!(bytes[..2], [0x11, 0x07]);
+        assert_eq!(bytes[2..], uuid.as_u128().to_le_bytes());
+        assert_eq!(parse(&bytes).unwrap()[0].service_uuids(), [uuid]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let structures = vec![
+            AdStructure::Flags(Flags::LE_GENERAL_DISCOVERABLE),
+            AdStructure::IncompleteServiceUuids32(vec![0x12345678]),
+            AdStructure::SolicitUuids16(vec![0x1812]),
+            AdStructure::ShortenedLocalName("ab".into()),
+            AdStructure::ServiceData32 { uuid: 0x12345678, data: vec![1, 2] },
+            AdStructure::ServiceData128 { uuid: Uuid::from_u128(0x1234), data: vec![] },
+            AdStructure::AdvertisingInterval(0x01234567),
+            AdStructure::Uri("mailto:a@b".into()),
+            AdStructure::LeSupportedFeatures(vec![0x01, 0x02]),
+            AdStructure::BroadcastName("Broadcast".into()),
+        ];
+        let bytes = encode(&structures).unwrap();
+        assert_eq!(bytes.len(), encoded_len(&structures));
+        assert_eq!(parse(&bytes).unwrap(), structures);
+    }
+
+    #[test]
+    fn uri_unknown_scheme() {
+        let structure = AdStructure::Uri("urn:x".into());
+        assert_eq!(structure.data(), [0x01, b'u', b'r', b'n', b':', b'x']);
+        assert_eq!(parse(&encode(std::slice::from_ref(&structure)).unwrap()).unwrap(), [structure]);
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(parse(&[0x02, 0x01, 0x06, 0x00, 0x00]).unwrap(), [AdStructure::Flags(Flags(0x06))]);
+        assert!(parse(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid() {
+        for bytes in [
+            &[0x03, 0x01, 0x06][..],
+            &[0x01, 0x01],
+            &[0x04, 0x03, 0x0d, 0x18, 0x0f],
+            &[0x03, 0x0a, 0x00, 0x00],
+            &[0x03, 0x09, 0xff, 0xfe],
+            &[0x02, 0xff, 0x4c],
+            &[0x04, 0x1a, 0x00, 0x00, 0x00],
+        ] {
+            assert!(parse(bytes).is_err(), "{:x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn too_long() {
+        let structure = AdStructure::Other { ad_type: 0x0c, data: vec![0; MAX_DATA_LEN + 1] };
+        let mut buf = Vec::new();
+        assert!(structure.write(&mut buf).is_err());
+        assert!(buf.is_empty());
+
+        let structure = AdStructure::Other { ad_type: 0x0c, data: vec![0; MAX_DATA_LEN] };
+        assert_eq!(encode(&[structure]).unwrap()[0], 0xff);
+    }
+
+    #[test]
+    fn map() {
+        let data = HashMap::from([(0xff, vec![0x4c, 0x00, 0x01]), (0x01, vec![0x06])]);
+        assert_eq!(
+            parse_map(&data).unwrap(),
+            [
+                AdStructure::Flags(Flags(0x06)),
+                AdStructure::ManufacturerSpecificData { company_id: 0x004c, data: vec![0x01] }
+            ]
+        );
+    }
+}
@@ -23,13 +23,15 @@ use uuid::Uuid;
 use crate::{
     adv,
     adv::{Advertisement, AdvertisementHandle, Capabilities, Feature, PlatformFeature, SecondaryChannel},
-    all_dbus_objects, device,
-    device::Device,
-    gatt, Address, AddressType, Error, ErrorKind, Event, InternalErrorKind, Modalias, Result, SessionInner,
-    SingleSessionToken, SERVICE_NAME, TIMEOUT,
+    adv_monitor, battery, dbus_objects_with_interface, device,
+    device::{AdvertisementReport, Device},
+    gatt, media, network, Address, AddressType, Error, ErrorKind, Event, InternalErrorKind, Modalias, Result,
+    SessionInner, SingleSessionToken, TIMEOUT,
 };
 
 pub(crate) const INTERFACE: &str = "org.bluez.Adapter1";
+pub(crate) const ADMIN_POLICY_SET_INTERFACE: &str = "org.bluez.AdminPolicySet1";
+pub(crate) const ADMIN_POLICY_STATUS_INTERFACE: &str = "org.bluez.AdminPolicyStatus1";
 pub(crate) const PATH: &str = "/org/bluez";
 pub(crate) const PREFIX: &str = "/org/bluez/";
 
@@ -63,7 +65,7 @@ impl Adapter {
     }
 
     fn proxy(&self) -> Proxy<'_, &SyncConnection> {
-        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
     }
 
     pub(crate) fn dbus_path(adapter_name: &str) -> Result<Path<'static>> {
@@ -98,11 +100,9 @@ impl Adapter {
     /// Bluetooth addresses of discovered Bluetooth devices.
     pub async fn device_addresses(&self) -> Result<Vec<Address>> {
         let mut addrs = Vec::new();
-        for (path, interfaces) in all_dbus_objects(&self.inner.connection).await? {
+        for path in dbus_objects_with_interface(&self.inner, device::INTERFACE).await? {
             match Device::parse_dbus_path(&path) {
-                Some((adapter, addr)) if adapter == *self.name && interfaces.contains_key(device::INTERFACE) => {
-                    addrs.push(addr)
-                }
+                Some((adapter, addr)) if adapter == *self.name => addrs.push(addr),
                 _ => (),
             }
         }
@@ -129,8 +129,28 @@ impl Adapter {
     /// yet when a [DeviceAdded event](AdapterEvent::DeviceAdded) occurs.
     /// Use [discover_devices_with_changes](Self::discover_devices_with_changes)
     /// when you want to be notified when the device properties change.
+    ///
+    /// Use [discover_devices_with_filter](Self::discover_devices_with_filter)
+    /// to restrict the discovery to specific devices.
     pub async fn discover_devices(&self) -> Result<impl Stream<Item = AdapterEvent>> {
-        let token = self.discovery_session().await?;
+        self.discover_devices_with_filter(DiscoveryFilter { duplicate_data: false, ..Default::default() }).await
+    }
+
+    /// This method starts the device discovery session using the specified discovery filter.
+    ///
+    /// Apart from the filter this behaves like [discover_devices](Self::discover_devices).
+    ///
+    /// When multiple discovery sessions are active on the same adapter within
+    /// this process, their filters are merged and the least restrictive
+    /// combination is applied.
+    /// Thus the returned stream may also contain devices that only match
+    /// the filter of another discovery session.
+    /// The merged filter is updated whenever a discovery session is started or
+    /// the stream of a discovery session is dropped.
+    pub async fn discover_devices_with_filter(
+        &self, filter: DiscoveryFilter,
+    ) -> Result<impl Stream<Item = AdapterEvent>> {
+        let token = self.discovery_session(filter).await?;
         let change_events = self.events().await?.map(move |evt| {
             let _token = &token;
             evt
@@ -193,31 +213,92 @@ impl Adapter {
         Ok(ReceiverStream::new(rx))
     }
 
-    async fn discovery_session(&self) -> Result<SingleSessionToken> {
+    /// This method starts a device discovery session and streams the advertisement
+    /// data received from remote devices.
+    ///
+    /// Duplicate data is enabled, so that a report is generated for every advertisement
+    /// received, even if its data did not change.
+    /// The reports are built directly from the property change notifications sent by
+    /// the Bluetooth daemon, thus no additional queries are performed.
+    ///
+    /// Use [advertisement_reports_with_filter](Self::advertisement_reports_with_filter)
+    /// to restrict the discovery to specific devices.
+    pub async fn advertisement_reports(&self) -> Result<impl Stream<Item = AdvertisementReport>> {
+        self.advertisement_reports_with_filter(DiscoveryFilter::default()).await
+    }
+
+    /// This method starts a device discovery session using the specified discovery filter
+    /// and streams the advertisement data received from remote devices.
+    ///
+    /// Duplicate data is always enabled, regardless of the setting in the filter.
+    pub async fn advertisement_reports_with_filter(
+        &self, filter: DiscoveryFilter,
+    ) -> Result<impl Stream<Item = AdvertisementReport>> {
+        let events = self.inner.child_property_events(self.dbus_path.clone()).await?;
+        let token = self.discovery_session(DiscoveryFilter { duplicate_data: true, ..filter }).await?;
+
+        let name = self.name.clone();
+        let stream = events.filter_map(move |event| {
+            let _token = &token;
+            let report = match event {
+                Event::PropertiesChanged { object, interface, changed } if interface == device::INTERFACE => {
+                    match Device::parse_dbus_path(&object) {
+                        Some((adapter, address)) if adapter == *name => {
+                            AdvertisementReport::from_prop_map(address, changed)
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            async move { report }
+        });
+        Ok(stream)
+    }
+
+    async fn discovery_session(&self, filter: DiscoveryFilter) -> Result<DiscoverySessionToken> {
+        let filter_token = {
+            let mut all_filters = self.inner.discovery_filters.lock().await;
+            let filters = all_filters.entry(self.dbus_path.clone()).or_default();
+            let id = filters.next_id;
+            filters.next_id += 1;
+            filters.active.insert(id, filter);
+            DiscoveryFilterToken { inner: self.inner.clone(), dbus_path: self.dbus_path.clone(), id }
+        };
+
+        let inner = self.inner.clone();
         let dbus_path = self.dbus_path.clone();
-        let connection = self.inner.connection.clone();
-        self.inner
+        let session_token = self
+            .inner
             .single_session(
                 &self.dbus_path,
                 async move {
-                    let filter = DiscoveryFilter {
-                        duplicate_data: false,
-                        transport: DiscoveryTransport::Auto,
-                        ..Default::default()
-                    };
-                    self.call_method("SetDiscoveryFilter", (filter.into_dict(),)).await?;
+                    let mut all_filters = self.inner.discovery_filters.lock().await;
+                    let filters = all_filters.entry(self.dbus_path.clone()).or_default();
+                    let merged = filters.merged().unwrap_or_default();
+                    self.call_method("SetDiscoveryFilter", (merged.clone().into_dict(),)).await?;
                     self.call_method("StartDiscovery", ()).await?;
+                    filters.applied = Some(merged);
                     Ok(())
                 },
                 async move {
-                    log::trace!("{}: {}.StopDiscovery ()", &dbus_path, SERVICE_NAME);
-                    let proxy = Proxy::new(SERVICE_NAME, &dbus_path, TIMEOUT, &*connection);
+                    let mut all_filters = inner.discovery_filters.lock().await;
+                    log::trace!("{}: {}.StopDiscovery ()", &dbus_path, &inner.service_name);
+                    let proxy = Proxy::new(&*inner.service_name, &dbus_path, TIMEOUT, &*inner.connection);
                     let result: std::result::Result<(), dbus::Error> =
                         proxy.method_call(INTERFACE, "StopDiscovery", ()).await;
-                    log::trace!("{}: {}.StopDiscovery () -> {:?}", &dbus_path, SERVICE_NAME, &result);
+                    log::trace!("{}: {}.StopDiscovery () -> {:?}", &dbus_path, &inner.service_name, &result);
+                    if let Some(filters) = all_filters.get_mut(&dbus_path) {
+                        filters.applied = None;
+                    }
                 },
             )
-            .await
+            .await?;
+
+        // Update the discovery filter, if discovery was already running.
+        DiscoveryFilters::update(&self.inner, &self.dbus_path).await?;
+
+        Ok(DiscoverySessionToken { _session: session_token, _filter: filter_token })
     }
 
     dbus_interface!();
@@ -259,8 +340,8 @@ impl Adapter {
     /// InvalidLength error indicates that the data
     /// provided generates a data packet which is too long.
     ///
-    /// The properties of this object are parsed when it is
-    /// registered, and any changes are ignored.
+    /// Use [AdvertisementHandle::update] to change the advertisement
+    /// after it has been registered.
     ///
     /// If the same object is registered twice it will result in
     /// an AlreadyExists error.
@@ -268,9 +349,99 @@ impl Adapter {
     /// If the maximum number of advertisement instances is
     /// reached it will result in NotPermitted error.
     ///
-    /// Drop the returned [AdvertisementHandle] to unregister the advertisement.
+    /// The advertisement is [validated](Advertisement::validate) against the
+    /// capabilities of the adapter before it is registered.
+    /// An [InvalidAdvertisement](ErrorKind::InvalidAdvertisement) error listing
+    /// all problems is returned if this fails.
+    ///
+    /// The returned [AdvertisementHandle] is a stream of events for the advertisement.
+    /// Drop it to unregister the advertisement.
     pub async fn advertise(&self, le_advertisement: Advertisement) -> Result<AdvertisementHandle> {
-        le_advertisement.register(self.inner.clone(), self.name.clone()).await
+        // Supported capabilities are experimental and may be incomplete.
+        let capabilities = match self.supported_advertising_capabilities().await {
+            Ok(capabilities) => capabilities,
+            Err(Error { kind: ErrorKind::Internal(InternalErrorKind::MissingKey(_)), .. }) => None,
+            Err(err) => return Err(err),
+        };
+        let supported_includes = self.supported_advertising_system_includes().await?;
+        le_advertisement.validate(capabilities.as_ref(), &supported_includes)?;
+
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        adv::RegisteredAdvertisement::new(le_advertisement, capabilities, supported_includes, event_tx)
+            .register(self.inner.clone(), self.name.clone(), event_rx)
+            .await
+    }
+
+    /// Registers an advertisement monitor for passive scanning.
+    ///
+    /// The Bluetooth daemon scans for advertisements matching the patterns of the
+    /// monitor in the background, without an active discovery session.
+    /// If supported by the controller, pattern matching is offloaded to it.
+    ///
+    /// The returned [MonitorHandle](adv_monitor::MonitorHandle) is a stream of events
+    /// for the monitor.
+    /// Drop it to unregister the advertisement monitor.
+    pub async fn register_monitor(&self, monitor: adv_monitor::Monitor) -> Result<adv_monitor::MonitorHandle> {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        adv_monitor::RegisteredMonitor::new(monitor, event_tx)
+            .register(self.inner.clone(), self.name.clone(), event_rx)
+            .await
+    }
+
+    /// Registers a battery provider.
+    ///
+    /// The returned [BatteryProviderHandle](battery::BatteryProviderHandle) is used to
+    /// supply the battery level of remote devices to the Bluetooth daemon.
+    /// Drop it to unregister the battery provider.
+    ///
+    /// The battery provider API of the Bluetooth daemon is experimental and
+    /// must be enabled by starting it with the `--experimental` option.
+    pub async fn register_battery_provider(&self) -> Result<battery::BatteryProviderHandle> {
+        battery::BatteryProviderHandle::register(self.inner.clone(), self.name.clone()).await
+    }
+
+    /// Registers a personal area network (PAN) server.
+    ///
+    /// Incoming PAN connections for the specified `role` are accepted and their
+    /// network interfaces are added to the existing network bridge named `bridge`.
+    /// Drop the returned [NetworkServerHandle](network::NetworkServerHandle) to
+    /// unregister the network server.
+    pub async fn register_network_server(
+        &self, role: network::Role, bridge: &str,
+    ) -> Result<network::NetworkServerHandle> {
+        network::NetworkServerHandle::register(self.inner.clone(), &self.name, role, bridge.to_string()).await
+    }
+
+    /// Registers a local media endpoint.
+    ///
+    /// Drop the returned [EndpointHandle](media::EndpointHandle) to unregister the endpoint.
+    pub async fn register_media_endpoint(&self, endpoint: media::Endpoint) -> Result<media::EndpointHandle> {
+        media::RegisteredEndpoint::new(&self.inner, endpoint).register(self.inner.clone(), self.name()).await
+    }
+
+    /// Supported platform features of advertisement monitoring.
+    ///
+    /// Returns [None] if advertisement monitoring is not supported by the adapter.
+    pub async fn supported_monitor_features(&self) -> Result<Option<BTreeSet<adv_monitor::PlatformFeature>>> {
+        let features: Option<Vec<String>> =
+            self.get_opt_property_with_interface("SupportedFeatures", adv_monitor::MANAGER_INTERFACE).await?;
+        Ok(features.map(|v| v.iter().filter_map(|s| s.parse().ok()).collect()))
+    }
+
+    /// Restricts the services that remote devices may use to the specified UUIDs.
+    ///
+    /// Connections to services not in the list are rejected and
+    /// devices that are connected using such services are disconnected.
+    /// Specify an empty set to allow all services.
+    ///
+    /// The allow list is reported by [service_allow_list](Self::service_allow_list)
+    /// and devices that are restricted by it are reported by
+    /// [Device::is_affected_by_policy].
+    ///
+    /// This requires the admin policy plugin of the Bluetooth daemon.
+    pub async fn set_service_allow_list(&self, uuids: HashSet<Uuid>) -> Result<()> {
+        let uuids: Vec<String> = uuids.into_iter().map(|uuid| uuid.to_string()).collect();
+        self.call_method_with_interface("SetServiceAllowList", (uuids,), ADMIN_POLICY_SET_INTERFACE).await
     }
 
     /// Registers a local GATT services hierarchy (GATT Server).
@@ -609,6 +780,37 @@ define_properties!(
                 v.iter().filter_map(|s| s.parse().ok()).collect()
             }),
         );
+
+        /// Supported types of advertisement monitors.
+        property(
+            SupportedMonitorTypes, BTreeSet<adv_monitor::Type>,
+            dbus: (adv_monitor::MANAGER_INTERFACE, "SupportedMonitorTypes", Vec<String>, OPTIONAL),
+            get: (supported_monitor_types, v => {
+                v.iter().filter_map(|s| s.parse().ok()).collect()
+            }),
+        );
+
+        // ===========================================================================================
+        // Admin policy properties
+        // ===========================================================================================
+
+        /// UUIDs of the services that remote devices are allowed to use.
+        ///
+        /// An empty set means that all services are allowed.
+        /// Use [Adapter::set_service_allow_list] to change it.
+        property(
+            ServiceAllowList, HashSet<Uuid>,
+            dbus: (ADMIN_POLICY_STATUS_INTERFACE, "ServiceAllowList", Vec<String>, OPTIONAL),
+            get: (service_allow_list, v => {
+                v
+                .iter()
+                .map(|uuid| {
+                    uuid.parse()
+                        .map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidUuid(uuid.to_string()))))
+                })
+                .collect::<Result<HashSet<Uuid>>>()?
+            }),
+        );
     }
 );
 
@@ -626,8 +828,10 @@ pub enum AdapterEvent {
 }
 
 /// Transport parameter determines the type of scan.
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Display, EnumString)]
-pub(crate) enum DiscoveryTransport {
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DiscoveryTransport {
     /// interleaved scan
     #[strum(serialize = "auto")]
     Auto,
@@ -646,19 +850,24 @@ impl Default for DiscoveryTransport {
 }
 
 /// Bluetooth device discovery filter.
+///
+/// Use [Adapter::discover_devices_with_filter] to start a discovery session using a filter.
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub(crate) struct DiscoveryFilter {
-    ///  Filter by service UUIDs, empty means match
-    ///  _any_ UUID.
-    ///
-    ///  When a remote device is found that advertises
-    ///  any UUID from UUIDs, it will be reported if:
-    ///  - pathloss and RSSI are both empty.
-    ///  - only pathloss param is set, device advertise
-    ///    TX power, and computed pathloss is less than
-    ///    pathloss param.
-    ///  - only RSSI param is set, and received RSSI is
-    ///    higher than RSSI param.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiscoveryFilter {
+    /// Filter by service UUIDs, empty means match
+    /// _any_ UUID.
+    ///
+    /// When a remote device is found that advertises
+    /// any UUID from UUIDs, it will be reported if:
+    ///
+    ///   * pathloss and RSSI are both empty.
+    ///   * only pathloss param is set, device advertise
+    ///     TX power, and computed pathloss is less than
+    ///     pathloss param.
+    ///   * only RSSI param is set, and received RSSI is
+    ///     higher than RSSI param.
     pub uuids: HashSet<Uuid>,
     /// RSSI threshold value.
     ///
@@ -668,27 +877,26 @@ pub(crate) struct DiscoveryFilter {
     /// filters have been set, the RSSI delta-threshold,
     /// that is imposed by StartDiscovery by default,
     /// will not be applied.
+    ///
+    /// RSSI and pathloss thresholds cannot be set at the same time.
     pub rssi: Option<i16>,
     /// Pathloss threshold value.
     ///
     /// PropertiesChanged signals will be emitted
     /// for already existing Device objects, with
     /// updated Pathloss value.
+    ///
+    /// RSSI and pathloss thresholds cannot be set at the same time.
     pub pathloss: Option<u16>,
     /// Transport parameter determines the type of
     /// scan.
     ///
-    /// Possible values:
-    ///     "auto"  - interleaved scan
-    ///     "bredr" - BR/EDR inquiry
-    ///     "le"    - LE scan only
-    ///
-    /// If "le" or "bredr" Transport is requested,
+    /// If [DiscoveryTransport::Le] or [DiscoveryTransport::BrEdr] is requested,
     /// and the controller doesn't support it,
-    /// org.bluez.Error.Failed error will be returned.
+    /// an [ErrorKind::Failed] error will be returned.
     ///
-    /// If "auto" transport is requested, scan will use
-    /// LE, BREDR, or both, depending on what's
+    /// If [DiscoveryTransport::Auto] is requested, scan will use
+    /// LE, BR/EDR, or both, depending on what's
     /// currently enabled on the controller.
     pub transport: DiscoveryTransport,
     /// Disables duplicate detection of advertisement data.
@@ -715,6 +923,8 @@ pub(crate) struct DiscoveryFilter {
     /// it work as a logical OR, also setting empty
     /// string "" pattern will match any device found.
     pub pattern: Option<String>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
 }
 
 impl Default for DiscoveryFilter {
@@ -727,6 +937,7 @@ impl Default for DiscoveryFilter {
             duplicate_data: true,
             discoverable: false,
             pattern: Default::default(),
+            _non_exhaustive: (),
         }
     }
 }
@@ -734,7 +945,7 @@ impl Default for DiscoveryFilter {
 impl DiscoveryFilter {
     fn into_dict(self) -> HashMap<&'static str, Variant<Box<dyn RefArg>>> {
         let mut hm: HashMap<&'static str, Variant<Box<dyn RefArg>>> = HashMap::new();
-        let Self { uuids, rssi, pathloss, transport, duplicate_data, discoverable, pattern } = self;
+        let Self { uuids, rssi, pathloss, transport, duplicate_data, discoverable, pattern, .. } = self;
         hm.insert("UUIDs", Variant(Box::new(uuids.into_iter().map(|uuid| uuid.to_string()).collect::<Vec<_>>())));
         if let Some(rssi) = rssi {
             hm.insert("RSSI", Variant(Box::new(rssi)));
@@ -750,4 +961,174 @@ impl DiscoveryFilter {
         }
         hm
     }
+
+    /// Merges the specified filters into the least restrictive filter that
+    /// matches all devices any of the filters would match.
+    ///
+    /// Returns [None] if no filters are specified.
+    fn merge<'a>(filters: impl IntoIterator<Item = &'a DiscoveryFilter>) -> Option<Self> {
+        let mut merged: Option<Self> = None;
+        for filter in filters {
+            merged = Some(match merged {
+                None => filter.clone(),
+                Some(m) => Self {
+                    uuids: if m.uuids.is_empty() || filter.uuids.is_empty() {
+                        HashSet::new()
+                    } else {
+                        m.uuids.union(&filter.uuids).cloned().collect()
+                    },
+                    rssi: match (m.rssi, filter.rssi) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        _ => None,
+                    },
+                    pathloss: match (m.pathloss, filter.pathloss) {
+                        (Some(a), Some(b)) => Some(a.max(b)),
+                        _ => None,
+                    },
+                    transport: if m.transport == filter.transport {
+                        m.transport
+                    } else {
+                        DiscoveryTransport::Auto
+                    },
+                    duplicate_data: m.duplicate_data || filter.duplicate_data,
+                    discoverable: m.discoverable || filter.discoverable,
+                    pattern: if m.pattern == filter.pattern { m.pattern } else { None },
+                    _non_exhaustive: (),
+                },
+            });
+        }
+        merged
+    }
+}
+
+/// Discovery filters of all discovery sessions of an adapter within this process.
+#[derive(Default)]
+pub(crate) struct DiscoveryFilters {
+    next_id: u64,
+    active: HashMap<u64, DiscoveryFilter>,
+    /// Filter that has been set on the adapter, if discovery is running.
+    applied: Option<DiscoveryFilter>,
+}
+
+impl DiscoveryFilters {
+    /// Merged filter of all active discovery sessions.
+    fn merged(&self) -> Option<DiscoveryFilter> {
+        DiscoveryFilter::merge(self.active.values())
+    }
+
+    /// Sets the merged filter on the adapter, if discovery is running and it has changed.
+    async fn update(inner: &SessionInner, dbus_path: &Path<'static>) -> Result<()> {
+        let mut all_filters = inner.discovery_filters.lock().await;
+        let filters = match all_filters.get_mut(dbus_path) {
+            Some(filters) => filters,
+            None => return Ok(()),
+        };
+        if filters.applied.is_none() {
+            return Ok(());
+        }
+
+        let merged = match filters.merged() {
+            Some(merged) => merged,
+            None => return Ok(()),
+        };
+        if filters.applied.as_ref() == Some(&merged) {
+            return Ok(());
+        }
+
+        log::trace!("{}: {}.SetDiscoveryFilter ({:?})", dbus_path, &inner.service_name, &merged);
+        let proxy = Proxy::new(&*inner.service_name, dbus_path, TIMEOUT, &*inner.connection);
+        proxy.method_call(INTERFACE, "SetDiscoveryFilter", (merged.clone().into_dict(),)).await?;
+        filters.applied = Some(merged);
+        Ok(())
+    }
+}
+
+/// Removes a discovery filter when dropped.
+struct DiscoveryFilterToken {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+    id: u64,
+}
+
+impl Drop for DiscoveryFilterToken {
+    fn drop(&mut self) {
+        let inner = self.inner.clone();
+        let dbus_path = self.dbus_path.clone();
+        let id = self.id;
+        tokio::spawn(async move {
+            {
+                let mut all_filters = inner.discovery_filters.lock().await;
+                if let Some(filters) = all_filters.get_mut(&dbus_path) {
+                    filters.active.remove(&id);
+                }
+            }
+            if let Err(err) = DiscoveryFilters::update(&inner, &dbus_path).await {
+                log::warn!("{}: updating discovery filter failed: {}", &dbus_path, &err);
+            }
+        });
+    }
+}
+
+/// Keeps a discovery session running.
+struct DiscoverySessionToken {
+    _session: SingleSessionToken,
+    _filter: DiscoveryFilterToken,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    use super::{DiscoveryFilter, DiscoveryTransport};
+
+    const UUID_A: Uuid = Uuid::from_u128(0x0000180f_0000_1000_8000_00805f9b34fb);
+    const UUID_B: Uuid = Uuid::from_u128(0x0000180d_0000_1000_8000_00805f9b34fb);
+
+    fn filter(uuids: &[Uuid], rssi: Option<i16>, transport: DiscoveryTransport) -> DiscoveryFilter {
+        DiscoveryFilter { uuids: uuids.iter().copied().collect(), rssi, transport, ..Default::default() }
+    }
+
+    #[test]
+    fn merge_none() {
+        assert_eq!(DiscoveryFilter::merge([]), None);
+    }
+
+    #[test]
+    fn merge_single() {
+        let a = filter(&[UUID_A], Some(-60), DiscoveryTransport::Le);
+        assert_eq!(DiscoveryFilter::merge([&a]), Some(a));
+    }
+
+    #[test]
+    fn merge_least_restrictive() {
+        let a = DiscoveryFilter { duplicate_data: true, ..filter(&[UUID_A], Some(-60), DiscoveryTransport::Le) };
+        let b = DiscoveryFilter {
+            discoverable: true,
+            pattern: Some("dev".to_string()),
+            ..filter(&[UUID_B], Some(-80), DiscoveryTransport::Le)
+        };
+        let merged = DiscoveryFilter::merge([&a, &b]).unwrap();
+        assert_eq!(merged.uuids, [UUID_A, UUID_B].into_iter().collect::<HashSet<_>>());
+        assert_eq!(merged.rssi, Some(-80));
+        assert_eq!(merged.transport, DiscoveryTransport::Le);
+        assert!(merged.duplicate_data);
+        assert!(merged.discoverable);
+        assert_eq!(merged.pattern, None);
+    }
+
+    #[test]
+    fn merge_unrestricted() {
+        let a = filter(&[UUID_A], Some(-60), DiscoveryTransport::Le);
+        let b = filter(&[], None, DiscoveryTransport::BrEdr);
+        let merged = DiscoveryFilter::merge([&a, &b]).unwrap();
+        assert!(merged.uuids.is_empty());
+        assert_eq!(merged.rssi, None);
+        assert_eq!(merged.transport, DiscoveryTransport::Auto);
+
+        let c = DiscoveryFilter { pathloss: Some(10), ..Default::default() };
+        let d = DiscoveryFilter { pathloss: Some(20), ..Default::default() };
+        assert_eq!(DiscoveryFilter::merge([&c, &d]).unwrap().pathloss, Some(20));
+        assert_eq!(DiscoveryFilter::merge([&c, &a]).unwrap().pathloss, None);
+    }
 }
@@ -1,25 +1,38 @@
 //! Bluetooth LE advertising.
 
 use dbus::{
-    arg::{PropMap, RefArg, Variant},
-    nonblock::Proxy,
+    arg::{prop_cast, PropMap, RefArg, Variant},
+    channel::Sender,
+    message::SignalArgs,
+    nonblock::{stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged, Proxy},
 };
 use dbus_crossroads::{Crossroads, IfaceBuilder, IfaceToken};
 use futures::channel::oneshot;
+use pin_project::{pin_project, pinned_drop};
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
     fmt,
+    pin::Pin,
     sync::Arc,
+    task::{Context, Poll},
     time::Duration,
 };
 use strum::{Display, EnumString};
+use tokio::{
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+use tokio_stream::wrappers::UnboundedReceiverStream;
 use uuid::Uuid;
 
-use crate::{read_dict, Adapter, Result, SessionInner, SERVICE_NAME, TIMEOUT};
+use crate::{
+    ad::{self, ad_type, AdStructure, Flags},
+    method_call, read_dict, Adapter, Error, ErrorKind, InternalErrorKind, Result, SessionInner, UuidExt, TIMEOUT,
+};
 
 pub(crate) const MANAGER_INTERFACE: &str = "org.bluez.LEAdvertisingManager1";
 pub(crate) const ADVERTISEMENT_INTERFACE: &str = "org.bluez.LEAdvertisement1";
-pub(crate) const ADVERTISEMENT_PREFIX: &str = publish_path!("advertising/");
+pub(crate) const ADVERTISEMENT_PREFIX: &str = "advertising/";
 
 /// Determines the type of advertising packet requested.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Display, EnumString)]
@@ -104,9 +117,13 @@ pub struct Capabilities {
     /// Maximum advertising scan response length.
     pub max_scan_response_length: u8,
     /// Minimum advertising TX power (dBm).
-    pub min_tx_power: i16,
+    ///
+    /// Only provided if the controller supports extended advertising.
+    pub min_tx_power: Option<i16>,
     /// Maximum advertising TX power (dBm).
-    pub max_tx_power: i16,
+    ///
+    /// Only provided if the controller supports extended advertising.
+    pub max_tx_power: Option<i16>,
 }
 
 impl Capabilities {
@@ -114,12 +131,93 @@ impl Capabilities {
         Ok(Self {
             max_advertisement_length: *read_dict(dict, "MaxAdvLen")?,
             max_scan_response_length: *read_dict(dict, "MaxScnRspLen")?,
-            min_tx_power: *read_dict(dict, "MinTxPower")?,
-            max_tx_power: *read_dict(dict, "MaxTxPower")?,
+            min_tx_power: prop_cast(dict, "MinTxPower").copied(),
+            max_tx_power: prop_cast(dict, "MaxTxPower").copied(),
         })
     }
 }
 
+/// Range of TX power in dBm accepted by the Bluetooth daemon.
+const MIN_TX_POWER: i16 = -127;
+const MAX_TX_POWER: i16 = 20;
+
+/// AD types that are handled by the Bluetooth daemon and thus cannot be used in
+/// [Advertisement::advertisting_data] and [Advertisement::scan_response_data].
+const RESERVED_AD_TYPES: &[u8] = &[
+    ad_type::FLAGS,
+    ad_type::INCOMPLETE_SERVICE_UUIDS_16,
+    ad_type::COMPLETE_SERVICE_UUIDS_16,
+    ad_type::INCOMPLETE_SERVICE_UUIDS_32,
+    ad_type::COMPLETE_SERVICE_UUIDS_32,
+    ad_type::INCOMPLETE_SERVICE_UUIDS_128,
+    ad_type::COMPLETE_SERVICE_UUIDS_128,
+    ad_type::SHORTENED_LOCAL_NAME,
+    ad_type::COMPLETE_LOCAL_NAME,
+    ad_type::TX_POWER_LEVEL,
+    ad_type::CLASS_OF_DEVICE,
+    ad_type::SIMPLE_PAIRING_HASH_C192,
+    ad_type::SIMPLE_PAIRING_RANDOMIZER_R192,
+    ad_type::DEVICE_ID,
+    ad_type::SECURITY_MANAGER_OOB_FLAGS,
+    ad_type::PERIPHERAL_CONNECTION_INTERVAL_RANGE,
+    ad_type::SOLICIT_UUIDS_16,
+    ad_type::SOLICIT_UUIDS_128,
+    ad_type::SERVICE_DATA_16,
+    ad_type::PUBLIC_TARGET_ADDRESS,
+    ad_type::RANDOM_TARGET_ADDRESS,
+    ad_type::APPEARANCE,
+    ad_type::ADVERTISING_INTERVAL,
+    ad_type::LE_DEVICE_ADDRESS,
+    ad_type::LE_ROLE,
+    ad_type::SIMPLE_PAIRING_HASH_C256,
+    ad_type::SIMPLE_PAIRING_RANDOMIZER_R256,
+    ad_type::SOLICIT_UUIDS_32,
+    ad_type::SERVICE_DATA_32,
+    ad_type::SERVICE_DATA_128,
+    ad_type::LE_SECURE_CONNECTIONS_CONFIRMATION_VALUE,
+    ad_type::LE_SECURE_CONNECTIONS_RANDOM_VALUE,
+    ad_type::LE_SUPPORTED_FEATURES,
+    ad_type::CHANNEL_MAP_UPDATE_INDICATION,
+    ad_type::PB_ADV,
+    ad_type::MESH_MESSAGE,
+    ad_type::MESH_BEACON,
+    ad_type::INFORMATION_DATA_3D,
+    ad_type::MANUFACTURER_SPECIFIC_DATA,
+];
+
+/// Problem that prevents an advertisement from being registered.
+///
+/// Found by [Advertisement::validate].
+#[derive(Clone, Debug, displaydoc::Display, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Problem {
+    /// advertising data length of {length} bytes exceeds maximum of {max} bytes
+    AdvertisingDataTooLong {
+        /// Encoded length.
+        length: usize,
+        /// Maximum length supported by the adapter.
+        max: u8,
+    },
+    /// scan response data length of {length} bytes exceeds maximum of {max} bytes
+    ScanResponseTooLong {
+        /// Encoded length.
+        length: usize,
+        /// Maximum length supported by the adapter.
+        max: u8,
+    },
+    /// system include {0} is not supported by the adapter
+    UnsupportedSystemInclude(Feature),
+    /// AD type {0:#04x} is handled by the Bluetooth daemon and cannot be used as advertising data
+    ReservedAdType(u8),
+    /// a broadcast advertisement cannot be discoverable
+    DiscoverableBroadcast,
+    /// minimum advertising interval exceeds maximum advertising interval
+    IntervalRange,
+    /// TX power of {0} dBm is outside of range from -127 dBm to 20 dBm
+    TxPowerOutOfRange(i16),
+}
+
 /// Bluetooth LE advertisement data definition.
 ///
 /// Specifies the Advertisement Data to be broadcast and some advertising
@@ -231,112 +329,692 @@ pub struct Advertisement {
     /// provided value must be in range [-127 to +20], where
     /// units are in dBm.
     pub tx_power: Option<i16>,
+    /// List of UUIDs to include in the "Service UUID" field of
+    /// the scan response data.
+    ///
+    /// Scan response fields are only supported by recent versions
+    /// of the Bluetooth daemon and ignored otherwise.
+    pub scan_response_service_uuids: BTreeSet<Uuid>,
+    /// Manufacturer Data fields to include in
+    /// the scan response data.
+    ///
+    /// Keys are the Manufacturer ID
+    /// to associate with the data.
+    pub scan_response_manufacturer_data: BTreeMap<u16, Vec<u8>>,
+    /// Array of UUIDs to include in "Service Solicitation"
+    /// scan response data.
+    pub scan_response_solicit_uuids: BTreeSet<Uuid>,
+    /// Service Data elements to include in the scan response data.
+    ///
+    /// The keys are the
+    /// UUID to associate with the data.
+    pub scan_response_service_data: BTreeMap<Uuid, Vec<u8>>,
+    /// Advertising Type to include in the scan response data.
+    ///
+    /// Key is the advertising type and value is the
+    /// data as byte array.
+    ///
+    /// The same restrictions as for [advertisting_data](Self::advertisting_data) apply.
+    pub scan_response_data: BTreeMap<u8, Vec<u8>>,
     #[doc(hidden)]
     pub _non_exhaustive: (),
 }
 
+/// AD structures of service UUIDs, solicit UUIDs, manufacturer data and service data.
+fn field_ad_structures(
+    service_uuids: &BTreeSet<Uuid>, solicit_uuids: &BTreeSet<Uuid>, manufacturer_data: &BTreeMap<u16, Vec<u8>>,
+    service_data: &BTreeMap<Uuid, Vec<u8>>,
+) -> Vec<AdStructure> {
+    let mut structures = AdStructure::service_uuid_lists(service_uuids.iter().copied());
+    structures.extend(AdStructure::solicit_uuid_lists(solicit_uuids.iter().copied()));
+    for (company_id, data) in manufacturer_data {
+        structures.push(AdStructure::ManufacturerSpecificData { company_id: *company_id, data: data.clone() });
+    }
+    for (uuid, data) in service_data {
+        structures.push(AdStructure::service_data(*uuid, data.clone()));
+    }
+    structures
+}
+
 impl Advertisement {
-    pub(crate) fn register_interface(cr: &mut Crossroads) -> IfaceToken<Self> {
-        cr.register(ADVERTISEMENT_INTERFACE, |ib: &mut IfaceBuilder<Self>| {
-            cr_property!(ib, "Type", la => {
-                Some(la.advertisement_type.to_string())
+    /// AD structures of the advertising data that will be sent for this advertisement.
+    ///
+    /// This follows the encoding of the Bluetooth daemon: UUIDs are given in their shortest
+    /// form and the flags, TX power and appearance structures are added as specified.
+    /// Values only known to the daemon, such as the current TX power or the appearance
+    /// from [system_includes](Self::system_includes), are represented by placeholders
+    /// of the correct size.
+    ///
+    /// The flags structure is also added by the daemon when the adapter is discoverable,
+    /// even if [discoverable](Self::discoverable) is not set.
+    pub fn ad_structures(&self) -> Vec<AdStructure> {
+        let mut structures = Vec::new();
+
+        if let Some(discoverable) = self.discoverable {
+            structures.push(AdStructure::Flags(if discoverable {
+                Flags::LE_GENERAL_DISCOVERABLE
+            } else {
+                Flags::default()
+            }));
+        }
+        structures.extend(field_ad_structures(
+            &self.service_uuids,
+            &self.solicit_uuids,
+            &self.manufacturer_data,
+            &self.service_data,
+        ));
+        if self.appearance.is_some() || self.system_includes.contains(&Feature::Appearance) {
+            structures.push(AdStructure::Appearance(self.appearance.unwrap_or_default()));
+        }
+        for (ad_type, data) in &self.advertisting_data {
+            structures.push(AdStructure::Other { ad_type: *ad_type, data: data.clone() });
+        }
+        if self.system_includes.contains(&Feature::TxPower) {
+            let tx_power = self.tx_power.unwrap_or_default().clamp(i8::MIN.into(), i8::MAX.into());
+            structures.push(AdStructure::TxPowerLevel(tx_power as i8));
+        }
+
+        structures
+    }
+
+    /// AD structures of the scan response data that will be sent for this advertisement.
+    ///
+    /// The Bluetooth daemon places the [local name](Self::local_name) into the scan response
+    /// and shortens it as necessary to fit.
+    /// The name of the adapter, which is used when the local name is only
+    /// requested through [system_includes](Self::system_includes), is not known in advance
+    /// and thus omitted.
+    pub fn scan_response_ad_structures(&self) -> Vec<AdStructure> {
+        let mut structures = field_ad_structures(
+            &self.scan_response_service_uuids,
+            &self.scan_response_solicit_uuids,
+            &self.scan_response_manufacturer_data,
+            &self.scan_response_service_data,
+        );
+        for (ad_type, data) in &self.scan_response_data {
+            structures.push(AdStructure::Other { ad_type: *ad_type, data: data.clone() });
+        }
+        if let Some(name) = &self.local_name {
+            structures.push(AdStructure::CompleteLocalName(name.clone()));
+        }
+        structures
+    }
+
+    /// Length of the encoded advertising data in bytes.
+    ///
+    /// The advertisement can only be registered if this does not exceed
+    /// [Capabilities::max_advertisement_length].
+    /// See [ad_structures](Self::ad_structures) for details.
+    pub fn advertising_data_len(&self) -> usize {
+        ad::encoded_len(&self.ad_structures())
+    }
+
+    /// Length of the encoded scan response data in bytes, excluding the local name.
+    ///
+    /// The advertisement can only be registered if this does not exceed
+    /// [Capabilities::max_scan_response_length].
+    /// The local name is not counted, since it is shortened to fit.
+    pub fn scan_response_data_len(&self) -> usize {
+        let structures: Vec<_> = self
+            .scan_response_ad_structures()
+            .into_iter()
+            .filter(|s| !matches!(s, AdStructure::CompleteLocalName(_)))
+            .collect();
+        ad::encoded_len(&structures)
+    }
+
+    /// D-Bus properties that differ in the `updated` advertisement.
+    ///
+    /// Returns the changed and the invalidated properties.
+    fn changed_properties(&self, updated: &Self) -> (PropMap, Vec<String>) {
+        let mut changed = PropMap::new();
+        let mut invalidated = Vec::new();
+        let mut put = |name: &str, value: Option<Box<dyn RefArg>>| match value {
+            Some(value) => {
+                changed.insert(name.to_string(), Variant(value));
+            }
+            None => invalidated.push(name.to_string()),
+        };
+        fn boxed<T: RefArg + 'static>(value: T) -> Box<dyn RefArg> {
+            Box::new(value)
+        }
+
+        if self.advertisement_type != updated.advertisement_type {
+            put("Type", Some(boxed(updated.advertisement_type.to_string())));
+        }
+        if self.service_uuids != updated.service_uuids {
+            put("ServiceUUIDs", Some(boxed(uuid_list(&updated.service_uuids))));
+        }
+        if self.manufacturer_data != updated.manufacturer_data {
+            put("ManufacturerData", Some(boxed(manufacturer_data_dict(&updated.manufacturer_data))));
+        }
+        if self.solicit_uuids != updated.solicit_uuids {
+            put("SolicitUUIDs", Some(boxed(uuid_list(&updated.solicit_uuids))));
+        }
+        if self.service_data != updated.service_data {
+            put("ServiceData", Some(boxed(service_data_dict(&updated.service_data))));
+        }
+        if self.advertisting_data != updated.advertisting_data {
+            put("Data", Some(boxed(data_dict(&updated.advertisting_data))));
+        }
+        if self.discoverable != updated.discoverable {
+            put("Discoverable", updated.discoverable.map(boxed));
+        }
+        if self.discoverable_timeout != updated.discoverable_timeout {
+            put("DiscoverableTimeout", updated.discoverable_timeout.map(|t| boxed(secs(t))));
+        }
+        if self.system_includes != updated.system_includes {
+            let includes: Vec<_> = updated.system_includes.iter().map(|v| v.to_string()).collect();
+            put("Includes", Some(boxed(includes)));
+        }
+        if self.local_name != updated.local_name {
+            put("LocalName", updated.local_name.clone().map(boxed));
+        }
+        if self.appearance != updated.appearance {
+            put("Appearance", updated.appearance.map(boxed));
+        }
+        if self.duration != updated.duration {
+            put("Duration", updated.duration.map(|t| boxed(secs(t))));
+        }
+        if self.timeout != updated.timeout {
+            put("Timeout", updated.timeout.map(|t| boxed(secs(t))));
+        }
+        if self.secondary_channel != updated.secondary_channel {
+            put("SecondaryChannel", updated.secondary_channel.map(|v| boxed(v.to_string())));
+        }
+        if self.min_interval != updated.min_interval {
+            put("MinInterval", updated.min_interval.map(|t| boxed(millis(t))));
+        }
+        if self.max_interval != updated.max_interval {
+            put("MaxInterval", updated.max_interval.map(|t| boxed(millis(t))));
+        }
+        if self.tx_power != updated.tx_power {
+            put("TxPower", updated.tx_power.map(boxed));
+        }
+        if self.scan_response_service_uuids != updated.scan_response_service_uuids {
+            put("ScanResponseServiceUUIDs", Some(boxed(uuid_list(&updated.scan_response_service_uuids))));
+        }
+        if self.scan_response_manufacturer_data != updated.scan_response_manufacturer_data {
+            put(
+                "ScanResponseManufacturerData",
+                Some(boxed(manufacturer_data_dict(&updated.scan_response_manufacturer_data))),
+            );
+        }
+        if self.scan_response_solicit_uuids != updated.scan_response_solicit_uuids {
+            put("ScanResponseSolicitUUIDs", Some(boxed(uuid_list(&updated.scan_response_solicit_uuids))));
+        }
+        if self.scan_response_service_data != updated.scan_response_service_data {
+            put("ScanResponseServiceData", Some(boxed(service_data_dict(&updated.scan_response_service_data))));
+        }
+        if self.scan_response_data != updated.scan_response_data {
+            put("ScanResponseData", Some(boxed(data_dict(&updated.scan_response_data))));
+        }
+
+        (changed, invalidated)
+    }
+
+    /// Checks whether the advertisement can be registered with an adapter having the
+    /// specified advertising capabilities and supported system includes.
+    ///
+    /// The capabilities and system includes are provided by
+    /// [Adapter::supported_advertising_capabilities] and
+    /// [Adapter::supported_advertising_system_includes].
+    /// If the capabilities are unknown, the data lengths are not checked.
+    /// [Adapter::advertise] performs this check automatically.
+    ///
+    /// Returns an error of kind [ErrorKind::InvalidAdvertisement] listing all
+    /// problems found.
+    pub fn validate(
+        &self, capabilities: Option<&Capabilities>, supported_includes: &BTreeSet<Feature>,
+    ) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if let Some(capabilities) = capabilities {
+            let length = self.advertising_data_len();
+            if length > capabilities.max_advertisement_length.into() {
+                problems
+                    .push(Problem::AdvertisingDataTooLong { length, max: capabilities.max_advertisement_length });
+            }
+            let length = self.scan_response_data_len();
+            if length > capabilities.max_scan_response_length.into() {
+                problems
+                    .push(Problem::ScanResponseTooLong { length, max: capabilities.max_scan_response_length });
+            }
+        }
+
+        if let Some(tx_power) = self.tx_power {
+            if !(MIN_TX_POWER..=MAX_TX_POWER).contains(&tx_power) {
+                problems.push(Problem::TxPowerOutOfRange(tx_power));
+            }
+        }
+
+        for include in &self.system_includes {
+            if !supported_includes.contains(include) {
+                problems.push(Problem::UnsupportedSystemInclude(*include));
+            }
+        }
+
+        for ad_type in self.advertisting_data.keys().chain(self.scan_response_data.keys()) {
+            if RESERVED_AD_TYPES.contains(ad_type) {
+                problems.push(Problem::ReservedAdType(*ad_type));
+            }
+        }
+
+        if self.advertisement_type == Type::Broadcast
+            && (self.discoverable == Some(true) || self.discoverable_timeout.is_some())
+        {
+            problems.push(Problem::DiscoverableBroadcast);
+        }
+
+        if let (Some(min), Some(max)) = (self.min_interval, self.max_interval) {
+            if min > max {
+                problems.push(Problem::IntervalRange);
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            let message = problems.iter().map(|p| p.to_string()).collect::<Vec<_>>().join("; ");
+            Err(Error { kind: ErrorKind::InvalidAdvertisement(problems), message })
+        }
+    }
+}
+
+/// Sets the fields of the advertisement from AD structures of advertising data.
+///
+/// Each structure is stored in the field that [Advertisement::ad_structures] takes it from.
+/// Flags set [discoverable](Advertisement::discoverable), a TX power level sets
+/// [tx_power](Advertisement::tx_power) and requests its inclusion, and local names set
+/// [local_name](Advertisement::local_name).
+/// Structures without a dedicated field are added to
+/// [advertisting_data](Advertisement::advertisting_data).
+impl Extend<AdStructure> for Advertisement {
+    fn extend<T: IntoIterator<Item = AdStructure>>(&mut self, iter: T) {
+        for structure in iter {
+            match structure {
+                AdStructure::Flags(flags) => {
+                    self.discoverable = Some(
+                        flags.contains(Flags::LE_GENERAL_DISCOVERABLE)
+                            || flags.contains(Flags::LE_LIMITED_DISCOVERABLE),
+                    )
+                }
+                AdStructure::IncompleteServiceUuids16(_)
+                | AdStructure::CompleteServiceUuids16(_)
+                | AdStructure::IncompleteServiceUuids32(_)
+                | AdStructure::CompleteServiceUuids32(_)
+                | AdStructure::IncompleteServiceUuids128(_)
+                | AdStructure::CompleteServiceUuids128(_) => self.service_uuids.extend(structure.service_uuids()),
+                AdStructure::SolicitUuids16(uuids) => {
+                    self.solicit_uuids.extend(uuids.into_iter().map(Uuid::from_u16))
+                }
+                AdStructure::SolicitUuids32(uuids) => {
+                    self.solicit_uuids.extend(uuids.into_iter().map(Uuid::from_u32))
+                }
+                AdStructure::SolicitUuids128(uuids) => self.solicit_uuids.extend(uuids),
+                AdStructure::ShortenedLocalName(name) | AdStructure::CompleteLocalName(name) => {
+                    self.local_name = Some(name)
+                }
+                AdStructure::TxPowerLevel(tx_power) => {
+                    self.tx_power = Some(tx_power.into());
+                    self.system_includes.insert(Feature::TxPower);
+                }
+                AdStructure::ServiceData16 { uuid, data } => {
+                    self.service_data.insert(Uuid::from_u16(uuid), data);
+                }
+                AdStructure::ServiceData32 { uuid, data } => {
+                    self.service_data.insert(Uuid::from_u32(uuid), data);
+                }
+                AdStructure::ServiceData128 { uuid, data } => {
+                    self.service_data.insert(uuid, data);
+                }
+                AdStructure::Appearance(appearance) => self.appearance = Some(appearance),
+                AdStructure::ManufacturerSpecificData { company_id, data } => {
+                    self.manufacturer_data.insert(company_id, data);
+                }
+                other => {
+                    self.advertisting_data.insert(other.ad_type(), other.data());
+                }
+            }
+        }
+    }
+}
+
+/// Creates an advertisement from AD structures of advertising data.
+///
+/// See the [Extend] implementation for how structures are mapped to fields.
+impl FromIterator<AdStructure> for Advertisement {
+    fn from_iter<T: IntoIterator<Item = AdStructure>>(iter: T) -> Self {
+        let mut adv = Self::default();
+        adv.extend(iter);
+        adv
+    }
+}
+
+fn uuid_list(uuids: &BTreeSet<Uuid>) -> Vec<String> {
+    uuids.iter().map(|uuid| uuid.to_string()).collect()
+}
+
+fn manufacturer_data_dict(data: &BTreeMap<u16, Vec<u8>>) -> HashMap<u16, Variant<Vec<u8>>> {
+    data.iter().map(|(k, v)| (*k, Variant(v.clone()))).collect()
+}
+
+fn service_data_dict(data: &BTreeMap<Uuid, Vec<u8>>) -> HashMap<String, Variant<Vec<u8>>> {
+    data.iter().map(|(k, v)| (k.to_string(), Variant(v.clone()))).collect()
+}
+
+fn data_dict(data: &BTreeMap<u8, Vec<u8>>) -> HashMap<u8, Variant<Vec<u8>>> {
+    data.iter().map(|(k, v)| (*k, Variant(v.clone()))).collect()
+}
+
+fn secs(t: Duration) -> u16 {
+    t.as_secs().min(u16::MAX as _) as u16
+}
+
+fn millis(t: Duration) -> u32 {
+    t.as_millis().min(u32::MAX as _) as u32
+}
+
+/// Bluetooth LE advertisement event.
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum AdvertisementEvent {
+    /// The advertisement has been removed by the Bluetooth daemon.
+    ///
+    /// This happens, for example, when its [timeout](Advertisement::timeout) has expired
+    /// or the adapter has been removed.
+    /// No further events are sent.
+    Released,
+}
+
+// ---------------
+// D-Bus interface
+// ---------------
+
+/// An advertisement exposed over D-Bus to bluez.
+pub(crate) struct RegisteredAdvertisement {
+    a: std::sync::Mutex<Advertisement>,
+    capabilities: Option<Capabilities>,
+    supported_includes: BTreeSet<Feature>,
+    event_tx: Mutex<Option<mpsc::UnboundedSender<AdvertisementEvent>>>,
+}
+
+impl RegisteredAdvertisement {
+    pub(crate) fn new(
+        advertisement: Advertisement, capabilities: Option<Capabilities>, supported_includes: BTreeSet<Feature>,
+        event_tx: mpsc::UnboundedSender<AdvertisementEvent>,
+    ) -> Self {
+        Self {
+            a: std::sync::Mutex::new(advertisement),
+            capabilities,
+            supported_includes,
+            event_tx: Mutex::new(Some(event_tx)),
+        }
+    }
+
+    pub(crate) fn register_interface(cr: &mut Crossroads) -> IfaceToken<Arc<Self>> {
+        cr.register(ADVERTISEMENT_INTERFACE, |ib: &mut IfaceBuilder<Arc<Self>>| {
+            cr_property!(ib, "Type", reg => {
+                Some(reg.a.lock().unwrap().advertisement_type.to_string())
+            });
+            cr_property!(ib, "ServiceUUIDs", reg => {
+                Some(uuid_list(&reg.a.lock().unwrap().service_uuids))
+            });
+            cr_property!(ib, "ManufacturerData", reg => {
+                Some(manufacturer_data_dict(&reg.a.lock().unwrap().manufacturer_data))
+            });
+            cr_property!(ib, "SolicitUUIDs", reg => {
+                Some(uuid_list(&reg.a.lock().unwrap().solicit_uuids))
             });
-            cr_property!(ib, "ServiceUUIDs", la => {
-                Some(la.service_uuids.iter().map(|uuid| uuid.to_string()).collect::<Vec<_>>())
+            cr_property!(ib, "ServiceData", reg => {
+                Some(service_data_dict(&reg.a.lock().unwrap().service_data))
             });
-            cr_property!(ib, "ManufacturerData", la => {
-                Some(la.manufacturer_data.clone().into_iter().map(|(k, v)| (k, Variant(v))).collect::<HashMap<_, _>>())
+            cr_property!(ib, "Data", reg => {
+                Some(data_dict(&reg.a.lock().unwrap().advertisting_data))
             });
-            cr_property!(ib, "SolicitUUIDs", la => {
-                Some(la.solicit_uuids.iter().map(|uuid| uuid.to_string()).collect::<Vec<_>>())
+            cr_property!(ib, "Discoverable", reg => {
+                reg.a.lock().unwrap().discoverable
             });
-            cr_property!(ib, "ServiceData", la => {
-                Some(la.service_data.iter().map(|(k, v)| (k.to_string(), Variant(v.clone()))).collect::<HashMap<_, _>>())
+            cr_property!(ib, "DiscoverableTimeout", reg => {
+                reg.a.lock().unwrap().discoverable_timeout.map(secs)
             });
-            cr_property!(ib, "Data", la => {
-                Some(la.advertisting_data.iter().map(|(k, v)| (*k, Variant(v.clone()))).collect::<HashMap<_, _>>())
+            cr_property!(ib, "Includes", reg => {
+                Some(reg.a.lock().unwrap().system_includes.iter().map(|v| v.to_string()).collect::<Vec<_>>())
             });
-            cr_property!(ib, "Discoverable", la => {
-                la.discoverable
+            cr_property!(ib, "LocalName", reg => {
+                reg.a.lock().unwrap().local_name.clone()
             });
-            cr_property!(ib, "DiscoverableTimeout", la => {
-                la.discoverable_timeout.map(|t| t.as_secs().min(u16::MAX as _) as u16)
+            cr_property!(ib, "Appearance", reg => {
+                reg.a.lock().unwrap().appearance
             });
-            cr_property!(ib, "Includes", la => {
-                Some(la.system_includes.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+            cr_property!(ib, "Duration", reg => {
+                reg.a.lock().unwrap().duration.map(secs)
             });
-            cr_property!(ib, "LocalName", la => {
-                la.local_name.clone()
+            cr_property!(ib, "Timeout", reg => {
+                reg.a.lock().unwrap().timeout.map(secs)
             });
-            cr_property!(ib, "Appearance", la => {
-                la.appearance
+            cr_property!(ib, "SecondaryChannel", reg => {
+                reg.a.lock().unwrap().secondary_channel.map(|v| v.to_string())
             });
-            cr_property!(ib, "Duration", la => {
-                la.duration.map(|t| t.as_secs().min(u16::MAX as _) as u16)
+            cr_property!(ib, "MinInterval", reg => {
+                reg.a.lock().unwrap().min_interval.map(millis)
             });
-            cr_property!(ib, "Timeout", la => {
-                la.timeout.map(|t| t.as_secs().min(u16::MAX as _) as u16)
+            cr_property!(ib, "MaxInterval", reg => {
+                reg.a.lock().unwrap().max_interval.map(millis)
             });
-            cr_property!(ib, "SecondaryChannel", la => {
-                la.secondary_channel.map(|v| v.to_string())
+            cr_property!(ib, "TxPower", reg => {
+                reg.a.lock().unwrap().tx_power
             });
-            cr_property!(ib, "MinInterval", la => {
-                la.min_interval.map(|t| t.as_millis().min(u32::MAX as _) as u32)
+            cr_property!(ib, "ScanResponseServiceUUIDs", reg => {
+                Some(uuid_list(&reg.a.lock().unwrap().scan_response_service_uuids))
             });
-            cr_property!(ib, "MaxInterval", la => {
-                la.max_interval.map(|t| t.as_millis().min(u32::MAX as _) as u32)
+            cr_property!(ib, "ScanResponseManufacturerData", reg => {
+                Some(manufacturer_data_dict(&reg.a.lock().unwrap().scan_response_manufacturer_data))
             });
-            cr_property!(ib, "TxPower", la => {
-                la.tx_power
+            cr_property!(ib, "ScanResponseSolicitUUIDs", reg => {
+                Some(uuid_list(&reg.a.lock().unwrap().scan_response_solicit_uuids))
+            });
+            cr_property!(ib, "ScanResponseServiceData", reg => {
+                Some(service_data_dict(&reg.a.lock().unwrap().scan_response_service_data))
+            });
+            cr_property!(ib, "ScanResponseData", reg => {
+                Some(data_dict(&reg.a.lock().unwrap().scan_response_data))
+            });
+            ib.method_with_cr_async("Release", (), (), |ctx, cr, ()| {
+                method_call(ctx, cr, |reg: Arc<Self>| async move {
+                    if let Some(event_tx) = reg.event_tx.lock().await.take() {
+                        let _ = event_tx.send(AdvertisementEvent::Released);
+                    }
+                    Ok(())
+                })
             });
         })
     }
 
+    /// Modifies the advertisement and notifies the Bluetooth daemon of the changed properties.
+    fn update(
+        &self, inner: &SessionInner, path: &dbus::Path<'static>, f: impl FnOnce(&mut Advertisement),
+    ) -> Result<()> {
+        let (changed_properties, invalidated_properties) = {
+            let mut a = self.a.lock().unwrap();
+            let mut updated = a.clone();
+            f(&mut updated);
+            updated.validate(self.capabilities.as_ref(), &self.supported_includes)?;
+            let changes = a.changed_properties(&updated);
+            *a = updated;
+            changes
+        };
+        if changed_properties.is_empty() && invalidated_properties.is_empty() {
+            return Ok(());
+        }
+
+        let ppc = PropertiesPropertiesChanged {
+            interface_name: ADVERTISEMENT_INTERFACE.to_string(),
+            changed_properties,
+            invalidated_properties,
+        };
+        inner
+            .connection
+            .send(ppc.to_emit_message(path))
+            .map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?;
+        Ok(())
+    }
+
     pub(crate) async fn register(
         self, inner: Arc<SessionInner>, adapter_name: Arc<String>,
+        event_rx: mpsc::UnboundedReceiver<AdvertisementEvent>,
     ) -> Result<AdvertisementHandle> {
-        let name = dbus::Path::new(format!("{}{}", ADVERTISEMENT_PREFIX, Uuid::new_v4().as_simple())).unwrap();
+        let name = dbus::Path::new(format!(
+            "{}{}",
+            inner.publish_path(ADVERTISEMENT_PREFIX),
+            Uuid::new_v4().as_simple()
+        ))
+        .unwrap();
         log::trace!("Publishing advertisement at {}", &name);
 
+        let reg = Arc::new(self);
         {
             let mut cr = inner.crossroads.lock().await;
-            cr.insert(name.clone(), &[inner.le_advertisment_token], self);
+            cr.insert(name.clone(), &[inner.le_advertisment_token], reg.clone());
         }
 
         log::trace!("Registering advertisement at {}", &name);
-        let proxy =
-            Proxy::new(SERVICE_NAME, Adapter::dbus_path(&adapter_name)?, TIMEOUT, inner.connection.clone());
-        proxy.method_call(MANAGER_INTERFACE, "RegisterAdvertisement", (name.clone(), PropMap::new())).await?;
+        let adapter_path = Adapter::dbus_path(&adapter_name)?;
+        let reg_name = name.clone();
+        inner
+            .register_with(
+                name.clone(),
+                adapter_path.clone(),
+                MANAGER_INTERFACE,
+                "RegisterAdvertisement",
+                move || (reg_name.clone(), PropMap::new()),
+            )
+            .await?;
 
         let (drop_tx, drop_rx) = oneshot::channel();
         let unreg_name = name.clone();
+        let unreg_inner = inner.clone();
         tokio::spawn(async move {
+            let inner = unreg_inner;
             let _ = drop_rx.await;
+            inner.forget_registration(unreg_name.clone()).await;
 
             log::trace!("Unregistering advertisement at {}", &unreg_name);
+            let proxy = Proxy::new(inner.service_name.clone(), adapter_path, TIMEOUT, inner.connection.clone());
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterAdvertisement", (unreg_name.clone(),)).await;
 
             log::trace!("Unpublishing advertisement at {}", &unreg_name);
             let mut cr = inner.crossroads.lock().await;
-            let _: Option<Self> = cr.remove(&unreg_name);
+            let _: Option<Arc<Self>> = cr.remove(&unreg_name);
         });
 
-        Ok(AdvertisementHandle { name, _drop_tx: drop_tx })
+        Ok(AdvertisementHandle {
+            name,
+            inner,
+            reg,
+            event_rx: UnboundedReceiverStream::new(event_rx),
+            rotation: None,
+            _drop_tx: drop_tx,
+        })
     }
 }
 
-/// Handle to active Bluetooth LE advertisement.
+/// Handle to active Bluetooth LE advertisement receiving its events.
+///
+/// The stream ends when the advertisement is released by the Bluetooth daemon.
 ///
 /// Drop to unregister advertisement.
+#[pin_project(PinnedDrop)]
 pub struct AdvertisementHandle {
     name: dbus::Path<'static>,
+    inner: Arc<SessionInner>,
+    reg: Arc<RegisteredAdvertisement>,
+    #[pin]
+    event_rx: UnboundedReceiverStream<AdvertisementEvent>,
+    rotation: Option<JoinHandle<()>>,
     _drop_tx: oneshot::Sender<()>,
 }
 
-impl Drop for AdvertisementHandle {
-    fn drop(&mut self) {
+impl AdvertisementHandle {
+    /// The currently advertised data.
+    pub fn advertisement(&self) -> Advertisement {
+        self.reg.a.lock().unwrap().clone()
+    }
+
+    /// Modifies the advertisement while it is being advertised.
+    ///
+    /// The function `f` is called to modify the advertisement and the Bluetooth
+    /// daemon is notified of all changed properties, so that it updates
+    /// the advertised data in place.
+    /// The modified advertisement is [validated](Advertisement::validate) against the
+    /// capabilities of the adapter; if this fails, the advertisement is left unchanged.
+    pub fn update(&self, f: impl FnOnce(&mut Advertisement)) -> Result<()> {
+        self.reg.update(&self.inner, &self.name, f)
+    }
+
+    /// Periodically modifies the advertisement, for example to rotate through
+    /// several payloads or to advertise a changing sensor value.
+    ///
+    /// Every `period` the function `f` is called with the advertisement and the number
+    /// of the update, starting at zero, and the changes are applied as by [update](Self::update).
+    /// Updates failing validation are skipped.
+    ///
+    /// This replaces a previously started rotation.
+    /// The rotation stops when the handle is dropped or the advertisement has been
+    /// [released](AdvertisementEvent::Released).
+    ///
+    /// Fails if `period` is zero.
+    pub fn rotate(
+        &mut self, period: Duration, mut f: impl FnMut(&mut Advertisement, u64) + Send + 'static,
+    ) -> Result<()> {
+        if period.is_zero() {
+            return Err(Error {
+                kind: ErrorKind::InvalidArguments,
+                message: "advertisement rotation period must not be zero".to_string(),
+            });
+        }
+
+        let inner = self.inner.clone();
+        let reg = self.reg.clone();
+        let name = self.name.clone();
+        let rotation = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.tick().await;
+            for n in 0.. {
+                interval.tick().await;
+                if reg.event_tx.lock().await.is_none() {
+                    break;
+                }
+                if let Err(err) = reg.update(&inner, &name, |adv| f(adv, n)) {
+                    log::warn!("Rotating advertisement {} failed: {}", &name, &err);
+                }
+            }
+        });
+
+        if let Some(old) = self.rotation.replace(rotation) {
+            old.abort();
+        }
+        Ok(())
+    }
+}
+
+impl futures::stream::Stream for AdvertisementHandle {
+    type Item = AdvertisementEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.project().event_rx.poll_next(cx)
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for AdvertisementHandle {
+    fn drop(self: Pin<&mut Self>) {
         // required for drop order
+        if let Some(rotation) = self.project().rotation.take() {
+            rotation.abort();
+        }
     }
 }
 
@@ -345,3 +1023,115 @@ impl fmt::Debug for AdvertisementHandle {
         write!(f, "AdvertisementHandle {{ {} }}", &self.name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{Advertisement, Capabilities, Feature, Problem, Type};
+    use crate::{
+        ad::{self, ad_type, AdStructure, Flags},
+        Error, ErrorKind, Uuid, UuidExt,
+    };
+
+    fn problems(adv: &Advertisement, capabilities: Option<&Capabilities>) -> Vec<Problem> {
+        match adv.validate(capabilities, &BTreeSet::new()) {
+            Ok(()) => Vec::new(),
+            Err(Error { kind: ErrorKind::InvalidAdvertisement(problems), .. }) => problems,
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn from_ad_structures() {
+        let structures = vec![
+            AdStructure::Flags(Flags::LE_GENERAL_DISCOVERABLE),
+            AdStructure::CompleteServiceUuids16(vec![0x180d]),
+            AdStructure::SolicitUuids16(vec![0x1812]),
+            AdStructure::ManufacturerSpecificData { company_id: 0x004c, data: vec![0x02] },
+            AdStructure::ServiceData16 { uuid: 0xfeaa, data: vec![0x10] },
+            AdStructure::Appearance(0x03c1),
+            AdStructure::Other { ad_type: 0x0c, data: vec![0x01] },
+            AdStructure::TxPowerLevel(-4),
+        ];
+        let adv: Advertisement = structures.iter().cloned().collect();
+
+        assert_eq!(adv.discoverable, Some(true));
+        assert_eq!(adv.service_uuids, BTreeSet::from([Uuid::from_u16(0x180d)]));
+        assert_eq!(adv.solicit_uuids, BTreeSet::from([Uuid::from_u16(0x1812)]));
+        assert_eq!(adv.manufacturer_data[&0x004c], [0x02]);
+        assert_eq!(adv.service_data[&Uuid::from_u16(0xfeaa)], [0x10]);
+        assert_eq!(adv.appearance, Some(0x03c1));
+        assert_eq!(adv.advertisting_data[&0x0c], [0x01]);
+        assert_eq!(adv.tx_power, Some(-4));
+        assert!(adv.system_includes.contains(&Feature::TxPower));
+
+        assert_eq!(adv.ad_structures(), structures);
+        assert_eq!(adv.advertising_data_len(), ad::encoded_len(&structures));
+    }
+
+    #[test]
+    fn local_name() {
+        let mut adv = Advertisement::default();
+        adv.extend([AdStructure::ShortenedLocalName("a".into()), AdStructure::BroadcastName("b".into())]);
+        assert_eq!(adv.local_name.as_deref(), Some("a"));
+        assert_eq!(adv.advertisting_data[&0x30], b"b");
+        assert_eq!(adv.scan_response_ad_structures(), [AdStructure::CompleteLocalName("a".into())]);
+    }
+
+    #[test]
+    fn validate() {
+        let capabilities = Capabilities {
+            max_advertisement_length: 31,
+            max_scan_response_length: 31,
+            min_tx_power: Some(-20),
+            max_tx_power: Some(10),
+        };
+        let adv = Advertisement {
+            manufacturer_data: [(0x004c, vec![0; 40])].into(),
+            scan_response_manufacturer_data: [(0x004c, vec![0; 30])].into(),
+            tx_power: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            problems(&adv, Some(&capabilities)),
+            [
+                Problem::AdvertisingDataTooLong { length: 44, max: 31 },
+                Problem::ScanResponseTooLong { length: 34, max: 31 },
+            ]
+        );
+        assert_eq!(problems(&adv, None), []);
+
+        let adv = Advertisement { tx_power: Some(21), ..Default::default() };
+        assert_eq!(problems(&adv, Some(&capabilities)), [Problem::TxPowerOutOfRange(21)]);
+        assert_eq!(problems(&adv, None), [Problem::TxPowerOutOfRange(21)]);
+        let adv = Advertisement { tx_power: Some(-128), ..Default::default() };
+        assert_eq!(problems(&adv, None), [Problem::TxPowerOutOfRange(-128)]);
+        let adv = Advertisement { tx_power: Some(-127), ..Default::default() };
+        assert_eq!(problems(&adv, None), []);
+    }
+
+    #[test]
+    fn validate_broadcast() {
+        let adv = Advertisement {
+            advertisement_type: Type::Broadcast,
+            discoverable: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(problems(&adv, None), []);
+        let adv = Advertisement { discoverable: Some(true), ..adv };
+        assert_eq!(problems(&adv, None), [Problem::DiscoverableBroadcast]);
+        let adv = Advertisement { discoverable: None, discoverable_timeout: Some(Default::default()), ..adv };
+        assert_eq!(problems(&adv, None), [Problem::DiscoverableBroadcast]);
+    }
+
+    #[test]
+    fn validate_reserved_ad_types() {
+        for ad_type in [ad_type::FLAGS, ad_type::MESH_MESSAGE, ad_type::MESH_BEACON, ad_type::PB_ADV, 0x3d] {
+            let adv = Advertisement { advertisting_data: [(ad_type, vec![0])].into(), ..Default::default() };
+            assert_eq!(problems(&adv, None), [Problem::ReservedAdType(ad_type)]);
+        }
+        let adv = Advertisement { advertisting_data: [(ad_type::URI, vec![0x01])].into(), ..Default::default() };
+        assert_eq!(problems(&adv, None), []);
+    }
+}
@@ -0,0 +1,332 @@
+//! Bluetooth LE advertisement monitoring.
+//!
+//! Advertisement monitors allow the Bluetooth daemon to passively scan for
+//! advertisements matching a set of patterns.
+//! If supported by the controller, pattern matching and RSSI filtering are
+//! offloaded to the controller, thus avoiding a power-hungry active discovery.
+
+use dbus::nonblock::Proxy;
+use dbus_crossroads::{Crossroads, IfaceBuilder, IfaceToken};
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use strum::{Display, EnumString};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use uuid::Uuid;
+
+use crate::{method_call, Adapter, Address, Device, Result, SessionInner, TIMEOUT};
+
+pub(crate) const MANAGER_INTERFACE: &str = "org.bluez.AdvertisementMonitorManager1";
+pub(crate) const MONITOR_INTERFACE: &str = "org.bluez.AdvertisementMonitor1";
+pub(crate) const MONITOR_PREFIX: &str = "monitor/";
+
+/// Advertisement monitor type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Type {
+    /// Patterns with logic OR applied.
+    ///
+    /// An advertisement matches if it matches any of the specified patterns.
+    #[strum(serialize = "or_patterns")]
+    OrPatterns,
+}
+
+impl Default for Type {
+    fn default() -> Self {
+        Self::OrPatterns
+    }
+}
+
+/// Advertisement monitoring platform feature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum PlatformFeature {
+    /// Controller supports offloading of pattern matching.
+    #[strum(serialize = "controller-patterns")]
+    ControllerPatterns,
+}
+
+/// Pattern that advertisement data is matched against.
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pattern {
+    /// Advertising data type to match.
+    ///
+    /// See the assigned numbers document for possible values,
+    /// for example 0xff for manufacturer specific data.
+    pub data_type: u8,
+    /// Byte offset within the advertising data where the content must occur.
+    pub start_position: u8,
+    /// Content that the advertising data must contain.
+    pub content: Vec<u8>,
+}
+
+/// RSSI sampling period of an advertisement monitor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RssiSamplingPeriod {
+    /// Report all advertisements.
+    All,
+    /// Report only the first advertisement of a device.
+    First,
+    /// Report advertisements of a device at most once per period.
+    ///
+    /// The period has a resolution of 100 ms and is clamped
+    /// to the range [100 ms, 25.4 s].
+    Period(Duration),
+}
+
+impl RssiSamplingPeriod {
+    fn to_u16(self) -> u16 {
+        match self {
+            Self::All => 0,
+            Self::First => 255,
+            Self::Period(period) => (period.as_millis() / 100).clamp(1, 254) as u16,
+        }
+    }
+}
+
+/// Bluetooth LE advertisement monitor definition.
+///
+/// Specifies the patterns that advertisements must match and the RSSI
+/// thresholds used to determine whether a device is found or lost.
+///
+/// The RSSI thresholds and timeouts must either be all specified or all be [None].
+///
+/// Use [Adapter::register_monitor] to register a new advertisement monitor.
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Monitor {
+    /// Type of the monitor.
+    pub monitor_type: Type,
+    /// RSSI threshold in dBm below which a device is considered lost.
+    ///
+    /// The device is lost if the RSSI stays below this value
+    /// for [rssi_low_timeout](Self::rssi_low_timeout).
+    /// Valid range is [-127, 20].
+    pub rssi_low_threshold: Option<i16>,
+    /// RSSI threshold in dBm above which a device is considered found.
+    ///
+    /// The device is found if the RSSI stays above this value
+    /// for [rssi_high_timeout](Self::rssi_high_timeout).
+    /// Valid range is [-127, 20].
+    pub rssi_high_threshold: Option<i16>,
+    /// Time the RSSI must stay below the low threshold for the device to be lost.
+    ///
+    /// Valid range is [1 s, 300 s].
+    /// The timeout has a resolution of one second and is rounded up.
+    pub rssi_low_timeout: Option<Duration>,
+    /// Time the RSSI must stay above the high threshold for the device to be found.
+    ///
+    /// Valid range is [1 s, 300 s].
+    /// The timeout has a resolution of one second and is rounded up.
+    pub rssi_high_timeout: Option<Duration>,
+    /// How often advertisements of a found device are propagated to the Bluetooth daemon.
+    pub rssi_sampling_period: Option<RssiSamplingPeriod>,
+    /// Patterns to match advertisements against.
+    pub patterns: Vec<Pattern>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+/// Converts an RSSI timeout into seconds, rounding up.
+fn timeout_secs(timeout: Duration) -> u16 {
+    let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+    secs.min(u16::MAX as _) as u16
+}
+
+/// Advertisement monitor event.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum MonitorEvent {
+    /// The monitor has been activated by the Bluetooth daemon.
+    Activated,
+    /// A device matching the monitor has been found.
+    DeviceFound(Address),
+    /// A previously found device matching the monitor has been lost.
+    DeviceLost(Address),
+}
+
+// ---------------
+// D-Bus interface
+// ---------------
+
+/// An advertisement monitor exposed over D-Bus to bluez.
+pub(crate) struct RegisteredMonitor {
+    m: Monitor,
+    event_tx: Mutex<Option<mpsc::UnboundedSender<MonitorEvent>>>,
+}
+
+impl RegisteredMonitor {
+    pub(crate) fn new(monitor: Monitor, event_tx: mpsc::UnboundedSender<MonitorEvent>) -> Self {
+        Self { m: monitor, event_tx: Mutex::new(Some(event_tx)) }
+    }
+
+    /// Queues an event without waiting for the stream to be polled,
+    /// so that replies to the Bluetooth daemon are never delayed.
+    async fn send_event(&self, event: MonitorEvent) {
+        if let Some(event_tx) = &*self.event_tx.lock().await {
+            let _ = event_tx.send(event);
+        }
+    }
+
+    pub(crate) fn register_interface(cr: &mut Crossroads) -> IfaceToken<Arc<Self>> {
+        cr.register(MONITOR_INTERFACE, |ib: &mut IfaceBuilder<Arc<Self>>| {
+            cr_property!(ib, "Type", reg => {
+                Some(reg.m.monitor_type.to_string())
+            });
+            cr_property!(ib, "RSSILowThreshold", reg => {
+                reg.m.rssi_low_threshold
+            });
+            cr_property!(ib, "RSSIHighThreshold", reg => {
+                reg.m.rssi_high_threshold
+            });
+            cr_property!(ib, "RSSILowTimeout", reg => {
+                reg.m.rssi_low_timeout.map(timeout_secs)
+            });
+            cr_property!(ib, "RSSIHighTimeout", reg => {
+                reg.m.rssi_high_timeout.map(timeout_secs)
+            });
+            cr_property!(ib, "RSSISamplingPeriod", reg => {
+                reg.m.rssi_sampling_period.map(|p| p.to_u16())
+            });
+            cr_property!(ib, "Patterns", reg => {
+                Some(reg.m.patterns.iter().map(|p| (p.start_position, p.data_type, p.content.clone())).collect::<Vec<_>>())
+            });
+            ib.method_with_cr_async("Release", (), (), |ctx, cr, ()| {
+                method_call(ctx, cr, |reg: Arc<Self>| async move {
+                    reg.event_tx.lock().await.take();
+                    Ok(())
+                })
+            });
+            ib.method_with_cr_async("Activate", (), (), |ctx, cr, ()| {
+                method_call(ctx, cr, |reg: Arc<Self>| async move {
+                    reg.send_event(MonitorEvent::Activated).await;
+                    Ok(())
+                })
+            });
+            ib.method_with_cr_async(
+                "DeviceFound",
+                ("device",),
+                (),
+                |ctx, cr, (device_path,): (dbus::Path<'static>,)| {
+                    method_call(ctx, cr, |reg: Arc<Self>| async move {
+                        match Device::parse_dbus_path(&device_path) {
+                            Some((_, address)) => reg.send_event(MonitorEvent::DeviceFound(address)).await,
+                            None => log::error!("Cannot parse device path: {}", &device_path),
+                        }
+                        Ok(())
+                    })
+                },
+            );
+            ib.method_with_cr_async(
+                "DeviceLost",
+                ("device",),
+                (),
+                |ctx, cr, (device_path,): (dbus::Path<'static>,)| {
+                    method_call(ctx, cr, |reg: Arc<Self>| async move {
+                        match Device::parse_dbus_path(&device_path) {
+                            Some((_, address)) => reg.send_event(MonitorEvent::DeviceLost(address)).await,
+                            None => log::error!("Cannot parse device path: {}", &device_path),
+                        }
+                        Ok(())
+                    })
+                },
+            );
+        })
+    }
+
+    pub(crate) async fn register(
+        self, inner: Arc<SessionInner>, adapter_name: Arc<String>,
+        event_rx: mpsc::UnboundedReceiver<MonitorEvent>,
+    ) -> Result<MonitorHandle> {
+        let root_path =
+            dbus::Path::new(format!("{}{}", inner.publish_path(MONITOR_PREFIX), Uuid::new_v4().as_simple()))
+                .unwrap();
+        let monitor_path = dbus::Path::new(format!("{}/monitor0", &root_path)).unwrap();
+
+        {
+            let mut cr = inner.crossroads.lock().await;
+            log::trace!("Publishing monitor root at {}", &root_path);
+            let om = cr.object_manager::<()>();
+            cr.insert(root_path.clone(), &[om], ());
+            log::trace!("Publishing monitor at {}", &monitor_path);
+            cr.insert(monitor_path.clone(), &[inner.adv_monitor_token], Arc::new(self));
+        }
+
+        log::trace!("Registering monitor root at {}", &root_path);
+        let adapter_path = Adapter::dbus_path(&adapter_name)?;
+        let reg_path = root_path.clone();
+        inner
+            .register_with(
+                root_path.clone(),
+                adapter_path.clone(),
+                MANAGER_INTERFACE,
+                "RegisterMonitor",
+                move || (reg_path.clone(),),
+            )
+            .await?;
+
+        let (drop_tx, drop_rx) = oneshot::channel();
+        let unreg_path = root_path.clone();
+        tokio::spawn(async move {
+            let _ = drop_rx.await;
+            inner.forget_registration(unreg_path.clone()).await;
+
+            log::trace!("Unregistering monitor root at {}", &unreg_path);
+            let proxy = Proxy::new(inner.service_name.clone(), adapter_path, TIMEOUT, inner.connection.clone());
+            let _: std::result::Result<(), dbus::Error> =
+                proxy.method_call(MANAGER_INTERFACE, "UnregisterMonitor", (unreg_path.clone(),)).await;
+
+            log::trace!("Unpublishing monitor root at {}", &unreg_path);
+            let mut cr = inner.crossroads.lock().await;
+            let _: Option<Arc<Self>> = cr.remove(&monitor_path);
+            let _: Option<()> = cr.remove(&unreg_path);
+        });
+
+        Ok(MonitorHandle { name: root_path, event_rx: UnboundedReceiverStream::new(event_rx), _drop_tx: drop_tx })
+    }
+}
+
+/// Handle to registered Bluetooth LE advertisement monitor receiving its events.
+///
+/// The stream ends when the monitor is released by the Bluetooth daemon.
+///
+/// Drop to unregister advertisement monitor.
+#[pin_project(PinnedDrop)]
+pub struct MonitorHandle {
+    name: dbus::Path<'static>,
+    #[pin]
+    event_rx: UnboundedReceiverStream<MonitorEvent>,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl futures::stream::Stream for MonitorHandle {
+    type Item = MonitorEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.project().event_rx.poll_next(cx)
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for MonitorHandle {
+    fn drop(self: Pin<&mut Self>) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for MonitorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MonitorHandle {{ {} }}", &self.name)
+    }
+}
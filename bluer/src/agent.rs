@@ -2,7 +2,7 @@
 
 use dbus::nonblock::Proxy;
 use dbus_crossroads::{Crossroads, IfaceBuilder, IfaceToken};
-use futures::{pin_mut, Future};
+use futures::{pin_mut, Future, FutureExt};
 use std::{fmt, pin::Pin, sync::Arc};
 use strum::IntoStaticStr;
 use tokio::{
@@ -11,12 +11,12 @@ use tokio::{
 };
 use uuid::Uuid;
 
-use crate::{method_call, Address, Device, Result, SessionInner, ERR_PREFIX, SERVICE_NAME, TIMEOUT};
+use crate::{method_call, session::RegisterFn, Address, Device, Result, SessionInner, ERR_PREFIX, TIMEOUT};
 
 pub(crate) const INTERFACE: &str = "org.bluez.Agent1";
 pub(crate) const MANAGER_INTERFACE: &str = "org.bluez.AgentManager1";
 pub(crate) const MANAGER_PATH: &str = "/org/bluez";
-pub(crate) const AGENT_PREFIX: &str = publish_path!("agent/");
+pub(crate) const AGENT_PREFIX: &str = "agent/";
 
 /// Error response from us to a Bluetooth agent request.
 #[derive(Clone, Copy, Debug, displaydoc::Display, Eq, PartialEq, Ord, PartialOrd, Hash, IntoStaticStr)]
@@ -454,7 +454,8 @@ impl RegisteredAgent {
     }
 
     pub(crate) async fn register(self, inner: Arc<SessionInner>) -> Result<AgentHandle> {
-        let name = dbus::Path::new(format!("{}{}", AGENT_PREFIX, Uuid::new_v4().as_simple())).unwrap();
+        let name = dbus::Path::new(format!("{}{}", inner.publish_path(AGENT_PREFIX), Uuid::new_v4().as_simple()))
+            .unwrap();
         let capability = self.a.capability();
         let request_default = self.a.request_default;
         log::trace!("Publishing agent at {} with capability {}", &name, &capability);
@@ -464,17 +465,16 @@ impl RegisteredAgent {
             cr.insert(name.clone(), &[inner.agent_token], Arc::new(self));
         }
 
-        log::trace!("Registering agent at {}", &name);
-        let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, inner.connection.clone());
-        proxy.method_call(MANAGER_INTERFACE, "RegisterAgent", (name.clone(), capability)).await?;
-        let connection = inner.connection.clone();
-
         let (drop_tx, drop_rx) = oneshot::channel();
         let unreg_name = name.clone();
+        let unreg_inner = inner.clone();
         tokio::spawn(async move {
+            let inner = unreg_inner;
             let _ = drop_rx.await;
+            inner.forget_registration(unreg_name.clone()).await;
 
             log::trace!("Unregistering agent at {}", &unreg_name);
+            let proxy = Proxy::new(inner.service_name.clone(), MANAGER_PATH, TIMEOUT, inner.connection.clone());
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterAgent", (unreg_name.clone(),)).await;
 
@@ -483,11 +483,24 @@ impl RegisteredAgent {
             let _: Option<Self> = cr.remove(&unreg_name);
         });
 
-        if request_default {
-            log::trace!("Requesting default agent for {}", &name);
-            let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, connection);
-            proxy.method_call(MANAGER_INTERFACE, "RequestDefaultAgent", (name.clone(),)).await?;
-        }
+        // The unregistration task is already running, so that the agent is
+        // unregistered if requesting it as default agent fails.
+        let reg_name = name.clone();
+        let register_fn: RegisterFn = Box::new(move |inner| {
+            let proxy = Proxy::new(inner.service_name.clone(), MANAGER_PATH, TIMEOUT, inner.connection.clone());
+            let name = reg_name.clone();
+            async move {
+                proxy.method_call(MANAGER_INTERFACE, "RegisterAgent", (name.clone(), capability)).await?;
+                if request_default {
+                    log::trace!("Requesting default agent for {}", &name);
+                    proxy.method_call(MANAGER_INTERFACE, "RequestDefaultAgent", (name,)).await?;
+                }
+                Ok(())
+            }
+            .boxed()
+        });
+        log::trace!("Registering agent at {}", &name);
+        inner.register(name.clone(), MANAGER_PATH.into(), register_fn).await?;
 
         Ok(AgentHandle { name, _drop_tx: drop_tx })
     }
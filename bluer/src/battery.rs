@@ -0,0 +1,312 @@
+//! Battery level of remote devices.
+//!
+//! The battery level reported by a remote device is available via
+//! [Device::battery_percentage](crate::Device::battery_percentage).
+//!
+//! An application that obtains the battery level of a remote device by other means,
+//! for example through a vendor-specific protocol, can supply it to the
+//! Bluetooth daemon by registering a battery provider using
+//! [Adapter::register_battery_provider].
+//! The Bluetooth daemon then exposes the provided battery level on the device.
+
+use dbus::{
+    arg::{PropMap, Variant},
+    channel::Sender,
+    message::SignalArgs,
+    nonblock::{
+        stdintf::org_freedesktop_dbus::{
+            ObjectManagerInterfacesAdded, ObjectManagerInterfacesRemoved, PropertiesPropertiesChanged,
+        },
+        Proxy,
+    },
+};
+use dbus_crossroads::{Crossroads, IfaceBuilder, IfaceToken};
+use futures::channel::oneshot;
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+use uuid::Uuid;
+
+use crate::{Adapter, Address, Device, Error, ErrorKind, InternalErrorKind, Result, SessionInner, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.Battery1";
+pub(crate) const PROVIDER_INTERFACE: &str = "org.bluez.BatteryProvider1";
+pub(crate) const PROVIDER_MANAGER_INTERFACE: &str = "org.bluez.BatteryProviderManager1";
+pub(crate) const PROVIDER_PREFIX: &str = "battery/";
+
+/// Battery of a remote device supplied by a battery provider.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Battery {
+    /// Address of the remote device the battery belongs to.
+    pub device: Address,
+    /// Battery level in percent.
+    ///
+    /// Must be in the range 0 to 100.
+    pub percentage: u8,
+    /// Describes where the battery information comes from.
+    ///
+    /// For example `HFP 1.7`, `HID` or the UUID of the profile used.
+    pub source: Option<String>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+fn check_percentage(percentage: u8) -> Result<()> {
+    if percentage > 100 {
+        return Err(Error {
+            kind: ErrorKind::InvalidArguments,
+            message: format!("battery percentage {percentage} exceeds 100"),
+        });
+    }
+    Ok(())
+}
+
+// ---------------
+// D-Bus interface
+// ---------------
+
+/// A battery exposed over D-Bus to bluez.
+pub(crate) struct RegisteredBattery {
+    device_path: dbus::Path<'static>,
+    battery: Mutex<Battery>,
+}
+
+impl RegisteredBattery {
+    fn properties(&self) -> PropMap {
+        let battery = self.battery.lock().unwrap();
+        let mut props = PropMap::new();
+        props.insert("Device".to_string(), Variant(Box::new(self.device_path.clone())));
+        props.insert("Percentage".to_string(), Variant(Box::new(battery.percentage)));
+        if let Some(source) = &battery.source {
+            props.insert("Source".to_string(), Variant(Box::new(source.clone())));
+        }
+        props
+    }
+
+    pub(crate) fn register_interface(cr: &mut Crossroads) -> IfaceToken<Arc<Self>> {
+        cr.register(PROVIDER_INTERFACE, |ib: &mut IfaceBuilder<Arc<Self>>| {
+            cr_property!(ib, "Device", reg => {
+                Some(reg.device_path.clone())
+            });
+            cr_property!(ib, "Percentage", reg => {
+                Some(reg.battery.lock().unwrap().percentage)
+            });
+            cr_property!(ib, "Source", reg => {
+                reg.battery.lock().unwrap().source.clone()
+            });
+        })
+    }
+}
+
+/// Handle to a battery provider registered with the Bluetooth daemon.
+///
+/// Use [add](Self::add) to provide the battery of a remote device.
+///
+/// Drop to unregister the battery provider.
+pub struct BatteryProviderHandle {
+    inner: Arc<SessionInner>,
+    adapter_name: Arc<String>,
+    name: dbus::Path<'static>,
+    next_id: AtomicU32,
+    batteries: Arc<Mutex<HashSet<dbus::Path<'static>>>>,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl BatteryProviderHandle {
+    pub(crate) async fn register(inner: Arc<SessionInner>, adapter_name: Arc<String>) -> Result<Self> {
+        let name =
+            dbus::Path::new(format!("{}{}", inner.publish_path(PROVIDER_PREFIX), Uuid::new_v4().as_simple()))
+                .unwrap();
+        log::trace!("Publishing battery provider at {}", &name);
+
+        {
+            let mut cr = inner.crossroads.lock().await;
+            let om = cr.object_manager::<()>();
+            cr.insert(name.clone(), &[om], ());
+        }
+
+        log::trace!("Registering battery provider at {}", &name);
+        let adapter_path = Adapter::dbus_path(&adapter_name)?;
+        let reg_name = name.clone();
+        inner
+            .register_with(
+                name.clone(),
+                adapter_path.clone(),
+                PROVIDER_MANAGER_INTERFACE,
+                "RegisterBatteryProvider",
+                move || (reg_name.clone(),),
+            )
+            .await?;
+
+        let batteries: Arc<Mutex<HashSet<dbus::Path<'static>>>> = Default::default();
+        let (drop_tx, drop_rx) = oneshot::channel();
+        let unreg_name = name.clone();
+        let unreg_inner = inner.clone();
+        let unreg_batteries = batteries.clone();
+        tokio::spawn(async move {
+            let inner = unreg_inner;
+            let _ = drop_rx.await;
+            inner.forget_registration(unreg_name.clone()).await;
+
+            log::trace!("Unregistering battery provider at {}", &unreg_name);
+            let proxy = Proxy::new(inner.service_name.clone(), adapter_path, TIMEOUT, inner.connection.clone());
+            let _: std::result::Result<(), dbus::Error> = proxy
+                .method_call(PROVIDER_MANAGER_INTERFACE, "UnregisterBatteryProvider", (unreg_name.clone(),))
+                .await;
+
+            log::trace!("Unpublishing battery provider at {}", &unreg_name);
+            let paths: Vec<_> = unreg_batteries.lock().unwrap().drain().collect();
+            let mut cr = inner.crossroads.lock().await;
+            for path in paths {
+                let _: Option<Arc<RegisteredBattery>> = cr.remove(&path);
+            }
+            let _: Option<()> = cr.remove(&unreg_name);
+        });
+
+        Ok(Self { inner, adapter_name, name, next_id: AtomicU32::new(0), batteries, _drop_tx: drop_tx })
+    }
+
+    /// Provides the battery of a remote device to the Bluetooth daemon.
+    ///
+    /// The battery level can be updated using the returned [ProvidedBattery].
+    /// Drop it to stop providing the battery.
+    pub async fn add(&self, battery: Battery) -> Result<ProvidedBattery> {
+        check_percentage(battery.percentage)?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let path = dbus::Path::new(format!("{}/battery{}", &self.name, id)).unwrap();
+        let reg = Arc::new(RegisteredBattery {
+            device_path: Device::dbus_path(&self.adapter_name, battery.device)?,
+            battery: Mutex::new(battery),
+        });
+
+        log::trace!("Publishing battery at {}", &path);
+        {
+            let mut cr = self.inner.crossroads.lock().await;
+            cr.insert(path.clone(), &[self.inner.battery_provider_token], reg.clone());
+        }
+        self.batteries.lock().unwrap().insert(path.clone());
+
+        let ia = ObjectManagerInterfacesAdded {
+            object: path.clone(),
+            interfaces: [(PROVIDER_INTERFACE.to_string(), reg.properties())].into_iter().collect(),
+        };
+        self.inner
+            .connection
+            .send(ia.to_emit_message(&self.name))
+            .map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?;
+
+        Ok(ProvidedBattery {
+            inner: self.inner.clone(),
+            provider: self.name.clone(),
+            path,
+            reg,
+            batteries: self.batteries.clone(),
+        })
+    }
+}
+
+impl Drop for BatteryProviderHandle {
+    fn drop(&mut self) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for BatteryProviderHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BatteryProviderHandle {{ {} }}", &self.name)
+    }
+}
+
+/// Battery of a remote device provided to the Bluetooth daemon.
+///
+/// Drop to stop providing the battery.
+pub struct ProvidedBattery {
+    inner: Arc<SessionInner>,
+    provider: dbus::Path<'static>,
+    path: dbus::Path<'static>,
+    reg: Arc<RegisteredBattery>,
+    batteries: Arc<Mutex<HashSet<dbus::Path<'static>>>>,
+}
+
+impl ProvidedBattery {
+    /// The provided battery.
+    pub fn battery(&self) -> Battery {
+        self.reg.battery.lock().unwrap().clone()
+    }
+
+    /// Updates the battery level in percent.
+    ///
+    /// Must be in the range 0 to 100.
+    pub fn set_percentage(&self, percentage: u8) -> Result<()> {
+        check_percentage(percentage)?;
+        self.reg.battery.lock().unwrap().percentage = percentage;
+
+        let mut changed = PropMap::new();
+        changed.insert("Percentage".to_string(), Variant(Box::new(percentage)));
+        self.emit_changed(changed, Vec::new())
+    }
+
+    /// Updates the description of where the battery information comes from.
+    pub fn set_source(&self, source: Option<String>) -> Result<()> {
+        self.reg.battery.lock().unwrap().source = source.clone();
+
+        let mut changed = PropMap::new();
+        let mut invalidated = Vec::new();
+        match source {
+            Some(source) => {
+                changed.insert("Source".to_string(), Variant(Box::new(source)));
+            }
+            None => invalidated.push("Source".to_string()),
+        }
+        self.emit_changed(changed, invalidated)
+    }
+
+    fn emit_changed(&self, changed_properties: PropMap, invalidated_properties: Vec<String>) -> Result<()> {
+        let ppc = PropertiesPropertiesChanged {
+            interface_name: PROVIDER_INTERFACE.to_string(),
+            changed_properties,
+            invalidated_properties,
+        };
+        self.inner
+            .connection
+            .send(ppc.to_emit_message(&self.path))
+            .map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?;
+        Ok(())
+    }
+}
+
+impl Drop for ProvidedBattery {
+    fn drop(&mut self) {
+        // The battery has already been unpublished if the provider is gone.
+        if !self.batteries.lock().unwrap().remove(&self.path) {
+            return;
+        }
+
+        let inner = self.inner.clone();
+        let provider = self.provider.clone();
+        let path = self.path.clone();
+        tokio::spawn(async move {
+            log::trace!("Unpublishing battery at {}", &path);
+            {
+                let mut cr = inner.crossroads.lock().await;
+                let _: Option<Arc<RegisteredBattery>> = cr.remove(&path);
+            }
+            let ir =
+                ObjectManagerInterfacesRemoved { object: path, interfaces: vec![PROVIDER_INTERFACE.to_string()] };
+            let _ = inner.connection.send(ir.to_emit_message(&provider));
+        });
+    }
+}
+
+impl fmt::Debug for ProvidedBattery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ProvidedBattery {{ {} }}", &self.path)
+    }
+}
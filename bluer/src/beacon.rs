@@ -0,0 +1,569 @@
+//! Proximity beacons.
+//!
+//! A [Beacon] is a broadcast advertisement in one of the common beacon formats:
+//! Apple iBeacon, Google Eddystone and AltBeacon.
+//! Beacons are encoded into an [AD structure](crate::ad::AdStructure) or, with the
+//! `bluetoothd` feature, into an [Advertisement](crate::adv::Advertisement) that
+//! can be sent using [Adapter::advertise](crate::Adapter::advertise).
+//!
+//! Received beacons are decoded from the manufacturer and service data of a device
+//! using [Beacon::decode] or from an
+//! [advertisement report](crate::AdvertisementReport) using [Beacon::from_report].
+//! The distance to a beacon can be estimated from the received signal strength
+//! using [Beacon::estimate_distance].
+//!
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    io::{Error, ErrorKind, Result},
+    time::Duration,
+};
+
+use crate::{ad::AdStructure, Uuid, UuidExt};
+
+/// Company identifier of Apple, used for iBeacon.
+pub const APPLE_COMPANY_ID: u16 = 0x004c;
+
+/// 16-bit service UUID of Eddystone.
+pub const EDDYSTONE_SERVICE_UUID: u16 = 0xfeaa;
+
+/// Path loss exponent used by [Beacon::estimate_distance].
+///
+/// This is the value for free space.
+pub const DEFAULT_PATH_LOSS_EXPONENT: f64 = 2.0;
+
+/// Signal loss between 0 m and 1 m in dB, used to convert Eddystone TX power.
+const LOSS_AT_1M: i8 = 41;
+
+const IBEACON_PREFIX: [u8; 2] = [0x02, 0x15];
+const ALTBEACON_CODE: [u8; 2] = [0xbe, 0xac];
+
+const EDDYSTONE_UID: u8 = 0x00;
+const EDDYSTONE_URL: u8 = 0x10;
+const EDDYSTONE_TLM: u8 = 0x20;
+const EDDYSTONE_EID: u8 = 0x30;
+
+/// Maximum length of an encoded Eddystone URL, excluding the scheme.
+const EDDYSTONE_URL_MAX_LEN: usize = 17;
+
+const EDDYSTONE_URL_SCHEMES: [&str; 4] = ["http://www.", "https://www.", "http://", "https://"];
+const EDDYSTONE_URL_EXPANSIONS: [&str; 14] = [
+    ".com/", ".org/", ".edu/", ".net/", ".info/", ".biz/", ".gov/", ".com", ".org", ".edu", ".net", ".info",
+    ".biz", ".gov",
+];
+
+fn invalid_input(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg)
+}
+
+/// Estimates the distance in meters to a transmitter from the received signal strength.
+///
+/// `measured_power` is the signal strength in dBm received at a distance of 1 m
+/// and `rssi` is the current signal strength in dBm.
+/// This uses the log-distance path loss model, with `path_loss_exponent` ranging from
+/// 2 in free space to about 4 indoors with obstacles.
+/// Since the signal strength fluctuates strongly, consider averaging the RSSI over several
+/// advertisements.
+pub fn estimate_distance(measured_power: i8, rssi: i16, path_loss_exponent: f64) -> f64 {
+    10f64.powf((measured_power as f64 - rssi as f64) / (10.0 * path_loss_exponent))
+}
+
+/// Apple iBeacon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IBeacon {
+    /// Proximity UUID identifying the beacons of an organization.
+    pub uuid: Uuid,
+    /// Major value, for example identifying a group of beacons.
+    pub major: u16,
+    /// Minor value, for example identifying an individual beacon.
+    pub minor: u16,
+    /// Signal strength in dBm received at a distance of 1 m.
+    pub measured_power: i8,
+}
+
+impl IBeacon {
+    fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        if company_id != APPLE_COMPANY_ID || data.len() != 23 || data[..2] != IBEACON_PREFIX {
+            return None;
+        }
+        Some(Self {
+            uuid: Uuid::from_bytes(data[2..18].try_into().unwrap()),
+            major: u16::from_be_bytes([data[18], data[19]]),
+            minor: u16::from_be_bytes([data[20], data[21]]),
+            measured_power: data[22] as i8,
+        })
+    }
+
+    fn manufacturer_data(&self) -> Vec<u8> {
+        let mut data = IBEACON_PREFIX.to_vec();
+        data.extend(self.uuid.as_bytes());
+        data.extend(self.major.to_be_bytes());
+        data.extend(self.minor.to_be_bytes());
+        data.push(self.measured_power as u8);
+        data
+    }
+}
+
+/// AltBeacon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AltBeacon {
+    /// Company identifier of the beacon manufacturer.
+    pub company_id: u16,
+    /// Beacon identifier.
+    ///
+    /// Commonly, the first 16 bytes identify the organization and
+    /// the remaining 4 bytes the individual beacon.
+    pub beacon_id: [u8; 20],
+    /// Signal strength in dBm received at a distance of 1 m.
+    pub reference_rssi: i8,
+    /// Reserved for use by the manufacturer.
+    pub reserved: u8,
+}
+
+impl AltBeacon {
+    fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        if data.len() != 24 || data[..2] != ALTBEACON_CODE {
+            return None;
+        }
+        Some(Self {
+            company_id,
+            beacon_id: data[2..22].try_into().unwrap(),
+            reference_rssi: data[22] as i8,
+            reserved: data[23],
+        })
+    }
+
+    fn manufacturer_data(&self) -> Vec<u8> {
+        let mut data = ALTBEACON_CODE.to_vec();
+        data.extend(self.beacon_id);
+        data.push(self.reference_rssi as u8);
+        data.push(self.reserved);
+        data
+    }
+}
+
+/// Eddystone telemetry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EddystoneTlm {
+    /// Battery voltage in mV.
+    ///
+    /// Zero if not supported.
+    pub battery_voltage: u16,
+    /// Temperature in units of 1/256 °C.
+    ///
+    /// `-32768` if not supported.
+    pub temperature: i16,
+    /// Number of advertisements sent since power-up or reboot.
+    pub advertising_count: u32,
+    /// Time since power-up or reboot, with a resolution of 0.1 s.
+    pub uptime: Duration,
+}
+
+impl EddystoneTlm {
+    /// Temperature in °C, if supported.
+    pub fn temperature_celsius(&self) -> Option<f32> {
+        match self.temperature {
+            i16::MIN => None,
+            t => Some(t as f32 / 256.0),
+        }
+    }
+}
+
+/// Eddystone frame.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Eddystone {
+    /// Unique beacon identifier.
+    Uid {
+        /// TX power in dBm, measured at a distance of 0 m.
+        tx_power: i8,
+        /// Namespace.
+        namespace: [u8; 10],
+        /// Instance within the namespace.
+        instance: [u8; 6],
+    },
+    /// Compressed URL.
+    Url {
+        /// TX power in dBm, measured at a distance of 0 m.
+        tx_power: i8,
+        /// URL.
+        url: String,
+    },
+    /// Unencrypted telemetry.
+    Tlm(EddystoneTlm),
+    /// Ephemeral identifier.
+    Eid {
+        /// TX power in dBm, measured at a distance of 0 m.
+        tx_power: i8,
+        /// Encrypted ephemeral identifier.
+        eid: [u8; 8],
+    },
+}
+
+impl Eddystone {
+    fn from_service_data(data: &[u8]) -> Option<Self> {
+        let (&frame_type, data) = data.split_first()?;
+        match frame_type {
+            EDDYSTONE_UID if data.len() == 17 || data.len() == 19 => Some(Self::Uid {
+                tx_power: data[0] as i8,
+                namespace: data[1..11].try_into().unwrap(),
+                instance: data[11..17].try_into().unwrap(),
+            }),
+            EDDYSTONE_URL if data.len() >= 2 => {
+                let mut url = EDDYSTONE_URL_SCHEMES.get(data[1] as usize)?.to_string();
+                for &b in &data[2..] {
+                    match EDDYSTONE_URL_EXPANSIONS.get(b as usize) {
+                        Some(expansion) => url.push_str(expansion),
+                        None if (0x21..0x7f).contains(&b) => url.push(b as char),
+                        None => return None,
+                    }
+                }
+                Some(Self::Url { tx_power: data[0] as i8, url })
+            }
+            EDDYSTONE_TLM if data.len() == 13 && data[0] == 0x00 => Some(Self::Tlm(EddystoneTlm {
+                battery_voltage: u16::from_be_bytes([data[1], data[2]]),
+                temperature: i16::from_be_bytes([data[3], data[4]]),
+                advertising_count: u32::from_be_bytes(data[5..9].try_into().unwrap()),
+                uptime: Duration::from_millis(u32::from_be_bytes(data[9..13].try_into().unwrap()) as u64 * 100),
+            })),
+            EDDYSTONE_EID if data.len() == 9 => {
+                Some(Self::Eid { tx_power: data[0] as i8, eid: data[1..9].try_into().unwrap() })
+            }
+            _ => None,
+        }
+    }
+
+    fn service_data(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        match self {
+            Self::Uid { tx_power, namespace, instance } => {
+                data.extend([EDDYSTONE_UID, *tx_power as u8]);
+                data.extend(namespace);
+                data.extend(instance);
+                data.extend([0, 0]);
+            }
+            Self::Url { tx_power, url } => {
+                data.extend([EDDYSTONE_URL, *tx_power as u8]);
+                let (scheme, mut rest) = EDDYSTONE_URL_SCHEMES
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, scheme)| url.strip_prefix(scheme).map(|rest| (i, rest)))
+                    .ok_or_else(|| invalid_input("URL scheme not supported by Eddystone"))?;
+                data.push(scheme as u8);
+                let start = data.len();
+                while let Some(c) = rest.chars().next() {
+                    match EDDYSTONE_URL_EXPANSIONS.iter().position(|e| rest.starts_with(e)) {
+                        Some(code) => {
+                            data.push(code as u8);
+                            rest = &rest[EDDYSTONE_URL_EXPANSIONS[code].len()..];
+                        }
+                        None if ('!'..='~').contains(&c) => {
+                            data.push(c as u8);
+                            rest = &rest[1..];
+                        }
+                        None => return Err(invalid_input("URL contains characters not supported by Eddystone")),
+                    }
+                }
+                if data.len() - start > EDDYSTONE_URL_MAX_LEN {
+                    return Err(invalid_input("encoded URL too long for Eddystone"));
+                }
+            }
+            Self::Tlm(tlm) => {
+                data.extend([EDDYSTONE_TLM, 0x00]);
+                data.extend(tlm.battery_voltage.to_be_bytes());
+                data.extend(tlm.temperature.to_be_bytes());
+                data.extend(tlm.advertising_count.to_be_bytes());
+                let uptime = (tlm.uptime.as_millis() / 100).min(u32::MAX as _) as u32;
+                data.extend(uptime.to_be_bytes());
+            }
+            Self::Eid { tx_power, eid } => {
+                data.extend([EDDYSTONE_EID, *tx_power as u8]);
+                data.extend(eid);
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// Proximity beacon.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Beacon {
+    /// Apple iBeacon.
+    IBeacon(IBeacon),
+    /// AltBeacon.
+    AltBeacon(AltBeacon),
+    /// Google Eddystone.
+    Eddystone(Eddystone),
+}
+
+impl Beacon {
+    /// Decodes a beacon from manufacturer specific data.
+    ///
+    /// Returns [None] if the data does not contain an iBeacon or AltBeacon.
+    pub fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        IBeacon::from_manufacturer_data(company_id, data)
+            .map(Self::IBeacon)
+            .or_else(|| AltBeacon::from_manufacturer_data(company_id, data).map(Self::AltBeacon))
+    }
+
+    /// Decodes a beacon from service data.
+    ///
+    /// Returns [None] if the data does not contain a supported Eddystone frame.
+    pub fn from_service_data(uuid: Uuid, data: &[u8]) -> Option<Self> {
+        match uuid.as_u16() {
+            Some(EDDYSTONE_SERVICE_UUID) => Eddystone::from_service_data(data).map(Self::Eddystone),
+            _ => None,
+        }
+    }
+
+    /// Decodes a beacon from an AD structure.
+    pub fn from_ad_structure(structure: &AdStructure) -> Option<Self> {
+        match structure {
+            AdStructure::ManufacturerSpecificData { company_id, data } => {
+                Self::from_manufacturer_data(*company_id, data)
+            }
+            AdStructure::ServiceData16 { uuid, data } => Self::from_service_data(Uuid::from_u16(*uuid), data),
+            _ => None,
+        }
+    }
+
+    /// Decodes all beacons contained in manufacturer and service data.
+    ///
+    /// The arguments are provided by [Device::manufacturer_data](crate::Device::manufacturer_data)
+    /// and [Device::service_data](crate::Device::service_data).
+    pub fn decode(manufacturer_data: &HashMap<u16, Vec<u8>>, service_data: &HashMap<Uuid, Vec<u8>>) -> Vec<Self> {
+        let mut beacons: Vec<_> = manufacturer_data
+            .iter()
+            .filter_map(|(company_id, data)| Self::from_manufacturer_data(*company_id, data))
+            .chain(service_data.iter().filter_map(|(uuid, data)| Self::from_service_data(*uuid, data)))
+            .collect();
+        beacons.sort();
+        beacons
+    }
+
+    /// Decodes all beacons contained in an advertisement report.
+    #[cfg(feature = "bluetoothd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+    pub fn from_report(report: &crate::AdvertisementReport) -> Vec<Self> {
+        Self::decode(&report.manufacturer_data, &report.service_data)
+    }
+
+    /// Encodes the beacon into an AD structure.
+    ///
+    /// Fails if an Eddystone URL cannot be encoded.
+    pub fn to_ad_structure(&self) -> Result<AdStructure> {
+        Ok(match self {
+            Self::IBeacon(beacon) => AdStructure::ManufacturerSpecificData {
+                company_id: APPLE_COMPANY_ID,
+                data: beacon.manufacturer_data(),
+            },
+            Self::AltBeacon(beacon) => AdStructure::ManufacturerSpecificData {
+                company_id: beacon.company_id,
+                data: beacon.manufacturer_data(),
+            },
+            Self::Eddystone(frame) => {
+                AdStructure::ServiceData16 { uuid: EDDYSTONE_SERVICE_UUID, data: frame.service_data()? }
+            }
+        })
+    }
+
+    /// Builds a broadcast advertisement of the beacon.
+    ///
+    /// Fails if an Eddystone URL cannot be encoded.
+    #[cfg(feature = "bluetoothd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+    pub fn to_advertisement(&self) -> Result<crate::adv::Advertisement> {
+        let mut adv =
+            crate::adv::Advertisement { advertisement_type: crate::adv::Type::Broadcast, ..Default::default() };
+        match self.to_ad_structure()? {
+            AdStructure::ManufacturerSpecificData { company_id, data } => {
+                adv.manufacturer_data.insert(company_id, data);
+            }
+            AdStructure::ServiceData16 { uuid, data } => {
+                adv.service_uuids.insert(Uuid::from_u16(uuid));
+                adv.service_data.insert(Uuid::from_u16(uuid), data);
+            }
+            _ => unreachable!(),
+        }
+        Ok(adv)
+    }
+
+    /// Signal strength in dBm that is received at a distance of 1 m.
+    ///
+    /// Returns [None] for Eddystone telemetry frames, since they contain no TX power.
+    pub fn measured_power(&self) -> Option<i8> {
+        match self {
+            Self::IBeacon(beacon) => Some(beacon.measured_power),
+            Self::AltBeacon(beacon) => Some(beacon.reference_rssi),
+            Self::Eddystone(Eddystone::Uid { tx_power, .. })
+            | Self::Eddystone(Eddystone::Url { tx_power, .. })
+            | Self::Eddystone(Eddystone::Eid { tx_power, .. }) => Some(tx_power.saturating_sub(LOSS_AT_1M)),
+            Self::Eddystone(Eddystone::Tlm(_)) => None,
+        }
+    }
+
+    /// Estimates the distance in meters to the beacon from the received signal strength `rssi` in dBm.
+    ///
+    /// This uses the [measured power](Self::measured_power) of the beacon and
+    /// the [DEFAULT_PATH_LOSS_EXPONENT].
+    /// Use [estimate_distance] to specify a path loss exponent for the environment.
+    pub fn estimate_distance(&self, rssi: i16) -> Option<f64> {
+        self.measured_power().map(|power| estimate_distance(power, rssi, DEFAULT_PATH_LOSS_EXPONENT))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use super::{AltBeacon, Beacon, Eddystone, EddystoneTlm, IBeacon, APPLE_COMPANY_ID, EDDYSTONE_SERVICE_UUID};
+    use crate::{ad::AdStructure, Uuid, UuidExt};
+
+    fn eddystone(data: &[u8]) -> Option<Beacon> {
+        Beacon::from_service_data(Uuid::from_u16(EDDYSTONE_SERVICE_UUID), data)
+    }
+
+    #[test]
+    fn ibeacon() {
+        let data = [
+            0x02, 0x15, 0xe2, 0xc5, 0x6d, 0xb5, 0xdf, 0xfb, 0x48, 0xd2, 0xb0, 0x60, 0xd0, 0xf5, 0xa7, 0x10, 0x96,
+            0xe0, 0x00, 0x01, 0x00, 0x02, 0xc5,
+        ];
+        let beacon = Beacon::IBeacon(IBeacon {
+            uuid: Uuid::from_u128(0xe2c56db5_dffb_48d2_b060_d0f5a71096e0),
+            major: 1,
+            minor: 2,
+            measured_power: -59,
+        });
+
+        assert_eq!(Beacon::from_manufacturer_data(APPLE_COMPANY_ID, &data), Some(beacon.clone()));
+        assert_eq!(
+            beacon.to_ad_structure().unwrap(),
+            AdStructure::ManufacturerSpecificData { company_id: APPLE_COMPANY_ID, data: data.to_vec() }
+        );
+        assert_eq!(beacon.measured_power(), Some(-59));
+        assert_eq!(beacon.estimate_distance(-59), Some(1.0));
+
+        assert_eq!(Beacon::from_manufacturer_data(0x0059, &data), None);
+        assert_eq!(Beacon::from_manufacturer_data(APPLE_COMPANY_ID, &data[..22]), None);
+    }
+
+    #[test]
+    fn altbeacon() {
+        let beacon = Beacon::AltBeacon(AltBeacon {
+            company_id: 0x0118,
+            beacon_id: [0x11; 20],
+            reference_rssi: -60,
+            reserved: 0x01,
+        });
+        let (company_id, data) = match beacon.to_ad_structure().unwrap() {
+            AdStructure::ManufacturerSpecificData { company_id, data } => (company_id, data),
+            other => panic!("wrong AD structure: {:?}", other),
+        };
+        assert_eq!(company_id, 0x0118);
+        assert_eq!(data[..2], [0xbe, 0xac]);
+        assert_eq!(data.len(), 24);
+        assert_eq!(Beacon::from_manufacturer_data(company_id, &data), Some(beacon));
+    }
+
+    #[test]
+    fn eddystone_uid() {
+        let mut data = vec![0x00, 0xee];
+        data.extend([0x01; 10]);
+        data.extend([0x02; 6]);
+        data.extend([0x00, 0x00]);
+        let beacon =
+            Beacon::Eddystone(Eddystone::Uid { tx_power: -18, namespace: [0x01; 10], instance: [0x02; 6] });
+
+        assert_eq!(eddystone(&data), Some(beacon.clone()));
+        assert_eq!(eddystone(&data[..18]), Some(beacon.clone()));
+        assert_eq!(
+            beacon.to_ad_structure().unwrap(),
+            AdStructure::ServiceData16 { uuid: EDDYSTONE_SERVICE_UUID, data }
+        );
+        assert_eq!(beacon.measured_power(), Some(-59));
+    }
+
+    #[test]
+    fn eddystone_url() {
+        let data = [0x10, 0xeb, 0x01, b'g', b'o', b'o', b'g', b'l', b'e', 0x07];
+        let beacon = Beacon::Eddystone(Eddystone::Url { tx_power: -21, url: "https://www.google.com".into() });
+
+        assert_eq!(eddystone(&data), Some(beacon.clone()));
+        assert_eq!(
+            beacon.to_ad_structure().unwrap(),
+            AdStructure::ServiceData16 { uuid: EDDYSTONE_SERVICE_UUID, data: data.to_vec() }
+        );
+
+        let beacon = Beacon::Eddystone(Eddystone::Url { tx_power: 0, url: "http://a.org/b.info".into() });
+        let data = match beacon.to_ad_structure().unwrap() {
+            AdStructure::ServiceData16 { data, .. } => data,
+            other => panic!("wrong AD structure: {:?}", other),
+        };
+        assert_eq!(data, [0x10, 0x00, 0x02, b'a', 0x01, b'b', 0x0b]);
+        assert_eq!(eddystone(&data), Some(beacon));
+    }
+
+    #[test]
+    fn eddystone_url_invalid() {
+        for url in ["ftp://example.com", "https://exa mple.com", "https://www.abcdefghijklmnopqr.com/"] {
+            let beacon = Beacon::Eddystone(Eddystone::Url { tx_power: 0, url: url.into() });
+            assert!(beacon.to_ad_structure().is_err(), "{}", url);
+        }
+
+        let beacon =
+            Beacon::Eddystone(Eddystone::Url { tx_power: 0, url: "https://www.abcdefghijklmnop.com/".into() });
+        assert!(beacon.to_ad_structure().is_ok());
+
+        assert_eq!(eddystone(&[0x10, 0x00, 0x04, b'a']), None);
+        assert_eq!(eddystone(&[0x10, 0x00, 0x00, 0x20]), None);
+        assert_eq!(eddystone(&[0x10, 0x00]), None);
+    }
+
+    #[test]
+    fn eddystone_tlm() {
+        let data = [0x20, 0x00, 0x0b, 0xb8, 0x19, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x64];
+        let tlm = EddystoneTlm {
+            battery_voltage: 3000,
+            temperature: 0x1980,
+            advertising_count: 256,
+            uptime: Duration::from_secs(10),
+        };
+        let beacon = Beacon::Eddystone(Eddystone::Tlm(tlm));
+
+        assert_eq!(eddystone(&data), Some(beacon.clone()));
+        assert_eq!(
+            beacon.to_ad_structure().unwrap(),
+            AdStructure::ServiceData16 { uuid: EDDYSTONE_SERVICE_UUID, data: data.to_vec() }
+        );
+        assert_eq!(tlm.temperature_celsius(), Some(25.5));
+        assert_eq!(EddystoneTlm { temperature: i16::MIN, ..tlm }.temperature_celsius(), None);
+        assert_eq!(beacon.measured_power(), None);
+        assert_eq!(beacon.estimate_distance(-60), None);
+
+        let mut unsupported_version = data;
+        unsupported_version[1] = 0x01;
+        assert_eq!(eddystone(&unsupported_version), None);
+        assert_eq!(eddystone(&data[..13]), None);
+    }
+
+    #[test]
+    fn decode() {
+        let manufacturer_data = HashMap::from([(0x0059, vec![0x01, 0x02])]);
+        let service_data = HashMap::from([
+            (Uuid::from_u16(EDDYSTONE_SERVICE_UUID), vec![0x30, 0xee, 1, 2, 3, 4, 5, 6, 7, 8]),
+            (Uuid::from_u16(0x180f), vec![0x30, 0xee, 1, 2, 3, 4, 5, 6, 7, 8]),
+        ]);
+        assert_eq!(
+            Beacon::decode(&manufacturer_data, &service_data),
+            [Beacon::Eddystone(Eddystone::Eid { tx_power: -18, eid: [1, 2, 3, 4, 5, 6, 7, 8] })]
+        );
+    }
+}
@@ -0,0 +1,539 @@
+//! Capture of HCI traffic into btsnoop and pcapng files.
+//!
+//! A [Capture] receives the HCI traffic of all controllers from the
+//! [monitor channel](crate::hci::Channel::Monitor) as [Record]s,
+//! which carry the reception time recorded by the kernel.
+//! Records can be
+//!
+//!   * written to any [std::io::Write] using a [Writer],
+//!   * written to a file that is rotated when it grows too large using a [FileWriter], or
+//!   * kept in memory using a [RingBuffer] that retains the most recent traffic
+//!     and can be dumped on demand.
+//!
+//! Both the [btsnoop](Format::Btsnoop) format used by `btmon` and
+//! the [pcapng](Format::Pcapng) format used by Wireshark are supported.
+//!
+//! Opening the monitor channel requires the `CAP_NET_RAW` capability.
+//!
+
+use crate::hci::{self, DevIndex, MonitorEvent, MonitorPacket};
+use futures::{ready, Stream};
+use std::{
+    collections::VecDeque,
+    convert::TryInto,
+    fs::{self, File},
+    io::{BufWriter, Error, ErrorKind, Result, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Difference between the btsnoop epoch (0 AD) and the Unix epoch in microseconds.
+const BTSNOOP_EPOCH_DELTA: u64 = 0x00dc_ddb3_0f2f_8000;
+
+/// btsnoop datalink type of the Linux monitor format.
+const BTSNOOP_DATALINK_MONITOR: u32 = 2001;
+
+/// pcapng link type `LINKTYPE_BLUETOOTH_LINUX_MONITOR`.
+const PCAPNG_LINKTYPE_MONITOR: u16 = 254;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+/// Capture file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    /// btsnoop format with the Linux monitor datalink type.
+    ///
+    /// This can be read by `btmon -r`.
+    Btsnoop,
+    /// pcapng format with the `LINKTYPE_BLUETOOTH_LINUX_MONITOR` link type.
+    ///
+    /// This can be read by Wireshark.
+    Pcapng,
+}
+
+impl Format {
+    /// Usual file name extension, without the leading dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Btsnoop => "btsnoop",
+            Self::Pcapng => "pcapng",
+        }
+    }
+}
+
+/// HCI traffic captured from the monitor channel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Record {
+    /// Reception time.
+    pub timestamp: SystemTime,
+    /// Index of the controller the record pertains to.
+    ///
+    /// This is [hci::DEV_NONE] for records not related to a controller.
+    pub index: DevIndex,
+    /// Monitor opcode.
+    pub opcode: u16,
+    /// Data following the monitor header.
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// Parses a packet starting with the monitor header.
+    pub fn from_bytes(buf: &[u8], timestamp: SystemTime) -> Result<Self> {
+        let (opcode, index, data) = hci::split_monitor_packet(buf)?;
+        Ok(Self { timestamp, index, opcode, data: data.to_vec() })
+    }
+
+    /// Parses the record into a monitor packet.
+    pub fn to_packet(&self) -> Result<MonitorPacket> {
+        Ok(MonitorPacket { index: self.index, event: MonitorEvent::from_bytes(self.opcode, &self.data)? })
+    }
+
+    /// Microseconds since the Unix epoch.
+    fn timestamp_us(&self) -> u64 {
+        self.timestamp.duration_since(UNIX_EPOCH).map(|d| d.as_micros() as u64).unwrap_or_default()
+    }
+
+    /// Size of the record when written in the specified format.
+    fn encoded_len(&self, format: Format) -> usize {
+        match format {
+            Format::Btsnoop => 24 + self.data.len(),
+            Format::Pcapng => 32 + pad4(4 + self.data.len()),
+        }
+    }
+}
+
+fn pad4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Receives HCI traffic of all controllers from the monitor channel.
+#[derive(Debug)]
+pub struct Capture {
+    socket: hci::Socket,
+    terminated: bool,
+}
+
+impl Capture {
+    /// Opens the monitor channel and enables kernel timestamps.
+    ///
+    /// Upon opening, the kernel sends records describing each existing controller,
+    /// so that captures are self-contained.
+    pub async fn open() -> Result<Self> {
+        let socket = hci::Socket::new()?;
+        socket.bind(hci::SocketAddr::monitor())?;
+        socket.set_timestamp(true)?;
+        Ok(Self { socket, terminated: false })
+    }
+
+    /// Receives a record.
+    pub async fn recv(&self) -> Result<Record> {
+        futures::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Attempts to receive a record.
+    pub fn poll_recv(&self, cx: &mut Context) -> Poll<Result<Record>> {
+        let (buf, timestamp) = ready!(self.poll_recv_buf(cx))?;
+        Poll::Ready(Record::from_bytes(&buf, timestamp))
+    }
+
+    fn poll_recv_buf(&self, cx: &mut Context) -> Poll<Result<(Vec<u8>, SystemTime)>> {
+        let (buf, timestamp) = ready!(self.socket.poll_recv_buf_timestamped(cx))?;
+        Poll::Ready(Ok((buf, timestamp.unwrap_or_else(SystemTime::now))))
+    }
+}
+
+/// Yields received records.
+///
+/// A malformed packet is yielded as an error and receiving continues.
+/// The stream ends after the socket fails.
+impl Stream for Capture {
+    type Item = Result<Record>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.terminated {
+            return Poll::Ready(None);
+        }
+        match ready!(self.poll_recv_buf(cx)) {
+            Ok((buf, timestamp)) => Poll::Ready(Some(Record::from_bytes(&buf, timestamp))),
+            Err(err) => {
+                self.terminated = true;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+impl AsRef<hci::Socket> for Capture {
+    fn as_ref(&self) -> &hci::Socket {
+        &self.socket
+    }
+}
+
+/// Writes records in a capture file format.
+#[derive(Debug)]
+pub struct Writer<W> {
+    inner: W,
+    format: Format,
+    written: u64,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new writer and writes the file header.
+    pub fn new(inner: W, format: Format) -> Result<Self> {
+        let mut this = Self { inner, format, written: 0 };
+        this.write_header()?;
+        Ok(this)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.inner.write_all(buf)?;
+        self.written += buf.len() as u64;
+        Ok(())
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let mut buf = Vec::new();
+        match self.format {
+            Format::Btsnoop => {
+                buf.extend_from_slice(b"btsnoop\0");
+                buf.extend_from_slice(&1u32.to_be_bytes());
+                buf.extend_from_slice(&BTSNOOP_DATALINK_MONITOR.to_be_bytes());
+            }
+            Format::Pcapng => {
+                buf.extend_from_slice(&PCAPNG_SECTION_HEADER.to_le_bytes());
+                buf.extend_from_slice(&28u32.to_le_bytes());
+                buf.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+                buf.extend_from_slice(&1u16.to_le_bytes());
+                buf.extend_from_slice(&0u16.to_le_bytes());
+                buf.extend_from_slice(&(-1i64).to_le_bytes());
+                buf.extend_from_slice(&28u32.to_le_bytes());
+
+                buf.extend_from_slice(&PCAPNG_INTERFACE_DESCRIPTION.to_le_bytes());
+                buf.extend_from_slice(&20u32.to_le_bytes());
+                buf.extend_from_slice(&PCAPNG_LINKTYPE_MONITOR.to_le_bytes());
+                buf.extend_from_slice(&0u16.to_le_bytes());
+                buf.extend_from_slice(&0u32.to_le_bytes());
+                buf.extend_from_slice(&20u32.to_le_bytes());
+            }
+        }
+        self.write_all(&buf)
+    }
+
+    /// Writes a record.
+    pub fn write(&mut self, record: &Record) -> Result<()> {
+        let len: u32 =
+            record.data.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "record too long"))?;
+        let ts = record.timestamp_us();
+
+        let mut buf = Vec::with_capacity(record.encoded_len(self.format));
+        match self.format {
+            Format::Btsnoop => {
+                let flags = (u32::from(record.index) << 16) | u32::from(record.opcode);
+                buf.extend_from_slice(&len.to_be_bytes());
+                buf.extend_from_slice(&len.to_be_bytes());
+                buf.extend_from_slice(&flags.to_be_bytes());
+                buf.extend_from_slice(&0u32.to_be_bytes());
+                buf.extend_from_slice(&(ts + BTSNOOP_EPOCH_DELTA).to_be_bytes());
+                buf.extend_from_slice(&record.data);
+            }
+            Format::Pcapng => {
+                let captured = len + 4;
+                let block_len = record.encoded_len(self.format) as u32;
+                buf.extend_from_slice(&PCAPNG_ENHANCED_PACKET.to_le_bytes());
+                buf.extend_from_slice(&block_len.to_le_bytes());
+                buf.extend_from_slice(&0u32.to_le_bytes());
+                buf.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+                buf.extend_from_slice(&(ts as u32).to_le_bytes());
+                buf.extend_from_slice(&captured.to_le_bytes());
+                buf.extend_from_slice(&captured.to_le_bytes());
+                buf.extend_from_slice(&record.index.to_be_bytes());
+                buf.extend_from_slice(&record.opcode.to_be_bytes());
+                buf.extend_from_slice(&record.data);
+                buf.resize(buf.len() + pad4(captured as usize) - captured as usize, 0);
+                buf.extend_from_slice(&block_len.to_le_bytes());
+            }
+        }
+        self.write_all(&buf)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    /// Capture file format.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Number of bytes written, including the file header.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Consumes this writer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Rotation policy of a [FileWriter].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rotation {
+    /// Size in bytes after which the capture file is rotated.
+    pub max_size: u64,
+    /// Number of rotated files to keep.
+    ///
+    /// Rotated files are named by appending `.1`, `.2`, ... to the path,
+    /// with `.1` being the most recent.
+    /// The oldest file is deleted when this number is exceeded.
+    pub max_files: usize,
+}
+
+/// Writes records into a capture file, optionally rotating it.
+#[derive(Debug)]
+pub struct FileWriter {
+    path: PathBuf,
+    rotation: Option<Rotation>,
+    writer: Writer<BufWriter<File>>,
+}
+
+impl FileWriter {
+    /// Creates the capture file, replacing an existing file.
+    pub fn create(path: impl AsRef<Path>, format: Format, rotation: Option<Rotation>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let writer = Writer::new(BufWriter::new(File::create(&path)?), format)?;
+        Ok(Self { path, rotation, writer })
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    fn rotate(&mut self, max_files: usize) -> Result<()> {
+        self.writer.flush()?;
+
+        if max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..max_files).rev() {
+                match fs::rename(self.rotated_path(n), self.rotated_path(n + 1)) {
+                    Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+                    _ => (),
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.writer = Writer::new(BufWriter::new(File::create(&self.path)?), self.writer.format())?;
+        Ok(())
+    }
+
+    /// Writes a record, rotating the file beforehand if it would exceed the maximum size.
+    pub fn write(&mut self, record: &Record) -> Result<()> {
+        if let Some(Rotation { max_size, max_files }) = self.rotation {
+            let size = self.writer.written() + record.encoded_len(self.writer.format()) as u64;
+            if size > max_size && self.writer.written() > header_len(self.writer.format()) {
+                self.rotate(max_files)?;
+            }
+        }
+        self.writer.write(record)
+    }
+
+    /// Flushes buffered records to the file.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+
+    /// Path of the current capture file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn header_len(format: Format) -> u64 {
+    match format {
+        Format::Btsnoop => 16,
+        Format::Pcapng => 48,
+    }
+}
+
+/// Keeps the most recent records in memory.
+///
+/// When the size limit is reached the oldest records are discarded.
+#[derive(Debug, Clone)]
+pub struct RingBuffer {
+    records: VecDeque<Record>,
+    size: usize,
+    max_size: usize,
+}
+
+impl RingBuffer {
+    /// Creates a ring buffer retaining records with a total data size of up to `max_size` bytes.
+    pub fn new(max_size: usize) -> Self {
+        Self { records: VecDeque::new(), size: 0, max_size }
+    }
+
+    /// Adds a record, discarding the oldest records as necessary.
+    ///
+    /// A record larger than the size limit is not retained.
+    pub fn push(&mut self, record: Record) {
+        self.size += record.data.len();
+        self.records.push_back(record);
+        while self.size > self.max_size {
+            match self.records.pop_front() {
+                Some(old) => self.size -= old.data.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Number of retained records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no records are retained.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Total data size of the retained records in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Iterates over the retained records, from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = &Record> {
+        self.records.iter()
+    }
+
+    /// Removes all records.
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.size = 0;
+    }
+
+    /// Writes all retained records in the specified format.
+    ///
+    /// The records are kept in the buffer.
+    pub fn dump<W: Write>(&self, inner: W, format: Format) -> Result<W> {
+        let mut writer = Writer::new(inner, format)?;
+        for record in &self.records {
+            writer.write(record)?;
+        }
+        writer.flush()?;
+        Ok(writer.into_inner())
+    }
+
+    /// Writes all retained records into a new capture file.
+    pub fn dump_to_file(&self, path: impl AsRef<Path>, format: Format) -> Result<()> {
+        self.dump(BufWriter::new(File::create(path)?), format)?;
+        Ok(())
+    }
+}
+
+impl Extend<Record> for RingBuffer {
+    fn extend<T: IntoIterator<Item = Record>>(&mut self, iter: T) {
+        for record in iter {
+            self.push(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{Format, Record, RingBuffer, Writer};
+    use crate::hci::{Event, MonitorEvent, Opcode};
+
+    fn record(data: &[u8]) -> Record {
+        Record { timestamp: UNIX_EPOCH + Duration::from_secs(1), index: 1, opcode: 0x0003, data: data.to_vec() }
+    }
+
+    #[test]
+    fn record_from_bytes() {
+        let buf = [0x03, 0x00, 0x01, 0x00, 0x07, 0x00, 0x0e, 0x05, 0x01, 0x09, 0x10, 0x00, 0xff];
+        let record = Record::from_bytes(&buf, UNIX_EPOCH + Duration::from_secs(1)).unwrap();
+        assert_eq!(record, self::record(&buf[6..]));
+        assert_eq!(
+            record.to_packet().unwrap().event,
+            MonitorEvent::Event(Event::CommandComplete {
+                num_packets: 1,
+                opcode: Opcode::READ_BD_ADDR,
+                return_params: vec![0x00, 0xff],
+            })
+        );
+        assert!(Record::from_bytes(&buf[..10], UNIX_EPOCH).is_err());
+    }
+
+    #[test]
+    fn btsnoop() {
+        let mut writer = Writer::new(Vec::new(), Format::Btsnoop).unwrap();
+        assert_eq!(writer.get_ref(), b"btsnoop\0\x00\x00\x00\x01\x00\x00\x07\xd1");
+        writer.write(&record(&[0x0e, 0x01, 0x00])).unwrap();
+        assert_eq!(writer.written(), 16 + 24 + 3);
+        assert_eq!(
+            &writer.into_inner()[16..],
+            [
+                0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x03, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0xdc, 0xdd, 0xb3, 0x0f, 0x3e, 0xc2, 0x40, 0x0e, 0x01, 0x00
+            ]
+        );
+    }
+
+    #[test]
+    fn pcapng() {
+        let mut writer = Writer::new(Vec::new(), Format::Pcapng).unwrap();
+        assert_eq!(
+            writer.get_ref(),
+            &[
+                0x0a, 0x0d, 0x0d, 0x0a, 0x1c, 0x00, 0x00, 0x00, 0x4d, 0x3c, 0x2b, 0x1a, 0x01, 0x00, 0x00, 0x00,
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x1c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+                0x14, 0x00, 0x00, 0x00, 0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,
+            ]
+        );
+        writer.write(&record(&[0x0e, 0x01, 0x00])).unwrap();
+        assert_eq!(writer.written(), 48 + 40);
+        assert_eq!(
+            &writer.into_inner()[48..],
+            [
+                0x06, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x40, 0x42, 0x0f, 0x00, 0x07, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x03,
+                0x0e, 0x01, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00,
+            ]
+        );
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let mut ring = RingBuffer::new(8);
+        ring.extend([record(&[1; 3]), record(&[2; 3]), record(&[3; 3])]);
+        assert_eq!(ring.len(), 2);
+        assert_eq!(ring.size(), 6);
+        assert_eq!(ring.iter().next().unwrap().data, [2; 3]);
+
+        ring.push(record(&[4; 9]));
+        assert!(ring.is_empty());
+        assert_eq!(ring.size(), 0);
+
+        ring.push(record(&[5; 3]));
+        let buf = ring.dump(Vec::new(), Format::Btsnoop).unwrap();
+        assert_eq!(buf.len(), 16 + 24 + 3);
+    }
+}
@@ -1,7 +1,7 @@
 //! Remote Bluetooth device.
 
 use dbus::{
-    arg::{RefArg, Variant},
+    arg::{PropMap, RefArg, Variant},
     nonblock::{Proxy, SyncConnection},
     Path,
 };
@@ -10,15 +10,16 @@ use std::{
     collections::{HashMap, HashSet},
     fmt,
     sync::Arc,
+    time::SystemTime,
 };
 use tokio::{sync::oneshot, time::sleep};
 use uuid::Uuid;
 
 use crate::{
-    all_dbus_objects,
+    adapter, battery, dbus_objects_with_interface,
     gatt::{self, remote::Service, SERVICE_INTERFACE},
-    Adapter, Address, AddressType, Error, ErrorKind, Event, InternalErrorKind, Modalias, Result, SessionInner,
-    SERVICE_NAME, TIMEOUT,
+    media, network, Adapter, Address, AddressType, Error, ErrorKind, Event, InternalErrorKind, Modalias, Result,
+    SessionInner, TIMEOUT,
 };
 
 pub(crate) const INTERFACE: &str = "org.bluez.Device1";
@@ -46,7 +47,7 @@ impl Device {
     }
 
     fn proxy(&self) -> Proxy<'_, &SyncConnection> {
-        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
     }
 
     pub(crate) fn dbus_path(adapter_name: &str, address: Address) -> Result<Path<'static>> {
@@ -142,12 +143,10 @@ impl Device {
         self.wait_for_services_resolved().await?;
 
         let mut services = Vec::new();
-        for (path, interfaces) in all_dbus_objects(&self.inner.connection).await? {
+        for path in dbus_objects_with_interface(&self.inner, SERVICE_INTERFACE).await? {
             match Service::parse_dbus_path(&path) {
                 Some((adapter, device_address, id))
-                    if adapter == *self.adapter_name
-                        && device_address == self.address
-                        && interfaces.contains_key(SERVICE_INTERFACE) =>
+                    if adapter == *self.adapter_name && device_address == self.address =>
                 {
                     services.push(self.service(id).await?);
                 }
@@ -163,6 +162,44 @@ impl Device {
         gatt::remote::Service::new(self.inner.clone(), self.adapter_name.clone(), self.address, service_id)
     }
 
+    /// Media transports to the remote device.
+    ///
+    /// A transport is created when a media endpoint of the remote device
+    /// has been configured to stream with a local endpoint.
+    pub async fn media_transports(&self) -> Result<Vec<media::Transport>> {
+        let mut transports = Vec::new();
+        for path in dbus_objects_with_interface(&self.inner, media::TRANSPORT_INTERFACE).await? {
+            match Self::parse_dbus_path_prefix(&path) {
+                Some(((adapter, address), p))
+                    if adapter == *self.adapter_name && address == self.address && p.starts_with('/') =>
+                {
+                    transports.push(media::Transport::new(self.inner.clone(), path)?);
+                }
+                _ => (),
+            }
+        }
+        Ok(transports)
+    }
+
+    /// Media players of the remote device.
+    ///
+    /// Media players are available while the remote device is connected
+    /// using a profile that supports remote control, such as AVRCP.
+    pub async fn media_players(&self) -> Result<Vec<media::player::Player>> {
+        let mut players = Vec::new();
+        for path in dbus_objects_with_interface(&self.inner, media::player::PLAYER_INTERFACE).await? {
+            match Self::parse_dbus_path_prefix(&path) {
+                Some(((adapter, address), p))
+                    if adapter == *self.adapter_name && address == self.address && p.starts_with('/') =>
+                {
+                    players.push(media::player::Player::new(self.inner.clone(), path)?);
+                }
+                _ => (),
+            }
+        }
+        Ok(players)
+    }
+
     dbus_interface!();
     dbus_default_interface!(INTERFACE);
 
@@ -256,10 +293,10 @@ impl Device {
     pub async fn pair(&self) -> Result<()> {
         let (done_tx, done_rx) = oneshot::channel();
         let dbus_path = self.dbus_path.clone();
-        let connection = self.inner.connection.clone();
+        let inner = self.inner.clone();
         tokio::spawn(async move {
             if done_rx.await.is_err() {
-                let proxy = Proxy::new(SERVICE_NAME, dbus_path, TIMEOUT, &*connection);
+                let proxy = Proxy::new(&*inner.service_name, dbus_path, TIMEOUT, &*inner.connection);
                 let _: std::result::Result<(), dbus::Error> =
                     proxy.method_call(INTERFACE, "CancelPairing", ()).await;
             }
@@ -269,6 +306,17 @@ impl Device {
         let _ = done_tx.send(());
         result
     }
+
+    /// Connects to the personal area network (PAN) service of the device.
+    ///
+    /// `role` specifies the role of the remote device, for example [Nap](network::Role::Nap)
+    /// to use it as a network access point.
+    /// The returned [NetworkHandle](network::NetworkHandle) provides the name of the
+    /// local network interface.
+    /// Drop it to disconnect.
+    pub async fn connect_network(&self, role: network::Role) -> Result<network::NetworkHandle> {
+        network::NetworkHandle::connect(self.inner.clone(), &self.adapter_name, self.address, role).await
+    }
 }
 
 define_properties!(
@@ -518,6 +566,44 @@ define_properties!(
                 mt
             }),
         );
+
+        /// Battery level of the remote device in percent.
+        ///
+        /// Only present if the battery level is known, either because the
+        /// device implements the Battery Service or from a battery provider.
+        property(
+            BatteryPercentage, u8,
+            dbus: (battery::INTERFACE, "Percentage", u8, OPTIONAL),
+            get: (battery_percentage, v => {v.to_owned()}),
+        );
+
+        /// Describes where the battery level information comes from.
+        ///
+        /// For example `HFP 1.7`, `HID` or the UUID of the profile used.
+        property(
+            BatterySource, String,
+            dbus: (battery::INTERFACE, "Source", String, OPTIONAL),
+            get: (battery_source, v => {v.to_owned()}),
+        );
+
+        /// Name of the local network interface of the personal area network (PAN)
+        /// connection to the device.
+        ///
+        /// Only present while connected.
+        property(
+            NetworkInterface, String,
+            dbus: (network::INTERFACE, "Interface", String, OPTIONAL),
+            get: (network_interface, v => {v.to_owned()}),
+        );
+
+        /// Indicates whether the device is restricted by the
+        /// [service allow list](Adapter::service_allow_list) of the adapter,
+        /// i.e. whether it offers services that are not allowed to be used.
+        property(
+            IsAffectedByPolicy, bool,
+            dbus: (adapter::ADMIN_POLICY_STATUS_INTERFACE, "AffectedByPolicy", bool, OPTIONAL),
+            get: (is_affected_by_policy, v => {v.to_owned()}),
+        );
     }
 );
 
@@ -529,3 +615,98 @@ pub enum DeviceEvent {
     /// Property changed.
     PropertyChanged(DeviceProperty),
 }
+
+/// Advertisement data received from a remote device during discovery.
+///
+/// Only the fields that were received within the advertisement report
+/// are set; all other fields are empty.
+///
+/// Use [Adapter::advertisement_reports] to obtain a stream of advertisement reports.
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdvertisementReport {
+    /// Address of the remote device.
+    pub address: Address,
+    /// Address type of the remote device.
+    pub address_type: Option<AddressType>,
+    /// Received Signal Strength Indicator.
+    pub rssi: Option<i16>,
+    /// Advertised transmitted power level.
+    pub tx_power: Option<i16>,
+    /// Manufacturer specific advertisement data by 16 bits manufacturer id.
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    /// Service advertisement data by service UUID.
+    pub service_data: HashMap<Uuid, Vec<u8>>,
+    /// Advertising flags.
+    pub advertising_flags: Vec<u8>,
+    /// Advertising data by advertising data type.
+    ///
+    /// Only types considered safe to be handled by
+    /// application are exposed.
+    pub advertising_data: HashMap<u8, Vec<u8>>,
+    /// Time the advertisement report was received.
+    pub timestamp: SystemTime,
+}
+
+impl AdvertisementReport {
+    /// Decodes [advertising_data](Self::advertising_data) into AD structures.
+    pub fn ad_structures(&self) -> std::io::Result<Vec<crate::ad::AdStructure>> {
+        crate::ad::parse_map(&self.advertising_data)
+    }
+
+    /// Builds an advertisement report from the changed properties of a device.
+    ///
+    /// Returns [None] if no advertisement data was changed.
+    pub(crate) fn from_prop_map(address: Address, changed: PropMap) -> Option<Self> {
+        let mut report = Self {
+            address,
+            address_type: None,
+            rssi: None,
+            tx_power: None,
+            manufacturer_data: HashMap::new(),
+            service_data: HashMap::new(),
+            advertising_flags: Vec::new(),
+            advertising_data: HashMap::new(),
+            timestamp: SystemTime::now(),
+        };
+
+        let mut advertised = false;
+        for prop in DeviceProperty::from_prop_map(changed) {
+            match prop {
+                DeviceProperty::AddressType(v) => report.address_type = Some(v),
+                DeviceProperty::Rssi(v) => {
+                    report.rssi = Some(v);
+                    advertised = true;
+                }
+                DeviceProperty::TxPower(v) => {
+                    report.tx_power = Some(v);
+                    advertised = true;
+                }
+                DeviceProperty::ManufacturerData(v) => {
+                    report.manufacturer_data = v;
+                    advertised = true;
+                }
+                DeviceProperty::ServiceData(v) => {
+                    report.service_data = v;
+                    advertised = true;
+                }
+                DeviceProperty::AdvertisingFlags(v) => {
+                    report.advertising_flags = v;
+                    advertised = true;
+                }
+                DeviceProperty::AdvertisingData(v) => {
+                    report.advertising_data = v;
+                    advertised = true;
+                }
+                _ => (),
+            }
+        }
+
+        if advertised {
+            Some(report)
+        } else {
+            None
+        }
+    }
+}
@@ -30,7 +30,7 @@ use super::{
 };
 use crate::{
     method_call, parent_path, Adapter, Address, DbusResult, Device, Error, ErrorKind, Result, SessionInner,
-    ERR_PREFIX, SERVICE_NAME, TIMEOUT,
+    ERR_PREFIX, TIMEOUT,
 };
 
 pub(crate) const MANAGER_INTERFACE: &str = "org.bluez.GattManager1";
@@ -1284,7 +1284,7 @@ impl RegisteredDescriptor {
 // Application
 // ===========================================================================================
 
-pub(crate) const GATT_APP_PREFIX: &str = publish_path!("gatt/app/");
+pub(crate) const GATT_APP_PREFIX: &str = "gatt/app/";
 
 /// Definition of local GATT application to publish over Bluetooth.
 #[derive(Debug, Default)]
@@ -1300,7 +1300,7 @@ impl Application {
         mut self, inner: Arc<SessionInner>, adapter_name: Arc<String>,
     ) -> crate::Result<ApplicationHandle> {
         let mut reg_paths = Vec::new();
-        let app_path = format!("{}{}", GATT_APP_PREFIX, Uuid::new_v4().as_simple());
+        let app_path = format!("{}{}", inner.publish_path(GATT_APP_PREFIX), Uuid::new_v4().as_simple());
         let app_path = dbus::Path::new(app_path).unwrap();
         log::trace!("Publishing application at {}", &app_path);
 
@@ -1349,16 +1349,26 @@ impl Application {
         }
 
         log::trace!("Registering application at {}", &app_path);
-        let proxy =
-            Proxy::new(SERVICE_NAME, Adapter::dbus_path(&adapter_name)?, TIMEOUT, inner.connection.clone());
-        proxy.method_call(MANAGER_INTERFACE, "RegisterApplication", (app_path.clone(), PropMap::new())).await?;
+        let adapter_path = Adapter::dbus_path(&adapter_name)?;
+        let app_path_reg = app_path.clone();
+        inner
+            .register_with(
+                app_path.clone(),
+                adapter_path.clone(),
+                MANAGER_INTERFACE,
+                "RegisterApplication",
+                move || (app_path_reg.clone(), PropMap::new()),
+            )
+            .await?;
 
         let (drop_tx, drop_rx) = oneshot::channel();
         let app_path_unreg = app_path.clone();
         tokio::spawn(async move {
             let _ = drop_rx.await;
+            inner.forget_registration(app_path_unreg.clone()).await;
 
             log::trace!("Unregistering application at {}", &app_path_unreg);
+            let proxy = Proxy::new(inner.service_name.clone(), adapter_path, TIMEOUT, inner.connection.clone());
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterApplication", (app_path_unreg,)).await;
 
@@ -1397,7 +1407,7 @@ impl fmt::Debug for ApplicationHandle {
 // GATT profile
 // ===========================================================================================
 
-pub(crate) const GATT_PROFILE_PREFIX: &str = publish_path!("gatt/profile/");
+pub(crate) const GATT_PROFILE_PREFIX: &str = "gatt/profile/";
 
 /// Definition of local profile (GATT client) instance.
 ///
@@ -1425,7 +1435,7 @@ impl Profile {
     pub(crate) async fn register(
         self, inner: Arc<SessionInner>, adapter_name: Arc<String>,
     ) -> crate::Result<ProfileHandle> {
-        let profile_path = format!("{}{}", GATT_PROFILE_PREFIX, Uuid::new_v4().as_simple());
+        let profile_path = format!("{}{}", inner.publish_path(GATT_PROFILE_PREFIX), Uuid::new_v4().as_simple());
         let profile_path = dbus::Path::new(profile_path).unwrap();
         log::trace!("Publishing profile at {}", &profile_path);
 
@@ -1436,18 +1446,26 @@ impl Profile {
         }
 
         log::trace!("Registering profile at {}", &profile_path);
-        let proxy =
-            Proxy::new(SERVICE_NAME, Adapter::dbus_path(&adapter_name)?, TIMEOUT, inner.connection.clone());
-        proxy
-            .method_call(MANAGER_INTERFACE, "RegisterApplication", (profile_path.clone(), PropMap::new()))
+        let adapter_path = Adapter::dbus_path(&adapter_name)?;
+        let profile_path_reg = profile_path.clone();
+        inner
+            .register_with(
+                profile_path.clone(),
+                adapter_path.clone(),
+                MANAGER_INTERFACE,
+                "RegisterApplication",
+                move || (profile_path_reg.clone(), PropMap::new()),
+            )
             .await?;
 
         let (drop_tx, drop_rx) = oneshot::channel();
         let profile_path_unreg = profile_path.clone();
         tokio::spawn(async move {
             let _ = drop_rx.await;
+            inner.forget_registration(profile_path_unreg.clone()).await;
 
             log::trace!("Unregistering profile at {}", &profile_path_unreg);
+            let proxy = Proxy::new(inner.service_name.clone(), adapter_path, TIMEOUT, inner.connection.clone());
             let _: std::result::Result<(), dbus::Error> = proxy
                 .method_call(MANAGER_INTERFACE, "UnregisterApplication", (profile_path_unreg.clone(),))
                 .await;
@@ -15,8 +15,8 @@ use super::{
     DESCRIPTOR_INTERFACE, SERVICE_INTERFACE,
 };
 use crate::{
-    all_dbus_objects, Address, Device, Error, ErrorKind, Event, InternalErrorKind, Result, SessionInner,
-    SingleSessionToken, SERVICE_NAME, TIMEOUT,
+    dbus_objects_with_interface, Address, Device, Error, ErrorKind, Event, InternalErrorKind, Result,
+    SessionInner, SingleSessionToken, TIMEOUT,
 };
 
 // ===========================================================================================
@@ -59,7 +59,7 @@ impl Service {
     }
 
     fn proxy(&self) -> Proxy<'_, &SyncConnection> {
-        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
     }
 
     pub(crate) fn dbus_path(adapter_name: &str, device_address: Address, id: u16) -> Result<Path<'static>> {
@@ -110,13 +110,12 @@ impl Service {
     /// GATT characteristics belonging to this service.
     pub async fn characteristics(&self) -> Result<Vec<Characteristic>> {
         let mut chars = Vec::new();
-        for (path, interfaces) in all_dbus_objects(&self.inner.connection).await? {
+        for path in dbus_objects_with_interface(&self.inner, CHARACTERISTIC_INTERFACE).await? {
             match Characteristic::parse_dbus_path(&path) {
                 Some((adapter, device_address, service_id, id))
                     if adapter == *self.adapter_name
                         && device_address == self.device_address
-                        && service_id == self.id
-                        && interfaces.contains_key(CHARACTERISTIC_INTERFACE) =>
+                        && service_id == self.id =>
                 {
                     chars.push(self.characteristic(id).await?)
                 }
@@ -216,7 +215,7 @@ impl Characteristic {
     }
 
     fn proxy(&self) -> Proxy<'_, &SyncConnection> {
-        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
     }
 
     pub(crate) fn dbus_path(
@@ -275,14 +274,13 @@ impl Characteristic {
     /// GATT descriptors belonging to this characteristic.
     pub async fn descriptors(&self) -> Result<Vec<Descriptor>> {
         let mut chars = Vec::new();
-        for (path, interfaces) in all_dbus_objects(&self.inner.connection).await? {
+        for path in dbus_objects_with_interface(&self.inner, DESCRIPTOR_INTERFACE).await? {
             match Descriptor::parse_dbus_path(&path) {
                 Some((adapter, device_address, service_id, char_id, id))
                     if adapter == *self.adapter_name
                         && device_address == self.device_address
                         && service_id == self.service_id
-                        && char_id == self.id
-                        && interfaces.contains_key(DESCRIPTOR_INTERFACE) =>
+                        && char_id == self.id =>
                 {
                     chars.push(self.descriptor(id).await?)
                 }
@@ -389,7 +387,7 @@ impl Characteristic {
 
     async fn notify_session(&self) -> Result<SingleSessionToken> {
         let dbus_path = self.dbus_path.clone();
-        let connection = self.inner.connection.clone();
+        let inner = self.inner.clone();
         self.inner
             .single_session(
                 &self.dbus_path,
@@ -398,11 +396,11 @@ impl Characteristic {
                     Ok(())
                 },
                 async move {
-                    log::trace!("{}: {}.StopNotify ()", &dbus_path, SERVICE_NAME);
-                    let proxy = Proxy::new(SERVICE_NAME, &dbus_path, TIMEOUT, &*connection);
+                    log::trace!("{}: {}.StopNotify ()", &dbus_path, &inner.service_name);
+                    let proxy = Proxy::new(&*inner.service_name, &dbus_path, TIMEOUT, &*inner.connection);
                     let result: std::result::Result<(), dbus::Error> =
                         proxy.method_call(CHARACTERISTIC_INTERFACE, "StopNotify", ()).await;
-                    log::trace!("{}: {}.StopNotify () -> {:?}", &dbus_path, SERVICE_NAME, &result);
+                    log::trace!("{}: {}.StopNotify () -> {:?}", &dbus_path, &inner.service_name, &result);
                 },
             )
             .await
@@ -572,7 +570,7 @@ impl Descriptor {
     }
 
     fn proxy(&self) -> Proxy<'_, &SyncConnection> {
-        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
     }
 
     pub(crate) fn dbus_path(
@@ -0,0 +1,1261 @@
+//! Host controller interface (HCI) sockets.
+//!
+//! HCI sockets provide direct access to the packets exchanged between
+//! the host and Bluetooth controllers and are mainly useful for diagnostics.
+//!
+//!   * A [Controller] opened using [Controller::open_raw] receives a copy of the HCI traffic of
+//!     a controller that remains managed by the kernel.
+//!     Sending commands requires the `CAP_NET_RAW` capability.
+//!   * A [Controller] opened using [Controller::open_user_channel] takes exclusive control of a controller.
+//!     The controller must be powered off and not be in use by the Bluetooth daemon.
+//!     This requires the `CAP_NET_ADMIN` capability.
+//!   * A [Monitor] receives the HCI traffic of all controllers, similar to `btmon`.
+//!     This requires the `CAP_NET_RAW` capability.
+//!
+//! Received packets are parsed into [Packet]s, with the common events decoded into [Event] variants.
+//! Both [Controller] and [Monitor] implement [Stream] for receiving packets.
+//!
+
+use crate::{
+    sock::{self, OwnedFd},
+    sys::{
+        bdaddr_t, hci_filter, sockaddr_hci, BTPROTO_HCI, HCI_ACLDATA_PKT, HCI_CHANNEL_CONTROL,
+        HCI_CHANNEL_LOGGING, HCI_CHANNEL_MONITOR, HCI_CHANNEL_RAW, HCI_CHANNEL_USER, HCI_COMMAND_PKT,
+        HCI_DEV_NONE, HCI_EVENT_PKT, HCI_FILTER, HCI_ISODATA_PKT, HCI_MON_ACL_RX_PKT, HCI_MON_ACL_TX_PKT,
+        HCI_MON_CLOSE_INDEX, HCI_MON_COMMAND_PKT, HCI_MON_DEL_INDEX, HCI_MON_EVENT_PKT, HCI_MON_HDR_SIZE,
+        HCI_MON_INDEX_INFO, HCI_MON_ISO_RX_PKT, HCI_MON_ISO_TX_PKT, HCI_MON_NEW_INDEX, HCI_MON_NEW_INDEX_SIZE,
+        HCI_MON_OPEN_INDEX, HCI_MON_SCO_RX_PKT, HCI_MON_SCO_TX_PKT, HCI_MON_SYSTEM_NOTE, HCI_MON_USER_LOGGING,
+        HCI_MON_VENDOR_DIAG, HCI_SCODATA_PKT, HCI_VENDOR_PKT, SOL_HCI,
+    },
+    Address,
+};
+use futures::{future::poll_fn, ready, Stream};
+#[cfg(feature = "capture")]
+use libc::SCM_TIMESTAMP;
+use libc::{AF_BLUETOOTH, EAGAIN, EINPROGRESS, SOCK_RAW, SOL_SOCKET, SO_ERROR, SO_TIMESTAMP};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+#[cfg(feature = "capture")]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{
+    convert::TryInto,
+    fmt,
+    io::{Error, ErrorKind, Result},
+    os::{
+        raw::c_int,
+        unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    },
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+use tokio::io::{unix::AsyncFd, ReadBuf};
+
+/// Size of the receive buffer for one packet.
+///
+/// This fits the monitor header followed by the largest possible payload.
+const RECV_BUF_SIZE: usize = HCI_MON_HDR_SIZE + u16::MAX as usize;
+
+/// Index of a Bluetooth controller.
+///
+/// This is the number in the kernel device name, i.e. `0` for `hci0`.
+pub type DevIndex = u16;
+
+/// Controller index used when a socket is not bound to a controller.
+pub const DEV_NONE: DevIndex = HCI_DEV_NONE;
+
+/// HCI socket channel.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Channel {
+    /// Raw access to a controller managed by the kernel.
+    Raw = HCI_CHANNEL_RAW as _,
+    /// Exclusive access to a controller.
+    User = HCI_CHANNEL_USER as _,
+    /// Monitor of all HCI traffic.
+    Monitor = HCI_CHANNEL_MONITOR as _,
+    /// Management interface of the kernel.
+    Control = HCI_CHANNEL_CONTROL as _,
+    /// Injection of log messages into the monitor channel.
+    Logging = HCI_CHANNEL_LOGGING as _,
+}
+
+/// An HCI socket address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SocketAddr {
+    /// Controller index.
+    ///
+    /// Specify [DEV_NONE] for channels that are not bound to a controller.
+    pub dev: DevIndex,
+    /// Channel.
+    pub channel: Channel,
+}
+
+impl SocketAddr {
+    /// Creates a new HCI socket address.
+    pub const fn new(dev: DevIndex, channel: Channel) -> Self {
+        Self { dev, channel }
+    }
+
+    /// Address of the monitor channel.
+    pub const fn monitor() -> Self {
+        Self { dev: DEV_NONE, channel: Channel::Monitor }
+    }
+}
+
+impl sock::SysSockAddr for SocketAddr {
+    type SysSockAddr = sockaddr_hci;
+
+    fn into_sys_sock_addr(self) -> Result<Self::SysSockAddr> {
+        Ok(sockaddr_hci { hci_family: AF_BLUETOOTH as _, hci_dev: self.dev, hci_channel: self.channel as _ })
+    }
+
+    fn try_from_sys_sock_addr(saddr: Self::SysSockAddr) -> Result<Self> {
+        if saddr.hci_family != AF_BLUETOOTH as _ {
+            return Err(Error::new(ErrorKind::InvalidInput, "sockaddr_hci::hci_family is not AF_BLUETOOTH"));
+        }
+        Ok(Self {
+            dev: saddr.hci_dev,
+            channel: Channel::from_u16(saddr.hci_channel)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid sockaddr_hci::hci_channel"))?,
+        })
+    }
+}
+
+/// Filter of packets received on the [raw channel](Channel::Raw).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Filter {
+    /// Bit mask of received packet types.
+    ///
+    /// Bit `n` is set to receive packets of [PacketType] `n`.
+    pub packet_types: u32,
+    /// Bit mask of received event codes.
+    ///
+    /// Bit `n` is set to receive events with code `n`.
+    pub events: u64,
+    /// Only receive command complete and command status events for this opcode.
+    ///
+    /// Set to zero to receive them for all opcodes.
+    pub opcode: u16,
+}
+
+impl Filter {
+    /// Filter that lets all packets pass.
+    pub const fn all() -> Self {
+        Self { packet_types: u32::MAX, events: u64::MAX, opcode: 0 }
+    }
+}
+
+impl From<Filter> for hci_filter {
+    fn from(f: Filter) -> Self {
+        hci_filter {
+            type_mask: f.packet_types,
+            event_mask: [f.events as u32, (f.events >> 32) as u32],
+            opcode: f.opcode,
+        }
+    }
+}
+
+impl From<hci_filter> for Filter {
+    fn from(f: hci_filter) -> Self {
+        Self {
+            packet_types: f.type_mask,
+            events: u64::from(f.event_mask[0]) | (u64::from(f.event_mask[1]) << 32),
+            opcode: f.opcode,
+        }
+    }
+}
+
+fn invalid_data(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn read_cstr(buf: &[u8]) -> String {
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).to_string()
+}
+
+fn read_addr(buf: &[u8]) -> Address {
+    let mut b = [0; 6];
+    b.copy_from_slice(&buf[..6]);
+    Address::from(bdaddr_t { b })
+}
+
+/// HCI packet type.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PacketType {
+    /// Command.
+    Command = HCI_COMMAND_PKT as _,
+    /// ACL data.
+    Acl = HCI_ACLDATA_PKT as _,
+    /// SCO data.
+    Sco = HCI_SCODATA_PKT as _,
+    /// Event.
+    Event = HCI_EVENT_PKT as _,
+    /// ISO data.
+    Iso = HCI_ISODATA_PKT as _,
+    /// Vendor-specific.
+    Vendor = HCI_VENDOR_PKT as _,
+}
+
+/// HCI command opcode.
+///
+/// It consists of the opcode group field (OGF) and the opcode command field (OCF).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Opcode(pub u16);
+
+impl Opcode {
+    /// Disconnect.
+    pub const DISCONNECT: Self = Self::new(0x01, 0x0006);
+    /// Reset.
+    pub const RESET: Self = Self::new(0x03, 0x0003);
+    /// Read local version information.
+    pub const READ_LOCAL_VERSION: Self = Self::new(0x04, 0x0001);
+    /// Read BD_ADDR.
+    pub const READ_BD_ADDR: Self = Self::new(0x04, 0x0009);
+    /// LE set scan parameters.
+    pub const LE_SET_SCAN_PARAMETERS: Self = Self::new(0x08, 0x000b);
+    /// LE set scan enable.
+    pub const LE_SET_SCAN_ENABLE: Self = Self::new(0x08, 0x000c);
+
+    /// Creates an opcode from the opcode group field (OGF) and
+    /// the opcode command field (OCF).
+    pub const fn new(ogf: u8, ocf: u16) -> Self {
+        Self(((ogf as u16) << 10) | (ocf & 0x03ff))
+    }
+
+    /// Opcode group field (OGF).
+    pub const fn ogf(&self) -> u8 {
+        (self.0 >> 10) as u8
+    }
+
+    /// Opcode command field (OCF).
+    pub const fn ocf(&self) -> u16 {
+        self.0 & 0x03ff
+    }
+}
+
+impl fmt::Debug for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Opcode(ogf=0x{:02x}, ocf=0x{:04x})", self.ogf(), self.ocf())
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:04x}", self.0)
+    }
+}
+
+/// HCI command.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Command {
+    /// Opcode.
+    pub opcode: Opcode,
+    /// Parameters.
+    ///
+    /// At most 255 bytes.
+    pub params: Vec<u8>,
+}
+
+impl Command {
+    /// Creates a new command.
+    pub fn new(opcode: Opcode, params: Vec<u8>) -> Self {
+        Self { opcode, params }
+    }
+
+    /// Disconnect command for the specified connection handle and reason.
+    pub fn disconnect(handle: u16, reason: u8) -> Self {
+        let mut params = handle.to_le_bytes().to_vec();
+        params.push(reason);
+        Self::new(Opcode::DISCONNECT, params)
+    }
+
+    /// Reset command.
+    pub fn reset() -> Self {
+        Self::new(Opcode::RESET, Vec::new())
+    }
+
+    /// Read local version information command.
+    pub fn read_local_version() -> Self {
+        Self::new(Opcode::READ_LOCAL_VERSION, Vec::new())
+    }
+
+    /// Read BD_ADDR command.
+    pub fn read_bd_addr() -> Self {
+        Self::new(Opcode::READ_BD_ADDR, Vec::new())
+    }
+
+    /// LE set scan enable command.
+    pub fn le_set_scan_enable(enable: bool, filter_duplicates: bool) -> Self {
+        Self::new(Opcode::LE_SET_SCAN_ENABLE, vec![enable.into(), filter_duplicates.into()])
+    }
+
+    /// Parses a command without packet type indicator.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        if buf.len() < 3 {
+            return Err(invalid_data("HCI command too short"));
+        }
+        let len = usize::from(buf[2]);
+        let params = buf.get(3..3 + len).ok_or_else(|| invalid_data("HCI command parameters truncated"))?;
+        Ok(Self { opcode: Opcode(read_u16(buf, 0)), params: params.to_vec() })
+    }
+
+    /// Serializes the command without packet type indicator.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let len: u8 =
+            self.params.len().try_into().map_err(|_| invalid_data("HCI command parameters too long"))?;
+        let mut buf = Vec::with_capacity(3 + self.params.len());
+        buf.extend_from_slice(&self.opcode.0.to_le_bytes());
+        buf.push(len);
+        buf.extend_from_slice(&self.params);
+        Ok(buf)
+    }
+}
+
+/// HCI ACL data packet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AclData {
+    /// Connection handle.
+    pub handle: u16,
+    /// Packet boundary flag.
+    pub packet_boundary: u8,
+    /// Broadcast flag.
+    pub broadcast: u8,
+    /// Data.
+    pub data: Vec<u8>,
+}
+
+impl AclData {
+    /// Parses ACL data without packet type indicator.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        if buf.len() < 4 {
+            return Err(invalid_data("HCI ACL data too short"));
+        }
+        let handle = read_u16(buf, 0);
+        let len = usize::from(read_u16(buf, 2));
+        let data = buf.get(4..4 + len).ok_or_else(|| invalid_data("HCI ACL data truncated"))?;
+        Ok(Self {
+            handle: handle & 0x0fff,
+            packet_boundary: ((handle >> 12) & 0x03) as u8,
+            broadcast: ((handle >> 14) & 0x03) as u8,
+            data: data.to_vec(),
+        })
+    }
+
+    /// Serializes the ACL data without packet type indicator.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let len: u16 = self.data.len().try_into().map_err(|_| invalid_data("HCI ACL data too long"))?;
+        let handle = (self.handle & 0x0fff)
+            | (u16::from(self.packet_boundary & 0x03) << 12)
+            | (u16::from(self.broadcast & 0x03) << 14);
+        let mut buf = Vec::with_capacity(4 + self.data.len());
+        buf.extend_from_slice(&handle.to_le_bytes());
+        buf.extend_from_slice(&len.to_le_bytes());
+        buf.extend_from_slice(&self.data);
+        Ok(buf)
+    }
+}
+
+const EVT_DISCONNECTION_COMPLETE: u8 = 0x05;
+const EVT_COMMAND_COMPLETE: u8 = 0x0e;
+const EVT_COMMAND_STATUS: u8 = 0x0f;
+const EVT_LE_META: u8 = 0x3e;
+
+/// HCI event.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Event {
+    /// A connection has been terminated.
+    DisconnectionComplete {
+        /// Status.
+        status: u8,
+        /// Connection handle.
+        handle: u16,
+        /// Reason for disconnection.
+        reason: u8,
+    },
+    /// A command has completed.
+    CommandComplete {
+        /// Number of commands the controller can accept.
+        num_packets: u8,
+        /// Opcode of the completed command.
+        opcode: Opcode,
+        /// Return parameters of the command.
+        ///
+        /// For most commands, the first byte is the status.
+        return_params: Vec<u8>,
+    },
+    /// A command has been received and is being processed.
+    CommandStatus {
+        /// Status.
+        status: u8,
+        /// Number of commands the controller can accept.
+        num_packets: u8,
+        /// Opcode of the command.
+        opcode: Opcode,
+    },
+    /// LE meta event.
+    LeMeta {
+        /// Subevent code.
+        subevent: u8,
+        /// Parameters of the subevent.
+        params: Vec<u8>,
+    },
+    /// Other event.
+    Other {
+        /// Event code.
+        code: u8,
+        /// Parameters.
+        params: Vec<u8>,
+    },
+}
+
+impl Event {
+    /// Event code.
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::DisconnectionComplete { .. } => EVT_DISCONNECTION_COMPLETE,
+            Self::CommandComplete { .. } => EVT_COMMAND_COMPLETE,
+            Self::CommandStatus { .. } => EVT_COMMAND_STATUS,
+            Self::LeMeta { .. } => EVT_LE_META,
+            Self::Other { code, .. } => *code,
+        }
+    }
+
+    /// Parses an event without packet type indicator.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        if buf.len() < 2 {
+            return Err(invalid_data("HCI event too short"));
+        }
+        let code = buf[0];
+        let len = usize::from(buf[1]);
+        let p = buf.get(2..2 + len).ok_or_else(|| invalid_data("HCI event parameters truncated"))?;
+
+        let event = match code {
+            EVT_DISCONNECTION_COMPLETE if p.len() >= 4 => {
+                Self::DisconnectionComplete { status: p[0], handle: read_u16(p, 1) & 0x0fff, reason: p[3] }
+            }
+            EVT_COMMAND_COMPLETE if p.len() >= 3 => Self::CommandComplete {
+                num_packets: p[0],
+                opcode: Opcode(read_u16(p, 1)),
+                return_params: p[3..].to_vec(),
+            },
+            EVT_COMMAND_STATUS if p.len() >= 4 => {
+                Self::CommandStatus { status: p[0], num_packets: p[1], opcode: Opcode(read_u16(p, 2)) }
+            }
+            EVT_LE_META if !p.is_empty() => Self::LeMeta { subevent: p[0], params: p[1..].to_vec() },
+            EVT_DISCONNECTION_COMPLETE | EVT_COMMAND_COMPLETE | EVT_COMMAND_STATUS | EVT_LE_META => {
+                return Err(invalid_data("HCI event parameters too short"))
+            }
+            _ => Self::Other { code, params: p.to_vec() },
+        };
+        Ok(event)
+    }
+
+    /// Serializes the event without packet type indicator.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut params = Vec::new();
+        match self {
+            Self::DisconnectionComplete { status, handle, reason } => {
+                params.push(*status);
+                params.extend_from_slice(&handle.to_le_bytes());
+                params.push(*reason);
+            }
+            Self::CommandComplete { num_packets, opcode, return_params } => {
+                params.push(*num_packets);
+                params.extend_from_slice(&opcode.0.to_le_bytes());
+                params.extend_from_slice(return_params);
+            }
+            Self::CommandStatus { status, num_packets, opcode } => {
+                params.push(*status);
+                params.push(*num_packets);
+                params.extend_from_slice(&opcode.0.to_le_bytes());
+            }
+            Self::LeMeta { subevent, params: p } => {
+                params.push(*subevent);
+                params.extend_from_slice(p);
+            }
+            Self::Other { params: p, .. } => params.extend_from_slice(p),
+        }
+
+        let len: u8 = params.len().try_into().map_err(|_| invalid_data("HCI event parameters too long"))?;
+        let mut buf = vec![self.code(), len];
+        buf.append(&mut params);
+        Ok(buf)
+    }
+}
+
+/// HCI packet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Packet {
+    /// Command.
+    Command(Command),
+    /// ACL data.
+    Acl(AclData),
+    /// SCO data, including its header.
+    Sco(Vec<u8>),
+    /// Event.
+    Event(Event),
+    /// ISO data, including its header.
+    Iso(Vec<u8>),
+    /// Vendor-specific data.
+    Vendor(Vec<u8>),
+}
+
+impl Packet {
+    /// Packet type.
+    pub fn packet_type(&self) -> PacketType {
+        match self {
+            Self::Command(_) => PacketType::Command,
+            Self::Acl(_) => PacketType::Acl,
+            Self::Sco(_) => PacketType::Sco,
+            Self::Event(_) => PacketType::Event,
+            Self::Iso(_) => PacketType::Iso,
+            Self::Vendor(_) => PacketType::Vendor,
+        }
+    }
+
+    /// Parses a packet starting with the packet type indicator.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        let (&ty, data) = buf.split_first().ok_or_else(|| invalid_data("empty HCI packet"))?;
+        let packet = match PacketType::from_u8(ty) {
+            Some(PacketType::Command) => Self::Command(Command::from_bytes(data)?),
+            Some(PacketType::Acl) => Self::Acl(AclData::from_bytes(data)?),
+            Some(PacketType::Sco) => Self::Sco(data.to_vec()),
+            Some(PacketType::Event) => Self::Event(Event::from_bytes(data)?),
+            Some(PacketType::Iso) => Self::Iso(data.to_vec()),
+            Some(PacketType::Vendor) => Self::Vendor(data.to_vec()),
+            None => return Err(invalid_data("unknown HCI packet type")),
+        };
+        Ok(packet)
+    }
+
+    /// Serializes the packet including the packet type indicator.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![self.packet_type() as u8];
+        match self {
+            Self::Command(cmd) => buf.append(&mut cmd.to_bytes()?),
+            Self::Acl(acl) => buf.append(&mut acl.to_bytes()?),
+            Self::Event(evt) => buf.append(&mut evt.to_bytes()?),
+            Self::Sco(data) | Self::Iso(data) | Self::Vendor(data) => buf.extend_from_slice(data),
+        }
+        Ok(buf)
+    }
+}
+
+/// An HCI socket that has not yet been converted to a [Controller] or [Monitor].
+///
+/// The primary use of this is to configure the socket before binding it.
+pub struct Socket {
+    fd: AsyncFd<OwnedFd>,
+    /// Buffer for receiving one packet, allocated on first use.
+    recv_buf: Mutex<Vec<u8>>,
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Socket").field("fd", &self.fd.as_raw_fd()).finish()
+    }
+}
+
+impl Socket {
+    /// Creates a new HCI socket.
+    pub fn new() -> Result<Self> {
+        Self::from_owned_fd(sock::socket(AF_BLUETOOTH, SOCK_RAW, BTPROTO_HCI)?)
+    }
+
+    /// Bind the socket to the given address.
+    pub fn bind(&self, sa: SocketAddr) -> Result<()> {
+        sock::bind(self.fd.get_ref(), sa)
+    }
+
+    /// Get the local address of this socket.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        sock::getsockname(self.fd.get_ref())
+    }
+
+    /// Get the packet filter.
+    ///
+    /// This is only supported on the [raw channel](Channel::Raw).
+    /// This corresponds to the `HCI_FILTER` socket option.
+    pub fn filter(&self) -> Result<Filter> {
+        let value: hci_filter = sock::getsockopt(self.fd.get_ref(), SOL_HCI, HCI_FILTER)?;
+        Ok(value.into())
+    }
+
+    /// Set the packet filter.
+    ///
+    /// This is only supported on the [raw channel](Channel::Raw).
+    /// This corresponds to the `HCI_FILTER` socket option.
+    pub fn set_filter(&self, filter: Filter) -> Result<()> {
+        let value: hci_filter = filter.into();
+        sock::setsockopt(self.fd.get_ref(), SOL_HCI, HCI_FILTER, &value)
+    }
+
+    /// Get whether the kernel records the reception time of packets.
+    ///
+    /// This corresponds to the `SO_TIMESTAMP` socket option.
+    pub fn is_timestamp(&self) -> Result<bool> {
+        let value: c_int = sock::getsockopt(self.fd.get_ref(), SOL_SOCKET, SO_TIMESTAMP)?;
+        Ok(value != 0)
+    }
+
+    /// Set whether the kernel records the reception time of packets.
+    ///
+    /// This corresponds to the `SO_TIMESTAMP` socket option.
+    pub fn set_timestamp(&self, timestamp: bool) -> Result<()> {
+        let value = c_int::from(timestamp);
+        sock::setsockopt(self.fd.get_ref(), SOL_SOCKET, SO_TIMESTAMP, &value)
+    }
+
+    /// Convert the bound socket into a [Controller].
+    pub fn into_controller(self) -> Controller {
+        Controller { socket: self, terminated: false }
+    }
+
+    /// Convert the bound socket into a [Monitor].
+    pub fn into_monitor(self) -> Monitor {
+        Monitor { socket: self, terminated: false }
+    }
+
+    /// Constructs a new [Socket] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Self::from_owned_fd(OwnedFd::new(fd))
+    }
+
+    fn from_owned_fd(fd: OwnedFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(fd)?, recv_buf: Mutex::new(Vec::new()) })
+    }
+
+    /// Attempts to receive one packet and returns a copy of it.
+    ///
+    /// The packet is received into the buffer of the socket, so that only
+    /// the received bytes are allocated for each packet.
+    fn poll_recv_buf(&self, cx: &mut Context) -> Poll<Result<Vec<u8>>> {
+        let mut buf = self.recv_buf.lock().unwrap();
+        if buf.is_empty() {
+            buf.resize(RECV_BUF_SIZE, 0);
+        }
+        let mut read_buf = ReadBuf::new(&mut buf);
+        ready!(self.poll_recv_priv(cx, &mut read_buf))?;
+        Poll::Ready(Ok(read_buf.filled().to_vec()))
+    }
+
+    /// Sends the buffer as one packet.
+    #[cfg(feature = "mgmt")]
+    pub(crate) async fn send_buf(&self, buf: &[u8]) -> Result<usize> {
+        self.send_priv(buf).await
+    }
+
+    /// Receives one packet into the buffer.
+    #[cfg(feature = "mgmt")]
+    pub(crate) async fn recv_buf(&self, buf: &mut [u8]) -> Result<usize> {
+        self.recv_priv(buf).await
+    }
+
+    /// Attempts to receive one packet together with its reception time and returns a copy of it.
+    ///
+    /// The packet is received into the buffer of the socket, like [poll_recv_buf](Self::poll_recv_buf).
+    /// The reception time is only available if [timestamping](Self::set_timestamp) is enabled.
+    #[cfg(feature = "capture")]
+    pub(crate) fn poll_recv_buf_timestamped(
+        &self, cx: &mut Context,
+    ) -> Poll<Result<(Vec<u8>, Option<SystemTime>)>> {
+        let mut buf = self.recv_buf.lock().unwrap();
+        if buf.is_empty() {
+            buf.resize(RECV_BUF_SIZE, 0);
+        }
+        let mut read_buf = ReadBuf::new(&mut buf);
+        loop {
+            let mut guard = ready!(self.fd.poll_read_ready(cx))?;
+            match guard.try_io(|inner| {
+                sock::recv_with_cmsg::<libc::timeval>(
+                    inner.get_ref(),
+                    &mut read_buf,
+                    0,
+                    SOL_SOCKET,
+                    SCM_TIMESTAMP,
+                )
+            }) {
+                Ok(result) => {
+                    let (_, tv) = result?;
+                    let timestamp = tv.map(|tv| {
+                        UNIX_EPOCH + Duration::new(tv.tv_sec as u64, (tv.tv_usec as u32).saturating_mul(1000))
+                    });
+                    return Poll::Ready(Ok((read_buf.filled().to_vec(), timestamp)));
+                }
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    sock_priv!();
+}
+
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for Socket {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_inner().into_raw_fd()
+    }
+}
+
+impl FromRawFd for Socket {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Socket::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// An HCI socket bound to a controller, exchanging [Packet]s with it.
+#[derive(Debug)]
+pub struct Controller {
+    socket: Socket,
+    terminated: bool,
+}
+
+impl Controller {
+    /// Opens the raw channel of the specified controller.
+    ///
+    /// The filter is set to receive all packets.
+    pub async fn open_raw(dev: DevIndex) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(SocketAddr::new(dev, Channel::Raw))?;
+        socket.set_filter(Filter::all())?;
+        Ok(socket.into_controller())
+    }
+
+    /// Opens the user channel of the specified controller, taking exclusive control of it.
+    pub async fn open_user_channel(dev: DevIndex) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(SocketAddr::new(dev, Channel::User))?;
+        Ok(socket.into_controller())
+    }
+
+    /// Sends a packet to the controller.
+    pub async fn send(&self, packet: &Packet) -> Result<()> {
+        let buf = packet.to_bytes()?;
+        self.socket.send_priv(&buf).await?;
+        Ok(())
+    }
+
+    /// Attempts to send a packet to the controller.
+    pub fn poll_send(&self, cx: &mut Context, packet: &Packet) -> Poll<Result<()>> {
+        let buf = packet.to_bytes()?;
+        ready!(self.socket.poll_send_priv(cx, &buf))?;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Sends a command to the controller.
+    ///
+    /// The result is reported by a [command complete](Event::CommandComplete) or
+    /// [command status](Event::CommandStatus) event.
+    pub async fn send_command(&self, command: &Command) -> Result<()> {
+        let mut buf = vec![HCI_COMMAND_PKT];
+        buf.append(&mut command.to_bytes()?);
+        self.socket.send_priv(&buf).await?;
+        Ok(())
+    }
+
+    /// Receives a packet.
+    pub async fn recv(&self) -> Result<Packet> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Attempts to receive a packet.
+    pub fn poll_recv(&self, cx: &mut Context) -> Poll<Result<Packet>> {
+        let buf = ready!(self.socket.poll_recv_buf(cx))?;
+        Poll::Ready(Packet::from_bytes(&buf))
+    }
+
+    /// Constructs a new [Controller] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)?, terminated: false })
+    }
+}
+
+/// Yields received packets.
+///
+/// A malformed packet is yielded as an error and receiving continues.
+/// The stream ends after the socket fails.
+impl Stream for Controller {
+    type Item = Result<Packet>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.terminated {
+            return Poll::Ready(None);
+        }
+        match ready!(self.socket.poll_recv_buf(cx)) {
+            Ok(buf) => Poll::Ready(Some(Packet::from_bytes(&buf))),
+            Err(err) => {
+                self.terminated = true;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+impl AsRef<Socket> for Controller {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for Controller {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for Controller {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Controller::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// Event received on the monitor channel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum MonitorEvent {
+    /// A controller has been added.
+    NewIndex {
+        /// Controller type.
+        controller_type: u8,
+        /// Bus the controller is attached to.
+        bus: u8,
+        /// Controller address.
+        addr: Address,
+        /// Kernel device name.
+        name: String,
+    },
+    /// A controller has been removed.
+    DelIndex,
+    /// A controller has been opened.
+    OpenIndex,
+    /// A controller has been closed.
+    CloseIndex,
+    /// Controller information.
+    IndexInfo {
+        /// Controller address.
+        addr: Address,
+        /// Manufacturer identifier.
+        manufacturer: u16,
+    },
+    /// Command sent to the controller.
+    Command(Command),
+    /// Event received from the controller.
+    Event(Event),
+    /// ACL data sent to the controller.
+    AclTx(AclData),
+    /// ACL data received from the controller.
+    AclRx(AclData),
+    /// SCO data sent to the controller, including its header.
+    ScoTx(Vec<u8>),
+    /// SCO data received from the controller, including its header.
+    ScoRx(Vec<u8>),
+    /// ISO data sent to the controller, including its header.
+    IsoTx(Vec<u8>),
+    /// ISO data received from the controller, including its header.
+    IsoRx(Vec<u8>),
+    /// Vendor-specific diagnostic data.
+    VendorDiag(Vec<u8>),
+    /// System note.
+    SystemNote(String),
+    /// Log message.
+    UserLogging {
+        /// Priority as defined by syslog.
+        priority: u8,
+        /// Identifier of the logging program.
+        ident: String,
+        /// Message.
+        message: String,
+    },
+    /// Other event.
+    Other {
+        /// Monitor opcode.
+        opcode: u16,
+        /// Data.
+        data: Vec<u8>,
+    },
+}
+
+impl MonitorEvent {
+    /// Parses the data following the monitor header.
+    pub(crate) fn from_bytes(opcode: u16, data: &[u8]) -> Result<Self> {
+        let event = match opcode {
+            HCI_MON_NEW_INDEX => {
+                if data.len() < HCI_MON_NEW_INDEX_SIZE {
+                    return Err(invalid_data("HCI monitor new index too short"));
+                }
+                Self::NewIndex {
+                    controller_type: data[0],
+                    bus: data[1],
+                    addr: read_addr(&data[2..8]),
+                    name: read_cstr(&data[8..16]),
+                }
+            }
+            HCI_MON_DEL_INDEX => Self::DelIndex,
+            HCI_MON_OPEN_INDEX => Self::OpenIndex,
+            HCI_MON_CLOSE_INDEX => Self::CloseIndex,
+            HCI_MON_INDEX_INFO => {
+                if data.len() < 8 {
+                    return Err(invalid_data("HCI monitor index info too short"));
+                }
+                Self::IndexInfo { addr: read_addr(&data[0..6]), manufacturer: read_u16(data, 6) }
+            }
+            HCI_MON_COMMAND_PKT => Self::Command(Command::from_bytes(data)?),
+            HCI_MON_EVENT_PKT => Self::Event(Event::from_bytes(data)?),
+            HCI_MON_ACL_TX_PKT => Self::AclTx(AclData::from_bytes(data)?),
+            HCI_MON_ACL_RX_PKT => Self::AclRx(AclData::from_bytes(data)?),
+            HCI_MON_SCO_TX_PKT => Self::ScoTx(data.to_vec()),
+            HCI_MON_SCO_RX_PKT => Self::ScoRx(data.to_vec()),
+            HCI_MON_ISO_TX_PKT => Self::IsoTx(data.to_vec()),
+            HCI_MON_ISO_RX_PKT => Self::IsoRx(data.to_vec()),
+            HCI_MON_VENDOR_DIAG => Self::VendorDiag(data.to_vec()),
+            HCI_MON_SYSTEM_NOTE => Self::SystemNote(read_cstr(data)),
+            HCI_MON_USER_LOGGING => {
+                if data.len() < 2 {
+                    return Err(invalid_data("HCI monitor user logging too short"));
+                }
+                let ident_len = usize::from(data[1]);
+                let ident =
+                    data.get(2..2 + ident_len).ok_or_else(|| invalid_data("HCI monitor ident truncated"))?;
+                Self::UserLogging {
+                    priority: data[0],
+                    ident: read_cstr(ident),
+                    message: read_cstr(&data[2 + ident_len..]),
+                }
+            }
+            _ => Self::Other { opcode, data: data.to_vec() },
+        };
+        Ok(event)
+    }
+}
+
+/// Packet received on the monitor channel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorPacket {
+    /// Index of the controller the event pertains to.
+    ///
+    /// This is [DEV_NONE] for events not related to a controller.
+    pub index: DevIndex,
+    /// Event.
+    pub event: MonitorEvent,
+}
+
+impl MonitorPacket {
+    /// Parses a packet starting with the monitor header.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        let (opcode, index, data) = split_monitor_packet(buf)?;
+        Ok(Self { index, event: MonitorEvent::from_bytes(opcode, data)? })
+    }
+}
+
+/// Splits a packet starting with the monitor header into
+/// monitor opcode, controller index and data.
+pub(crate) fn split_monitor_packet(buf: &[u8]) -> Result<(u16, DevIndex, &[u8])> {
+    if buf.len() < HCI_MON_HDR_SIZE {
+        return Err(invalid_data("HCI monitor packet too short"));
+    }
+    let opcode = read_u16(buf, 0);
+    let index = read_u16(buf, 2);
+    let len = usize::from(read_u16(buf, 4));
+    let data = buf
+        .get(HCI_MON_HDR_SIZE..HCI_MON_HDR_SIZE + len)
+        .ok_or_else(|| invalid_data("HCI monitor packet truncated"))?;
+    Ok((opcode, index, data))
+}
+
+/// An HCI socket bound to the monitor channel, receiving the HCI traffic of all controllers.
+#[derive(Debug)]
+pub struct Monitor {
+    socket: Socket,
+    terminated: bool,
+}
+
+impl Monitor {
+    /// Opens the monitor channel.
+    ///
+    /// Upon opening, the kernel sends a [new index event](MonitorEvent::NewIndex) for
+    /// each existing controller.
+    pub async fn open() -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(SocketAddr::monitor())?;
+        Ok(socket.into_monitor())
+    }
+
+    /// Receives a packet.
+    pub async fn recv(&self) -> Result<MonitorPacket> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Attempts to receive a packet.
+    pub fn poll_recv(&self, cx: &mut Context) -> Poll<Result<MonitorPacket>> {
+        let buf = ready!(self.socket.poll_recv_buf(cx))?;
+        Poll::Ready(MonitorPacket::from_bytes(&buf))
+    }
+
+    /// Constructs a new [Monitor] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)?, terminated: false })
+    }
+}
+
+/// Yields received packets.
+///
+/// A malformed packet is yielded as an error and receiving continues.
+/// The stream ends after the socket fails.
+impl Stream for Monitor {
+    type Item = Result<MonitorPacket>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.terminated {
+            return Poll::Ready(None);
+        }
+        match ready!(self.socket.poll_recv_buf(cx)) {
+            Ok(buf) => Poll::Ready(Some(MonitorPacket::from_bytes(&buf))),
+            Err(err) => {
+                self.terminated = true;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+impl AsRef<Socket> for Monitor {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for Monitor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for Monitor {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Monitor::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::{
+        AclData, Command, Event, MonitorEvent, MonitorPacket, Opcode, Packet, PacketType, DEV_NONE, RECV_BUF_SIZE,
+    };
+    use crate::Address;
+
+    #[test]
+    fn opcode() {
+        let opcode = Opcode::LE_SET_SCAN_ENABLE;
+        assert_eq!(opcode.0, 0x200c);
+        assert_eq!(opcode.ogf(), 0x08);
+        assert_eq!(opcode.ocf(), 0x000c);
+        assert_eq!(Opcode::RESET.0, 0x0c03);
+    }
+
+    #[test]
+    fn command() {
+        let cmd = Command::disconnect(0x0040, 0x13);
+        let buf = Packet::Command(cmd.clone()).to_bytes().unwrap();
+        assert_eq!(buf, [0x01, 0x06, 0x04, 0x03, 0x40, 0x00, 0x13]);
+        assert_eq!(Packet::from_bytes(&buf).unwrap(), Packet::Command(cmd));
+
+        assert_eq!(Command::from_bytes(&[0x03, 0x0c, 0x01]).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert!(Command::new(Opcode::RESET, vec![0; 256]).to_bytes().is_err());
+    }
+
+    #[test]
+    fn acl_data() {
+        let buf = [0x02, 0x01, 0x20, 0x03, 0x00, 0xaa, 0xbb, 0xcc];
+        let packet = Packet::from_bytes(&buf).unwrap();
+        let acl = AclData { handle: 0x001, packet_boundary: 2, broadcast: 0, data: vec![0xaa, 0xbb, 0xcc] };
+        assert_eq!(packet, Packet::Acl(acl));
+        assert_eq!(packet.to_bytes().unwrap(), buf);
+
+        assert!(AclData::from_bytes(&buf[1..7]).is_err());
+    }
+
+    #[test]
+    fn acl_data_max_len() {
+        let acl = AclData { handle: 0x0fff, packet_boundary: 0, broadcast: 0, data: vec![0x55; u16::MAX.into()] };
+        let buf = Packet::Acl(acl.clone()).to_bytes().unwrap();
+        assert!(buf.len() <= RECV_BUF_SIZE);
+        assert_eq!(Packet::from_bytes(&buf).unwrap(), Packet::Acl(acl));
+    }
+
+    #[test]
+    fn event() {
+        // Command complete for HCI_Reset with status success.
+        let buf = [0x04, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00];
+        let packet = Packet::from_bytes(&buf).unwrap();
+        assert_eq!(
+            packet,
+            Packet::Event(Event::CommandComplete {
+                num_packets: 1,
+                opcode: Opcode::RESET,
+                return_params: vec![0x00]
+            })
+        );
+        assert_eq!(packet.to_bytes().unwrap(), buf);
+
+        let buf = [0x0f, 0x04, 0x00, 0x01, 0x06, 0x04];
+        let event = Event::from_bytes(&buf).unwrap();
+        assert_eq!(event, Event::CommandStatus { status: 0, num_packets: 1, opcode: Opcode::DISCONNECT });
+        assert_eq!(event.to_bytes().unwrap(), buf);
+
+        let buf = [0x05, 0x04, 0x00, 0x40, 0x00, 0x13];
+        let event = Event::from_bytes(&buf).unwrap();
+        assert_eq!(event, Event::DisconnectionComplete { status: 0, handle: 0x0040, reason: 0x13 });
+        assert_eq!(event.to_bytes().unwrap(), buf);
+
+        let buf = [0x3e, 0x03, 0x02, 0x01, 0x00];
+        let event = Event::from_bytes(&buf).unwrap();
+        assert_eq!(event, Event::LeMeta { subevent: 0x02, params: vec![0x01, 0x00] });
+        assert_eq!(event.to_bytes().unwrap(), buf);
+
+        let buf = [0xff, 0x02, 0x12, 0x34];
+        let event = Event::from_bytes(&buf).unwrap();
+        assert_eq!(event, Event::Other { code: 0xff, params: vec![0x12, 0x34] });
+        assert_eq!(event.to_bytes().unwrap(), buf);
+    }
+
+    #[test]
+    fn event_invalid() {
+        assert!(Event::from_bytes(&[0x0e]).is_err());
+        assert!(Event::from_bytes(&[0x0e, 0x04, 0x01, 0x03]).is_err());
+        assert!(Event::from_bytes(&[0x0e, 0x02, 0x01, 0x03]).is_err());
+        assert!(Event::from_bytes(&[0x3e, 0x00]).is_err());
+    }
+
+    #[test]
+    fn packet_invalid() {
+        assert!(Packet::from_bytes(&[]).is_err());
+        assert!(Packet::from_bytes(&[0x00, 0x01]).is_err());
+        let packet = Packet::from_bytes(&[PacketType::Iso as u8, 0x01, 0x02]).unwrap();
+        assert_eq!(packet, Packet::Iso(vec![0x01, 0x02]));
+    }
+
+    #[test]
+    fn monitor_new_index() {
+        let mut buf = vec![0x00, 0x00, 0x00, 0x00, 0x10, 0x00];
+        buf.extend_from_slice(&[0x00, 0x01, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11]);
+        buf.extend_from_slice(b"hci0\0\0\0\0");
+        let packet = MonitorPacket::from_bytes(&buf).unwrap();
+        assert_eq!(packet.index, 0);
+        assert_eq!(
+            packet.event,
+            MonitorEvent::NewIndex {
+                controller_type: 0,
+                bus: 1,
+                addr: Address::new([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]),
+                name: "hci0".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn monitor_event() {
+        let buf = [0x03, 0x00, 0x01, 0x00, 0x07, 0x00, 0x0e, 0x05, 0x01, 0x09, 0x10, 0x00, 0xff];
+        let packet = MonitorPacket::from_bytes(&buf).unwrap();
+        assert_eq!(packet.index, 1);
+        assert_eq!(
+            packet.event,
+            MonitorEvent::Event(Event::CommandComplete {
+                num_packets: 1,
+                opcode: Opcode::READ_BD_ADDR,
+                return_params: vec![0x00, 0xff],
+            })
+        );
+    }
+
+    #[test]
+    fn monitor_user_logging() {
+        let mut buf = vec![0x0d, 0x00, 0xff, 0xff, 0x0b, 0x00, 0x06, 0x05];
+        buf.extend_from_slice(b"test\0msg\0");
+        let packet = MonitorPacket::from_bytes(&buf).unwrap();
+        assert_eq!(packet.index, DEV_NONE);
+        assert_eq!(
+            packet.event,
+            MonitorEvent::UserLogging { priority: 6, ident: "test".to_string(), message: "msg".to_string() }
+        );
+    }
+
+    #[test]
+    fn monitor_invalid() {
+        assert!(MonitorPacket::from_bytes(&[0x00, 0x00, 0x00]).is_err());
+        assert!(MonitorPacket::from_bytes(&[0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x0e]).is_err());
+        assert!(MonitorPacket::from_bytes(&[0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x01]).is_err());
+    }
+}
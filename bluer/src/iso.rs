@@ -0,0 +1,910 @@
+//! Isochronous channel (ISO) sockets for LE Audio.
+//!
+//! ISO sockets carry time-bounded audio data over Bluetooth Low Energy,
+//! either as a connected isochronous stream (CIS) between two devices (unicast)
+//! or as a broadcast isochronous stream (BIS) from one source to any number of sinks.
+//!
+//! A unicast stream is established using [SeqPacket::connect] and received using a [Listener].
+//!
+//! A broadcast source is created using [SeqPacket::broadcast].
+//! A broadcast sink is created by binding a [Listener] to a [SocketAddr] whose [broadcast](SocketAddr::broadcast)
+//! part specifies the address and advertising SID of the broadcast source and the BIS indices to synchronize to.
+//!
+//! ISO sockets require Linux 6.4 or later.
+//! Depending on the kernel configuration, ISO socket support may need to be enabled as an experimental feature
+//! of the Bluetooth subsystem.
+//!
+
+use crate::{
+    sock::{self, OwnedFd},
+    sys::{
+        bt_iso_bcast_qos, bt_iso_io_qos, bt_iso_qos, bt_iso_ucast_qos, sockaddr_iso, sockaddr_iso_bc,
+        BTPROTO_ISO, BT_DEFER_SETUP, BT_ISO_QOS, BT_ISO_QOS_BIG_UNSET, BT_ISO_QOS_BIS_UNSET,
+        BT_ISO_QOS_CIG_UNSET, BT_ISO_QOS_CIS_UNSET, BT_ISO_SYNC_TIMEOUT, ISO_MAX_NUM_BIS,
+        SOCKADDR_ISO_UNICAST_LEN,
+    },
+    Address, AddressType,
+};
+use futures::ready;
+use libc::{
+    AF_BLUETOOTH, EAGAIN, EINPROGRESS, SHUT_RD, SHUT_RDWR, SHUT_WR, SOCK_SEQPACKET, SOL_BLUETOOTH, SOL_SOCKET,
+    SO_ERROR, SO_RCVBUF, TIOCINQ, TIOCOUTQ,
+};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+    io::{Error, ErrorKind, Result},
+    mem::size_of,
+    net::Shutdown,
+    os::{
+        raw::c_int,
+        unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    },
+    str::FromStr,
+    task::{Context, Poll},
+};
+use tokio::io::{unix::AsyncFd, ReadBuf};
+
+/// Possible bit values for [IoQos::phy].
+pub mod phy {
+    pub use crate::sys::{
+        BT_ISO_PHY_1M as LE_1M, BT_ISO_PHY_2M as LE_2M, BT_ISO_PHY_ANY as ANY, BT_ISO_PHY_CODED as LE_CODED,
+    };
+}
+
+/// Maximum number of broadcast isochronous streams (BIS) in a [BroadcastAddr].
+pub const MAX_NUM_BIS: usize = ISO_MAX_NUM_BIS;
+
+/// An ISO socket address.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SocketAddr {
+    /// Device address.
+    ///
+    /// When listening or binding, specify [Address::any] for any local adapter address.
+    ///
+    /// When connecting, specify [Address::any] to establish a broadcast isochronous stream
+    /// instead of a connected isochronous stream.
+    pub addr: Address,
+    /// Device address type.
+    pub addr_type: AddressType,
+    /// Broadcast part of the address.
+    ///
+    /// When binding, this specifies the broadcast isochronous streams
+    /// to transmit or synchronize to.
+    pub broadcast: Option<BroadcastAddr>,
+}
+
+impl SocketAddr {
+    /// Creates a new ISO socket address for a connected isochronous stream.
+    pub const fn new(addr: Address, addr_type: AddressType) -> Self {
+        Self { addr, addr_type, broadcast: None }
+    }
+
+    /// Creates a new ISO socket address with the specified broadcast part.
+    pub const fn new_broadcast(addr: Address, addr_type: AddressType, broadcast: BroadcastAddr) -> Self {
+        Self { addr, addr_type, broadcast: Some(broadcast) }
+    }
+
+    /// When specified to [Socket::bind] binds to any public, local adapter address.
+    ///
+    /// When specified to [Socket::connect] establishes a broadcast isochronous stream.
+    pub const fn any_le() -> Self {
+        Self { addr: Address::any(), addr_type: AddressType::LePublic, broadcast: None }
+    }
+}
+
+/// Broadcast part of an ISO socket address.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BroadcastAddr {
+    /// Address of the broadcast source.
+    ///
+    /// When binding a broadcast source, specify [Address::any].
+    pub addr: Address,
+    /// Address type of the broadcast source.
+    pub addr_type: AddressType,
+    /// Advertising set identifier (SID) of the periodic advertising train
+    /// carrying the broadcast isochronous group.
+    pub sid: u8,
+    /// Indices of the broadcast isochronous streams (BIS).
+    ///
+    /// At most [MAX_NUM_BIS] streams can be specified, otherwise
+    /// binding or connecting fails with an invalid input error.
+    pub bis: Vec<u8>,
+}
+
+impl sock::SysSockAddr for SocketAddr {
+    type SysSockAddr = sockaddr_iso;
+
+    fn into_sys_sock_addr(self) -> Result<Self::SysSockAddr> {
+        let broadcast = self.broadcast.unwrap_or_default();
+        let num_bis = broadcast.bis.len();
+        if num_bis > ISO_MAX_NUM_BIS {
+            return Err(Error::new(ErrorKind::InvalidInput, "too many broadcast isochronous streams"));
+        }
+        let mut bc_bis = [0; ISO_MAX_NUM_BIS];
+        bc_bis[..num_bis].copy_from_slice(&broadcast.bis);
+
+        Ok(sockaddr_iso {
+            iso_family: AF_BLUETOOTH as _,
+            iso_bdaddr: self.addr.into(),
+            iso_bdaddr_type: self.addr_type as _,
+            iso_bc: sockaddr_iso_bc {
+                bc_bdaddr: broadcast.addr.into(),
+                bc_bdaddr_type: broadcast.addr_type as _,
+                bc_sid: broadcast.sid,
+                bc_num_bis: num_bis as _,
+                bc_bis,
+            },
+        })
+    }
+
+    fn try_from_sys_sock_addr(saddr: Self::SysSockAddr) -> Result<Self> {
+        if saddr.iso_family != AF_BLUETOOTH as _ {
+            return Err(Error::new(ErrorKind::InvalidInput, "sockaddr_iso::iso_family is not AF_BLUETOOTH"));
+        }
+
+        let bc = &saddr.iso_bc;
+        let broadcast = if bc.bc_num_bis > 0 {
+            let num_bis = usize::from(bc.bc_num_bis).min(ISO_MAX_NUM_BIS);
+            Some(BroadcastAddr {
+                addr: Address::from(bc.bc_bdaddr.clone()),
+                addr_type: AddressType::from_u8(bc.bc_bdaddr_type).unwrap_or_default(),
+                sid: bc.bc_sid,
+                bis: bc.bc_bis[..num_bis].to_vec(),
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            addr: Address::from(saddr.iso_bdaddr),
+            addr_type: AddressType::from_u8(saddr.iso_bdaddr_type)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid sockaddr_iso::iso_bdaddr_type"))?,
+            broadcast,
+        })
+    }
+
+    fn sys_sock_addr_len(&self) -> usize {
+        if self.broadcast.is_some() {
+            size_of::<sockaddr_iso>()
+        } else {
+            SOCKADDR_ISO_UNICAST_LEN
+        }
+    }
+
+    fn is_valid_sys_sock_addr_len(len: usize) -> bool {
+        len == SOCKADDR_ISO_UNICAST_LEN || len == size_of::<sockaddr_iso>()
+    }
+}
+
+/// ISO framing mode.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Framing {
+    /// Unframed.
+    ///
+    /// Each SDU is transmitted in a single ISO data PDU.
+    Unframed = 0x00,
+    /// Framed.
+    ///
+    /// SDUs may be segmented into multiple ISO data PDUs.
+    Framed = 0x01,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Self::Unframed
+    }
+}
+
+/// ISO packing scheme of multiple streams in a group.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Packing {
+    /// Sequential.
+    Sequential = 0x00,
+    /// Interleaved.
+    Interleaved = 0x01,
+}
+
+impl Default for Packing {
+    fn default() -> Self {
+        Self::Sequential
+    }
+}
+
+/// QoS of one direction of an isochronous stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IoQos {
+    /// SDU interval in microseconds.
+    pub interval: u32,
+    /// Maximum transport latency in milliseconds.
+    pub latency: u16,
+    /// Maximum SDU size in bytes.
+    ///
+    /// Set to zero if this direction is unused.
+    pub sdu: u16,
+    /// PHY bit field.
+    ///
+    /// Possible values are defined in the [phy] module.
+    pub phy: u8,
+    /// Retransmission number.
+    pub rtn: u8,
+}
+
+impl Default for IoQos {
+    /// The default used by the kernel: 10 ms SDU interval, 10 ms latency,
+    /// 40 byte SDUs on the LE 2M PHY with two retransmissions.
+    fn default() -> Self {
+        Self { interval: 10_000, latency: 10, sdu: 40, phy: phy::LE_2M, rtn: 2 }
+    }
+}
+
+impl From<IoQos> for bt_iso_io_qos {
+    fn from(q: IoQos) -> Self {
+        bt_iso_io_qos { interval: q.interval, latency: q.latency, sdu: q.sdu, phy: q.phy, rtn: q.rtn }
+    }
+}
+
+impl From<bt_iso_io_qos> for IoQos {
+    fn from(q: bt_iso_io_qos) -> Self {
+        Self { interval: q.interval, latency: q.latency, sdu: q.sdu, phy: q.phy, rtn: q.rtn }
+    }
+}
+
+/// QoS of a connected isochronous stream (CIS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnicastQos {
+    /// Connected isochronous group (CIG) identifier.
+    ///
+    /// Use [UnicastQos::CIG_UNSET] to let the kernel allocate one.
+    pub cig: u8,
+    /// Connected isochronous stream (CIS) identifier.
+    ///
+    /// Use [UnicastQos::CIS_UNSET] to let the kernel allocate one.
+    pub cis: u8,
+    /// Sleep clock accuracy.
+    pub sca: u8,
+    /// Packing scheme.
+    pub packing: Packing,
+    /// Framing mode.
+    pub framing: Framing,
+    /// QoS of received data.
+    pub input: IoQos,
+    /// QoS of transmitted data.
+    pub output: IoQos,
+}
+
+impl UnicastQos {
+    /// Unset CIG identifier.
+    pub const CIG_UNSET: u8 = BT_ISO_QOS_CIG_UNSET;
+    /// Unset CIS identifier.
+    pub const CIS_UNSET: u8 = BT_ISO_QOS_CIS_UNSET;
+}
+
+impl Default for UnicastQos {
+    fn default() -> Self {
+        Self {
+            cig: Self::CIG_UNSET,
+            cis: Self::CIS_UNSET,
+            sca: 0,
+            packing: Packing::default(),
+            framing: Framing::default(),
+            input: IoQos::default(),
+            output: IoQos::default(),
+        }
+    }
+}
+
+impl From<UnicastQos> for bt_iso_ucast_qos {
+    fn from(q: UnicastQos) -> Self {
+        bt_iso_ucast_qos {
+            cig: q.cig,
+            cis: q.cis,
+            sca: q.sca,
+            packing: q.packing as _,
+            framing: q.framing as _,
+            in_: q.input.into(),
+            out: q.output.into(),
+        }
+    }
+}
+
+impl TryFrom<bt_iso_ucast_qos> for UnicastQos {
+    type Error = Error;
+    fn try_from(q: bt_iso_ucast_qos) -> Result<Self> {
+        Ok(Self {
+            cig: q.cig,
+            cis: q.cis,
+            sca: q.sca,
+            packing: Packing::from_u8(q.packing)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid bt_iso_ucast_qos::packing"))?,
+            framing: Framing::from_u8(q.framing)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid bt_iso_ucast_qos::framing"))?,
+            input: q.in_.into(),
+            output: q.out.into(),
+        })
+    }
+}
+
+/// Broadcast code used to encrypt a broadcast isochronous group.
+///
+/// ## String representation
+/// A broadcast code can be parsed from a string of at most 16 bytes in UTF-8 encoding,
+/// which is padded with zeros, as specified by the Bluetooth Core specification.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BroadcastCode(pub [u8; 16]);
+
+impl fmt::Debug for BroadcastCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BroadcastCode(..)")
+    }
+}
+
+impl From<[u8; 16]> for BroadcastCode {
+    fn from(code: [u8; 16]) -> Self {
+        Self(code)
+    }
+}
+
+/// Invalid broadcast code error.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InvalidBroadcastCode;
+
+impl fmt::Display for InvalidBroadcastCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "broadcast code must be at most 16 bytes long")
+    }
+}
+
+impl std::error::Error for InvalidBroadcastCode {}
+
+impl FromStr for BroadcastCode {
+    type Err = InvalidBroadcastCode;
+    fn from_str(s: &str) -> std::result::Result<Self, InvalidBroadcastCode> {
+        let bytes = s.as_bytes();
+        if bytes.len() > 16 {
+            return Err(InvalidBroadcastCode);
+        }
+        let mut code = [0; 16];
+        code[..bytes.len()].copy_from_slice(bytes);
+        Ok(Self(code))
+    }
+}
+
+/// QoS of a broadcast isochronous stream (BIS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BroadcastQos {
+    /// Broadcast isochronous group (BIG) identifier.
+    ///
+    /// Use [BroadcastQos::BIG_UNSET] to let the kernel allocate one.
+    pub big: u8,
+    /// Broadcast isochronous stream (BIS) index.
+    ///
+    /// Use [BroadcastQos::BIS_UNSET] to let the kernel allocate one.
+    pub bis: u8,
+    /// Ratio of the periodic advertising interval to the ISO interval.
+    pub sync_factor: u8,
+    /// Packing scheme.
+    pub packing: Packing,
+    /// Framing mode.
+    pub framing: Framing,
+    /// QoS of received data, used by a broadcast sink.
+    pub input: IoQos,
+    /// QoS of transmitted data, used by a broadcast source.
+    pub output: IoQos,
+    /// Broadcast code, if the broadcast isochronous group is encrypted.
+    pub encryption: Option<BroadcastCode>,
+    /// Periodic advertising synchronization options.
+    pub options: u8,
+    /// Maximum number of periodic advertising events that can be skipped.
+    pub skip: u16,
+    /// Periodic advertising synchronization timeout in units of 10 ms.
+    pub sync_timeout: u16,
+    /// Constant tone extension (CTE) types not to synchronize to.
+    pub sync_cte_type: u8,
+    /// Maximum number of subevents used to receive data.
+    pub mse: u8,
+    /// Broadcast isochronous group synchronization timeout in units of 10 ms.
+    pub timeout: u16,
+}
+
+impl BroadcastQos {
+    /// Unset BIG identifier.
+    pub const BIG_UNSET: u8 = BT_ISO_QOS_BIG_UNSET;
+    /// Unset BIS index.
+    pub const BIS_UNSET: u8 = BT_ISO_QOS_BIS_UNSET;
+}
+
+impl Default for BroadcastQos {
+    fn default() -> Self {
+        Self {
+            big: Self::BIG_UNSET,
+            bis: Self::BIS_UNSET,
+            sync_factor: 0x01,
+            packing: Packing::default(),
+            framing: Framing::default(),
+            input: IoQos::default(),
+            output: IoQos::default(),
+            encryption: None,
+            options: 0,
+            skip: 0,
+            sync_timeout: BT_ISO_SYNC_TIMEOUT,
+            sync_cte_type: 0,
+            mse: 0,
+            timeout: BT_ISO_SYNC_TIMEOUT,
+        }
+    }
+}
+
+impl From<BroadcastQos> for bt_iso_bcast_qos {
+    fn from(q: BroadcastQos) -> Self {
+        bt_iso_bcast_qos {
+            big: q.big,
+            bis: q.bis,
+            sync_factor: q.sync_factor,
+            packing: q.packing as _,
+            framing: q.framing as _,
+            in_: q.input.into(),
+            out: q.output.into(),
+            encryption: q.encryption.is_some().into(),
+            bcode: q.encryption.unwrap_or_default().0,
+            options: q.options,
+            skip: q.skip,
+            sync_timeout: q.sync_timeout,
+            sync_cte_type: q.sync_cte_type,
+            mse: q.mse,
+            timeout: q.timeout,
+        }
+    }
+}
+
+impl TryFrom<bt_iso_bcast_qos> for BroadcastQos {
+    type Error = Error;
+    fn try_from(q: bt_iso_bcast_qos) -> Result<Self> {
+        Ok(Self {
+            big: q.big,
+            bis: q.bis,
+            sync_factor: q.sync_factor,
+            packing: Packing::from_u8(q.packing)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid bt_iso_bcast_qos::packing"))?,
+            framing: Framing::from_u8(q.framing)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid bt_iso_bcast_qos::framing"))?,
+            input: q.in_.into(),
+            output: q.out.into(),
+            encryption: if q.encryption != 0 { Some(BroadcastCode(q.bcode)) } else { None },
+            options: q.options,
+            skip: q.skip,
+            sync_timeout: q.sync_timeout,
+            sync_cte_type: q.sync_cte_type,
+            mse: q.mse,
+            timeout: q.timeout,
+        })
+    }
+}
+
+/// An ISO socket that has not yet been converted to a [Listener] or [SeqPacket].
+///
+/// The primary use of this is to configure the socket before connecting or listening.
+pub struct Socket {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Socket").field("fd", &self.fd.as_raw_fd()).finish()
+    }
+}
+
+impl Socket {
+    /// Creates a new socket of sequential packet type.
+    pub fn new() -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(sock::socket(AF_BLUETOOTH, SOCK_SEQPACKET, BTPROTO_ISO)?)? })
+    }
+
+    /// Convert the socket into a [Listener].
+    ///
+    /// If the socket is bound to a [broadcast address](SocketAddr::broadcast), this
+    /// synchronizes to the specified broadcast isochronous streams.
+    ///
+    /// `backlog` defines the maximum number of pending connections are queued by the operating system
+    /// at any given time.
+    pub fn listen(self, backlog: u32) -> Result<Listener> {
+        sock::listen(
+            self.fd.get_ref(),
+            backlog.try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid backlog"))?,
+        )?;
+        Ok(Listener { socket: self })
+    }
+
+    /// Establish an isochronous stream with a peer at the specified socket address.
+    ///
+    /// If the address is [Address::any], a broadcast isochronous stream is
+    /// established using the broadcast parameters the socket is bound to.
+    pub async fn connect(self, sa: SocketAddr) -> Result<SeqPacket> {
+        self.connect_priv(sa).await?;
+        Ok(SeqPacket { socket: self })
+    }
+
+    /// Bind the socket to the given address.
+    pub fn bind(&self, sa: SocketAddr) -> Result<()> {
+        sock::bind(self.fd.get_ref(), sa)
+    }
+
+    /// Get the local address of this socket.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        sock::getsockname(self.fd.get_ref())
+    }
+
+    /// Get the peer address of this socket.
+    fn peer_addr_priv(&self) -> Result<SocketAddr> {
+        sock::getpeername(self.fd.get_ref())
+    }
+
+    fn qos(&self) -> Result<bt_iso_qos> {
+        sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_ISO_QOS)
+    }
+
+    fn set_qos(&self, qos: &bt_iso_qos) -> Result<()> {
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_ISO_QOS, qos)
+    }
+
+    /// Get QoS of a connected isochronous stream.
+    ///
+    /// This corresponds to the `BT_ISO_QOS` socket option.
+    pub fn unicast_qos(&self) -> Result<UnicastQos> {
+        let qos = self.qos()?;
+        UnicastQos::try_from(unsafe { qos.ucast })
+    }
+
+    /// Set QoS of a connected isochronous stream.
+    ///
+    /// This must be set before connecting or listening.
+    ///
+    /// This corresponds to the `BT_ISO_QOS` socket option.
+    pub fn set_unicast_qos(&self, qos: UnicastQos) -> Result<()> {
+        let mut value = self.qos()?;
+        value.ucast = qos.into();
+        self.set_qos(&value)
+    }
+
+    /// Get QoS of a broadcast isochronous stream.
+    ///
+    /// This corresponds to the `BT_ISO_QOS` socket option.
+    pub fn broadcast_qos(&self) -> Result<BroadcastQos> {
+        let qos = self.qos()?;
+        BroadcastQos::try_from(unsafe { qos.bcast })
+    }
+
+    /// Set QoS of a broadcast isochronous stream.
+    ///
+    /// This must be set before connecting or listening.
+    /// Set [BroadcastQos::encryption] to the broadcast code for
+    /// transmitting or receiving an encrypted broadcast.
+    ///
+    /// This corresponds to the `BT_ISO_QOS` socket option.
+    pub fn set_broadcast_qos(&self, qos: BroadcastQos) -> Result<()> {
+        let value = bt_iso_qos { bcast: qos.into() };
+        self.set_qos(&value)
+    }
+
+    /// Get whether deferred setup of incoming connections is enabled.
+    ///
+    /// This corresponds to the `BT_DEFER_SETUP` socket option.
+    pub fn is_defer_setup(&self) -> Result<bool> {
+        let value: u32 = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_DEFER_SETUP)?;
+        Ok(value != 0)
+    }
+
+    /// Set whether deferred setup of incoming connections is enabled.
+    ///
+    /// When enabled on a listening socket, incoming streams are accepted
+    /// without being confirmed.
+    /// The stream is confirmed once the accepted socket is first received from
+    /// and rejected when it is closed beforehand.
+    ///
+    /// This corresponds to the `BT_DEFER_SETUP` socket option.
+    pub fn set_defer_setup(&self, defer_setup: bool) -> Result<()> {
+        let value = u32::from(defer_setup);
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_DEFER_SETUP, &value)
+    }
+
+    /// Get maximum transmission unit (MTU) for sending.
+    ///
+    /// This corresponds to the maximum SDU size of the [output QoS](UnicastQos::output).
+    pub fn send_mtu(&self) -> Result<u16> {
+        Ok(unsafe { self.qos()?.ucast.out.sdu })
+    }
+
+    /// Get maximum transmission unit (MTU) for receiving.
+    ///
+    /// This corresponds to the maximum SDU size of the [input QoS](UnicastQos::input).
+    pub fn recv_mtu(&self) -> Result<u16> {
+        Ok(unsafe { self.qos()?.ucast.in_.sdu })
+    }
+
+    /// Gets the maximum socket receive buffer in bytes.
+    ///
+    /// This corresponds to the `SO_RCVBUF` socket option.
+    pub fn recv_buffer(&self) -> Result<i32> {
+        sock::getsockopt(self.fd.get_ref(), SOL_SOCKET, SO_RCVBUF)
+    }
+
+    /// Sets the maximum socket receive buffer in bytes.
+    ///
+    /// This corresponds to the `SO_RCVBUF` socket option.
+    pub fn set_recv_buffer(&self, recv_buffer: i32) -> Result<()> {
+        sock::setsockopt(self.fd.get_ref(), SOL_SOCKET, SO_RCVBUF, &recv_buffer)
+    }
+
+    /// Get the number of bytes in the input buffer.
+    ///
+    /// This corresponds to the `TIOCINQ` IOCTL.
+    pub fn input_buffer(&self) -> Result<u32> {
+        let value: c_int = sock::ioctl_read(self.fd.get_ref(), TIOCINQ)?;
+        Ok(value as _)
+    }
+
+    /// Get the number of bytes in the output buffer.
+    ///
+    /// This corresponds to the `TIOCOUTQ` IOCTL.
+    pub fn output_buffer(&self) -> Result<u32> {
+        let value: c_int = sock::ioctl_read(self.fd.get_ref(), TIOCOUTQ)?;
+        Ok(value as _)
+    }
+
+    /// Constructs a new [Socket] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(OwnedFd::new(fd))? })
+    }
+
+    fn from_owned_fd(fd: OwnedFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(fd)? })
+    }
+
+    sock_priv!();
+    shutdown_sock_priv!();
+}
+
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for Socket {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_inner().into_raw_fd()
+    }
+}
+
+impl FromRawFd for Socket {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Socket::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// An ISO socket server, listening for [SeqPacket] connections.
+///
+/// When bound to a [broadcast address](SocketAddr::broadcast), this acts as a broadcast sink
+/// and each accepted [SeqPacket] is a broadcast isochronous stream.
+#[derive(Debug)]
+pub struct Listener {
+    socket: Socket,
+}
+
+impl Listener {
+    /// Creates a new Listener, which will be bound to the specified socket address.
+    ///
+    /// Specify [SocketAddr::any_le] for any local adapter address.
+    ///
+    /// Use [Socket::new], [Socket::bind] and [Socket::listen] instead if you need to
+    /// configure the QoS.
+    pub async fn bind(sa: SocketAddr) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(sa)?;
+        socket.listen(1)
+    }
+
+    /// Accepts a new incoming connection from this listener.
+    pub async fn accept(&self) -> Result<(SeqPacket, SocketAddr)> {
+        let (socket, sa) = self.socket.accept_priv().await?;
+        Ok((SeqPacket { socket }, sa))
+    }
+
+    /// Polls to accept a new incoming connection to this listener.
+    pub fn poll_accept(&self, cx: &mut Context) -> Poll<Result<(SeqPacket, SocketAddr)>> {
+        let (socket, sa) = ready!(self.socket.poll_accept_priv(cx))?;
+        Poll::Ready(Ok((SeqPacket { socket }, sa)))
+    }
+
+    /// Constructs a new [Listener] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)? })
+    }
+}
+
+impl AsRef<Socket> for Listener {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for Listener {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Listener::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// An isochronous stream, transferring one SDU per packet.
+#[derive(Debug)]
+pub struct SeqPacket {
+    socket: Socket,
+}
+
+impl SeqPacket {
+    /// Establish a connected isochronous stream (CIS) with a peer at the specified socket address
+    /// using the specified QoS.
+    ///
+    /// Uses any local Bluetooth adapter.
+    pub async fn connect(addr: SocketAddr, qos: UnicastQos) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(SocketAddr::any_le())?;
+        socket.set_unicast_qos(qos)?;
+        socket.connect(addr).await
+    }
+
+    /// Creates a broadcast source transmitting a broadcast isochronous stream (BIS)
+    /// using the specified QoS.
+    ///
+    /// The [broadcast part](SocketAddr::broadcast) of the local address `sa` specifies
+    /// the advertising SID and BIS indices.
+    pub async fn broadcast(sa: SocketAddr, qos: BroadcastQos) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(sa)?;
+        socket.set_broadcast_qos(qos)?;
+        socket.connect(SocketAddr::any_le()).await
+    }
+
+    /// Gets the peer address of this stream.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.socket.peer_addr_priv()
+    }
+
+    /// Sends an SDU.
+    ///
+    /// The SDU length must not exceed the [Self::send_mtu].
+    pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+        self.socket.send_priv(buf).await
+    }
+
+    /// Attempts to send an SDU.
+    ///
+    /// The SDU length must not exceed the [Self::send_mtu].
+    pub fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize>> {
+        self.socket.poll_send_priv(cx, buf)
+    }
+
+    /// Receives an SDU.
+    ///
+    /// The provided buffer must be of length [Self::recv_mtu], otherwise
+    /// the SDU may be truncated.
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        self.socket.recv_priv(buf).await
+    }
+
+    /// Attempts to receive an SDU.
+    ///
+    /// The provided buffer must be of length [Self::recv_mtu], otherwise
+    /// the SDU may be truncated.
+    pub fn poll_recv(&self, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<()>> {
+        self.socket.poll_recv_priv(cx, buf)
+    }
+
+    /// Shuts down the read, write, or both halves of this stream.
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        self.socket.shutdown_priv(how)
+    }
+
+    /// Maximum transmission unit (MTU) for sending.
+    pub fn send_mtu(&self) -> Result<usize> {
+        self.socket.send_mtu().map(|v| v.into())
+    }
+
+    /// Maximum transmission unit (MTU) for receiving.
+    pub fn recv_mtu(&self) -> Result<usize> {
+        self.socket.recv_mtu().map(|v| v.into())
+    }
+
+    /// Constructs a new [SeqPacket] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)? })
+    }
+}
+
+impl AsRef<Socket> for SeqPacket {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for SeqPacket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for SeqPacket {
+    fn into_raw_fd(self) -> RawFd {
+        self.socket.into_raw_fd()
+    }
+}
+
+impl FromRawFd for SeqPacket {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [SeqPacket::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
@@ -128,14 +128,14 @@ impl SocketAddr {
 impl sock::SysSockAddr for SocketAddr {
     type SysSockAddr = sockaddr_l2;
 
-    fn into_sys_sock_addr(self) -> Self::SysSockAddr {
-        sockaddr_l2 {
+    fn into_sys_sock_addr(self) -> Result<Self::SysSockAddr> {
+        Ok(sockaddr_l2 {
             l2_family: AF_BLUETOOTH as _,
             l2_psm: self.psm.to_le(),
             l2_cid: self.cid.to_le(),
             l2_bdaddr: self.addr.into(),
             l2_bdaddr_type: self.addr_type as _,
-        }
+        })
     }
 
     fn try_from_sys_sock_addr(saddr: Self::SysSockAddr) -> Result<Self> {
@@ -431,6 +431,8 @@ impl<Type> Socket<Type> {
     }
 
     sock_priv!();
+    shutdown_sock_priv!();
+    stream_sock_priv!();
 }
 
 impl<Type> AsRawFd for Socket<Type> {
@@ -914,6 +916,12 @@ impl AsRawFd for SeqPacket {
     }
 }
 
+impl IntoRawFd for SeqPacket {
+    fn into_raw_fd(self) -> RawFd {
+        self.socket.into_raw_fd()
+    }
+}
+
 impl FromRawFd for SeqPacket {
     /// Constructs a new instance of `Self` from the given raw file
     /// descriptor.
@@ -32,7 +32,13 @@
 //!         * callback-based interface
 //!         * low-overhead [AsyncRead] and [AsyncWrite] streams
 //! * [sending Bluetooth Low Energy advertisements](Adapter::advertise)
+//! * [passive monitoring of Bluetooth Low Energy advertisements](Adapter::register_monitor)
+//! * [encoding and decoding of advertising data](ad)
+//! * [iBeacon, Eddystone and AltBeacon proximity beacons](beacon)
 //! * [Bluetooth authorization agent](agent::Agent)
+//! * [battery level of remote devices](battery) and battery providers
+//! * [media endpoints and transports](media) for audio streaming and [remote control of media players](media::player)
+//! * [personal area networking (PAN)](network) as client and server
 //! * efficient event dispatching
 //!     * not affected by D-Bus match rule count
 //!     * O(1) in number of subscriptions
@@ -46,6 +52,32 @@
 //!     * support for classic Bluetooth (BR/EDR)
 //!     * stream oriented
 //!     * async IO interface with [AsyncRead] and [AsyncWrite] support
+//! * [SDP records and client](sdp)
+//!     * typed builder for service records serialized into the XML format of the Bluetooth daemon
+//!     * service search attribute queries of remote devices
+//! * [object exchange (OBEX)](obex)
+//!     * native client and server over RFCOMM and L2CAP with single response mode
+//!     * object push, file transfer, phonebook access and message access using the Bluetooth OBEX daemon
+//!     * progress of transfers
+//! * [SCO sockets](sco) for voice links
+//!     * support for classic Bluetooth (BR/EDR)
+//!     * sequential packet oriented
+//!     * CVSD and transparent voice settings
+//! * [ISO sockets](iso) for LE Audio
+//!     * connected isochronous streams (CIS) and broadcast isochronous streams (BIS)
+//!     * sequential packet oriented
+//!     * QoS configuration and encrypted broadcasts
+//! * [HCI sockets](hci) for diagnostics
+//!     * raw and user channel access to controllers
+//!     * monitor channel for all HCI traffic
+//!     * parsing of commands, events and ACL data
+//! * [capture of HCI traffic](capture) for troubleshooting
+//!     * btsnoop and pcapng file formats
+//!     * file rotation and in-memory ring buffer
+//! * [management API client](mgmt) for controller settings not exposed by the Bluetooth daemon
+//!     * static address, LE-only and Secure Connections Only modes
+//!     * PHY configuration and experimental features
+//!     * auto-connect devices and connection parameters
 //! * [database of assigned numbers](id)
 //!     * manufacturer ids
 //!     * services classes, GATT services, characteristics and descriptors
@@ -57,9 +89,17 @@
 //! The following crate features are available.
 //!
 //! * `bluetoothd`: Enables all functions requiring a running Bluetooth daemon.
+//! * `capture`: Enables capture of HCI traffic into files.
+//! * `hci`: Enables HCI sockets.
 //! * `id`: Enables database of assigned numbers.
+//! * `iso`: Enables ISO sockets.
 //! * `l2cap`: Enables L2CAP sockets.
+//! * `mgmt`: Enables the Bluetooth management API client.
+//! * `mock`: Enables a mock Bluetooth daemon for testing.
+//! * `obex`: Enables object exchange (OBEX). Using the OBEX daemon additionally requires `bluetoothd`.
 //! * `rfcomm`: Enables RFCOMM sockets.
+//! * `sco`: Enables SCO sockets.
+//! * `sdp`: Enables SDP records and client.
 //! * `serde`: Enables serialization and deserialization of some data types.
 //!
 //! To enable all crate features specify the `full` crate feature.
@@ -69,10 +109,14 @@
 //! Then obtain a Bluetooth adapter using [Session::adapter].
 //! From there on you can access most of the functionality using the methods provided by [Adapter].
 //!
-//! ## L2CAP and RFCOMM sockets
-//! Refer to the [l2cap] and [rfcomm] modules.
+//! ## L2CAP, RFCOMM, SCO, ISO and HCI sockets
+//! Refer to the [l2cap], [rfcomm], [sco], [iso] and [hci] modules.
 //! No [Session] and therefore no running Bluetooth daemon is required.
 //!
+//! ## Bluetooth management API
+//! Refer to the [mgmt] module.
+//! It also works without a running Bluetooth daemon.
+//!
 //! [Linux Bluetooth protocol stack (BlueZ)]: http://www.bluez.org/
 //! [GATT services]: https://www.oreilly.com/library/view/getting-started-with/9781491900550/ch04.html
 //! [AsyncRead]: tokio::io::AsyncRead
@@ -85,8 +129,8 @@ compile_error!("BlueR only supports the Linux operating system.");
 
 #[cfg(feature = "bluetoothd")]
 use dbus::{
-    arg::{prop_cast, AppendAll, PropMap, RefArg, Variant},
-    nonblock::{stdintf::org_freedesktop_dbus::ObjectManager, Proxy, SyncConnection},
+    arg::{prop_cast, AppendAll, RefArg, Variant},
+    nonblock::{stdintf::org_freedesktop_dbus::ObjectManager, Proxy},
     Path,
 };
 #[cfg(feature = "bluetoothd")]
@@ -113,24 +157,46 @@ use tokio::task::JoinError;
 pub(crate) const SERVICE_NAME: &str = "org.bluez";
 #[cfg(feature = "bluetoothd")]
 pub(crate) const ERR_PREFIX: &str = "org.bluez.Error.";
+#[cfg(all(feature = "obex", feature = "bluetoothd"))]
+pub(crate) const OBEX_ERR_PREFIX: &str = "org.bluez.obex.Error.";
 #[cfg(feature = "bluetoothd")]
 pub(crate) const TIMEOUT: Duration = Duration::from_secs(120);
 
+/// Default D-Bus path prefix for objects published by a session.
 #[cfg(feature = "bluetoothd")]
-macro_rules! publish_path {
-    ($path:expr) => {
-        concat!("/org/bluez/", env!("CARGO_PKG_NAME"), "/", $path)
-    };
-}
+pub(crate) const DEFAULT_PUBLISH_PREFIX: &str = concat!("/org/bluez/", env!("CARGO_PKG_NAME"));
 
+/// Implements property access and method calls for a D-Bus object.
+///
+/// By default properties are served from `self.inner.object_cache` when it is present.
+/// Use `dbus_interface!(uncached)` for objects whose inner state has no object cache.
 #[cfg(feature = "bluetoothd")]
 macro_rules! dbus_interface {
     () => {
+        dbus_interface!(@impl object_cache);
+    };
+    (uncached) => {
+        dbus_interface!(@impl);
+    };
+    (@impl $($object_cache:ident)?) => {
         #[allow(dead_code)]
         async fn get_property_with_interface<R>(&self, name: &str, interface: &str) -> crate::Result<R>
         where
             R: for<'b> dbus::arg::Get<'b> + std::fmt::Debug + 'static,
         {
+            $(
+                if let Some($object_cache) = &self.inner.$object_cache {
+                    return match $object_cache.property(&self.dbus_path, interface, name)? {
+                        Some(value) => Ok(value),
+                        None => Err(dbus::Error::new_custom(
+                            "org.freedesktop.DBus.Error.InvalidArgs",
+                            "No such property",
+                        )
+                        .into()),
+                    };
+                }
+            )?
+
             use dbus::nonblock::stdintf::org_freedesktop_dbus::Properties;
             let value = self.proxy().get(interface, name).await?;
             log::trace!("{}: {}.{} = {:?}", &self.proxy().path, &interface, &name, &value);
@@ -144,6 +210,12 @@ macro_rules! dbus_interface {
         where
             R: for<'b> dbus::arg::Get<'b> + std::fmt::Debug + 'static,
         {
+            $(
+                if let Some($object_cache) = &self.inner.$object_cache {
+                    return $object_cache.property(&self.dbus_path, interface, name);
+                }
+            )?
+
             use dbus::nonblock::stdintf::org_freedesktop_dbus::Properties;
             match self.proxy().get(interface, name).await {
                 Ok(value) => {
@@ -165,7 +237,12 @@ macro_rules! dbus_interface {
         {
             use dbus::nonblock::stdintf::org_freedesktop_dbus::Properties;
             log::trace!("{}: {}.{} := {:?}", &self.proxy().path, &interface, &name, &value);
-            self.proxy().set(interface, name, value).await?;
+            self.proxy().set(interface, name, &value).await?;
+            $(
+                if let Some($object_cache) = &self.inner.$object_cache {
+                    $object_cache.set_property(&self.dbus_path, interface, name, &value);
+                }
+            )?
             Ok(())
         }
 
@@ -505,10 +582,11 @@ macro_rules! read_opt_prop {
     };
 }
 
-#[cfg(any(feature = "l2cap", feature = "rfcomm"))]
+#[cfg(any(feature = "hci", feature = "iso", feature = "l2cap", feature = "rfcomm", feature = "sco"))]
 #[macro_use]
 mod sock;
 
+pub mod ad;
 #[cfg(feature = "bluetoothd")]
 mod adapter;
 #[cfg(feature = "bluetoothd")]
@@ -516,18 +594,55 @@ mod adapter;
 pub mod adv;
 #[cfg(feature = "bluetoothd")]
 #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+pub mod adv_monitor;
+#[cfg(feature = "bluetoothd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
 pub mod agent;
 #[cfg(feature = "bluetoothd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+pub mod battery;
+pub mod beacon;
+#[cfg(feature = "capture")]
+#[cfg_attr(docsrs, doc(cfg(feature = "capture")))]
+pub mod capture;
+#[cfg(feature = "bluetoothd")]
 mod device;
 #[cfg(feature = "bluetoothd")]
 #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
 pub mod gatt;
+#[cfg(feature = "hci")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hci")))]
+pub mod hci;
+#[cfg(feature = "iso")]
+#[cfg_attr(docsrs, doc(cfg(feature = "iso")))]
+pub mod iso;
 #[cfg(feature = "l2cap")]
 #[cfg_attr(docsrs, doc(cfg(feature = "l2cap")))]
 pub mod l2cap;
+#[cfg(feature = "bluetoothd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+pub mod media;
+#[cfg(feature = "mgmt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mgmt")))]
+pub mod mgmt;
+#[cfg(feature = "mock")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mock")))]
+pub mod mock;
+#[cfg(feature = "bluetoothd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+pub mod network;
+#[cfg(feature = "obex")]
+#[cfg_attr(docsrs, doc(cfg(feature = "obex")))]
+pub mod obex;
 #[cfg(feature = "rfcomm")]
 #[cfg_attr(docsrs, doc(cfg(feature = "rfcomm")))]
 pub mod rfcomm;
+#[cfg(feature = "sco")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sco")))]
+pub mod sco;
+#[cfg(feature = "sdp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sdp")))]
+pub mod sdp;
 #[cfg(feature = "bluetoothd")]
 mod session;
 mod sys;
@@ -620,6 +735,9 @@ pub enum ErrorKind {
     /// the target object was either not present or removed
     #[strum(disabled)]
     NotFound,
+    /// invalid advertisement
+    #[strum(disabled)]
+    InvalidAdvertisement(Vec<adv::Problem>),
     /// internal error: {0}
     #[strum(disabled)]
     Internal(InternalErrorKind),
@@ -683,11 +801,10 @@ impl From<dbus::Error> for Error {
         if err.name() == Some("org.freedesktop.DBus.Error.UnknownObject") {
             return Self::new(ErrorKind::NotFound);
         }
-        let kind = match err
-            .name()
-            .and_then(|name| name.strip_prefix(ERR_PREFIX))
-            .and_then(|s| ErrorKind::from_str(s).ok())
-        {
+        let name = err.name().and_then(|name| name.strip_prefix(ERR_PREFIX));
+        #[cfg(feature = "obex")]
+        let name = name.or_else(|| err.name().and_then(|name| name.strip_prefix(OBEX_ERR_PREFIX)));
+        let kind = match name.and_then(|s| ErrorKind::from_str(s).ok()) {
             Some(kind) => kind,
             _ => ErrorKind::Internal(InternalErrorKind::DBus(err.name().unwrap_or_default().to_string())),
         };
@@ -753,6 +870,7 @@ impl From<Error> for std::io::Error {
             ErrorKind::NotificationSessionStopped => E::ConnectionReset,
             ErrorKind::IndicationUnconfirmed => E::TimedOut,
             ErrorKind::NotFound => E::NotFound,
+            ErrorKind::InvalidAdvertisement(_) => E::InvalidInput,
             ErrorKind::Internal(InternalErrorKind::Io(err)) => err,
             ErrorKind::Internal(_) => E::Other,
         };
@@ -983,13 +1101,20 @@ impl FromStr for Modalias {
     }
 }
 
-/// Gets all D-Bus objects from the BlueZ service.
+/// Gets the paths of all D-Bus objects from the BlueZ service implementing the specified interface.
 #[cfg(feature = "bluetoothd")]
-async fn all_dbus_objects(
-    connection: &SyncConnection,
-) -> Result<HashMap<Path<'static>, HashMap<String, PropMap>>> {
-    let p = Proxy::new(SERVICE_NAME, "/", TIMEOUT, connection);
-    Ok(p.get_managed_objects().await?)
+async fn dbus_objects_with_interface(inner: &SessionInner, interface: &str) -> Result<Vec<Path<'static>>> {
+    if let Some(object_cache) = &inner.object_cache {
+        return Ok(object_cache.paths_with_interface(interface));
+    }
+
+    let p = Proxy::new(&*inner.service_name, "/", TIMEOUT, &*inner.connection);
+    let objects = p.get_managed_objects().await?;
+    Ok(objects
+        .into_iter()
+        .filter(|(_, interfaces)| interfaces.contains_key(interface))
+        .map(|(path, _)| path)
+        .collect())
 }
 
 /// Read value from D-Bus dictionary.
@@ -0,0 +1,526 @@
+//! Media endpoints and transports for audio streaming.
+//!
+//! A media endpoint announces that the application can stream audio using a particular
+//! profile and codec, for example as an A2DP sink or source.
+//! Register it using [Adapter::register_media_endpoint].
+//!
+//! Once a remote device connects, the Bluetooth daemon negotiates the codec configuration
+//! with the endpoint and creates a [Transport].
+//! [Acquire](Transport::acquire) the transport to obtain a [TransportStream] for sending
+//! or receiving the encoded audio data.
+//! Acquiring a transport requires the `l2cap` feature for classic audio (A2DP)
+//! and the `iso` feature for LE Audio.
+//!
+//! Media players of remote devices can be remote controlled using the [player] module.
+
+#[cfg(any(feature = "l2cap", feature = "iso"))]
+use dbus::arg::OwnedFd;
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use dbus_crossroads::{Crossroads, IfaceBuilder, IfaceToken};
+use futures::{stream, Future, Stream, StreamExt};
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{Arc, Weak},
+};
+use strum::{Display, EnumString, IntoStaticStr};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::{
+    method_call, Adapter, Address, Device, Error, ErrorKind, Event, InternalErrorKind, Result, SessionInner,
+    ERR_PREFIX, TIMEOUT,
+};
+
+pub mod player;
+#[cfg(any(feature = "l2cap", feature = "iso"))]
+mod transport_stream;
+
+#[cfg(any(feature = "l2cap", feature = "iso"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "l2cap", feature = "iso"))))]
+pub use transport_stream::TransportStream;
+
+pub(crate) const MANAGER_INTERFACE: &str = "org.bluez.Media1";
+pub(crate) const ENDPOINT_INTERFACE: &str = "org.bluez.MediaEndpoint1";
+pub(crate) const TRANSPORT_INTERFACE: &str = "org.bluez.MediaTransport1";
+pub(crate) const ENDPOINT_PREFIX: &str = "media/";
+
+/// Error response from us to a media endpoint request.
+#[derive(Clone, Copy, Debug, displaydoc::Display, Eq, PartialEq, Ord, PartialOrd, Hash, IntoStaticStr)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ReqError {
+    /// Request was rejected.
+    Rejected,
+    /// Invalid arguments.
+    InvalidArguments,
+}
+
+impl std::error::Error for ReqError {}
+
+impl Default for ReqError {
+    fn default() -> Self {
+        Self::Rejected
+    }
+}
+
+impl From<ReqError> for dbus::MethodErr {
+    fn from(err: ReqError) -> Self {
+        let name: &'static str = err.into();
+        Self::from((ERR_PREFIX.to_string() + name, &err.to_string()))
+    }
+}
+
+/// Result of a media endpoint request to us.
+pub type ReqResult<T> = std::result::Result<T, ReqError>;
+
+/// Arguments for a select configuration request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SelectConfiguration {
+    /// Codec capabilities of the remote endpoint.
+    pub capabilities: Vec<u8>,
+}
+
+/// Function handling a select configuration request.
+///
+/// Returns the selected codec configuration.
+pub type SelectConfigurationFn =
+    Box<dyn (Fn(SelectConfiguration) -> Pin<Box<dyn Future<Output = ReqResult<Vec<u8>>> + Send>>) + Send + Sync>;
+
+/// Arguments for a set configuration request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SetConfiguration {
+    /// Adapter making the request.
+    pub adapter: String,
+    /// Address of remote device the transport is connected to.
+    ///
+    /// [None] for broadcast transports.
+    pub device: Option<Address>,
+    /// Transport that has been configured.
+    pub transport: Transport,
+    /// Codec configuration of the transport.
+    pub configuration: Vec<u8>,
+}
+
+/// Function handling a set configuration request.
+pub type SetConfigurationFn =
+    Box<dyn (Fn(SetConfiguration) -> Pin<Box<dyn Future<Output = ReqResult<()>> + Send>>) + Send + Sync>;
+
+/// Arguments for a clear configuration request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ClearConfiguration {
+    /// Transport whose configuration has been cleared.
+    pub transport: Transport,
+}
+
+/// Function handling a clear configuration request.
+pub type ClearConfigurationFn =
+    Box<dyn (Fn(ClearConfiguration) -> Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync>;
+
+/// Local media endpoint definition.
+///
+/// Use [Adapter::register_media_endpoint] to register the endpoint.
+///
+/// Some predefined endpoint UUIDs:
+///
+///   * A2DP source: `0000110a-0000-1000-8000-00805f9b34fb`
+///   * A2DP sink: `0000110b-0000-1000-8000-00805f9b34fb`
+///
+/// The codec is identified by its A2DP codec id, for example `0x00` for SBC,
+/// or `0xff` for vendor-specific codecs.
+#[derive(Default)]
+pub struct Endpoint {
+    /// Profile UUID of the endpoint.
+    pub uuid: Uuid,
+    /// Assigned number of the codec that the endpoint implements.
+    pub codec: u8,
+    /// Vendor-specific company id and codec id.
+    ///
+    /// The company id is stored in the upper 16 bits.
+    /// Only used if the codec is vendor-specific.
+    pub vendor: Option<u32>,
+    /// Codec capabilities of the endpoint.
+    pub capabilities: Vec<u8>,
+    /// Metadata of the endpoint.
+    ///
+    /// Used by LE Audio endpoints only.
+    pub metadata: Option<Vec<u8>>,
+    /// Whether the endpoint supports delay reporting.
+    pub delay_reporting: bool,
+    /// Called to select a codec configuration based on the capabilities of a remote endpoint.
+    ///
+    /// Set to [None] to reject all configuration proposals.
+    /// Note that this is required for the endpoint to initiate streaming.
+    pub select_configuration: Option<SelectConfigurationFn>,
+    /// Called when a transport has been configured for the endpoint.
+    ///
+    /// Returning an error rejects the configuration.
+    /// Set to [None] to accept all configurations.
+    pub set_configuration: Option<SetConfigurationFn>,
+    /// Called when the configuration of a transport has been cleared.
+    pub clear_configuration: Option<ClearConfigurationFn>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("uuid", &self.uuid)
+            .field("codec", &self.codec)
+            .field("vendor", &self.vendor)
+            .field("capabilities", &self.capabilities)
+            .field("metadata", &self.metadata)
+            .field("delay_reporting", &self.delay_reporting)
+            .finish()
+    }
+}
+
+impl Endpoint {
+    fn to_dict(&self) -> PropMap {
+        let mut pm = PropMap::new();
+        pm.insert("UUID".to_string(), Variant(self.uuid.to_string().box_clone()));
+        pm.insert("Codec".to_string(), Variant(self.codec.box_clone()));
+        if let Some(vendor) = &self.vendor {
+            pm.insert("Vendor".to_string(), Variant(vendor.box_clone()));
+        }
+        pm.insert("Capabilities".to_string(), Variant(self.capabilities.box_clone()));
+        if let Some(metadata) = &self.metadata {
+            pm.insert("Metadata".to_string(), Variant(metadata.box_clone()));
+        }
+        pm.insert("DelayReporting".to_string(), Variant(self.delay_reporting.box_clone()));
+        pm
+    }
+}
+
+pub(crate) struct RegisteredEndpoint {
+    inner: Weak<SessionInner>,
+    e: Endpoint,
+}
+
+impl RegisteredEndpoint {
+    pub(crate) fn new(inner: &Arc<SessionInner>, endpoint: Endpoint) -> Self {
+        Self { inner: Arc::downgrade(inner), e: endpoint }
+    }
+
+    fn transport(&self, path: dbus::Path<'static>) -> ReqResult<Transport> {
+        let inner = self.inner.upgrade().ok_or(ReqError::Rejected)?;
+        match Transport::new(inner, path.clone()) {
+            Ok(transport) => Ok(transport),
+            Err(_) => {
+                log::error!("Cannot parse transport path {}", &path);
+                Err(ReqError::Rejected)
+            }
+        }
+    }
+
+    pub(crate) fn register_interface(cr: &mut Crossroads) -> IfaceToken<Arc<Self>> {
+        cr.register(ENDPOINT_INTERFACE, |ib: &mut IfaceBuilder<Arc<Self>>| {
+            ib.method_with_cr_async(
+                "SelectConfiguration",
+                ("capabilities",),
+                ("configuration",),
+                |ctx, cr, (capabilities,): (Vec<u8>,)| {
+                    method_call(ctx, cr, |reg: Arc<Self>| async move {
+                        match &reg.e.select_configuration {
+                            Some(f) => Ok((f(SelectConfiguration { capabilities }).await?,)),
+                            None => Err(ReqError::Rejected.into()),
+                        }
+                    })
+                },
+            );
+            ib.method_with_cr_async(
+                "SetConfiguration",
+                ("transport", "properties"),
+                (),
+                |ctx, cr, (transport, props): (dbus::Path<'static>, PropMap)| {
+                    method_call(ctx, cr, |reg: Arc<Self>| async move {
+                        let transport = reg.transport(transport)?;
+                        let configuration = read_opt_prop!(&props, "Configuration", Vec<u8>).unwrap_or_default();
+                        if let Some(f) = &reg.e.set_configuration {
+                            f(SetConfiguration {
+                                adapter: transport.adapter_name().to_string(),
+                                device: transport.device_address(),
+                                transport,
+                                configuration,
+                            })
+                            .await?;
+                        }
+                        Ok(())
+                    })
+                },
+            );
+            ib.method_with_cr_async(
+                "ClearConfiguration",
+                ("transport",),
+                (),
+                |ctx, cr, (transport,): (dbus::Path<'static>,)| {
+                    method_call(ctx, cr, |reg: Arc<Self>| async move {
+                        let transport = reg.transport(transport)?;
+                        if let Some(f) = &reg.e.clear_configuration {
+                            f(ClearConfiguration { transport }).await;
+                        }
+                        Ok(())
+                    })
+                },
+            );
+            ib.method_with_cr_async("Release", (), (), |ctx, cr, ()| {
+                method_call(ctx, cr, |_reg: Arc<Self>| async move { Ok(()) })
+            });
+        })
+    }
+
+    pub(crate) async fn register(self, inner: Arc<SessionInner>, adapter_name: &str) -> Result<EndpointHandle> {
+        let name =
+            dbus::Path::new(format!("{}{}", inner.publish_path(ENDPOINT_PREFIX), Uuid::new_v4().as_simple()))
+                .unwrap();
+        let props = self.e.to_dict();
+        log::trace!("Publishing media endpoint at {}", &name);
+
+        {
+            let mut cr = inner.crossroads.lock().await;
+            cr.insert(name.clone(), &[inner.media_endpoint_token], Arc::new(self));
+        }
+
+        log::trace!("Registering media endpoint at {}", &name);
+        let adapter_path = Adapter::dbus_path(adapter_name)?;
+        let reg_name = name.clone();
+        inner
+            .register_with(name.clone(), adapter_path.clone(), MANAGER_INTERFACE, "RegisterEndpoint", move || {
+                (
+                    reg_name.clone(),
+                    props.iter().map(|(k, v)| (k.clone(), Variant(v.0.box_clone()))).collect::<PropMap>(),
+                )
+            })
+            .await?;
+
+        let (drop_tx, drop_rx) = oneshot::channel();
+        let unreg_name = name.clone();
+        tokio::spawn(async move {
+            let _ = drop_rx.await;
+            inner.forget_registration(unreg_name.clone()).await;
+
+            log::trace!("Unregistering media endpoint at {}", &unreg_name);
+            let proxy = Proxy::new(inner.service_name.clone(), adapter_path, TIMEOUT, inner.connection.clone());
+            let _: std::result::Result<(), dbus::Error> =
+                proxy.method_call(MANAGER_INTERFACE, "UnregisterEndpoint", (unreg_name.clone(),)).await;
+
+            log::trace!("Unpublishing media endpoint at {}", &unreg_name);
+            let mut cr = inner.crossroads.lock().await;
+            let _: Option<Arc<Self>> = cr.remove(&unreg_name);
+        });
+
+        Ok(EndpointHandle { name, _drop_tx: drop_tx })
+    }
+}
+
+/// Handle to registered media endpoint.
+///
+/// Drop to unregister media endpoint.
+pub struct EndpointHandle {
+    name: dbus::Path<'static>,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl Drop for EndpointHandle {
+    fn drop(&mut self) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for EndpointHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EndpointHandle {{ {} }}", &self.name)
+    }
+}
+
+// ===========================================================================================
+// Transport
+// ===========================================================================================
+
+/// State of a media transport.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TransportState {
+    /// Not streaming.
+    #[strum(serialize = "idle")]
+    Idle,
+    /// Streaming but not acquired.
+    #[strum(serialize = "pending")]
+    Pending,
+    /// Streaming and acquired.
+    #[strum(serialize = "active")]
+    Active,
+    /// Broadcast streaming.
+    #[strum(serialize = "broadcasting")]
+    Broadcasting,
+}
+
+/// Interface to a media transport.
+#[derive(Clone)]
+pub struct Transport {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+    adapter_name: Arc<String>,
+    device_address: Option<Address>,
+}
+
+impl fmt::Debug for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transport {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Transport {
+    pub(crate) fn new(inner: Arc<SessionInner>, dbus_path: Path<'static>) -> Result<Self> {
+        let adapter_name = match Adapter::parse_dbus_path_prefix(&dbus_path) {
+            Some((adapter_name, p)) if p.starts_with('/') => Arc::new(adapter_name.to_string()),
+            _ => return Err(Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue))),
+        };
+        // Broadcast transports are not bound to a remote device.
+        let device_address = match Device::parse_dbus_path_prefix(&dbus_path) {
+            Some(((_, device_address), p)) if p.starts_with('/') => Some(device_address),
+            _ => None,
+        };
+        Ok(Self { inner, dbus_path, adapter_name, device_address })
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(TRANSPORT_INTERFACE);
+
+    /// The Bluetooth adapter name.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// The Bluetooth address of the remote device.
+    ///
+    /// [None] for broadcast transports.
+    pub fn device_address(&self) -> Option<Address> {
+        self.device_address
+    }
+
+    /// Streams transport property changes.
+    ///
+    /// The stream ends when the transport is removed.
+    pub async fn events(&self) -> Result<impl Stream<Item = TransportEvent>> {
+        let events = self.inner.events(self.dbus_path.clone(), false).await?;
+        let stream = events.flat_map(move |event| match event {
+            Event::PropertiesChanged { changed, .. } => stream::iter(
+                TransportProperty::from_prop_map(changed).into_iter().map(TransportEvent::PropertyChanged),
+            )
+            .boxed(),
+            _ => stream::empty().boxed(),
+        });
+
+        Ok(stream)
+    }
+
+    /// Acquires the transport for streaming.
+    ///
+    /// Fails with [ErrorKind::NotSupported] if the transport uses a socket type
+    /// whose crate feature is not enabled.
+    ///
+    /// Use [release](Self::release) to release the transport when streaming is finished.
+    #[cfg(any(feature = "l2cap", feature = "iso"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "l2cap", feature = "iso"))))]
+    pub async fn acquire(&self) -> Result<TransportStream> {
+        let (fd, read_mtu, write_mtu): (OwnedFd, u16, u16) = self.call_method("Acquire", ()).await?;
+        TransportStream::new(self, fd, read_mtu, write_mtu)
+    }
+
+    /// Acquires the transport only if it is in the [pending](TransportState::Pending) state.
+    ///
+    /// Fails if the remote device has not initiated streaming.
+    /// Use [release](Self::release) to release the transport when streaming is finished.
+    #[cfg(any(feature = "l2cap", feature = "iso"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "l2cap", feature = "iso"))))]
+    pub async fn try_acquire(&self) -> Result<TransportStream> {
+        let (fd, read_mtu, write_mtu): (OwnedFd, u16, u16) = self.call_method("TryAcquire", ()).await?;
+        TransportStream::new(self, fd, read_mtu, write_mtu)
+    }
+
+    /// Releases the acquired transport.
+    pub async fn release(&self) -> Result<()> {
+        self.call_method("Release", ()).await
+    }
+}
+
+define_properties!(
+    Transport,
+    /// Media transport property.
+    pub TransportProperty => {
+        /// UUID of the profile the transport is for.
+        property(
+            Uuid, Uuid,
+            dbus: (TRANSPORT_INTERFACE, "UUID", String, MANDATORY),
+            get: (uuid, v => {
+                v.parse()
+                    .map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidUuid(v.to_string()))))?
+            }),
+        );
+
+        /// Assigned number of the codec that the transport uses.
+        property(
+            Codec, u8,
+            dbus: (TRANSPORT_INTERFACE, "Codec", u8, MANDATORY),
+            get: (codec, v => {v.to_owned()}),
+        );
+
+        /// Codec configuration of the transport.
+        property(
+            Configuration, Vec<u8>,
+            dbus: (TRANSPORT_INTERFACE, "Configuration", Vec<u8>, MANDATORY),
+            get: (configuration, v => {v.to_owned()}),
+        );
+
+        /// State of the transport.
+        property(
+            State, TransportState,
+            dbus: (TRANSPORT_INTERFACE, "State", String, MANDATORY),
+            get: (state, v => {
+                v.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))?
+            }),
+        );
+
+        /// Transport delay in 1/10 of a millisecond.
+        ///
+        /// Only available if the remote endpoint supports delay reporting.
+        property(
+            Delay, u16,
+            dbus: (TRANSPORT_INTERFACE, "Delay", u16, OPTIONAL),
+            get: (delay, v => {v.to_owned()}),
+        );
+
+        /// Volume level of the transport in the range 0 to 127.
+        ///
+        /// Only available if the remote device supports volume control.
+        property(
+            Volume, u16,
+            dbus: (TRANSPORT_INTERFACE, "Volume", u16, OPTIONAL),
+            get: (volume, v => {v.to_owned()}),
+            set: (set_volume, v => {v}),
+        );
+    }
+);
+
+/// Media transport event.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TransportEvent {
+    /// Property changed.
+    PropertyChanged(TransportProperty),
+}
@@ -0,0 +1,540 @@
+//! Remote control of media players on remote devices.
+//!
+//! A remote device that supports the AVRCP target role, for example a phone,
+//! exposes its media players, which can be obtained using
+//! [Device::media_players](crate::Device::media_players).
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use futures::{stream, Stream, StreamExt};
+use std::{collections::HashMap, fmt, sync::Arc};
+use strum::{Display, EnumString};
+
+use crate::{
+    read_dict, Address, Device, Error, ErrorKind, Event, InternalErrorKind, Result, SessionInner, TIMEOUT,
+};
+
+pub(crate) const PLAYER_INTERFACE: &str = "org.bluez.MediaPlayer1";
+pub(crate) const FOLDER_INTERFACE: &str = "org.bluez.MediaFolder1";
+pub(crate) const ITEM_INTERFACE: &str = "org.bluez.MediaItem1";
+
+/// Playback status of a media player.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Status {
+    /// Playing.
+    #[strum(serialize = "playing")]
+    Playing,
+    /// Stopped.
+    #[strum(serialize = "stopped")]
+    Stopped,
+    /// Paused.
+    #[strum(serialize = "paused")]
+    Paused,
+    /// Seeking forward.
+    #[strum(serialize = "forward-seek")]
+    ForwardSeek,
+    /// Seeking backward.
+    #[strum(serialize = "reverse-seek")]
+    ReverseSeek,
+    /// Error.
+    #[strum(serialize = "error")]
+    Error,
+}
+
+/// Repeat mode of a media player.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Repeat {
+    /// Repeat is disabled.
+    #[strum(serialize = "off")]
+    Off,
+    /// Repeat the current track.
+    #[strum(serialize = "singletrack")]
+    SingleTrack,
+    /// Repeat all tracks.
+    #[strum(serialize = "alltracks")]
+    AllTracks,
+    /// Repeat the current group.
+    #[strum(serialize = "group")]
+    Group,
+}
+
+/// Shuffle mode of a media player.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Shuffle {
+    /// Shuffle is disabled.
+    #[strum(serialize = "off")]
+    Off,
+    /// Shuffle all tracks.
+    #[strum(serialize = "alltracks")]
+    AllTracks,
+    /// Shuffle the current group.
+    #[strum(serialize = "group")]
+    Group,
+}
+
+/// Scan mode of a media player.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Scan {
+    /// Scan is disabled.
+    #[strum(serialize = "off")]
+    Off,
+    /// Scan all tracks.
+    #[strum(serialize = "alltracks")]
+    AllTracks,
+    /// Scan the current group.
+    #[strum(serialize = "group")]
+    Group,
+}
+
+/// Type of a media item.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ItemType {
+    /// Video.
+    #[strum(serialize = "video")]
+    Video,
+    /// Audio.
+    #[strum(serialize = "audio")]
+    Audio,
+    /// Folder.
+    #[strum(serialize = "folder")]
+    Folder,
+}
+
+/// Track metadata.
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Track {
+    /// Track title.
+    pub title: Option<String>,
+    /// Track artist.
+    pub artist: Option<String>,
+    /// Track album.
+    pub album: Option<String>,
+    /// Track genre.
+    pub genre: Option<String>,
+    /// Number of tracks in total.
+    pub number_of_tracks: Option<u32>,
+    /// Track number.
+    pub track_number: Option<u32>,
+    /// Track duration in milliseconds.
+    pub duration: Option<u32>,
+}
+
+impl Track {
+    pub(crate) fn from_dict(dict: &HashMap<String, Variant<Box<dyn RefArg + 'static>>>) -> Self {
+        Self {
+            title: read_dict(dict, "Title").ok().cloned(),
+            artist: read_dict(dict, "Artist").ok().cloned(),
+            album: read_dict(dict, "Album").ok().cloned(),
+            genre: read_dict(dict, "Genre").ok().cloned(),
+            number_of_tracks: read_dict(dict, "NumberOfTracks").ok().cloned(),
+            track_number: read_dict(dict, "TrackNumber").or_else(|_| read_dict(dict, "Number")).ok().cloned(),
+            duration: read_dict(dict, "Duration").ok().cloned(),
+        }
+    }
+}
+
+fn parse_device_path(dbus_path: &Path) -> Result<(Arc<String>, Address)> {
+    match Device::parse_dbus_path_prefix(dbus_path) {
+        Some(((adapter_name, device_address), p)) if p.starts_with('/') => {
+            Ok((Arc::new(adapter_name.to_string()), device_address))
+        }
+        _ => Err(Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue))),
+    }
+}
+
+// ===========================================================================================
+// Player
+// ===========================================================================================
+
+/// Interface to a media player of a remote device.
+#[derive(Clone)]
+pub struct Player {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+    adapter_name: Arc<String>,
+    device_address: Address,
+}
+
+impl fmt::Debug for Player {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Player {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Player {
+    pub(crate) fn new(inner: Arc<SessionInner>, dbus_path: Path<'static>) -> Result<Self> {
+        let (adapter_name, device_address) = parse_device_path(&dbus_path)?;
+        Ok(Self { inner, dbus_path, adapter_name, device_address })
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(PLAYER_INTERFACE);
+
+    /// The Bluetooth adapter name.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// The Bluetooth address of the remote device.
+    pub fn device_address(&self) -> Address {
+        self.device_address
+    }
+
+    /// Streams player property changes.
+    ///
+    /// The stream ends when the player is removed.
+    pub async fn events(&self) -> Result<impl Stream<Item = PlayerEvent>> {
+        let events = self.inner.events(self.dbus_path.clone(), false).await?;
+        let stream = events.flat_map(move |event| match event {
+            Event::PropertiesChanged { interface, changed, .. } if interface == PLAYER_INTERFACE => {
+                stream::iter(PlayerProperty::from_prop_map(changed).into_iter().map(PlayerEvent::PropertyChanged))
+                    .boxed()
+            }
+            _ => stream::empty().boxed(),
+        });
+
+        Ok(stream)
+    }
+
+    /// Resumes playback.
+    pub async fn play(&self) -> Result<()> {
+        self.call_method("Play", ()).await
+    }
+
+    /// Pauses playback.
+    pub async fn pause(&self) -> Result<()> {
+        self.call_method("Pause", ()).await
+    }
+
+    /// Stops playback.
+    pub async fn stop(&self) -> Result<()> {
+        self.call_method("Stop", ()).await
+    }
+
+    /// Skips to the next track.
+    pub async fn next(&self) -> Result<()> {
+        self.call_method("Next", ()).await
+    }
+
+    /// Skips to the previous track.
+    pub async fn previous(&self) -> Result<()> {
+        self.call_method("Previous", ()).await
+    }
+
+    /// Seeks forward in the current track.
+    ///
+    /// Seeking continues until playback is resumed using [play](Self::play)
+    /// or stopped otherwise.
+    pub async fn fast_forward(&self) -> Result<()> {
+        self.call_method("FastForward", ()).await
+    }
+
+    /// Seeks backward in the current track.
+    ///
+    /// Seeking continues until playback is resumed using [play](Self::play)
+    /// or stopped otherwise.
+    pub async fn rewind(&self) -> Result<()> {
+        self.call_method("Rewind", ()).await
+    }
+
+    /// The current folder of the player for browsing its media items.
+    ///
+    /// Only available if the player is [browsable](Self::is_browsable).
+    pub fn folder(&self) -> Folder {
+        Folder { inner: self.inner.clone(), dbus_path: self.dbus_path.clone() }
+    }
+}
+
+define_properties!(
+    Player,
+    /// Media player property.
+    pub PlayerProperty => {
+        /// Player name.
+        property(
+            Name, String,
+            dbus: (PLAYER_INTERFACE, "Name", String, OPTIONAL),
+            get: (name, v => {v.to_owned()}),
+        );
+
+        /// Player type, for example `Audio`, `Video`, `Audio Broadcasting`
+        /// or `Video Broadcasting`.
+        property(
+            Type, String,
+            dbus: (PLAYER_INTERFACE, "Type", String, OPTIONAL),
+            get: (player_type, v => {v.to_owned()}),
+        );
+
+        /// Player subtype, for example `Audio Book` or `Podcast`.
+        property(
+            Subtype, String,
+            dbus: (PLAYER_INTERFACE, "Subtype", String, OPTIONAL),
+            get: (subtype, v => {v.to_owned()}),
+        );
+
+        /// Playback status.
+        property(
+            Status, Status,
+            dbus: (PLAYER_INTERFACE, "Status", String, MANDATORY),
+            get: (status, v => {
+                v.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))?
+            }),
+        );
+
+        /// Playback position in milliseconds.
+        ///
+        /// Changing the position emits a change event, but
+        /// the position is not updated continuously during playback.
+        property(
+            Position, u32,
+            dbus: (PLAYER_INTERFACE, "Position", u32, MANDATORY),
+            get: (position, v => {v.to_owned()}),
+        );
+
+        /// Metadata of the current track.
+        property(
+            Track, Track,
+            dbus: (PLAYER_INTERFACE, "Track", HashMap<String, Variant<Box<dyn RefArg + 'static>>>, MANDATORY),
+            get: (track, v => {Track::from_dict(v)}),
+        );
+
+        /// Whether the equalizer is enabled.
+        property(
+            Equalizer, bool,
+            dbus: (PLAYER_INTERFACE, "Equalizer", String, OPTIONAL),
+            get: (is_equalizer, v => {v == "on"}),
+            set: (set_equalizer, v => {if v { "on".to_string() } else { "off".to_string() }}),
+        );
+
+        /// Repeat mode.
+        property(
+            Repeat, Repeat,
+            dbus: (PLAYER_INTERFACE, "Repeat", String, OPTIONAL),
+            get: (repeat, v => {
+                v.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))?
+            }),
+            set: (set_repeat, v => {v.to_string()}),
+        );
+
+        /// Shuffle mode.
+        property(
+            Shuffle, Shuffle,
+            dbus: (PLAYER_INTERFACE, "Shuffle", String, OPTIONAL),
+            get: (shuffle, v => {
+                v.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))?
+            }),
+            set: (set_shuffle, v => {v.to_string()}),
+        );
+
+        /// Scan mode.
+        property(
+            Scan, Scan,
+            dbus: (PLAYER_INTERFACE, "Scan", String, OPTIONAL),
+            get: (scan, v => {
+                v.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))?
+            }),
+            set: (set_scan, v => {v.to_string()}),
+        );
+
+        /// Whether the media items of the player can be browsed using [Player::folder].
+        property(
+            Browsable, bool,
+            dbus: (PLAYER_INTERFACE, "Browsable", bool, OPTIONAL),
+            get: (is_browsable, v => {v.to_owned()}),
+        );
+
+        /// Whether the media items of the player can be searched using [Folder::search].
+        property(
+            Searchable, bool,
+            dbus: (PLAYER_INTERFACE, "Searchable", bool, OPTIONAL),
+            get: (is_searchable, v => {v.to_owned()}),
+        );
+    }
+);
+
+/// Media player event.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum PlayerEvent {
+    /// Property changed.
+    PropertyChanged(PlayerProperty),
+}
+
+// ===========================================================================================
+// Folder
+// ===========================================================================================
+
+/// Interface to a folder of media items of a remote media player.
+#[derive(Clone)]
+pub struct Folder {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for Folder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Folder {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Folder {
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(FOLDER_INTERFACE);
+
+    /// Lists the media items of the folder.
+    ///
+    /// If `range` is specified, only the items from its start index
+    /// up to and including its end index are listed.
+    pub async fn items(&self, range: Option<(u32, u32)>) -> Result<Vec<Item>> {
+        let mut filter = PropMap::new();
+        if let Some((start, end)) = range {
+            filter.insert("Start".to_string(), Variant(start.box_clone()));
+            filter.insert("End".to_string(), Variant(end.box_clone()));
+        }
+        let (items,): (HashMap<Path<'static>, PropMap>,) = self.call_method("ListItems", (filter,)).await?;
+        let mut items: Vec<_> =
+            items.into_keys().map(|dbus_path| Item { inner: self.inner.clone(), dbus_path }).collect();
+        // Item paths end with a running number, thus shorter paths come first.
+        items.sort_by(|a, b| (a.dbus_path.len(), &a.dbus_path).cmp(&(b.dbus_path.len(), &b.dbus_path)));
+        Ok(items)
+    }
+
+    /// Searches for media items matching the specified value.
+    ///
+    /// Returns a folder containing the search results.
+    pub async fn search(&self, value: &str) -> Result<Folder> {
+        let (dbus_path,): (Path<'static>,) = self.call_method("Search", (value, PropMap::new())).await?;
+        Ok(Folder { inner: self.inner.clone(), dbus_path })
+    }
+
+    /// Changes the current folder of the player to the specified folder item.
+    ///
+    /// The items of the previous folder may be removed and have to be listed again.
+    pub async fn change_folder(&self, folder: &Item) -> Result<()> {
+        self.call_method("ChangeFolder", (folder.dbus_path.clone(),)).await
+    }
+}
+
+define_properties!(
+    Folder,
+    /// Media folder property.
+    pub FolderProperty => {
+        /// Number of items in the folder.
+        property(
+            NumberOfItems, u32,
+            dbus: (FOLDER_INTERFACE, "NumberOfItems", u32, MANDATORY),
+            get: (number_of_items, v => {v.to_owned()}),
+        );
+
+        /// Folder name.
+        property(
+            Name, String,
+            dbus: (FOLDER_INTERFACE, "Name", String, MANDATORY),
+            get: (name, v => {v.to_owned()}),
+        );
+    }
+);
+
+// ===========================================================================================
+// Item
+// ===========================================================================================
+
+/// Interface to a media item of a remote media player.
+#[derive(Clone)]
+pub struct Item {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Item {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Item {
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(ITEM_INTERFACE);
+
+    /// Plays the item.
+    pub async fn play(&self) -> Result<()> {
+        self.call_method("Play", ()).await
+    }
+
+    /// Adds the item to the now playing list.
+    pub async fn add_to_now_playing(&self) -> Result<()> {
+        self.call_method("AddtoNowPlaying", ()).await
+    }
+}
+
+define_properties!(
+    Item,
+    /// Media item property.
+    pub ItemProperty => {
+        /// Item name.
+        property(
+            Name, String,
+            dbus: (ITEM_INTERFACE, "Name", String, OPTIONAL),
+            get: (name, v => {v.to_owned()}),
+        );
+
+        /// Item type.
+        property(
+            Type, ItemType,
+            dbus: (ITEM_INTERFACE, "Type", String, MANDATORY),
+            get: (item_type, v => {
+                v.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))?
+            }),
+        );
+
+        /// Folder type, for example `Albums` or `Playlists`.
+        ///
+        /// Only available for folder items.
+        property(
+            FolderType, String,
+            dbus: (ITEM_INTERFACE, "FolderType", String, OPTIONAL),
+            get: (folder_type, v => {v.to_owned()}),
+        );
+
+        /// Whether the item can be played.
+        property(
+            Playable, bool,
+            dbus: (ITEM_INTERFACE, "Playable", bool, OPTIONAL),
+            get: (is_playable, v => {v.to_owned()}),
+        );
+
+        /// Track metadata of the item.
+        property(
+            Metadata, Track,
+            dbus: (ITEM_INTERFACE, "Metadata", HashMap<String, Variant<Box<dyn RefArg + 'static>>>, OPTIONAL),
+            get: (metadata, v => {Track::from_dict(v)}),
+        );
+    }
+);
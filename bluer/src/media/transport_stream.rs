@@ -0,0 +1,276 @@
+//! Streaming over acquired media transports.
+
+use dbus::arg::OwnedFd;
+use futures::{future::poll_fn, ready, task::noop_waker_ref};
+use std::{
+    mem::MaybeUninit,
+    net::Shutdown,
+    os::unix::io::{AsRawFd, IntoRawFd, RawFd},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::Transport;
+#[cfg(feature = "iso")]
+use crate::iso;
+#[cfg(feature = "l2cap")]
+use crate::l2cap;
+use crate::{sock, sys, Address, Error, ErrorKind, Result};
+
+/// Socket of an acquired media transport.
+///
+/// Classic audio is carried over L2CAP and LE Audio over isochronous channels.
+#[derive(Debug)]
+enum TransportSocket {
+    #[cfg(feature = "l2cap")]
+    L2cap(l2cap::SeqPacket),
+    #[cfg(feature = "iso")]
+    Iso(iso::SeqPacket),
+}
+
+impl TransportSocket {
+    /// Takes ownership of the transport socket passed by the Bluetooth daemon.
+    fn new(fd: OwnedFd) -> Result<Self> {
+        let fd = unsafe { sock::OwnedFd::new(fd.into_fd()) };
+        if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) } == -1 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let protocol: libc::c_int = sock::getsockopt(&fd, libc::SOL_SOCKET, libc::SO_PROTOCOL)?;
+        match protocol {
+            #[cfg(feature = "l2cap")]
+            sys::BTPROTO_L2CAP => Ok(Self::L2cap(unsafe { l2cap::SeqPacket::from_raw_fd(fd.into_raw_fd()) }?)),
+            #[cfg(feature = "iso")]
+            sys::BTPROTO_ISO => Ok(Self::Iso(unsafe { iso::SeqPacket::from_raw_fd(fd.into_raw_fd()) }?)),
+            _ => Err(Error::new(ErrorKind::NotSupported)),
+        }
+    }
+
+    fn poll_recv(&self, cx: &mut Context, buf: &mut ReadBuf) -> Poll<std::io::Result<()>> {
+        match *self {
+            #[cfg(feature = "l2cap")]
+            Self::L2cap(ref socket) => socket.poll_recv(cx, buf),
+            #[cfg(feature = "iso")]
+            Self::Iso(ref socket) => socket.poll_recv(cx, buf),
+        }
+    }
+
+    fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        match *self {
+            #[cfg(feature = "l2cap")]
+            Self::L2cap(ref socket) => socket.poll_send(cx, buf),
+            #[cfg(feature = "iso")]
+            Self::Iso(ref socket) => socket.poll_send(cx, buf),
+        }
+    }
+
+    fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+        match *self {
+            #[cfg(feature = "l2cap")]
+            Self::L2cap(ref socket) => socket.shutdown(how),
+            #[cfg(feature = "iso")]
+            Self::Iso(ref socket) => socket.shutdown(how),
+        }
+    }
+}
+
+impl AsRawFd for TransportSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        match *self {
+            #[cfg(feature = "l2cap")]
+            Self::L2cap(ref socket) => socket.as_raw_fd(),
+            #[cfg(feature = "iso")]
+            Self::Iso(ref socket) => socket.as_raw_fd(),
+        }
+    }
+}
+
+impl IntoRawFd for TransportSocket {
+    fn into_raw_fd(self) -> RawFd {
+        match self {
+            #[cfg(feature = "l2cap")]
+            Self::L2cap(socket) => socket.into_raw_fd(),
+            #[cfg(feature = "iso")]
+            Self::Iso(socket) => socket.into_raw_fd(),
+        }
+    }
+}
+
+/// Streams encoded audio data over an acquired media transport.
+///
+/// Each send or receive operation transfers a single media packet.
+#[derive(Debug)]
+pub struct TransportStream {
+    adapter_name: String,
+    device_address: Option<Address>,
+    read_mtu: usize,
+    write_mtu: usize,
+    socket: TransportSocket,
+    buf: Vec<u8>,
+}
+
+impl TransportStream {
+    pub(super) fn new(transport: &Transport, fd: OwnedFd, read_mtu: u16, write_mtu: u16) -> Result<Self> {
+        Ok(Self {
+            adapter_name: transport.adapter_name().to_string(),
+            device_address: transport.device_address(),
+            read_mtu: read_mtu.into(),
+            write_mtu: write_mtu.into(),
+            socket: TransportSocket::new(fd)?,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Name of adapter.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// Address of remote device.
+    ///
+    /// [None] for broadcast transports.
+    pub fn device_address(&self) -> Option<Address> {
+        self.device_address
+    }
+
+    /// Maximum size of a received packet.
+    pub fn read_mtu(&self) -> usize {
+        self.read_mtu
+    }
+
+    /// Maximum size of a sent packet.
+    pub fn write_mtu(&self) -> usize {
+        self.write_mtu
+    }
+
+    fn poll_recv_packet(&self, cx: &mut Context) -> Poll<std::io::Result<Vec<u8>>> {
+        let mut buf = vec![0; self.read_mtu];
+        let mut read_buf = ReadBuf::new(&mut buf);
+        ready!(self.socket.poll_recv(cx, &mut read_buf))?;
+        let n = read_buf.filled().len();
+        buf.truncate(n);
+        Poll::Ready(Ok(buf))
+    }
+
+    fn poll_send_packet(&self, cx: &mut Context, buf: &[u8]) -> Poll<std::io::Result<()>> {
+        if buf.len() > self.write_mtu {
+            return Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                "data length exceeds MTU",
+            )));
+        }
+        match ready!(self.socket.poll_send(cx, buf)) {
+            Ok(n) if n == buf.len() => Poll::Ready(Ok(())),
+            Ok(_) => Poll::Ready(Err(std::io::Error::new(std::io::ErrorKind::Other, "partial write occured"))),
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+
+    /// Try to receive a single packet.
+    ///
+    /// Does not wait for new data to arrive.
+    pub fn try_recv(&self) -> std::io::Result<Vec<u8>> {
+        match self.poll_recv_packet(&mut Context::from_waker(noop_waker_ref())) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Err(std::io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    /// Receive a single packet.
+    ///
+    /// Waits for data to arrive.
+    pub async fn recv(&self) -> std::io::Result<Vec<u8>> {
+        poll_fn(|cx| self.poll_recv_packet(cx)).await
+    }
+
+    /// Tries to send a single packet.
+    ///
+    /// The length of `buf` must not exceed [Self::write_mtu].
+    ///
+    /// Does not wait for send space to become available.
+    pub fn try_send(&self, buf: &[u8]) -> std::io::Result<()> {
+        match self.poll_send_packet(&mut Context::from_waker(noop_waker_ref()), buf) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Err(std::io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    /// Sends a single packet.
+    ///
+    /// The length of `buf` must not exceed [Self::write_mtu].
+    ///
+    /// Waits for send space to become available.
+    pub async fn send(&self, buf: &[u8]) -> std::io::Result<()> {
+        poll_fn(|cx| self.poll_send_packet(cx, buf)).await
+    }
+}
+
+impl AsyncRead for TransportStream {
+    /// Attempts to read from the transport into `buf`.
+    ///
+    /// When a buffer of size less than [read_mtu] bytes is provided, the received
+    /// packet will be buffered internally and split over multiple read operations.
+    /// Thus, for best efficiency, provide a buffer of at least [read_mtu] bytes.
+    ///
+    /// [read_mtu]: TransportStream::read_mtu
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let buf_space = buf.remaining();
+        if !this.buf.is_empty() {
+            // Return buffered data first, if any.
+            let to_read = buf_space.min(this.buf.len());
+            let remaining = this.buf.split_off(to_read);
+            buf.put_slice(&this.buf);
+            this.buf = remaining;
+            Poll::Ready(Ok(()))
+        } else if buf_space < this.read_mtu {
+            // If provided buffer is too small, read into temporary buffer.
+            let mut mtu_buf: Vec<MaybeUninit<u8>> = vec![MaybeUninit::uninit(); this.read_mtu];
+            let mut mtu_read_buf = ReadBuf::uninit(&mut mtu_buf);
+            ready!(this.socket.poll_recv(cx, &mut mtu_read_buf))?;
+            let n = mtu_read_buf.filled().len();
+            mtu_buf.truncate(n);
+            let mut mtu_buf: Vec<u8> = mtu_buf.into_iter().map(|v| unsafe { v.assume_init() }).collect();
+
+            // Then fill provided buffer appropriately and keep the rest in
+            // our internal buffer.
+            this.buf = mtu_buf.split_off(buf_space.min(n));
+            buf.put_slice(&mtu_buf);
+
+            Poll::Ready(Ok(()))
+        } else {
+            this.socket.poll_recv(cx, buf)
+        }
+    }
+}
+
+impl AsyncWrite for TransportStream {
+    /// Attempt to write bytes from `buf` into the transport.
+    ///
+    /// A single write operation will send no more than [write_mtu](TransportStream::write_mtu) bytes.
+    /// However, attempting to send a larger buffer will not result in an error but a partial send.
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        let max_len = buf.len().min(self.write_mtu);
+        self.socket.poll_send(cx, &buf[..max_len])
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<std::io::Result<()>> {
+        Poll::Ready(self.socket.shutdown(Shutdown::Write))
+    }
+}
+
+impl AsRawFd for TransportStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for TransportStream {
+    fn into_raw_fd(self) -> RawFd {
+        self.socket.into_raw_fd()
+    }
+}
@@ -0,0 +1,1080 @@
+//! Bluetooth management (mgmt) API client.
+//!
+//! The management interface of the kernel provides access to controller settings
+//! that are not exposed by the Bluetooth daemon over D-Bus, for example
+//! the static random address, LE-only mode, Secure Connections Only mode,
+//! the PHY configuration, experimental features and connection parameters used
+//! for automatic connection establishment.
+//!
+//! A [Client] exchanges commands and events with the kernel over an [HCI socket](crate::hci)
+//! bound to the [control channel](crate::hci::Channel::Control).
+//! It does not require a running Bluetooth daemon.
+//! Reading information is permitted to all users, while changing settings requires
+//! the `CAP_NET_ADMIN` capability.
+//!
+//! Note that the Bluetooth daemon also uses the management interface and
+//! may override settings changed by other clients.
+//!
+
+use crate::{
+    hci::{self, Channel, DevIndex},
+    sys::bdaddr_t,
+    Address, AddressType,
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    Stream,
+};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::{
+    collections::HashSet,
+    convert::TryInto,
+    fmt,
+    hash::Hash,
+    io::{Error, ErrorKind, Result},
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+pub use crate::hci::DEV_NONE;
+
+/// Size of the packet header.
+const HDR_SIZE: usize = 6;
+
+/// Size of the receive buffer for one packet.
+const RECV_BUF_SIZE: usize = HDR_SIZE + u16::MAX as usize;
+
+/// Management command opcodes.
+pub mod opcode {
+    /// Read management version information.
+    pub const READ_VERSION: u16 = 0x0001;
+    /// Read controller index list.
+    pub const READ_INDEX_LIST: u16 = 0x0003;
+    /// Read controller information.
+    pub const READ_INFO: u16 = 0x0004;
+    /// Set powered.
+    pub const SET_POWERED: u16 = 0x0005;
+    /// Set connectable.
+    pub const SET_CONNECTABLE: u16 = 0x0007;
+    /// Set bondable.
+    pub const SET_BONDABLE: u16 = 0x0009;
+    /// Set low energy.
+    pub const SET_LE: u16 = 0x000d;
+    /// Set BR/EDR.
+    pub const SET_BREDR: u16 = 0x002a;
+    /// Set static address.
+    pub const SET_STATIC_ADDRESS: u16 = 0x002b;
+    /// Set Secure Connections.
+    pub const SET_SECURE_CONN: u16 = 0x002d;
+    /// Add device.
+    pub const ADD_DEVICE: u16 = 0x0033;
+    /// Remove device.
+    pub const REMOVE_DEVICE: u16 = 0x0034;
+    /// Load connection parameters.
+    pub const LOAD_CONN_PARAM: u16 = 0x0035;
+    /// Get PHY configuration.
+    pub const GET_PHY_CONFIGURATION: u16 = 0x0044;
+    /// Set PHY configuration.
+    pub const SET_PHY_CONFIGURATION: u16 = 0x0045;
+    /// Read experimental features information.
+    pub const READ_EXP_FEATURES_INFO: u16 = 0x0049;
+    /// Set experimental feature.
+    pub const SET_EXP_FEATURE: u16 = 0x004a;
+}
+
+const EV_CMD_COMPLETE: u16 = 0x0001;
+const EV_CMD_STATUS: u16 = 0x0002;
+const EV_CONTROLLER_ERROR: u16 = 0x0003;
+const EV_INDEX_ADDED: u16 = 0x0004;
+const EV_INDEX_REMOVED: u16 = 0x0005;
+const EV_NEW_SETTINGS: u16 = 0x0006;
+const EV_DEVICE_CONNECTED: u16 = 0x000b;
+const EV_DEVICE_DISCONNECTED: u16 = 0x000c;
+const EV_DISCOVERING: u16 = 0x0013;
+const EV_PHY_CONFIGURATION_CHANGED: u16 = 0x0026;
+const EV_EXP_FEATURE_CHANGED: u16 = 0x0027;
+
+/// Experimental feature enabling ISO sockets.
+pub const EXP_FEATURE_ISO_SOCKET: Uuid = Uuid::from_u128(0x6fbaf188_05e0_496a_9885_d6ddfdb4e03e);
+
+/// Status of a management command.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Status(pub u8);
+
+impl Status {
+    /// Success.
+    pub const SUCCESS: Self = Self(0x00);
+    /// Unknown command.
+    pub const UNKNOWN_COMMAND: Self = Self(0x01);
+    /// Not connected.
+    pub const NOT_CONNECTED: Self = Self(0x02);
+    /// Failed.
+    pub const FAILED: Self = Self(0x03);
+    /// Connect failed.
+    pub const CONNECT_FAILED: Self = Self(0x04);
+    /// Authentication failed.
+    pub const AUTH_FAILED: Self = Self(0x05);
+    /// Not paired.
+    pub const NOT_PAIRED: Self = Self(0x06);
+    /// No resources.
+    pub const NO_RESOURCES: Self = Self(0x07);
+    /// Timeout.
+    pub const TIMEOUT: Self = Self(0x08);
+    /// Already connected.
+    pub const ALREADY_CONNECTED: Self = Self(0x09);
+    /// Busy.
+    pub const BUSY: Self = Self(0x0a);
+    /// Rejected.
+    pub const REJECTED: Self = Self(0x0b);
+    /// Not supported.
+    pub const NOT_SUPPORTED: Self = Self(0x0c);
+    /// Invalid parameters.
+    pub const INVALID_PARAMS: Self = Self(0x0d);
+    /// Disconnected.
+    pub const DISCONNECTED: Self = Self(0x0e);
+    /// Not powered.
+    pub const NOT_POWERED: Self = Self(0x0f);
+    /// Cancelled.
+    pub const CANCELLED: Self = Self(0x10);
+    /// Invalid index.
+    pub const INVALID_INDEX: Self = Self(0x11);
+    /// Blocked through rfkill.
+    pub const RFKILLED: Self = Self(0x12);
+    /// Already paired.
+    pub const ALREADY_PAIRED: Self = Self(0x13);
+    /// Permission denied.
+    pub const PERMISSION_DENIED: Self = Self(0x14);
+
+    fn name(&self) -> Option<&'static str> {
+        const NAMES: [&str; 21] = [
+            "success",
+            "unknown command",
+            "not connected",
+            "failed",
+            "connect failed",
+            "authentication failed",
+            "not paired",
+            "no resources",
+            "timeout",
+            "already connected",
+            "busy",
+            "rejected",
+            "not supported",
+            "invalid parameters",
+            "disconnected",
+            "not powered",
+            "cancelled",
+            "invalid index",
+            "blocked through rfkill",
+            "already paired",
+            "permission denied",
+        ];
+        NAMES.get(usize::from(self.0)).copied()
+    }
+}
+
+impl fmt::Debug for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Status(0x{:02x})", self.0)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "unknown status 0x{:02x}", self.0),
+        }
+    }
+}
+
+/// A management command failed with an error status.
+///
+/// This is returned as the inner error of an [std::io::Error].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandError {
+    /// Opcode of the failed command.
+    pub opcode: u16,
+    /// Status.
+    pub status: Status,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "management command 0x{:04x} failed: {}", self.opcode, self.status)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<CommandError> for Error {
+    fn from(err: CommandError) -> Self {
+        let kind = match err.status {
+            Status::NOT_SUPPORTED | Status::UNKNOWN_COMMAND => ErrorKind::Unsupported,
+            Status::INVALID_PARAMS | Status::INVALID_INDEX => ErrorKind::InvalidInput,
+            Status::PERMISSION_DENIED => ErrorKind::PermissionDenied,
+            Status::TIMEOUT => ErrorKind::TimedOut,
+            _ => ErrorKind::Other,
+        };
+        Error::new(kind, err)
+    }
+}
+
+fn invalid_data(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Reads the value of type `T` at the start of a parameter buffer.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid_data("management packet too short"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn addr(&mut self) -> Result<Address> {
+        Ok(Address::from(bdaddr_t { b: self.bytes(6)?.try_into().unwrap() }))
+    }
+
+    fn addr_type(&mut self) -> Result<AddressType> {
+        AddressType::from_u8(self.u8()?).ok_or_else(|| invalid_data("invalid address type"))
+    }
+
+    fn uuid(&mut self) -> Result<Uuid> {
+        Ok(Uuid::from_u128(u128::from_le_bytes(self.bytes(16)?.try_into().unwrap())))
+    }
+
+    fn string(&mut self, n: usize) -> Result<String> {
+        let buf = self.bytes(n)?;
+        let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+        Ok(String::from_utf8_lossy(&buf[..len]).to_string())
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.0)
+    }
+}
+
+/// Builds a command packet starting with the management header.
+fn command_packet(index: DevIndex, opcode: u16, params: &[u8]) -> Result<Vec<u8>> {
+    let len: u16 =
+        params.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "parameters too long"))?;
+    let mut buf = Vec::with_capacity(HDR_SIZE + params.len());
+    buf.extend_from_slice(&opcode.to_le_bytes());
+    buf.extend_from_slice(&index.to_le_bytes());
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(params);
+    Ok(buf)
+}
+
+fn put_addr(buf: &mut Vec<u8>, addr: Address, addr_type: AddressType) {
+    let addr: bdaddr_t = addr.into();
+    buf.extend_from_slice(&addr.b);
+    buf.push(addr_type as u8);
+}
+
+fn bits_to_set<T>(bits: u32) -> HashSet<T>
+where
+    T: FromPrimitive + Eq + Hash,
+{
+    (0..32).filter(|n| bits & (1 << n) != 0).filter_map(T::from_u32).collect()
+}
+
+fn set_to_bits<T>(set: &HashSet<T>) -> u32
+where
+    T: Copy + Into<u32>,
+{
+    set.iter().fold(0, |bits, &v| bits | (1 << v.into()))
+}
+
+/// Controller setting.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Setting {
+    /// Powered.
+    Powered = 0,
+    /// Connectable.
+    Connectable = 1,
+    /// Fast connectable.
+    FastConnectable = 2,
+    /// Discoverable.
+    Discoverable = 3,
+    /// Bondable.
+    Bondable = 4,
+    /// Link level security.
+    LinkSecurity = 5,
+    /// Secure Simple Pairing.
+    SecureSimplePairing = 6,
+    /// Classic Bluetooth (BR/EDR).
+    BrEdr = 7,
+    /// High speed.
+    HighSpeed = 8,
+    /// Bluetooth Low Energy.
+    Le = 9,
+    /// Advertising.
+    Advertising = 10,
+    /// Secure Connections.
+    SecureConnections = 11,
+    /// Debug keys.
+    DebugKeys = 12,
+    /// Privacy.
+    Privacy = 13,
+    /// Controller configuration.
+    Configuration = 14,
+    /// Static address.
+    StaticAddress = 15,
+    /// PHY configuration.
+    PhyConfiguration = 16,
+    /// Wideband speech.
+    WidebandSpeech = 17,
+    /// Connected isochronous stream central.
+    CisCentral = 18,
+    /// Connected isochronous stream peripheral.
+    CisPeripheral = 19,
+    /// Isochronous broadcaster.
+    IsoBroadcaster = 20,
+    /// Synchronized receiver.
+    IsoSyncReceiver = 21,
+    /// Link layer privacy.
+    LlPrivacy = 22,
+}
+
+impl From<Setting> for u32 {
+    fn from(s: Setting) -> Self {
+        s as u32
+    }
+}
+
+/// PHY and packet type.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Phy {
+    /// BR 1M 1-slot.
+    Br1M1Slot = 0,
+    /// BR 1M 3-slot.
+    Br1M3Slot = 1,
+    /// BR 1M 5-slot.
+    Br1M5Slot = 2,
+    /// EDR 2M 1-slot.
+    Edr2M1Slot = 3,
+    /// EDR 2M 3-slot.
+    Edr2M3Slot = 4,
+    /// EDR 2M 5-slot.
+    Edr2M5Slot = 5,
+    /// EDR 3M 1-slot.
+    Edr3M1Slot = 6,
+    /// EDR 3M 3-slot.
+    Edr3M3Slot = 7,
+    /// EDR 3M 5-slot.
+    Edr3M5Slot = 8,
+    /// LE 1M transmit.
+    Le1MTx = 9,
+    /// LE 1M receive.
+    Le1MRx = 10,
+    /// LE 2M transmit.
+    Le2MTx = 11,
+    /// LE 2M receive.
+    Le2MRx = 12,
+    /// LE Coded transmit.
+    LeCodedTx = 13,
+    /// LE Coded receive.
+    LeCodedRx = 14,
+}
+
+impl From<Phy> for u32 {
+    fn from(p: Phy) -> Self {
+        p as u32
+    }
+}
+
+/// Secure Connections mode.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SecureConnections {
+    /// Disabled.
+    Disabled = 0x00,
+    /// Enabled.
+    Enabled = 0x01,
+    /// Secure Connections Only mode.
+    Only = 0x02,
+}
+
+/// Action to take for a device added using [Client::add_device].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AddDeviceAction {
+    /// Scan in the background and report the device when found.
+    Report = 0x00,
+    /// Allow incoming connections.
+    AllowIncoming = 0x01,
+    /// Connect automatically when the device is found.
+    AutoConnect = 0x02,
+}
+
+/// Management interface version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version {
+    /// Version.
+    pub version: u8,
+    /// Revision.
+    pub revision: u16,
+}
+
+/// Controller information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControllerInfo {
+    /// Public address.
+    pub addr: Address,
+    /// Bluetooth core specification version.
+    pub bluetooth_version: u8,
+    /// Manufacturer identifier.
+    pub manufacturer: u16,
+    /// Supported settings.
+    pub supported_settings: HashSet<Setting>,
+    /// Current settings.
+    pub current_settings: HashSet<Setting>,
+    /// Class of device.
+    pub class: u32,
+    /// Name.
+    pub name: String,
+    /// Short name.
+    pub short_name: String,
+}
+
+/// PHY configuration of a controller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhyConfiguration {
+    /// Supported PHYs.
+    pub supported: HashSet<Phy>,
+    /// PHYs that can be enabled or disabled.
+    pub configurable: HashSet<Phy>,
+    /// Selected PHYs.
+    pub selected: HashSet<Phy>,
+}
+
+/// Experimental feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExpFeature {
+    /// UUID identifying the feature.
+    pub uuid: Uuid,
+    /// Flags.
+    ///
+    /// Bit 0 indicates whether the feature is enabled.
+    pub flags: u32,
+}
+
+impl ExpFeature {
+    /// Whether the feature is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+}
+
+/// Connection parameters of a device.
+///
+/// These are used when connecting to the device, for example
+/// after it has been added using [AddDeviceAction::AutoConnect].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnParams {
+    /// Device address.
+    pub addr: Address,
+    /// Device address type.
+    pub addr_type: AddressType,
+    /// Minimum connection interval in units of 1.25 ms.
+    pub min_interval: u16,
+    /// Maximum connection interval in units of 1.25 ms.
+    pub max_interval: u16,
+    /// Peripheral latency in number of connection events.
+    pub latency: u16,
+    /// Supervision timeout in units of 10 ms.
+    pub timeout: u16,
+}
+
+/// Management event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Event {
+    /// A command has completed.
+    CommandComplete {
+        /// Opcode of the command.
+        opcode: u16,
+        /// Status.
+        status: Status,
+        /// Return parameters.
+        params: Vec<u8>,
+    },
+    /// A command has failed or is pending.
+    CommandStatus {
+        /// Opcode of the command.
+        opcode: u16,
+        /// Status.
+        status: Status,
+    },
+    /// The controller reported an error.
+    ControllerError {
+        /// Error code.
+        code: u8,
+    },
+    /// A controller has been added.
+    IndexAdded,
+    /// A controller has been removed.
+    IndexRemoved,
+    /// The current settings of a controller have changed.
+    NewSettings(HashSet<Setting>),
+    /// A device has connected.
+    DeviceConnected {
+        /// Device address.
+        addr: Address,
+        /// Device address type.
+        addr_type: AddressType,
+        /// Flags.
+        flags: u32,
+        /// Extended inquiry response or advertising data.
+        eir: Vec<u8>,
+    },
+    /// A device has disconnected.
+    DeviceDisconnected {
+        /// Device address.
+        addr: Address,
+        /// Device address type.
+        addr_type: AddressType,
+        /// Reason.
+        reason: u8,
+    },
+    /// Device discovery has started or stopped.
+    Discovering {
+        /// Address types being discovered as bit field.
+        address_types: u8,
+        /// Whether discovery is active.
+        discovering: bool,
+    },
+    /// The selected PHYs have changed.
+    PhyConfigurationChanged(HashSet<Phy>),
+    /// An experimental feature has been enabled or disabled.
+    ExpFeatureChanged(ExpFeature),
+    /// Other event.
+    Other {
+        /// Event code.
+        code: u16,
+        /// Parameters.
+        params: Vec<u8>,
+    },
+}
+
+impl Event {
+    fn from_bytes(code: u16, params: &[u8]) -> Result<Self> {
+        let mut r = Reader(params);
+        let event = match code {
+            EV_CMD_COMPLETE => {
+                Self::CommandComplete { opcode: r.u16()?, status: Status(r.u8()?), params: r.rest().to_vec() }
+            }
+            EV_CMD_STATUS => Self::CommandStatus { opcode: r.u16()?, status: Status(r.u8()?) },
+            EV_CONTROLLER_ERROR => Self::ControllerError { code: r.u8()? },
+            EV_INDEX_ADDED => Self::IndexAdded,
+            EV_INDEX_REMOVED => Self::IndexRemoved,
+            EV_NEW_SETTINGS => Self::NewSettings(bits_to_set(r.u32()?)),
+            EV_DEVICE_CONNECTED => {
+                let addr = r.addr()?;
+                let addr_type = r.addr_type()?;
+                let flags = r.u32()?;
+                let eir_len = r.u16()?;
+                Self::DeviceConnected { addr, addr_type, flags, eir: r.bytes(eir_len.into())?.to_vec() }
+            }
+            EV_DEVICE_DISCONNECTED => {
+                Self::DeviceDisconnected { addr: r.addr()?, addr_type: r.addr_type()?, reason: r.u8()? }
+            }
+            EV_DISCOVERING => Self::Discovering { address_types: r.u8()?, discovering: r.u8()? != 0 },
+            EV_PHY_CONFIGURATION_CHANGED => Self::PhyConfigurationChanged(bits_to_set(r.u32()?)),
+            EV_EXP_FEATURE_CHANGED => Self::ExpFeatureChanged(ExpFeature { uuid: r.uuid()?, flags: r.u32()? }),
+            _ => Self::Other { code, params: params.to_vec() },
+        };
+        Ok(event)
+    }
+}
+
+/// Management event together with the index of the controller it pertains to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventPacket {
+    /// Controller index.
+    ///
+    /// This is [DEV_NONE] for events not related to a controller.
+    pub index: DevIndex,
+    /// Event.
+    pub event: Event,
+}
+
+impl EventPacket {
+    /// Parses a packet starting with the management header.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        let mut r = Reader(buf);
+        let code = r.u16()?;
+        let index = r.u16()?;
+        let len = r.u16()?;
+        let params = r.bytes(len.into())?;
+        Ok(Self { index, event: Event::from_bytes(code, params)? })
+    }
+}
+
+struct PendingCommand {
+    opcode: u16,
+    index: DevIndex,
+    tx: oneshot::Sender<Result<Vec<u8>>>,
+}
+
+struct ClientInner {
+    socket: hci::Socket,
+    pending: Mutex<Vec<PendingCommand>>,
+    event_txs: Mutex<Vec<mpsc::UnboundedSender<EventPacket>>>,
+}
+
+impl ClientInner {
+    fn dispatch(&self, buf: &[u8]) {
+        let packet = match EventPacket::from_bytes(buf) {
+            Ok(packet) => packet,
+            Err(err) => {
+                log::warn!("Received invalid management packet: {}", err);
+                return;
+            }
+        };
+
+        let response = match &packet.event {
+            Event::CommandComplete { opcode, status, params } => Some((*opcode, *status, params.clone())),
+            Event::CommandStatus { opcode, status } => Some((*opcode, *status, Vec::new())),
+            _ => None,
+        };
+
+        match response {
+            Some((opcode, status, params)) => {
+                let mut pending = self.pending.lock().unwrap();
+                pending.retain(|p| !p.tx.is_canceled());
+                if let Some(pos) = pending.iter().position(|p| p.opcode == opcode && p.index == packet.index) {
+                    let p = pending.remove(pos);
+                    let result = if status == Status::SUCCESS {
+                        Ok(params)
+                    } else {
+                        Err(CommandError { opcode, status }.into())
+                    };
+                    let _ = p.tx.send(result);
+                }
+            }
+            None => {
+                let mut event_txs = self.event_txs.lock().unwrap();
+                event_txs.retain(|tx| tx.unbounded_send(packet.clone()).is_ok());
+            }
+        }
+    }
+}
+
+/// Bluetooth management API client.
+///
+/// Commands can be sent concurrently.
+/// The client stops receiving when dropped.
+pub struct Client {
+    inner: Arc<ClientInner>,
+    _term_tx: oneshot::Sender<()>,
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Client").field("socket", &self.inner.socket).finish()
+    }
+}
+
+impl Client {
+    /// Opens a new connection to the management interface of the kernel.
+    ///
+    /// This must be called from within a Tokio runtime.
+    pub async fn new() -> Result<Self> {
+        let socket = hci::Socket::new()?;
+        socket.bind(hci::SocketAddr::new(DEV_NONE, Channel::Control))?;
+
+        let inner =
+            Arc::new(ClientInner { socket, pending: Mutex::new(Vec::new()), event_txs: Mutex::new(Vec::new()) });
+
+        let (term_tx, mut term_rx) = oneshot::channel();
+        let task_inner = inner.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0; RECV_BUF_SIZE];
+            loop {
+                tokio::select! {
+                    res = task_inner.socket.recv_buf(&mut buf) => match res {
+                        Ok(n) => task_inner.dispatch(&buf[..n]),
+                        Err(err) => {
+                            log::warn!("Receiving from management socket failed: {}", &err);
+                            break;
+                        }
+                    },
+                    _ = &mut term_rx => break,
+                }
+            }
+
+            task_inner.pending.lock().unwrap().clear();
+            task_inner.event_txs.lock().unwrap().clear();
+        });
+
+        Ok(Self { inner, _term_tx: term_tx })
+    }
+
+    /// Sends a command to the specified controller and waits for its completion.
+    ///
+    /// Specify [DEV_NONE] as index for commands not related to a controller.
+    ///
+    /// Returns the return parameters of the command.
+    /// If the command fails, a [CommandError] is returned as the inner error.
+    pub async fn command(&self, index: DevIndex, opcode: u16, params: &[u8]) -> Result<Vec<u8>> {
+        let buf = command_packet(index, opcode, params)?;
+
+        let (tx, rx) = oneshot::channel();
+        self.inner.pending.lock().unwrap().push(PendingCommand { opcode, index, tx });
+        self.inner.socket.send_buf(&buf).await?;
+
+        rx.await.map_err(|_| Error::new(ErrorKind::ConnectionAborted, "management socket closed"))?
+    }
+
+    /// Streams events of all controllers.
+    pub fn events(&self) -> impl Stream<Item = EventPacket> {
+        let (tx, rx) = mpsc::unbounded();
+        self.inner.event_txs.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Reads the version of the management interface.
+    pub async fn read_version(&self) -> Result<Version> {
+        let ret = self.command(DEV_NONE, opcode::READ_VERSION, &[]).await?;
+        let mut r = Reader(&ret);
+        Ok(Version { version: r.u8()?, revision: r.u16()? })
+    }
+
+    /// Reads the indices of all controllers.
+    pub async fn read_index_list(&self) -> Result<Vec<DevIndex>> {
+        let ret = self.command(DEV_NONE, opcode::READ_INDEX_LIST, &[]).await?;
+        let mut r = Reader(&ret);
+        let n = r.u16()?;
+        (0..n).map(|_| r.u16()).collect()
+    }
+
+    /// Reads information about the specified controller.
+    pub async fn read_controller_info(&self, index: DevIndex) -> Result<ControllerInfo> {
+        let ret = self.command(index, opcode::READ_INFO, &[]).await?;
+        let mut r = Reader(&ret);
+        Ok(ControllerInfo {
+            addr: r.addr()?,
+            bluetooth_version: r.u8()?,
+            manufacturer: r.u16()?,
+            supported_settings: bits_to_set(r.u32()?),
+            current_settings: bits_to_set(r.u32()?),
+            class: {
+                let c = r.bytes(3)?;
+                u32::from_le_bytes([c[0], c[1], c[2], 0])
+            },
+            name: r.string(249)?,
+            short_name: r.string(11)?,
+        })
+    }
+
+    async fn set_setting(&self, index: DevIndex, opcode: u16, params: &[u8]) -> Result<HashSet<Setting>> {
+        let ret = self.command(index, opcode, params).await?;
+        Ok(bits_to_set(Reader(&ret).u32()?))
+    }
+
+    /// Powers the specified controller on or off.
+    ///
+    /// Returns the current settings.
+    pub async fn set_powered(&self, index: DevIndex, powered: bool) -> Result<HashSet<Setting>> {
+        self.set_setting(index, opcode::SET_POWERED, &[powered.into()]).await
+    }
+
+    /// Sets whether the specified controller is connectable.
+    ///
+    /// Returns the current settings.
+    pub async fn set_connectable(&self, index: DevIndex, connectable: bool) -> Result<HashSet<Setting>> {
+        self.set_setting(index, opcode::SET_CONNECTABLE, &[connectable.into()]).await
+    }
+
+    /// Sets whether the specified controller is bondable.
+    ///
+    /// Returns the current settings.
+    pub async fn set_bondable(&self, index: DevIndex, bondable: bool) -> Result<HashSet<Setting>> {
+        self.set_setting(index, opcode::SET_BONDABLE, &[bondable.into()]).await
+    }
+
+    /// Enables or disables Bluetooth Low Energy on the specified controller.
+    ///
+    /// Returns the current settings.
+    pub async fn set_le(&self, index: DevIndex, le: bool) -> Result<HashSet<Setting>> {
+        self.set_setting(index, opcode::SET_LE, &[le.into()]).await
+    }
+
+    /// Enables or disables classic Bluetooth (BR/EDR) on the specified controller.
+    ///
+    /// Disabling BR/EDR while LE is enabled puts a dual-mode controller into LE-only mode.
+    /// This is only possible while the controller is powered off.
+    ///
+    /// Returns the current settings.
+    pub async fn set_bredr(&self, index: DevIndex, bredr: bool) -> Result<HashSet<Setting>> {
+        self.set_setting(index, opcode::SET_BREDR, &[bredr.into()]).await
+    }
+
+    /// Sets the static random address of the specified controller.
+    ///
+    /// Specify [Address::any] to remove the static address.
+    /// This is only possible while the controller is powered off.
+    ///
+    /// Returns the current settings.
+    pub async fn set_static_address(&self, index: DevIndex, addr: Address) -> Result<HashSet<Setting>> {
+        let addr: bdaddr_t = addr.into();
+        self.set_setting(index, opcode::SET_STATIC_ADDRESS, &addr.b).await
+    }
+
+    /// Sets the Secure Connections mode of the specified controller.
+    ///
+    /// Returns the current settings.
+    pub async fn set_secure_connections(
+        &self, index: DevIndex, mode: SecureConnections,
+    ) -> Result<HashSet<Setting>> {
+        self.set_setting(index, opcode::SET_SECURE_CONN, &[mode as u8]).await
+    }
+
+    /// Gets the PHY configuration of the specified controller.
+    pub async fn phy_configuration(&self, index: DevIndex) -> Result<PhyConfiguration> {
+        let ret = self.command(index, opcode::GET_PHY_CONFIGURATION, &[]).await?;
+        let mut r = Reader(&ret);
+        Ok(PhyConfiguration {
+            supported: bits_to_set(r.u32()?),
+            configurable: bits_to_set(r.u32()?),
+            selected: bits_to_set(r.u32()?),
+        })
+    }
+
+    /// Sets the selected PHYs of the specified controller.
+    pub async fn set_phy_configuration(&self, index: DevIndex, selected: &HashSet<Phy>) -> Result<()> {
+        self.command(index, opcode::SET_PHY_CONFIGURATION, &set_to_bits(selected).to_le_bytes()).await?;
+        Ok(())
+    }
+
+    /// Reads the experimental features supported by the specified controller.
+    ///
+    /// Specify [DEV_NONE] as index for features not related to a controller.
+    pub async fn exp_features(&self, index: DevIndex) -> Result<Vec<ExpFeature>> {
+        let ret = self.command(index, opcode::READ_EXP_FEATURES_INFO, &[]).await?;
+        let mut r = Reader(&ret);
+        let n = r.u16()?;
+        (0..n).map(|_| Ok(ExpFeature { uuid: r.uuid()?, flags: r.u32()? })).collect()
+    }
+
+    /// Enables or disables an experimental feature.
+    ///
+    /// Specify [DEV_NONE] as index for features not related to a controller.
+    ///
+    /// Returns the new state of the feature.
+    pub async fn set_exp_feature(&self, index: DevIndex, uuid: Uuid, enable: bool) -> Result<ExpFeature> {
+        let mut params = uuid.as_u128().to_le_bytes().to_vec();
+        params.push(enable.into());
+        let ret = self.command(index, opcode::SET_EXP_FEATURE, &params).await?;
+        let mut r = Reader(&ret);
+        Ok(ExpFeature { uuid: r.uuid()?, flags: r.u32()? })
+    }
+
+    /// Adds a device to the list of devices handled by the kernel of the specified controller.
+    pub async fn add_device(
+        &self, index: DevIndex, addr: Address, addr_type: AddressType, action: AddDeviceAction,
+    ) -> Result<()> {
+        let mut params = Vec::new();
+        put_addr(&mut params, addr, addr_type);
+        params.push(action as u8);
+        self.command(index, opcode::ADD_DEVICE, &params).await?;
+        Ok(())
+    }
+
+    /// Removes a device added using [Client::add_device].
+    ///
+    /// Specify [Address::any] to remove all devices.
+    pub async fn remove_device(&self, index: DevIndex, addr: Address, addr_type: AddressType) -> Result<()> {
+        let mut params = Vec::new();
+        put_addr(&mut params, addr, addr_type);
+        self.command(index, opcode::REMOVE_DEVICE, &params).await?;
+        Ok(())
+    }
+
+    /// Loads the connection parameters of devices into the specified controller.
+    pub async fn load_conn_params(&self, index: DevIndex, conn_params: &[ConnParams]) -> Result<()> {
+        let count: u16 = conn_params
+            .len()
+            .try_into()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "too many connection parameters"))?;
+        let mut params = count.to_le_bytes().to_vec();
+        for p in conn_params {
+            put_addr(&mut params, p.addr, p.addr_type);
+            params.extend_from_slice(&p.min_interval.to_le_bytes());
+            params.extend_from_slice(&p.max_interval.to_le_bytes());
+            params.extend_from_slice(&p.latency.to_le_bytes());
+            params.extend_from_slice(&p.timeout.to_le_bytes());
+        }
+        self.command(index, opcode::LOAD_CONN_PARAM, &params).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    use super::{
+        command_packet, opcode, put_addr, Event, EventPacket, ExpFeature, Setting, Status, DEV_NONE,
+        EXP_FEATURE_ISO_SOCKET,
+    };
+    use crate::{Address, AddressType};
+
+    #[test]
+    fn opcodes() {
+        // Command codes as listed in doc/mgmt-api.txt of BlueZ.
+        let opcodes = [
+            (opcode::READ_VERSION, 0x0001),
+            (opcode::READ_INDEX_LIST, 0x0003),
+            (opcode::READ_INFO, 0x0004),
+            (opcode::SET_POWERED, 0x0005),
+            (opcode::SET_CONNECTABLE, 0x0007),
+            (opcode::SET_BONDABLE, 0x0009),
+            (opcode::SET_LE, 0x000d),
+            (opcode::SET_BREDR, 0x002a),
+            (opcode::SET_STATIC_ADDRESS, 0x002b),
+            (opcode::SET_SECURE_CONN, 0x002d),
+            (opcode::ADD_DEVICE, 0x0033),
+            (opcode::REMOVE_DEVICE, 0x0034),
+            (opcode::LOAD_CONN_PARAM, 0x0035),
+            (opcode::GET_PHY_CONFIGURATION, 0x0044),
+            (opcode::SET_PHY_CONFIGURATION, 0x0045),
+            (opcode::READ_EXP_FEATURES_INFO, 0x0049),
+            (opcode::SET_EXP_FEATURE, 0x004a),
+        ];
+        for (opcode, code) in opcodes {
+            let buf = command_packet(DEV_NONE, opcode, &[]).unwrap();
+            assert_eq!(buf[..2], u16::to_le_bytes(code), "opcode 0x{:04x}", code);
+        }
+    }
+
+    #[test]
+    fn command_header() {
+        let buf = command_packet(0, opcode::SET_POWERED, &[0x01]).unwrap();
+        assert_eq!(buf, [0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01]);
+
+        let buf = command_packet(DEV_NONE, opcode::READ_INDEX_LIST, &[]).unwrap();
+        assert_eq!(buf, [0x03, 0x00, 0xff, 0xff, 0x00, 0x00]);
+
+        assert!(command_packet(0, opcode::LOAD_CONN_PARAM, &vec![0; 0x10000]).is_err());
+    }
+
+    #[test]
+    fn command_address() {
+        let mut params = Vec::new();
+        put_addr(&mut params, Address::new([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]), AddressType::LeRandom);
+        params.push(0x02);
+        let buf = command_packet(1, opcode::ADD_DEVICE, &params).unwrap();
+        assert_eq!(buf, [0x33, 0x00, 0x01, 0x00, 0x08, 0x00, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x02, 0x02]);
+    }
+
+    #[test]
+    fn command_complete() {
+        let buf = [0x01, 0x00, 0x00, 0x00, 0x07, 0x00, 0x2a, 0x00, 0x00, 0x81, 0x02, 0x00, 0x00];
+        let packet = EventPacket::from_bytes(&buf).unwrap();
+        assert_eq!(packet.index, 0);
+        assert_eq!(
+            packet.event,
+            Event::CommandComplete {
+                opcode: opcode::SET_BREDR,
+                status: Status::SUCCESS,
+                params: vec![0x81, 0x02, 0x00, 0x00]
+            }
+        );
+    }
+
+    #[test]
+    fn command_status() {
+        let buf = [0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x2d, 0x00, 0x0c];
+        let packet = EventPacket::from_bytes(&buf).unwrap();
+        assert_eq!(
+            packet.event,
+            Event::CommandStatus { opcode: opcode::SET_SECURE_CONN, status: Status::NOT_SUPPORTED }
+        );
+    }
+
+    #[test]
+    fn new_settings() {
+        let buf = [0x06, 0x00, 0x00, 0x00, 0x04, 0x00, 0x81, 0x02, 0x00, 0x00];
+        let packet = EventPacket::from_bytes(&buf).unwrap();
+        let settings: HashSet<_> = [Setting::Powered, Setting::BrEdr, Setting::Le].into_iter().collect();
+        assert_eq!(packet.event, Event::NewSettings(settings));
+    }
+
+    #[test]
+    fn device_connected() {
+        let mut buf = vec![0x0b, 0x00, 0x00, 0x00, 0x11, 0x00];
+        buf.extend_from_slice(&[0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x01]);
+        buf.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x04, 0x00]);
+        buf.extend_from_slice(&[0x03, 0x19, 0xc1, 0x03]);
+        let packet = EventPacket::from_bytes(&buf).unwrap();
+        assert_eq!(
+            packet.event,
+            Event::DeviceConnected {
+                addr: Address::new([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]),
+                addr_type: AddressType::LePublic,
+                flags: 0,
+                eir: vec![0x03, 0x19, 0xc1, 0x03],
+            }
+        );
+    }
+
+    #[test]
+    fn exp_feature_changed() {
+        let mut buf = vec![0x27, 0x00, 0xff, 0xff, 0x14, 0x00];
+        buf.extend_from_slice(&EXP_FEATURE_ISO_SOCKET.as_u128().to_le_bytes());
+        buf.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+        let packet = EventPacket::from_bytes(&buf).unwrap();
+        assert_eq!(packet.index, DEV_NONE);
+        assert_eq!(packet.event, Event::ExpFeatureChanged(ExpFeature { uuid: EXP_FEATURE_ISO_SOCKET, flags: 1 }));
+        assert_eq!(EXP_FEATURE_ISO_SOCKET, "6fbaf188-05e0-496a-9885-d6ddfdb4e03e".parse::<Uuid>().unwrap());
+    }
+
+    #[test]
+    fn other_event() {
+        let buf = [0x12, 0x00, 0x00, 0x00, 0x02, 0x00, 0xaa, 0xbb];
+        let packet = EventPacket::from_bytes(&buf).unwrap();
+        assert_eq!(packet.event, Event::Other { code: 0x12, params: vec![0xaa, 0xbb] });
+    }
+
+    #[test]
+    fn invalid_event() {
+        assert!(EventPacket::from_bytes(&[0x01, 0x00, 0x00]).is_err());
+        assert!(EventPacket::from_bytes(&[0x06, 0x00, 0x00, 0x00, 0x04, 0x00, 0x01]).is_err());
+        assert!(EventPacket::from_bytes(&[0x06, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,874 @@
+//! Mock Bluetooth daemon for testing.
+//!
+//! This module provides an in-process fake of the Bluetooth daemon (`org.bluez`)
+//! that can be served on a private D-Bus bus.
+//! A [Session] connected to the same bus using [Session::new_on_bus] can then
+//! be used to exercise code using this library without Bluetooth hardware or
+//! a running Bluetooth daemon.
+//!
+//! Adapters, devices and remote GATT services are scripted using the handles
+//! returned by [Bluetoothd::add_adapter] and friends.
+//! Property changes made through these handles are signalled to all
+//! sessions connected to the bus, exactly like the Bluetooth daemon does.
+//!
+//! A private bus can be started using [Bus::start], which requires the `dbus-daemon`
+//! binary to be available.
+//!
+//! # Example
+//! ```no_run
+//! # async fn example() -> bluer::Result<()> {
+//! use bluer::{mock::{Bluetoothd, Bus}, Address, Session};
+//!
+//! let bus = Bus::start()?;
+//! let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+//! let mock_adapter = bluetoothd.add_adapter("hci0", Address::new([0, 1, 2, 3, 4, 5]));
+//! let mock_device = mock_adapter.add_device(Address::new([6, 7, 8, 9, 10, 11]));
+//! mock_device.set_property("RSSI", -60i16);
+//!
+//! let session = Session::new_on_bus(bus.address()).await?;
+//! let adapter = session.adapter("hci0")?;
+//! let device = adapter.device(Address::new([6, 7, 8, 9, 10, 11]))?;
+//! assert_eq!(device.rssi().await?, Some(-60));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Session]: crate::Session
+//! [Session::new_on_bus]: crate::Session::new_on_bus
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    channel::Sender,
+    message::{MatchRule, SignalArgs},
+    nonblock::{
+        stdintf::org_freedesktop_dbus::{
+            ObjectManagerInterfacesAdded, ObjectManagerInterfacesRemoved, PropertiesPropertiesChanged,
+        },
+        Proxy, SyncConnection,
+    },
+    Message, MethodErr, Path,
+};
+use dbus_tokio::connection::IOResourceError;
+use futures::StreamExt;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex, Weak},
+};
+use tokio::task::{spawn_blocking, JoinHandle};
+use uuid::Uuid;
+
+use crate::{
+    adapter, adv, agent, battery, device,
+    gatt::{CHARACTERISTIC_INTERFACE, DESCRIPTOR_INTERFACE, SERVICE_INTERFACE},
+    session::connect_bus,
+    Address, DbusResult, Error, ErrorKind, Result, ERR_PREFIX, SERVICE_NAME, TIMEOUT,
+};
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+
+/// Private D-Bus message bus for testing.
+///
+/// A `dbus-daemon` process is started and killed when this is dropped.
+pub struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl fmt::Debug for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Bus {{ {} }}", &self.address)
+    }
+}
+
+impl Bus {
+    /// Starts a private D-Bus message bus.
+    ///
+    /// This requires the `dbus-daemon` binary to be in the search path.
+    pub fn start() -> Result<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let mut address = String::new();
+        let stdout = daemon.stdout.take().unwrap();
+        if let Err(err) = BufReader::new(stdout).read_line(&mut address) {
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+            return Err(err.into());
+        }
+        let address = address.trim().to_string();
+        if address.is_empty() {
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+            return Err(Error {
+                kind: ErrorKind::Failed,
+                message: "dbus-daemon did not print its address".to_string(),
+            });
+        }
+
+        Ok(Self { daemon, address })
+    }
+
+    /// D-Bus address of the bus.
+    ///
+    /// Pass this to [Session::new_on_bus](crate::Session::new_on_bus) and
+    /// [Bluetoothd::new_on_bus].
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// Method the mock Bluetooth daemon uses for pairing with a device.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum PairingMethod {
+    /// Pairing succeeds without asking the agent.
+    JustWorks,
+    /// Agent is asked to confirm the specified passkey.
+    Confirmation(u32),
+    /// Agent is asked to enter a passkey, which must match the specified passkey.
+    Passkey(u32),
+    /// Agent is asked to enter a PIN code, which must match the specified PIN code.
+    PinCode(String),
+    /// Agent is asked to authorize the pairing.
+    Authorization,
+    /// Pairing fails without asking the agent.
+    Fail,
+}
+
+impl Default for PairingMethod {
+    fn default() -> Self {
+        Self::JustWorks
+    }
+}
+
+/// Objects and registrations of the mock Bluetooth daemon.
+#[derive(Default)]
+struct State {
+    objects: BTreeMap<Path<'static>, BTreeMap<String, PropMap>>,
+    next_child_ids: HashMap<Path<'static>, u16>,
+    pairing_methods: HashMap<Path<'static>, PairingMethod>,
+    agent: Option<(String, Path<'static>)>,
+    advertisements: HashMap<Path<'static>, Vec<(String, Path<'static>)>>,
+}
+
+/// Shared state of the mock Bluetooth daemon.
+struct MockInner {
+    connection: Arc<SyncConnection>,
+    state: Mutex<State>,
+    dbus_task: JoinHandle<IOResourceError>,
+}
+
+impl Drop for MockInner {
+    fn drop(&mut self) {
+        self.dbus_task.abort();
+    }
+}
+
+fn clone_prop_map(props: &PropMap) -> PropMap {
+    props.iter().map(|(k, v)| (k.clone(), Variant(v.0.box_clone()))).collect()
+}
+
+fn bluez_err(name: &str, message: &str) -> MethodErr {
+    MethodErr::from((format!("{ERR_PREFIX}{name}"), message))
+}
+
+impl MockInner {
+    fn emit(&self, msg: Message) {
+        let _ = self.connection.send(msg);
+    }
+
+    fn add_object(&self, path: Path<'static>, interfaces: BTreeMap<String, PropMap>) {
+        let msg = ObjectManagerInterfacesAdded {
+            object: path.clone(),
+            interfaces: interfaces.iter().map(|(k, v)| (k.clone(), clone_prop_map(v))).collect(),
+        }
+        .to_emit_message(&Path::from("/"));
+        self.state.lock().unwrap().objects.insert(path, interfaces);
+        self.emit(msg);
+    }
+
+    fn add_interface(&self, path: &Path<'static>, interface: &str, props: PropMap) {
+        let msg = ObjectManagerInterfacesAdded {
+            object: path.clone(),
+            interfaces: [(interface.to_string(), clone_prop_map(&props))].into_iter().collect(),
+        }
+        .to_emit_message(&Path::from("/"));
+        match self.state.lock().unwrap().objects.get_mut(path) {
+            Some(ifaces) => ifaces.insert(interface.to_string(), props),
+            None => return,
+        };
+        self.emit(msg);
+    }
+
+    fn remove_object(&self, path: &Path<'static>) {
+        let prefix = format!("{path}/");
+        let removed: Vec<_> = {
+            let mut state = self.state.lock().unwrap();
+            let paths: Vec<_> =
+                state.objects.keys().filter(|p| *p == path || p.starts_with(&prefix)).cloned().collect();
+            paths.into_iter().rev().filter_map(|p| state.objects.remove(&p).map(|ifaces| (p, ifaces))).collect()
+        };
+        for (object, interfaces) in removed {
+            let msg = ObjectManagerInterfacesRemoved { object, interfaces: interfaces.into_keys().collect() }
+                .to_emit_message(&Path::from("/"));
+            self.emit(msg);
+        }
+    }
+
+    fn next_child_id(&self, parent: &Path<'static>) -> u16 {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_child_ids.entry(parent.clone()).or_insert(1);
+        let this_id = *id;
+        *id += 1;
+        this_id
+    }
+
+    fn set_property(&self, path: &Path<'static>, interface: &str, name: &str, value: Box<dyn RefArg>) {
+        let changed = {
+            let mut state = self.state.lock().unwrap();
+            let props = match state.objects.get_mut(path).and_then(|ifaces| ifaces.get_mut(interface)) {
+                Some(props) => props,
+                None => return,
+            };
+            props.insert(name.to_string(), Variant(value.box_clone()));
+            let mut changed = PropMap::new();
+            changed.insert(name.to_string(), Variant(value));
+            changed
+        };
+        let msg = PropertiesPropertiesChanged {
+            interface_name: interface.to_string(),
+            changed_properties: changed,
+            invalidated_properties: Vec::new(),
+        }
+        .to_emit_message(path);
+        self.emit(msg);
+    }
+
+    fn property(&self, path: &Path<'static>, interface: &str, name: &str) -> Option<Box<dyn RefArg>> {
+        let state = self.state.lock().unwrap();
+        state.objects.get(path)?.get(interface)?.get(name).map(|v| v.0.box_clone())
+    }
+
+    /// Handles a method call and sends the reply.
+    async fn handle_method_call(self: Arc<Self>, msg: Message) {
+        let path = msg.path().map(|p| p.into_static()).unwrap_or_default();
+        let interface = msg.interface().map(|i| i.to_string()).unwrap_or_default();
+        let member = msg.member().map(|m| m.to_string()).unwrap_or_default();
+
+        let result = if interface == device::INTERFACE && member == "Pair" {
+            self.pair(path.clone()).await.map(|()| msg.method_return())
+        } else {
+            self.dispatch(&msg, &path, &interface, &member)
+        };
+        log::trace!("Mock: {}: {}.{} (...) -> {:?}", &path, &interface, &member, result.as_ref().err());
+        let reply = match result {
+            Ok(reply) => reply,
+            Err(err) => err.to_message(&msg),
+        };
+        if !msg.get_no_reply() {
+            self.emit(reply);
+        }
+    }
+
+    fn dispatch(
+        &self, msg: &Message, path: &Path<'static>, interface: &str, member: &str,
+    ) -> DbusResult<Message> {
+        let invalid_args = |_| MethodErr::invalid_arg("arguments");
+        if interface != PROPERTIES_INTERFACE && interface != OBJECT_MANAGER_INTERFACE {
+            let state = self.state.lock().unwrap();
+            match state.objects.get(path) {
+                Some(ifaces) if ifaces.contains_key(interface) => (),
+                Some(_) => return Err(MethodErr::no_interface(interface)),
+                None => return Err(MethodErr::no_path(path)),
+            }
+        }
+        match (interface, member) {
+            (OBJECT_MANAGER_INTERFACE, "GetManagedObjects") => {
+                let state = self.state.lock().unwrap();
+                let objects: HashMap<_, HashMap<_, _>> = state
+                    .objects
+                    .iter()
+                    .map(|(p, ifaces)| {
+                        (p.clone(), ifaces.iter().map(|(i, props)| (i.clone(), clone_prop_map(props))).collect())
+                    })
+                    .collect();
+                Ok(msg.return_with_args((objects,)))
+            }
+            (PROPERTIES_INTERFACE, "Get") => {
+                let (iface, name): (&str, &str) = msg.read2().map_err(invalid_args)?;
+                match self.property(path, iface, name) {
+                    Some(value) => Ok(msg.return_with_args((Variant(value),))),
+                    None => Err(MethodErr::from((
+                        "org.freedesktop.DBus.Error.InvalidArgs",
+                        format!("No such property '{name}'"),
+                    ))),
+                }
+            }
+            (PROPERTIES_INTERFACE, "GetAll") => {
+                let iface: &str = msg.read1().map_err(invalid_args)?;
+                let state = self.state.lock().unwrap();
+                let props = state.objects.get(path).and_then(|ifaces| ifaces.get(iface)).map(clone_prop_map);
+                Ok(msg.return_with_args((props.unwrap_or_default(),)))
+            }
+            (PROPERTIES_INTERFACE, "Set") => {
+                let (iface, name, value): (&str, &str, Variant<Box<dyn RefArg>>) =
+                    msg.read3().map_err(invalid_args)?;
+                if self.property(path, iface, name).is_none() {
+                    return Err(MethodErr::from((
+                        "org.freedesktop.DBus.Error.InvalidArgs",
+                        format!("No such property '{name}'"),
+                    )));
+                }
+                self.set_property(path, iface, name, value.0);
+                Ok(msg.method_return())
+            }
+            (adapter::INTERFACE, "StartDiscovery") => {
+                self.set_property(path, adapter::INTERFACE, "Discovering", Box::new(true));
+                Ok(msg.method_return())
+            }
+            (adapter::INTERFACE, "StopDiscovery") => {
+                self.set_property(path, adapter::INTERFACE, "Discovering", Box::new(false));
+                Ok(msg.method_return())
+            }
+            (adapter::INTERFACE, "SetDiscoveryFilter") => {
+                let _: PropMap = msg.read1().map_err(invalid_args)?;
+                Ok(msg.method_return())
+            }
+            (adapter::INTERFACE, "GetDiscoveryFilters") => {
+                let filters =
+                    vec!["UUIDs", "RSSI", "Pathloss", "Transport", "DuplicateData", "Discoverable", "Pattern"];
+                Ok(msg.return_with_args((filters,)))
+            }
+            (adapter::INTERFACE, "RemoveDevice") => {
+                let device: Path = msg.read1().map_err(invalid_args)?;
+                self.remove_object(&device.into_static());
+                Ok(msg.method_return())
+            }
+            (adapter::ADMIN_POLICY_SET_INTERFACE, "SetServiceAllowList") => {
+                let uuids: Vec<String> = msg.read1().map_err(invalid_args)?;
+                self.set_service_allow_list(path, uuids);
+                Ok(msg.method_return())
+            }
+            (device::INTERFACE, "Connect") => {
+                self.set_property(path, device::INTERFACE, "Connected", Box::new(true));
+                self.set_property(path, device::INTERFACE, "ServicesResolved", Box::new(true));
+                Ok(msg.method_return())
+            }
+            (device::INTERFACE, "Disconnect") => {
+                self.set_property(path, device::INTERFACE, "ServicesResolved", Box::new(false));
+                self.set_property(path, device::INTERFACE, "Connected", Box::new(false));
+                Ok(msg.method_return())
+            }
+            (device::INTERFACE, "ConnectProfile") | (device::INTERFACE, "DisconnectProfile") => {
+                let _: &str = msg.read1().map_err(invalid_args)?;
+                Ok(msg.method_return())
+            }
+            (device::INTERFACE, "CancelPairing") => Ok(msg.method_return()),
+            (CHARACTERISTIC_INTERFACE, "ReadValue") | (DESCRIPTOR_INTERFACE, "ReadValue") => {
+                let options: PropMap = msg.read1().map_err(invalid_args)?;
+                let offset = options.get("offset").and_then(|v| v.0.as_u64()).unwrap_or_default() as usize;
+                let value = self
+                    .property(path, interface, "Value")
+                    .and_then(|v| dbus::arg::cast::<Vec<u8>>(&*v).cloned())
+                    .unwrap_or_default();
+                if offset > value.len() {
+                    return Err(bluez_err("InvalidOffset", "Invalid offset"));
+                }
+                Ok(msg.return_with_args((value[offset..].to_vec(),)))
+            }
+            (CHARACTERISTIC_INTERFACE, "WriteValue") | (DESCRIPTOR_INTERFACE, "WriteValue") => {
+                let (value, _options): (Vec<u8>, PropMap) = msg.read2().map_err(invalid_args)?;
+                let mut state = self.state.lock().unwrap();
+                if let Some(props) = state.objects.get_mut(path).and_then(|ifaces| ifaces.get_mut(interface)) {
+                    props.insert("Value".to_string(), Variant(Box::new(value)));
+                }
+                Ok(msg.method_return())
+            }
+            (CHARACTERISTIC_INTERFACE, "StartNotify") => {
+                self.set_property(path, CHARACTERISTIC_INTERFACE, "Notifying", Box::new(true));
+                Ok(msg.method_return())
+            }
+            (CHARACTERISTIC_INTERFACE, "StopNotify") => {
+                self.set_property(path, CHARACTERISTIC_INTERFACE, "Notifying", Box::new(false));
+                Ok(msg.method_return())
+            }
+            (agent::MANAGER_INTERFACE, "RegisterAgent") => {
+                let (agent_path, _capability): (Path, &str) = msg.read2().map_err(invalid_args)?;
+                let sender = msg.sender().map(|s| s.to_string()).unwrap_or_default();
+                self.state.lock().unwrap().agent = Some((sender, agent_path.into_static()));
+                Ok(msg.method_return())
+            }
+            (agent::MANAGER_INTERFACE, "UnregisterAgent") => {
+                let agent_path: Path = msg.read1().map_err(invalid_args)?;
+                let mut state = self.state.lock().unwrap();
+                match &state.agent {
+                    Some((_, registered)) if *registered == agent_path => {
+                        state.agent = None;
+                        Ok(msg.method_return())
+                    }
+                    _ => Err(bluez_err("DoesNotExist", "No such agent")),
+                }
+            }
+            (agent::MANAGER_INTERFACE, "RequestDefaultAgent") => Ok(msg.method_return()),
+            (adv::MANAGER_INTERFACE, "RegisterAdvertisement") => {
+                let (adv_path, _options): (Path, PropMap) = msg.read2().map_err(invalid_args)?;
+                let sender = msg.sender().map(|s| s.to_string()).unwrap_or_default();
+                let count = {
+                    let mut state = self.state.lock().unwrap();
+                    let advs = state.advertisements.entry(path.clone()).or_default();
+                    let adv = (sender, adv_path.into_static());
+                    if advs.contains(&adv) {
+                        return Err(bluez_err("AlreadyExists", "Already Exists"));
+                    }
+                    advs.push(adv);
+                    advs.len()
+                };
+                self.set_property(path, adv::MANAGER_INTERFACE, "ActiveInstances", Box::new(count as u8));
+                Ok(msg.method_return())
+            }
+            (adv::MANAGER_INTERFACE, "UnregisterAdvertisement") => {
+                let adv_path: Path = msg.read1().map_err(invalid_args)?;
+                let count = {
+                    let mut state = self.state.lock().unwrap();
+                    let advs = state.advertisements.entry(path.clone()).or_default();
+                    match advs.iter().position(|(_, p)| *p == adv_path) {
+                        Some(pos) => advs.remove(pos),
+                        None => return Err(bluez_err("DoesNotExist", "Does Not Exist")),
+                    };
+                    advs.len()
+                };
+                self.set_property(path, adv::MANAGER_INTERFACE, "ActiveInstances", Box::new(count as u8));
+                Ok(msg.method_return())
+            }
+            _ => Err(MethodErr::no_method(member)),
+        }
+    }
+
+    /// Sets the service allow list of the adapter and updates which of its devices are affected by it.
+    fn set_service_allow_list(&self, adapter_path: &Path<'static>, uuids: Vec<String>) {
+        let prefix = format!("{adapter_path}/");
+        let affected: Vec<_> = {
+            let state = self.state.lock().unwrap();
+            state
+                .objects
+                .iter()
+                .filter(|(p, _)| p.starts_with(&prefix))
+                .filter_map(|(p, ifaces)| {
+                    let dev_uuids = ifaces.get(device::INTERFACE)?.get("UUIDs")?.0.as_iter()?;
+                    let affected = !uuids.is_empty()
+                        && dev_uuids
+                            .filter_map(|u| u.as_str().map(|u| u.to_string()))
+                            .any(|u| !uuids.contains(&u));
+                    Some((p.clone(), affected))
+                })
+                .collect()
+        };
+        self.set_property(
+            adapter_path,
+            adapter::ADMIN_POLICY_STATUS_INTERFACE,
+            "ServiceAllowList",
+            Box::new(uuids),
+        );
+        for (path, affected) in affected {
+            self.set_property(
+                &path,
+                adapter::ADMIN_POLICY_STATUS_INTERFACE,
+                "AffectedByPolicy",
+                Box::new(affected),
+            );
+        }
+    }
+
+    /// Performs pairing with the device using its pairing method.
+    async fn pair(self: &Arc<Self>, device_path: Path<'static>) -> DbusResult<()> {
+        let (method, agent) = {
+            let state = self.state.lock().unwrap();
+            (state.pairing_methods.get(&device_path).cloned().unwrap_or_default(), state.agent.clone())
+        };
+        let agent = || match &agent {
+            Some((name, path)) => Ok(Proxy::new(name.clone(), path.clone(), TIMEOUT, self.connection.clone())),
+            None => Err(bluez_err("AuthenticationFailed", "No agent registered")),
+        };
+        let agent_err = |err: dbus::Error| match err.name() {
+            Some(name) if name.ends_with("Canceled") => bluez_err("AuthenticationCanceled", "Canceled by agent"),
+            _ => bluez_err("AuthenticationRejected", "Rejected by agent"),
+        };
+        let mismatch = || bluez_err("AuthenticationFailed", "Authentication failed");
+
+        match method {
+            PairingMethod::JustWorks => (),
+            PairingMethod::Confirmation(passkey) => {
+                agent()?
+                    .method_call(agent::INTERFACE, "RequestConfirmation", (device_path.clone(), passkey))
+                    .await
+                    .map_err(agent_err)?;
+            }
+            PairingMethod::Passkey(expected) => {
+                let (passkey,): (u32,) = agent()?
+                    .method_call(agent::INTERFACE, "RequestPasskey", (device_path.clone(),))
+                    .await
+                    .map_err(agent_err)?;
+                if passkey != expected {
+                    return Err(mismatch());
+                }
+            }
+            PairingMethod::PinCode(expected) => {
+                let (pin_code,): (String,) = agent()?
+                    .method_call(agent::INTERFACE, "RequestPinCode", (device_path.clone(),))
+                    .await
+                    .map_err(agent_err)?;
+                if pin_code != expected {
+                    return Err(mismatch());
+                }
+            }
+            PairingMethod::Authorization => {
+                agent()?
+                    .method_call(agent::INTERFACE, "RequestAuthorization", (device_path.clone(),))
+                    .await
+                    .map_err(agent_err)?;
+            }
+            PairingMethod::Fail => return Err(mismatch()),
+        }
+
+        self.set_property(&device_path, device::INTERFACE, "Paired", Box::new(true));
+        self.set_property(&device_path, device::INTERFACE, "Bonded", Box::new(true));
+        Ok(())
+    }
+}
+
+macro_rules! props {
+    ($($name:expr => $value:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut props = PropMap::new();
+        $(
+            props.insert($name.to_string(), Variant(Box::new($value) as Box<dyn RefArg>));
+        )*
+        props
+    }};
+}
+
+macro_rules! mock_object {
+    ($name:ident, $interface:expr) => {
+        impl $name {
+            /// D-Bus object path.
+            pub fn dbus_path(&self) -> &Path<'static> {
+                &self.path
+            }
+
+            /// Sets the value of the property with the specified D-Bus name.
+            ///
+            /// A property change signal is emitted.
+            pub fn set_property(&self, name: &str, value: impl RefArg + 'static) {
+                self.inner.set_property(&self.path, $interface, name, Box::new(value));
+            }
+
+            /// Gets the value of the property with the specified D-Bus name.
+            ///
+            /// Returns [None] if the property does not exist or has a different type.
+            pub fn property<T: Clone + 'static>(&self, name: &str) -> Option<T> {
+                let value = self.inner.property(&self.path, $interface, name)?;
+                dbus::arg::cast::<T>(&*value).cloned()
+            }
+
+            /// Removes the object including all child objects.
+            pub fn remove(&self) {
+                self.inner.remove_object(&self.path);
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{} {{ {} }}", stringify!($name), &self.path)
+            }
+        }
+    };
+}
+
+/// Mock Bluetooth daemon.
+///
+/// It is unpublished when the last clone is dropped.
+#[derive(Clone)]
+pub struct Bluetoothd {
+    inner: Arc<MockInner>,
+}
+
+impl fmt::Debug for Bluetoothd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Bluetoothd {{ {} }}", self.inner.connection.unique_name())
+    }
+}
+
+impl Bluetoothd {
+    /// Serves a mock Bluetooth daemon on the D-Bus bus with the specified address.
+    ///
+    /// The name `org.bluez` is acquired on the bus.
+    pub async fn new_on_bus(address: &str) -> Result<Self> {
+        let address = address.to_string();
+        let (resource, connection) = spawn_blocking(move || connect_bus(&address)).await??;
+        let dbus_task = tokio::spawn(resource);
+        log::trace!("Mock connected to D-Bus with unique name {}", &connection.unique_name());
+
+        let inner = Arc::new(MockInner { connection: connection.clone(), state: Default::default(), dbus_task });
+        inner.add_object(
+            Path::from(agent::MANAGER_PATH),
+            [(agent::MANAGER_INTERFACE.to_string(), props!())].into_iter().collect(),
+        );
+
+        let mc_callback = connection.add_match(MatchRule::new_method_call()).await?;
+        let mc_inner: Weak<MockInner> = Arc::downgrade(&inner);
+        tokio::spawn(async move {
+            let (_mc_callback, mut mc_stream) = mc_callback.msg_stream();
+            while let Some(msg) = mc_stream.next().await {
+                let mc_inner = match mc_inner.upgrade() {
+                    Some(inner) => inner,
+                    None => return,
+                };
+                tokio::spawn(mc_inner.handle_method_call(msg));
+            }
+        });
+
+        connection.request_name(SERVICE_NAME, false, true, true).await?;
+
+        Ok(Self { inner })
+    }
+
+    /// Adds a powered-off Bluetooth adapter with the specified name and address.
+    pub fn add_adapter(&self, name: &str, address: Address) -> MockAdapter {
+        let path = adapter::Adapter::dbus_path(name).unwrap();
+        let props = props! {
+            "Address" => address.to_string(),
+            "AddressType" => "public".to_string(),
+            "Name" => name.to_string(),
+            "Alias" => name.to_string(),
+            "Class" => 0u32,
+            "Powered" => false,
+            "Discoverable" => false,
+            "Pairable" => false,
+            "PairableTimeout" => 0u32,
+            "DiscoverableTimeout" => 180u32,
+            "Discovering" => false,
+            "UUIDs" => Vec::<String>::new(),
+        };
+        let interfaces = [
+            (adapter::INTERFACE.to_string(), props),
+            (adapter::ADMIN_POLICY_SET_INTERFACE.to_string(), props!()),
+            (
+                adapter::ADMIN_POLICY_STATUS_INTERFACE.to_string(),
+                props! { "ServiceAllowList" => Vec::<String>::new() },
+            ),
+            (
+                adv::MANAGER_INTERFACE.to_string(),
+                props! {
+                    "ActiveInstances" => 0u8,
+                    "SupportedInstances" => 5u8,
+                    "SupportedIncludes" => vec!["tx-power".to_string(), "appearance".to_string(), "local-name".to_string()],
+                },
+            ),
+        ];
+        self.inner.add_object(path.clone(), interfaces.into_iter().collect());
+        MockAdapter { inner: self.inner.clone(), path, name: name.to_string() }
+    }
+
+    /// Whether an agent is registered.
+    pub fn is_agent_registered(&self) -> bool {
+        self.inner.state.lock().unwrap().agent.is_some()
+    }
+}
+
+/// Bluetooth adapter of the mock Bluetooth daemon.
+#[derive(Clone)]
+pub struct MockAdapter {
+    inner: Arc<MockInner>,
+    path: Path<'static>,
+    name: String,
+}
+
+mock_object!(MockAdapter, adapter::INTERFACE);
+
+impl MockAdapter {
+    /// The Bluetooth adapter name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Object paths of the advertisements registered with the adapter.
+    pub fn advertisements(&self) -> Vec<Path<'static>> {
+        let state = self.inner.state.lock().unwrap();
+        state.advertisements.get(&self.path).into_iter().flatten().map(|(_, path)| path.clone()).collect()
+    }
+
+    /// Adds a remote Bluetooth LE device with the specified address.
+    ///
+    /// Use [MockDevice::set_property] to set further device properties, for
+    /// example `RSSI`, `ManufacturerData` or `ServiceData`.
+    pub fn add_device(&self, address: Address) -> MockDevice {
+        let path = device::Device::dbus_path(&self.name, address).unwrap();
+        let props = props! {
+            "Address" => address.to_string(),
+            "AddressType" => "public".to_string(),
+            "Alias" => address.to_string().replace(':', "-"),
+            "Paired" => false,
+            "Bonded" => false,
+            "Connected" => false,
+            "Trusted" => false,
+            "Blocked" => false,
+            "LegacyPairing" => false,
+            "ServicesResolved" => false,
+            "UUIDs" => Vec::<String>::new(),
+            "Adapter" => self.path.clone(),
+        };
+        let interfaces = [
+            (device::INTERFACE.to_string(), props),
+            (adapter::ADMIN_POLICY_STATUS_INTERFACE.to_string(), props! { "AffectedByPolicy" => false }),
+        ];
+        self.inner.add_object(path.clone(), interfaces.into_iter().collect());
+        MockDevice { inner: self.inner.clone(), path }
+    }
+}
+
+/// Remote Bluetooth device of the mock Bluetooth daemon.
+#[derive(Clone)]
+pub struct MockDevice {
+    inner: Arc<MockInner>,
+    path: Path<'static>,
+}
+
+mock_object!(MockDevice, device::INTERFACE);
+
+impl MockDevice {
+    /// Sets the method used when pairing is requested.
+    ///
+    /// By default [PairingMethod::JustWorks] is used.
+    pub fn set_pairing_method(&self, method: PairingMethod) {
+        self.inner.state.lock().unwrap().pairing_methods.insert(self.path.clone(), method);
+    }
+
+    /// Sets the battery level of the device in percent.
+    ///
+    /// The battery interface is added to the device, if it is not yet present.
+    pub fn set_battery_percentage(&self, percentage: u8) {
+        if self.inner.property(&self.path, battery::INTERFACE, "Percentage").is_some() {
+            self.inner.set_property(&self.path, battery::INTERFACE, "Percentage", Box::new(percentage));
+        } else {
+            self.inner.add_interface(&self.path, battery::INTERFACE, props! { "Percentage" => percentage });
+        }
+    }
+
+    /// Adds a remote GATT service.
+    ///
+    /// The service is published immediately, regardless of whether the device is connected.
+    /// Connecting to the device through a session marks its services as resolved.
+    pub fn add_service(&self, uuid: Uuid, primary: bool) -> MockService {
+        let id = self.inner.next_child_id(&self.path);
+        let path = Path::new(format!("{}/service{:04x}", &self.path, id)).unwrap();
+        let props = props! {
+            "UUID" => uuid.to_string(),
+            "Primary" => primary,
+            "Device" => self.path.clone(),
+            "Includes" => Vec::<Path<'static>>::new(),
+            "Handle" => id,
+        };
+        self.inner.add_object(path.clone(), [(SERVICE_INTERFACE.to_string(), props)].into_iter().collect());
+        MockService { inner: self.inner.clone(), path }
+    }
+}
+
+/// Remote GATT service of the mock Bluetooth daemon.
+#[derive(Clone)]
+pub struct MockService {
+    inner: Arc<MockInner>,
+    path: Path<'static>,
+}
+
+mock_object!(MockService, SERVICE_INTERFACE);
+
+impl MockService {
+    /// Adds a GATT characteristic with the specified flags and initial value.
+    ///
+    /// The flags are specified using their D-Bus names, for example `read`, `write` and `notify`.
+    pub fn add_characteristic(&self, uuid: Uuid, flags: &[&str], value: Vec<u8>) -> MockCharacteristic {
+        let id = self.inner.next_child_id(&self.path);
+        let path = Path::new(format!("{}/char{:04x}", &self.path, id)).unwrap();
+        let props = props! {
+            "UUID" => uuid.to_string(),
+            "Service" => self.path.clone(),
+            "Value" => value,
+            "Notifying" => false,
+            "Flags" => flags.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            "Handle" => id,
+        };
+        self.inner
+            .add_object(path.clone(), [(CHARACTERISTIC_INTERFACE.to_string(), props)].into_iter().collect());
+        MockCharacteristic { inner: self.inner.clone(), path }
+    }
+}
+
+/// Remote GATT characteristic of the mock Bluetooth daemon.
+///
+/// Values written by clients can be obtained using [value](Self::value).
+#[derive(Clone)]
+pub struct MockCharacteristic {
+    inner: Arc<MockInner>,
+    path: Path<'static>,
+}
+
+mock_object!(MockCharacteristic, CHARACTERISTIC_INTERFACE);
+
+impl MockCharacteristic {
+    /// Current value of the characteristic.
+    pub fn value(&self) -> Vec<u8> {
+        self.property("Value").unwrap_or_default()
+    }
+
+    /// Sets the value of the characteristic.
+    ///
+    /// Clients that have enabled notifications receive the new value.
+    pub fn set_value(&self, value: Vec<u8>) {
+        self.set_property("Value", value);
+    }
+
+    /// Adds a GATT characteristic descriptor with the specified flags and initial value.
+    pub fn add_descriptor(&self, uuid: Uuid, flags: &[&str], value: Vec<u8>) -> MockDescriptor {
+        let id = self.inner.next_child_id(&self.path);
+        let path = Path::new(format!("{}/desc{:04x}", &self.path, id)).unwrap();
+        let props = props! {
+            "UUID" => uuid.to_string(),
+            "Characteristic" => self.path.clone(),
+            "Value" => value,
+            "Flags" => flags.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            "Handle" => id,
+        };
+        self.inner.add_object(path.clone(), [(DESCRIPTOR_INTERFACE.to_string(), props)].into_iter().collect());
+        MockDescriptor { inner: self.inner.clone(), path }
+    }
+}
+
+/// Remote GATT characteristic descriptor of the mock Bluetooth daemon.
+#[derive(Clone)]
+pub struct MockDescriptor {
+    inner: Arc<MockInner>,
+    path: Path<'static>,
+}
+
+mock_object!(MockDescriptor, DESCRIPTOR_INTERFACE);
+
+impl MockDescriptor {
+    /// Current value of the descriptor.
+    pub fn value(&self) -> Vec<u8> {
+        self.property("Value").unwrap_or_default()
+    }
+}
@@ -0,0 +1,156 @@
+//! Personal area networking (PAN).
+//!
+//! A remote device offering a PAN service, for example a phone providing tethering,
+//! is connected using [Device::connect_network](crate::Device::connect_network).
+//! The Bluetooth daemon then creates a local network interface, which must be configured
+//! by the application or a network manager, for example using DHCP.
+//!
+//! To accept incoming PAN connections, register a network server using
+//! [Adapter::register_network_server](crate::Adapter::register_network_server).
+//! Network interfaces of connecting devices are then added to the specified bridge.
+
+use dbus::nonblock::Proxy;
+use futures::channel::oneshot;
+use std::{fmt, sync::Arc};
+use strum::{Display, EnumString};
+
+use crate::{session::RegistrationKey, Adapter, Address, Device, Result, SessionInner, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.Network1";
+pub(crate) const SERVER_INTERFACE: &str = "org.bluez.NetworkServer1";
+
+/// PAN role.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Role {
+    /// PAN user.
+    #[strum(serialize = "panu")]
+    Panu,
+    /// Network access point, providing access to another network.
+    #[strum(serialize = "nap")]
+    Nap,
+    /// Group ad-hoc network, connecting PAN users with each other.
+    #[strum(serialize = "gn")]
+    Gn,
+}
+
+/// Handle to a PAN connection to a remote device.
+///
+/// Drop to disconnect.
+pub struct NetworkHandle {
+    device_address: Address,
+    role: Role,
+    interface: String,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl NetworkHandle {
+    pub(crate) async fn connect(
+        inner: Arc<SessionInner>, adapter_name: &str, address: Address, role: Role,
+    ) -> Result<Self> {
+        let device_path = Device::dbus_path(adapter_name, address)?;
+        log::trace!("{}: {}.Connect ({})", &device_path, INTERFACE, role);
+        let proxy =
+            Proxy::new(inner.service_name.clone(), device_path.clone(), TIMEOUT, inner.connection.clone());
+        let (interface,): (String,) = proxy.method_call(INTERFACE, "Connect", (role.to_string(),)).await?;
+        log::trace!("{}: network interface {}", &device_path, &interface);
+
+        let (drop_tx, drop_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let _ = drop_rx.await;
+
+            log::trace!("{}: {}.Disconnect ()", &device_path, INTERFACE);
+            let _: std::result::Result<(), dbus::Error> = proxy.method_call(INTERFACE, "Disconnect", ()).await;
+        });
+
+        Ok(Self { device_address: address, role, interface, _drop_tx: drop_tx })
+    }
+
+    /// Address of the remote device.
+    pub fn device_address(&self) -> Address {
+        self.device_address
+    }
+
+    /// Role of the remote device.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Name of the local network interface, for example `bnep0`.
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+}
+
+impl Drop for NetworkHandle {
+    fn drop(&mut self) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for NetworkHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NetworkHandle {{ {} {} {} }}", &self.device_address, &self.role, &self.interface)
+    }
+}
+
+/// Handle to a registered PAN network server.
+///
+/// Drop to unregister the network server.
+pub struct NetworkServerHandle {
+    role: Role,
+    bridge: String,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl NetworkServerHandle {
+    pub(crate) async fn register(
+        inner: Arc<SessionInner>, adapter_name: &str, role: Role, bridge: String,
+    ) -> Result<Self> {
+        log::trace!("Registering network server for {} with bridge {}", &role, &bridge);
+        let adapter_path = Adapter::dbus_path(adapter_name)?;
+        let key = RegistrationKey::NetworkServer(adapter_path.clone(), role);
+        let reg_bridge = bridge.clone();
+        inner
+            .register_with(key.clone(), adapter_path.clone(), SERVER_INTERFACE, "Register", move || {
+                (role.to_string(), reg_bridge.clone())
+            })
+            .await?;
+
+        let (drop_tx, drop_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let _ = drop_rx.await;
+            inner.forget_registration(key).await;
+
+            log::trace!("Unregistering network server for {}", &role);
+            let proxy = Proxy::new(inner.service_name.clone(), adapter_path, TIMEOUT, inner.connection.clone());
+            let _: std::result::Result<(), dbus::Error> =
+                proxy.method_call(SERVER_INTERFACE, "Unregister", (role.to_string(),)).await;
+        });
+
+        Ok(Self { role, bridge, _drop_tx: drop_tx })
+    }
+
+    /// Served role.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Name of the network bridge that connections are added to.
+    pub fn bridge(&self) -> &str {
+        &self.bridge
+    }
+}
+
+impl Drop for NetworkServerHandle {
+    fn drop(&mut self) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for NetworkServerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NetworkServerHandle {{ {} {} }}", &self.role, &self.bridge)
+    }
+}
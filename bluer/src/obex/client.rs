@@ -0,0 +1,236 @@
+//! Native OBEX client.
+
+use std::{
+    io::{Error, ErrorKind, Result},
+    mem::take,
+};
+
+use super::{
+    packet::{Header, Headers, Opcode, Request, Response, ResponseCode, SetPathFlags, Srm, MIN_PACKET_LEN},
+    transport::Transport,
+    ConnectParams,
+};
+
+/// Number of bytes available for body data in a packet of the specified length.
+pub(crate) fn body_space(packet_len: usize, max_packet_len: u16) -> Result<usize> {
+    // Body header consists of identifier and length.
+    match (max_packet_len as usize).checked_sub(packet_len + 3) {
+        Some(space) => Ok(space),
+        None => Err(Error::new(ErrorKind::InvalidInput, "OBEX headers exceed maximum packet length")),
+    }
+}
+
+/// OBEX client.
+///
+/// The client performs requests over a [Transport] to an OBEX server,
+/// splitting objects into multiple packets as necessary.
+/// Single response mode (SRM) is used if the transport and the server support it.
+///
+/// Operations fail with an [std::io::Error] containing the [ResponseCode]
+/// if the server does not respond with a success code.
+#[derive(Debug)]
+pub struct Client<T> {
+    transport: T,
+    max_packet_len: u16,
+    connection_id: Option<u32>,
+}
+
+impl<T: Transport> Client<T> {
+    /// Creates a new client using the specified transport.
+    ///
+    /// Use [connect](Self::connect) to establish an OBEX connection.
+    pub fn new(transport: T) -> Self {
+        Self { transport, max_packet_len: MIN_PACKET_LEN, connection_id: None }
+    }
+
+    /// The transport.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Consumes the client and returns the transport.
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Negotiated maximum packet length.
+    pub fn max_packet_len(&self) -> u16 {
+        self.max_packet_len
+    }
+
+    /// Connection identifier assigned by the server.
+    ///
+    /// It is included in all requests.
+    pub fn connection_id(&self) -> Option<u32> {
+        self.connection_id
+    }
+
+    /// Sends a single request packet and receives the response packet.
+    pub async fn request(&mut self, request: &Request) -> Result<Response> {
+        self.send(request).await?;
+        self.recv(matches!(request.opcode, Opcode::Connect(_))).await
+    }
+
+    async fn send(&mut self, request: &Request) -> Result<()> {
+        self.transport.send_packet(&request.to_bytes()?).await
+    }
+
+    async fn recv(&mut self, connect: bool) -> Result<Response> {
+        let buf = self.transport.recv_packet().await?;
+        Response::from_bytes(&buf, connect)
+    }
+
+    /// Headers of the first packet of an operation.
+    fn first_headers(&self, headers: Vec<Header>) -> Vec<Header> {
+        match self.connection_id {
+            Some(id) => [Header::ConnectionId(id)].into_iter().chain(headers).collect(),
+            None => headers,
+        }
+    }
+
+    /// Establishes an OBEX connection.
+    ///
+    /// To connect to a specific service, include its [Target](Header::Target) header.
+    /// The connection identifier assigned by the server is stored and sent with
+    /// all further requests.
+    pub async fn connect(&mut self, headers: Vec<Header>) -> Result<Response> {
+        let params = ConnectParams { max_packet_len: self.transport.max_packet_len(), ..Default::default() };
+        let request = Request { opcode: Opcode::Connect(params), is_final: true, headers };
+        let response = self.request(&request).await?.check()?;
+
+        let remote_max = response.connect.map(|p| p.max_packet_len).unwrap_or(MIN_PACKET_LEN);
+        self.max_packet_len = params.max_packet_len.min(remote_max.max(MIN_PACKET_LEN));
+        self.connection_id = response.connection_id();
+        Ok(response)
+    }
+
+    /// Terminates the OBEX connection.
+    pub async fn disconnect(&mut self) -> Result<Response> {
+        let request =
+            Request { opcode: Opcode::Disconnect, is_final: true, headers: self.first_headers(Vec::new()) };
+        let response = self.request(&request).await?.check()?;
+        self.connection_id = None;
+        Ok(response)
+    }
+
+    /// Sends an object to the server.
+    ///
+    /// The object is described by `headers`, for example using the [Name](Header::Name)
+    /// and [Type](Header::Type) headers, and its contents are given by `body`.
+    pub async fn put(&mut self, headers: Vec<Header>, body: &[u8]) -> Result<Response> {
+        self.put_object(headers, Some(body)).await
+    }
+
+    /// Deletes the object described by `headers` on the server.
+    pub async fn delete(&mut self, headers: Vec<Header>) -> Result<Response> {
+        self.put_object(headers, None).await
+    }
+
+    async fn put_object(&mut self, headers: Vec<Header>, mut body: Option<&[u8]>) -> Result<Response> {
+        let mut headers = self.first_headers(headers);
+        if self.transport.supports_srm() {
+            headers.push(Header::Srm(Srm::Enable));
+        }
+
+        let mut first = true;
+        let mut srm = false;
+        let mut wait = false;
+        loop {
+            let mut request = Request { opcode: Opcode::Put, is_final: false, headers: take(&mut headers) };
+            match body {
+                Some(data) => {
+                    let space = body_space(request.to_bytes()?.len(), self.max_packet_len)?;
+                    if data.len() <= space {
+                        request.headers.push(Header::EndOfBody(data.to_vec()));
+                        request.is_final = true;
+                    } else {
+                        request.headers.push(Header::Body(data[..space].to_vec()));
+                        body = Some(&data[space..]);
+                    }
+                }
+                None => request.is_final = true,
+            }
+            self.send(&request).await?;
+            if request.is_final {
+                break;
+            }
+
+            // In single response mode the server only responds to the first and final
+            // packets, unless it asks us to wait.
+            if first || !srm || wait {
+                let response = self.recv(false).await?;
+                if response.code != ResponseCode::CONTINUE {
+                    return response.check();
+                }
+                if first {
+                    srm = self.transport.supports_srm() && response.srm() == Some(Srm::Enable);
+                }
+                wait = srm && response.srm_parameters().map(|p| p.is_wait()).unwrap_or_default();
+            }
+            first = false;
+        }
+
+        self.recv(false).await?.check()
+    }
+
+    /// Requests an object from the server.
+    ///
+    /// The object is described by `headers`, for example using the [Name](Header::Name)
+    /// and [Type](Header::Type) headers.
+    /// Returns the final response containing all non-body headers sent by the server
+    /// and the object contents.
+    pub async fn get(&mut self, headers: Vec<Header>) -> Result<(Response, Vec<u8>)> {
+        let mut headers = self.first_headers(headers);
+        if self.transport.supports_srm() {
+            headers.push(Header::Srm(Srm::Enable));
+        }
+        self.send(&Request { opcode: Opcode::Get, is_final: true, headers }).await?;
+
+        let mut first = true;
+        let mut srm = false;
+        let mut response_headers = Vec::new();
+        let mut body = Vec::new();
+        loop {
+            let response = self.recv(false).await?;
+            if first {
+                srm = self.transport.supports_srm() && response.srm() == Some(Srm::Enable);
+                first = false;
+            }
+            for header in response.headers {
+                match header {
+                    Header::Body(data) | Header::EndOfBody(data) => body.extend(data),
+                    Header::Srm(_) | Header::SrmParameters(_) => (),
+                    other => response_headers.push(other),
+                }
+            }
+
+            match response.code {
+                ResponseCode::CONTINUE if srm => (),
+                ResponseCode::CONTINUE => {
+                    self.send(&Request::new(Opcode::Get)).await?;
+                }
+                code => {
+                    let response = Response { code, connect: None, headers: response_headers }.check()?;
+                    return Ok((response, body));
+                }
+            }
+        }
+    }
+
+    /// Changes the current folder on the server.
+    ///
+    /// The folder is specified by the [Name](Header::Name) header.
+    /// Omit it to change to the root folder, or use [SetPathFlags::parent] to
+    /// change to the parent folder.
+    pub async fn set_path(&mut self, flags: SetPathFlags, headers: Vec<Header>) -> Result<Response> {
+        let request =
+            Request { opcode: Opcode::SetPath(flags), is_final: true, headers: self.first_headers(headers) };
+        self.request(&request).await?.check()
+    }
+
+    /// Aborts the current multi-packet operation.
+    pub async fn abort(&mut self) -> Result<Response> {
+        let request = Request { opcode: Opcode::Abort, is_final: true, headers: self.first_headers(Vec::new()) };
+        self.request(&request).await?.check()
+    }
+}
@@ -0,0 +1,43 @@
+//! Object exchange (OBEX).
+//!
+//! There are two ways to exchange objects with a remote device:
+//!
+//!   1. Use the OBEX protocol implementation of this module over an
+//!      [RFCOMM stream](crate::rfcomm::Stream) or an
+//!      [L2CAP sequential packet socket](crate::l2cap::SeqPacket) (GOEP 2.0).
+//!      A [Client] performs requests on a remote OBEX server, while a [Server]
+//!      dispatches received requests to a custom [Service].
+//!      This does not require a running Bluetooth daemon.
+//!   2. Use the OBEX daemon `obexd` of BlueZ, which runs on the D-Bus session bus
+//!      and implements the object push (OPP), file transfer (FTP), phonebook access (PBAP)
+//!      and message access (MAP) profiles.
+//!      This requires the `bluetoothd` crate feature in addition to `obex`.
+//!      Use an [ObexClient] to [create a session](ObexClient::create_session) with a remote device.
+//!      The functions available on an [ObexSession] depend on its [Target].
+//!      Data is exchanged through [Transfer]s, which report their progress while
+//!      the data is written to or read from a local file.
+//!
+
+mod client;
+mod packet;
+mod server;
+mod transport;
+
+pub use client::Client;
+pub use packet::{
+    ConnectParams, Header, HeaderValue, Headers, Opcode, Request, Response, ResponseCode, SetPathFlags, Srm,
+    SrmParameters, MAX_PACKET_LEN, MIN_PACKET_LEN,
+};
+pub use server::{Server, Service, ServiceResult, DEFAULT_MAX_REQUEST_LEN};
+pub use transport::Transport;
+
+#[cfg(feature = "bluetoothd")]
+mod obexd;
+
+#[cfg(feature = "bluetoothd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+pub use obexd::{
+    FolderEntry, FolderEntryType, Message, MessageEntry, MessageFilter, MessageStatus, MessageType, ObexClient,
+    ObexSession, Phonebook, PhonebookLocation, SessionOptions, Target, Transfer, TransferEvent, TransferProgress,
+    TransferStatus, VCardEntry, VCardFilter, VCardFormat, VCardOrder, VCardSearchField,
+};
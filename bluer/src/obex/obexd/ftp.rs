@@ -0,0 +1,112 @@
+//! File transfer profile (FTP).
+
+use dbus::arg::PropMap;
+use std::path::Path as FsPath;
+use strum::{Display, EnumString};
+
+use super::{file_arg, parse_value, ObexSession, Transfer};
+use crate::{read_dict, Result};
+
+pub(crate) const FILE_TRANSFER_INTERFACE: &str = "org.bluez.obex.FileTransfer1";
+
+/// Type of a folder entry.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum FolderEntryType {
+    /// Folder.
+    #[strum(serialize = "folder")]
+    Folder,
+    /// File.
+    #[strum(serialize = "file")]
+    File,
+}
+
+/// Entry of a folder on the remote device.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct FolderEntry {
+    /// Name.
+    pub name: String,
+    /// Entry type.
+    pub entry_type: FolderEntryType,
+    /// Size in bytes.
+    pub size: Option<u64>,
+    /// Permissions, for example `RWD`.
+    pub permission: Option<String>,
+    /// Modification time, for example `20230131T120000Z`.
+    pub modified: Option<String>,
+    /// Access time.
+    pub accessed: Option<String>,
+    /// Creation time.
+    pub created: Option<String>,
+}
+
+impl FolderEntry {
+    fn from_dict(dict: &PropMap) -> Result<Self> {
+        Ok(Self {
+            name: read_dict::<String>(dict, "Name")?.clone(),
+            entry_type: parse_value(read_dict::<String>(dict, "Type")?)?,
+            size: read_dict(dict, "Size").ok().cloned(),
+            permission: read_dict(dict, "Permission").ok().cloned(),
+            modified: read_dict(dict, "Modified").ok().cloned(),
+            accessed: read_dict(dict, "Accessed").ok().cloned(),
+            created: read_dict(dict, "Created").ok().cloned(),
+        })
+    }
+}
+
+/// File transfer functions.
+///
+/// These require the [file transfer](super::Target::FileTransfer) target.
+impl ObexSession {
+    /// Changes the current folder on the remote device.
+    ///
+    /// Use `..` to change to the parent folder.
+    pub async fn change_folder(&self, folder: &str) -> Result<()> {
+        self.call_method_with_interface("ChangeFolder", (folder,), FILE_TRANSFER_INTERFACE).await
+    }
+
+    /// Creates a folder on the remote device and changes into it.
+    pub async fn create_folder(&self, folder: &str) -> Result<()> {
+        self.call_method_with_interface("CreateFolder", (folder,), FILE_TRANSFER_INTERFACE).await
+    }
+
+    /// Lists the contents of the current folder on the remote device.
+    pub async fn list_folder(&self) -> Result<Vec<FolderEntry>> {
+        let (entries,): (Vec<PropMap>,) =
+            self.call_method_with_interface("ListFolder", (), FILE_TRANSFER_INTERFACE).await?;
+        entries.iter().map(FolderEntry::from_dict).collect()
+    }
+
+    /// Copies the file `source_file` from the current folder on the remote device
+    /// into the local file `target_file`.
+    ///
+    /// If `target_file` is empty, a temporary file is created, whose name
+    /// is available from [Transfer::filename].
+    pub async fn get_file(&self, target_file: &FsPath, source_file: &str) -> Result<Transfer> {
+        self.start_transfer("GetFile", (file_arg(target_file)?, source_file), FILE_TRANSFER_INTERFACE).await
+    }
+
+    /// Copies the local file `source_file` into the file `target_file` in the
+    /// current folder on the remote device.
+    pub async fn put_file(&self, source_file: &FsPath, target_file: &str) -> Result<Transfer> {
+        self.start_transfer("PutFile", (file_arg(source_file)?, target_file), FILE_TRANSFER_INTERFACE).await
+    }
+
+    /// Copies a file within the remote device.
+    pub async fn copy_file(&self, source_file: &str, target_file: &str) -> Result<()> {
+        self.call_method_with_interface("CopyFile", (source_file, target_file), FILE_TRANSFER_INTERFACE).await
+    }
+
+    /// Moves a file within the remote device.
+    pub async fn move_file(&self, source_file: &str, target_file: &str) -> Result<()> {
+        self.call_method_with_interface("MoveFile", (source_file, target_file), FILE_TRANSFER_INTERFACE).await
+    }
+
+    /// Deletes a file or an empty folder in the current folder on the remote device.
+    pub async fn delete(&self, file: &str) -> Result<()> {
+        self.call_method_with_interface("Delete", (file,), FILE_TRANSFER_INTERFACE).await
+    }
+}
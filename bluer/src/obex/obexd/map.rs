@@ -0,0 +1,299 @@
+//! Message access profile (MAP).
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use std::{collections::HashMap, fmt, path::Path as FsPath, sync::Arc};
+use strum::{Display, EnumString};
+
+use super::{file_arg, parse_value, ObexInner, ObexSession, Transfer};
+use crate::{parent_path, read_dict, Result, TIMEOUT};
+
+pub(crate) const MESSAGE_ACCESS_INTERFACE: &str = "org.bluez.obex.MessageAccess1";
+pub(crate) const MESSAGE_INTERFACE: &str = "org.bluez.obex.Message1";
+
+/// Type of a message.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum MessageType {
+    /// Email.
+    #[strum(serialize = "email")]
+    Email,
+    /// GSM short message.
+    #[strum(serialize = "sms-gsm")]
+    SmsGsm,
+    /// CDMA short message.
+    #[strum(serialize = "sms-cdma")]
+    SmsCdma,
+    /// Multimedia message.
+    #[strum(serialize = "mms")]
+    Mms,
+    /// Instant message.
+    #[strum(serialize = "im")]
+    Im,
+}
+
+/// Reception status of a message.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum MessageStatus {
+    /// Message has been received completely.
+    #[strum(serialize = "complete")]
+    Complete,
+    /// Message has been received partially.
+    #[strum(serialize = "fractioned")]
+    Fractioned,
+    /// Only a notification of the message has been received.
+    #[strum(serialize = "notification")]
+    Notification,
+}
+
+/// Filter for listing messages.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct MessageFilter {
+    /// Index of the first message to return.
+    pub offset: Option<u16>,
+    /// Maximum number of messages to return.
+    pub max_count: Option<u16>,
+    /// Maximum length of the returned subjects.
+    pub subject_length: Option<u8>,
+    /// Message fields to return.
+    ///
+    /// The supported fields are returned by [ObexSession::message_filter_fields].
+    pub fields: Option<Vec<String>>,
+    /// Only return messages of the specified types.
+    pub types: Option<Vec<MessageType>>,
+    /// Only return messages sent or received at or after the specified time,
+    /// for example `20230131T120000`.
+    pub period_begin: Option<String>,
+    /// Only return messages sent or received before the specified time.
+    pub period_end: Option<String>,
+    /// Only return read or unread messages.
+    pub read: Option<bool>,
+    /// Only return messages with a matching recipient.
+    pub recipient: Option<String>,
+    /// Only return messages with a matching sender.
+    pub sender: Option<String>,
+    /// Only return messages with or without high priority.
+    pub priority: Option<bool>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl MessageFilter {
+    fn to_dict(&self) -> PropMap {
+        let mut dict = PropMap::new();
+        if let Some(offset) = self.offset {
+            dict.insert("Offset".to_string(), Variant(offset.box_clone()));
+        }
+        if let Some(max_count) = self.max_count {
+            dict.insert("MaxCount".to_string(), Variant(max_count.box_clone()));
+        }
+        if let Some(subject_length) = self.subject_length {
+            dict.insert("SubjectLength".to_string(), Variant(subject_length.box_clone()));
+        }
+        if let Some(fields) = &self.fields {
+            dict.insert("Fields".to_string(), Variant(fields.box_clone()));
+        }
+        if let Some(types) = &self.types {
+            let types: Vec<_> = types.iter().map(|t| t.to_string()).collect();
+            dict.insert("Types".to_string(), Variant(types.box_clone()));
+        }
+        if let Some(period_begin) = &self.period_begin {
+            dict.insert("PeriodBegin".to_string(), Variant(period_begin.box_clone()));
+        }
+        if let Some(period_end) = &self.period_end {
+            dict.insert("PeriodEnd".to_string(), Variant(period_end.box_clone()));
+        }
+        if let Some(read) = self.read {
+            dict.insert("Read".to_string(), Variant(read.box_clone()));
+        }
+        if let Some(recipient) = &self.recipient {
+            dict.insert("Recipient".to_string(), Variant(recipient.box_clone()));
+        }
+        if let Some(sender) = &self.sender {
+            dict.insert("Sender".to_string(), Variant(sender.box_clone()));
+        }
+        if let Some(priority) = self.priority {
+            dict.insert("Priority".to_string(), Variant(priority.box_clone()));
+        }
+        dict
+    }
+}
+
+/// Entry of a message listing.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct MessageEntry {
+    /// The message.
+    pub message: Message,
+    /// Folder containing the message.
+    pub folder: Option<String>,
+    /// Subject.
+    pub subject: Option<String>,
+    /// Time the message was sent or received, for example `20230131T120000`.
+    pub timestamp: Option<String>,
+    /// Name of the sender.
+    pub sender: Option<String>,
+    /// Address of the sender.
+    pub sender_address: Option<String>,
+    /// Reply-to address.
+    pub reply_to: Option<String>,
+    /// Name of the recipient.
+    pub recipient: Option<String>,
+    /// Address of the recipient.
+    pub recipient_address: Option<String>,
+    /// Message type.
+    pub message_type: Option<MessageType>,
+    /// Size in bytes.
+    pub size: Option<u64>,
+    /// Reception status.
+    pub status: Option<MessageStatus>,
+    /// Whether the message has high priority.
+    pub priority: Option<bool>,
+    /// Whether the message has been read.
+    pub read: Option<bool>,
+    /// Whether the message has been sent.
+    pub sent: Option<bool>,
+    /// Whether the message is protected by digital rights management.
+    pub protected: Option<bool>,
+}
+
+impl MessageEntry {
+    fn from_dict(message: Message, dict: &PropMap) -> Result<Self> {
+        let message_type = match read_dict::<String>(dict, "Type") {
+            Ok(v) => Some(parse_value(v)?),
+            Err(_) => None,
+        };
+        let status = match read_dict::<String>(dict, "Status") {
+            Ok(v) => Some(parse_value(v)?),
+            Err(_) => None,
+        };
+        Ok(Self {
+            message,
+            folder: read_dict(dict, "Folder").ok().cloned(),
+            subject: read_dict(dict, "Subject").ok().cloned(),
+            timestamp: read_dict(dict, "Timestamp").ok().cloned(),
+            sender: read_dict(dict, "Sender").ok().cloned(),
+            sender_address: read_dict(dict, "SenderAddress").ok().cloned(),
+            reply_to: read_dict(dict, "ReplyTo").ok().cloned(),
+            recipient: read_dict(dict, "Recipient").ok().cloned(),
+            recipient_address: read_dict(dict, "RecipientAddress").ok().cloned(),
+            message_type,
+            size: read_dict(dict, "Size").ok().cloned(),
+            status,
+            priority: read_dict(dict, "Priority").ok().cloned(),
+            read: read_dict(dict, "Read").ok().cloned(),
+            sent: read_dict(dict, "Sent").ok().cloned(),
+            protected: read_dict(dict, "Protected").ok().cloned(),
+        })
+    }
+}
+
+/// Message access functions.
+///
+/// These require the [message access](super::Target::MessageAccess) target.
+impl ObexSession {
+    /// Changes the current folder on the remote device.
+    ///
+    /// Use `..` to change to the parent folder and an empty string to change
+    /// to the root folder.
+    pub async fn set_message_folder(&self, folder: &str) -> Result<()> {
+        self.call_method_with_interface("SetFolder", (folder,), MESSAGE_ACCESS_INTERFACE).await
+    }
+
+    /// Lists the names of the subfolders of the current folder.
+    pub async fn message_folders(&self) -> Result<Vec<String>> {
+        let (folders,): (Vec<PropMap>,) =
+            self.call_method_with_interface("ListFolders", (PropMap::new(),), MESSAGE_ACCESS_INTERFACE).await?;
+        folders.iter().map(|dict| Ok(read_dict::<String>(dict, "Name")?.clone())).collect()
+    }
+
+    /// Lists the messages in the specified subfolder of the current folder.
+    ///
+    /// Use an empty string to list the messages of the current folder.
+    pub async fn list_messages(&self, folder: &str, filter: &MessageFilter) -> Result<Vec<MessageEntry>> {
+        let (messages,): (HashMap<Path<'static>, PropMap>,) = self
+            .call_method_with_interface("ListMessages", (folder, filter.to_dict()), MESSAGE_ACCESS_INTERFACE)
+            .await?;
+        let mut entries = messages
+            .into_iter()
+            .map(|(dbus_path, dict)| {
+                MessageEntry::from_dict(Message { inner: self.inner.clone(), dbus_path }, &dict)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.message.dbus_path.cmp(&b.message.dbus_path));
+        Ok(entries)
+    }
+
+    /// Message fields supported by [MessageFilter::fields].
+    pub async fn message_filter_fields(&self) -> Result<Vec<String>> {
+        let (fields,): (Vec<String>,) =
+            self.call_method_with_interface("ListFilterFields", (), MESSAGE_ACCESS_INTERFACE).await?;
+        Ok(fields)
+    }
+
+    /// Requests the remote device to check for new messages.
+    pub async fn update_inbox(&self) -> Result<()> {
+        self.call_method_with_interface("UpdateInbox", (), MESSAGE_ACCESS_INTERFACE).await
+    }
+
+    /// Pushes the message in the local file `source_file`, which must be
+    /// in bMessage format, into the specified folder on the remote device.
+    pub async fn push_message(&self, source_file: &FsPath, folder: &str) -> Result<Transfer> {
+        self.start_transfer(
+            "PushMessage",
+            (file_arg(source_file)?, folder, PropMap::new()),
+            MESSAGE_ACCESS_INTERFACE,
+        )
+        .await
+    }
+}
+
+/// Message on a remote device.
+#[derive(Clone)]
+pub struct Message {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Message {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Message {
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!(uncached);
+    dbus_default_interface!(MESSAGE_INTERFACE);
+
+    /// Downloads the message into the local file `target_file` in bMessage format.
+    ///
+    /// If `target_file` is empty, a temporary file is created, whose name
+    /// is available from [Transfer::filename].
+    /// Attachments are included if `attachment` is true.
+    pub async fn get(&self, target_file: &FsPath, attachment: bool) -> Result<Transfer> {
+        // Transfers are children of the session, which is the parent of the message.
+        let events = self.inner.child_events(parent_path(&self.dbus_path)).await?;
+        let result = self.call_method("Get", (file_arg(target_file)?, attachment)).await?;
+        Ok(Transfer::started(self.inner.clone(), result, events))
+    }
+
+    /// Marks the message as read or unread.
+    pub async fn set_read(&self, read: bool) -> Result<()> {
+        self.set_property("Read", read).await
+    }
+
+    /// Marks the message as deleted or undeleted.
+    pub async fn set_deleted(&self, deleted: bool) -> Result<()> {
+        self.set_property("Deleted", deleted).await
+    }
+}
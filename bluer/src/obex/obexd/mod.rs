@@ -0,0 +1,356 @@
+//! Bindings to the Bluetooth OBEX daemon.
+
+use dbus::{
+    arg::{AppendAll, PropMap, RefArg, Variant},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use dbus_tokio::connection;
+use futures::channel::mpsc;
+use std::{fmt, path::Path as FsPath, sync::Arc};
+use strum::{Display, EnumString};
+use tokio::{sync::oneshot, task::spawn_blocking, task::JoinHandle};
+use uuid::Uuid;
+
+use crate::{
+    session::{connect_bus, SubscriptionReq},
+    Address, Error, ErrorKind, Event, InternalErrorKind, Result, TIMEOUT,
+};
+
+mod ftp;
+mod map;
+mod pbap;
+mod transfer;
+
+pub use ftp::*;
+pub use map::*;
+pub use pbap::*;
+pub use transfer::*;
+
+/// D-Bus service name of the OBEX daemon.
+pub(crate) const SERVICE_NAME: &str = "org.bluez.obex";
+pub(crate) const MANAGER_PATH: &str = "/org/bluez/obex";
+pub(crate) const CLIENT_INTERFACE: &str = "org.bluez.obex.Client1";
+pub(crate) const SESSION_INTERFACE: &str = "org.bluez.obex.Session1";
+pub(crate) const OBJECT_PUSH_INTERFACE: &str = "org.bluez.obex.ObjectPush1";
+
+/// Shared state of a connection to the OBEX daemon.
+pub(crate) struct ObexInner {
+    pub connection: Arc<SyncConnection>,
+    pub service_name: String,
+    pub event_sub_tx: mpsc::Sender<SubscriptionReq>,
+    dbus_task: Option<JoinHandle<connection::IOResourceError>>,
+}
+
+impl ObexInner {
+    /// Subscribes to events for the specified path.
+    pub async fn events(
+        &self, path: Path<'static>, child_objects: bool,
+    ) -> Result<mpsc::UnboundedReceiver<Event>> {
+        Event::subscribe(&mut self.event_sub_tx.clone(), path, child_objects, false).await
+    }
+
+    /// Subscribes to events for the specified path and its direct child objects,
+    /// including their property changes.
+    pub async fn child_events(&self, path: Path<'static>) -> Result<mpsc::UnboundedReceiver<Event>> {
+        Event::subscribe(&mut self.event_sub_tx.clone(), path, true, true).await
+    }
+}
+
+impl Drop for ObexInner {
+    fn drop(&mut self) {
+        if let Some(dbus_task) = &self.dbus_task {
+            dbus_task.abort();
+        }
+    }
+}
+
+/// Converts a local file path into a D-Bus argument.
+pub(crate) fn file_arg(path: &FsPath) -> Result<String> {
+    match path.to_str() {
+        Some(path) => Ok(path.to_string()),
+        None => Err(Error {
+            kind: ErrorKind::InvalidArguments,
+            message: format!("file path is not valid UTF-8: {}", path.display()),
+        }),
+    }
+}
+
+/// Parses a string received from the OBEX daemon.
+pub(crate) fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T> {
+    value.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))
+}
+
+/// OBEX service of a remote device to connect to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Target {
+    /// File transfer profile (FTP).
+    #[strum(serialize = "ftp")]
+    FileTransfer,
+    /// Message access profile (MAP).
+    #[strum(serialize = "map")]
+    MessageAccess,
+    /// Object push profile (OPP).
+    #[strum(serialize = "opp")]
+    ObjectPush,
+    /// Phonebook access profile (PBAP).
+    #[strum(serialize = "pbap")]
+    PhonebookAccess,
+    /// Synchronization profile.
+    #[strum(serialize = "sync")]
+    Synchronization,
+}
+
+/// Options for creating an OBEX session.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SessionOptions {
+    /// Address of the local adapter to use.
+    ///
+    /// If unspecified, the default adapter is used.
+    pub source: Option<Address>,
+    /// RFCOMM channel of the service on the remote device.
+    ///
+    /// If unspecified, it is determined using an SDP query.
+    pub channel: Option<u8>,
+    /// L2CAP PSM of the service on the remote device.
+    ///
+    /// If unspecified, it is determined using an SDP query.
+    pub psm: Option<u16>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl SessionOptions {
+    fn to_dict(&self, target: Target) -> PropMap {
+        let mut dict = PropMap::new();
+        dict.insert("Target".to_string(), Variant(target.to_string().box_clone()));
+        if let Some(source) = self.source {
+            dict.insert("Source".to_string(), Variant(source.to_string().box_clone()));
+        }
+        if let Some(channel) = self.channel {
+            dict.insert("Channel".to_string(), Variant(channel.box_clone()));
+        }
+        if let Some(psm) = self.psm {
+            dict.insert("PSM".to_string(), Variant(psm.box_clone()));
+        }
+        dict
+    }
+}
+
+// ===========================================================================================
+// Client
+// ===========================================================================================
+
+/// Connection to the Bluetooth OBEX daemon.
+#[derive(Clone)]
+pub struct ObexClient {
+    inner: Arc<ObexInner>,
+}
+
+impl fmt::Debug for ObexClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ObexClient {{ {} }}", self.inner.connection.unique_name())
+    }
+}
+
+impl ObexClient {
+    /// Connects to the OBEX daemon on the D-Bus session bus.
+    pub async fn new() -> Result<Self> {
+        let (resource, connection) = spawn_blocking(connection::new_session_sync).await??;
+        Self::from_connection(connection, tokio::spawn(resource)).await
+    }
+
+    /// Connects to the OBEX daemon on the D-Bus bus with the specified address.
+    ///
+    /// The address must be a D-Bus server address, for example `unix:path=/run/user/1000/bus`.
+    pub async fn new_on_bus(address: &str) -> Result<Self> {
+        let address = address.to_string();
+        let (resource, connection) = spawn_blocking(move || connect_bus(&address)).await??;
+        Self::from_connection(connection, tokio::spawn(resource)).await
+    }
+
+    async fn from_connection(
+        connection: Arc<SyncConnection>, dbus_task: JoinHandle<connection::IOResourceError>,
+    ) -> Result<Self> {
+        log::trace!("Connected to D-Bus with unique name {}", &connection.unique_name());
+
+        let (event_sub_tx, event_sub_rx) = mpsc::channel(1);
+        Event::handle_connection(connection.clone(), SERVICE_NAME, None, event_sub_rx).await?;
+
+        let inner = Arc::new(ObexInner {
+            connection,
+            service_name: SERVICE_NAME.to_string(),
+            event_sub_tx,
+            dbus_task: Some(dbus_task),
+        });
+        Ok(Self { inner })
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(&*self.inner.service_name, MANAGER_PATH, TIMEOUT, &*self.inner.connection)
+    }
+
+    /// Creates an OBEX session with the specified service of a remote device.
+    ///
+    /// The session is removed when the returned [ObexSession] is dropped.
+    pub async fn create_session(
+        &self, destination: Address, target: Target, options: SessionOptions,
+    ) -> Result<ObexSession> {
+        let args = (destination.to_string(), options.to_dict(target));
+        log::trace!("{}: {}.CreateSession {:?}", MANAGER_PATH, CLIENT_INTERFACE, &args);
+        let (dbus_path,): (Path<'static>,) =
+            self.proxy().method_call(CLIENT_INTERFACE, "CreateSession", args).await?;
+        log::trace!("Created OBEX session {}", &dbus_path);
+
+        let (drop_tx, drop_rx) = oneshot::channel();
+        let inner = self.inner.clone();
+        let unreg_path = dbus_path.clone();
+        tokio::spawn(async move {
+            let _ = drop_rx.await;
+
+            log::trace!("Removing OBEX session {}", &unreg_path);
+            let proxy = Proxy::new(inner.service_name.clone(), MANAGER_PATH, TIMEOUT, inner.connection.clone());
+            let _: std::result::Result<(), dbus::Error> =
+                proxy.method_call(CLIENT_INTERFACE, "RemoveSession", (unreg_path,)).await;
+        });
+
+        Ok(ObexSession { inner: self.inner.clone(), dbus_path, target, _drop_tx: drop_tx })
+    }
+}
+
+// ===========================================================================================
+// Session
+// ===========================================================================================
+
+/// OBEX session with a remote device.
+///
+/// Drop to disconnect the session.
+/// This cancels all transfers that are still in progress.
+pub struct ObexSession {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+    target: Target,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl Drop for ObexSession {
+    fn drop(&mut self) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for ObexSession {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ObexSession {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl ObexSession {
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!(uncached);
+    dbus_default_interface!(SESSION_INTERFACE);
+
+    /// OBEX service the session is connected to.
+    pub fn target(&self) -> Target {
+        self.target
+    }
+
+    /// Starts a transfer by calling the specified D-Bus method.
+    ///
+    /// Events of the transfer are subscribed before the method is called, so that
+    /// its progress is not missed if it finishes quickly.
+    async fn start_transfer<A>(&self, method: &str, args: A, interface: &str) -> Result<Transfer>
+    where
+        A: AppendAll + fmt::Debug,
+    {
+        let events = self.inner.child_events(self.dbus_path.clone()).await?;
+        let result = self.call_method_with_interface(method, args, interface).await?;
+        Ok(Transfer::started(self.inner.clone(), result, events))
+    }
+
+    /// Obtains the capabilities of the remote device as XML document.
+    pub async fn capabilities(&self) -> Result<String> {
+        let (caps,): (String,) = self.call_method("GetCapabilities", ()).await?;
+        Ok(caps)
+    }
+
+    /// Sends a local file to the remote device.
+    ///
+    /// Requires the [object push](Target::ObjectPush) target.
+    pub async fn send_file(&self, source_file: &FsPath) -> Result<Transfer> {
+        self.start_transfer("SendFile", (file_arg(source_file)?,), OBJECT_PUSH_INTERFACE).await
+    }
+
+    /// Pulls the default business card of the remote device into the specified local file.
+    ///
+    /// Requires the [object push](Target::ObjectPush) target.
+    pub async fn pull_business_card(&self, target_file: &FsPath) -> Result<Transfer> {
+        self.start_transfer("PullBusinessCard", (file_arg(target_file)?,), OBJECT_PUSH_INTERFACE).await
+    }
+
+    /// Sends the business card in `client_file` to the remote device and
+    /// pulls its default business card into `target_file`.
+    ///
+    /// Requires the [object push](Target::ObjectPush) target.
+    pub async fn exchange_business_cards(&self, client_file: &FsPath, target_file: &FsPath) -> Result<Transfer> {
+        self.start_transfer(
+            "ExchangeBusinessCards",
+            (file_arg(client_file)?, file_arg(target_file)?),
+            OBJECT_PUSH_INTERFACE,
+        )
+        .await
+    }
+}
+
+define_properties!(
+    ObexSession,
+    /// OBEX session property.
+    pub ObexSessionProperty => {
+        /// Address of the local adapter.
+        property(
+            Source, Address,
+            dbus: (SESSION_INTERFACE, "Source", String, MANDATORY),
+            get: (source, v => { v.parse()? }),
+        );
+
+        /// Address of the remote device.
+        property(
+            Destination, Address,
+            dbus: (SESSION_INTERFACE, "Destination", String, MANDATORY),
+            get: (destination, v => { v.parse()? }),
+        );
+
+        /// RFCOMM channel of the connection.
+        property(
+            Channel, u8,
+            dbus: (SESSION_INTERFACE, "Channel", u8, OPTIONAL),
+            get: (channel, v => {v.to_owned()}),
+        );
+
+        /// L2CAP PSM of the connection.
+        property(
+            Psm, u16,
+            dbus: (SESSION_INTERFACE, "PSM", u16, OPTIONAL),
+            get: (psm, v => {v.to_owned()}),
+        );
+
+        /// UUID of the OBEX service.
+        property(
+            TargetUuid, Uuid,
+            dbus: (SESSION_INTERFACE, "Target", String, MANDATORY),
+            get: (target_uuid, v => { parse_value(v)? }),
+        );
+
+        /// Root folder of the session on the remote device.
+        property(
+            Root, String,
+            dbus: (SESSION_INTERFACE, "Root", String, OPTIONAL),
+            get: (root, v => {v.to_owned()}),
+        );
+    }
+);
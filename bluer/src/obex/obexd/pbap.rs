@@ -0,0 +1,232 @@
+//! Phonebook access profile (PBAP).
+
+use dbus::arg::{PropMap, RefArg, Variant};
+use std::path::Path as FsPath;
+use strum::{Display, EnumString};
+
+use super::{file_arg, ObexSession, Transfer};
+use crate::Result;
+
+pub(crate) const PHONEBOOK_ACCESS_INTERFACE: &str = "org.bluez.obex.PhonebookAccess1";
+
+/// Storage location of a phonebook.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum PhonebookLocation {
+    /// Internal memory of the phone.
+    #[strum(serialize = "int")]
+    Internal,
+    /// SIM card.
+    #[strum(serialize = "sim1")]
+    Sim1,
+}
+
+/// Phonebook of a remote device.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Phonebook {
+    /// Main phonebook.
+    #[strum(serialize = "pb")]
+    Contacts,
+    /// Incoming call history.
+    #[strum(serialize = "ich")]
+    IncomingCalls,
+    /// Outgoing call history.
+    #[strum(serialize = "och")]
+    OutgoingCalls,
+    /// Missed call history.
+    #[strum(serialize = "mch")]
+    MissedCalls,
+    /// Combined call history.
+    #[strum(serialize = "cch")]
+    CombinedCalls,
+    /// Speed dials.
+    #[strum(serialize = "spd")]
+    SpeedDials,
+    /// Favorite contacts.
+    #[strum(serialize = "fav")]
+    Favorites,
+}
+
+/// vCard format.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum VCardFormat {
+    /// vCard 2.1.
+    #[strum(serialize = "vcard21")]
+    V21,
+    /// vCard 3.0.
+    #[strum(serialize = "vcard30")]
+    V30,
+}
+
+/// Sort order of vCard listings.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum VCardOrder {
+    /// By handle.
+    #[strum(serialize = "indexed")]
+    Indexed,
+    /// Alphabetically by name.
+    #[strum(serialize = "alphanumeric")]
+    Alphanumeric,
+    /// By phonetic name.
+    #[strum(serialize = "phonetic")]
+    Phonetic,
+}
+
+/// Field to search vCards by.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum VCardSearchField {
+    /// Name.
+    #[strum(serialize = "name")]
+    Name,
+    /// Phone number.
+    #[strum(serialize = "number")]
+    Number,
+    /// Sound.
+    #[strum(serialize = "sound")]
+    Sound,
+}
+
+/// Filter for pulling and listing vCards.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct VCardFilter {
+    /// vCard format of pulled vCards.
+    pub format: Option<VCardFormat>,
+    /// Sort order of listings.
+    pub order: Option<VCardOrder>,
+    /// Index of the first vCard to return.
+    pub offset: Option<u16>,
+    /// Maximum number of vCards to return.
+    pub max_count: Option<u16>,
+    /// vCard fields to include in pulled vCards.
+    ///
+    /// The supported fields are returned by [ObexSession::vcard_filter_fields].
+    pub fields: Option<Vec<String>>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl VCardFilter {
+    fn to_dict(&self) -> PropMap {
+        let mut dict = PropMap::new();
+        if let Some(format) = self.format {
+            dict.insert("Format".to_string(), Variant(format.to_string().box_clone()));
+        }
+        if let Some(order) = self.order {
+            dict.insert("Order".to_string(), Variant(order.to_string().box_clone()));
+        }
+        if let Some(offset) = self.offset {
+            dict.insert("Offset".to_string(), Variant(offset.box_clone()));
+        }
+        if let Some(max_count) = self.max_count {
+            dict.insert("MaxCount".to_string(), Variant(max_count.box_clone()));
+        }
+        if let Some(fields) = &self.fields {
+            dict.insert("Fields".to_string(), Variant(fields.box_clone()));
+        }
+        dict
+    }
+}
+
+/// Entry of a vCard listing.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct VCardEntry {
+    /// Handle of the vCard, for example `1.vcf`.
+    ///
+    /// Use it to [pull the vCard](ObexSession::pull_vcard).
+    pub handle: String,
+    /// Name of the contact.
+    pub name: String,
+}
+
+impl VCardEntry {
+    fn from_listing(listing: Vec<(String, String)>) -> Vec<Self> {
+        listing.into_iter().map(|(handle, name)| Self { handle, name }).collect()
+    }
+}
+
+/// Phonebook access functions.
+///
+/// These require the [phonebook access](super::Target::PhonebookAccess) target.
+impl ObexSession {
+    /// Selects the phonebook used by subsequent operations.
+    pub async fn select_phonebook(&self, location: PhonebookLocation, phonebook: Phonebook) -> Result<()> {
+        self.call_method_with_interface(
+            "Select",
+            (location.to_string(), phonebook.to_string()),
+            PHONEBOOK_ACCESS_INTERFACE,
+        )
+        .await
+    }
+
+    /// Pulls all vCards of the selected phonebook into the local file `target_file`.
+    ///
+    /// If `target_file` is empty, a temporary file is created, whose name
+    /// is available from [Transfer::filename].
+    pub async fn pull_all_vcards(&self, target_file: &FsPath, filter: &VCardFilter) -> Result<Transfer> {
+        self.start_transfer("PullAll", (file_arg(target_file)?, filter.to_dict()), PHONEBOOK_ACCESS_INTERFACE)
+            .await
+    }
+
+    /// Lists the vCards of the selected phonebook.
+    pub async fn list_vcards(&self, filter: &VCardFilter) -> Result<Vec<VCardEntry>> {
+        let (listing,): (Vec<(String, String)>,) =
+            self.call_method_with_interface("List", (filter.to_dict(),), PHONEBOOK_ACCESS_INTERFACE).await?;
+        Ok(VCardEntry::from_listing(listing))
+    }
+
+    /// Pulls the vCard with the specified handle into the local file `target_file`.
+    ///
+    /// If `target_file` is empty, a temporary file is created, whose name
+    /// is available from [Transfer::filename].
+    pub async fn pull_vcard(&self, handle: &str, target_file: &FsPath, filter: &VCardFilter) -> Result<Transfer> {
+        self.start_transfer(
+            "Pull",
+            (handle, file_arg(target_file)?, filter.to_dict()),
+            PHONEBOOK_ACCESS_INTERFACE,
+        )
+        .await
+    }
+
+    /// Searches the vCards of the selected phonebook.
+    pub async fn search_vcards(
+        &self, field: VCardSearchField, value: &str, filter: &VCardFilter,
+    ) -> Result<Vec<VCardEntry>> {
+        let (listing,): (Vec<(String, String)>,) = self
+            .call_method_with_interface(
+                "Search",
+                (field.to_string(), value, filter.to_dict()),
+                PHONEBOOK_ACCESS_INTERFACE,
+            )
+            .await?;
+        Ok(VCardEntry::from_listing(listing))
+    }
+
+    /// Number of entries in the selected phonebook.
+    pub async fn phonebook_size(&self) -> Result<u16> {
+        let (size,): (u16,) = self.call_method_with_interface("GetSize", (), PHONEBOOK_ACCESS_INTERFACE).await?;
+        Ok(size)
+    }
+
+    /// Updates the version counters of the selected phonebook on the remote device.
+    pub async fn update_phonebook_version(&self) -> Result<()> {
+        self.call_method_with_interface("UpdateVersion", (), PHONEBOOK_ACCESS_INTERFACE).await
+    }
+
+    /// vCard fields supported by [VCardFilter::fields].
+    pub async fn vcard_filter_fields(&self) -> Result<Vec<String>> {
+        let (fields,): (Vec<String>,) =
+            self.call_method_with_interface("ListFilterFields", (), PHONEBOOK_ACCESS_INTERFACE).await?;
+        Ok(fields)
+    }
+}
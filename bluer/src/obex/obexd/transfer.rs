@@ -0,0 +1,301 @@
+//! OBEX transfers.
+
+use dbus::{
+    arg::PropMap,
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use futures::{
+    channel::mpsc,
+    future,
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use strum::{Display, EnumString};
+
+use super::{parse_value, ObexInner};
+use crate::{Error, ErrorKind, Event, Result, TIMEOUT};
+
+pub(crate) const TRANSFER_INTERFACE: &str = "org.bluez.obex.Transfer1";
+
+/// Status of an OBEX transfer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TransferStatus {
+    /// Transfer is waiting to be started.
+    #[strum(serialize = "queued")]
+    Queued,
+    /// Transfer is in progress.
+    #[strum(serialize = "active")]
+    Active,
+    /// Transfer has been suspended.
+    #[strum(serialize = "suspended")]
+    Suspended,
+    /// Transfer has completed successfully.
+    #[strum(serialize = "complete")]
+    Complete,
+    /// Transfer has failed.
+    #[strum(serialize = "error")]
+    Error,
+}
+
+impl TransferStatus {
+    /// Whether the transfer has finished, either successfully or not.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Complete | Self::Error)
+    }
+}
+
+/// Progress of an OBEX transfer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct TransferProgress {
+    /// Transfer status.
+    pub status: TransferStatus,
+    /// Number of bytes transferred so far.
+    pub transferred: u64,
+    /// Total size of the transferred object in bytes, if known.
+    pub size: Option<u64>,
+}
+
+/// OBEX transfer event.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TransferEvent {
+    /// Property changed.
+    PropertyChanged(TransferProperty),
+}
+
+impl TransferProgress {
+    fn update(&mut self, property: TransferProperty) -> bool {
+        match property {
+            TransferProperty::Status(status) => self.status = status,
+            TransferProperty::Transferred(transferred) => self.transferred = transferred,
+            TransferProperty::Size(size) => self.size = Some(size),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Transfer of an object over an OBEX session.
+///
+/// The OBEX daemon removes the transfer once it has finished.
+#[derive(Clone)]
+pub struct Transfer {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+    started: Arc<Mutex<Option<Started>>>,
+}
+
+/// Progress when a transfer was started and events of its parent subscribed before.
+struct Started {
+    progress: TransferProgress,
+    events: mpsc::UnboundedReceiver<Event>,
+}
+
+impl fmt::Debug for Transfer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transfer {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Transfer {
+    /// Creates a transfer from the result of the D-Bus method call that started it.
+    ///
+    /// `events` must have been subscribed for the parent of the transfer before the call.
+    pub(crate) fn started(
+        inner: Arc<ObexInner>, (dbus_path, props): (Path<'static>, PropMap),
+        events: mpsc::UnboundedReceiver<Event>,
+    ) -> Self {
+        let mut progress = TransferProgress { status: TransferStatus::Queued, transferred: 0, size: None };
+        for property in TransferProperty::from_prop_map(props) {
+            progress.update(property);
+        }
+        Self { inner, dbus_path, started: Arc::new(Mutex::new(Some(Started { progress, events }))) }
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(&*self.inner.service_name, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!(uncached);
+    dbus_default_interface!(TRANSFER_INTERFACE);
+
+    /// Cancels the transfer.
+    pub async fn cancel(&self) -> Result<()> {
+        self.call_method("Cancel", ()).await
+    }
+
+    /// Suspends the transfer.
+    pub async fn suspend(&self) -> Result<()> {
+        self.call_method("Suspend", ()).await
+    }
+
+    /// Resumes a suspended transfer.
+    pub async fn resume(&self) -> Result<()> {
+        self.call_method("Resume", ()).await
+    }
+
+    /// Streams transfer property changes.
+    ///
+    /// The stream ends when the transfer is removed.
+    pub async fn events(&self) -> Result<impl Stream<Item = TransferEvent>> {
+        let events = self.inner.events(self.dbus_path.clone(), false).await?;
+        Ok(self.transfer_events(events))
+    }
+
+    /// Converts events of the transfer or its parent into transfer events.
+    ///
+    /// The stream ends when the transfer is removed.
+    fn transfer_events(&self, events: mpsc::UnboundedReceiver<Event>) -> BoxStream<'static, TransferEvent> {
+        let path = self.dbus_path.clone();
+        let removed_path = self.dbus_path.clone();
+        events
+            .take_while(move |event| {
+                future::ready(!matches!(event, Event::ObjectRemoved { object, .. } if *object == removed_path))
+            })
+            .flat_map(move |event| match event {
+                Event::PropertiesChanged { object, interface, changed }
+                    if object == path && interface == TRANSFER_INTERFACE =>
+                {
+                    stream::iter(
+                        TransferProperty::from_prop_map(changed).into_iter().map(TransferEvent::PropertyChanged),
+                    )
+                    .boxed()
+                }
+                _ => stream::empty().boxed(),
+            })
+            .boxed()
+    }
+
+    /// Streams the progress of the transfer.
+    ///
+    /// The current progress is returned first, followed by an update whenever
+    /// the status or the number of transferred bytes changes.
+    /// The stream ends after the transfer has finished.
+    ///
+    /// The first call after the transfer has been started reports its progress from
+    /// the start, even if the transfer has already finished and been removed by the
+    /// OBEX daemon.
+    /// Subsequent calls, including from clones, query the current progress, which
+    /// fails with [ErrorKind::NotFound] once the transfer has been removed.
+    pub async fn progress(&self) -> Result<impl Stream<Item = TransferProgress>> {
+        let started = self.started.lock().unwrap().take();
+        let (initial, events) = match started {
+            Some(Started { progress, events }) => (progress, self.transfer_events(events)),
+            None => {
+                let events = self.events().await?.boxed();
+                let initial = TransferProgress {
+                    status: self.status().await?,
+                    transferred: self.transferred().await?.unwrap_or_default(),
+                    size: self.size().await?,
+                };
+                (initial, events)
+            }
+        };
+
+        let stream = stream::unfold(Some((initial, true, events)), |state| async move {
+            let (mut progress, initial, mut events) = state?;
+            if initial {
+                return Some((progress, Some((progress, false, events))));
+            }
+            if progress.status.is_finished() {
+                return None;
+            }
+            loop {
+                let TransferEvent::PropertyChanged(property) = events.next().await?;
+                if progress.update(property) {
+                    return Some((progress, Some((progress, false, events))));
+                }
+            }
+        });
+
+        Ok(stream)
+    }
+
+    /// Waits for the transfer to finish.
+    ///
+    /// Returns an error if the transfer fails or is cancelled.
+    /// See [progress](Self::progress) for calling this after the transfer may have finished.
+    pub async fn wait(&self) -> Result<()> {
+        let progress = self.progress().await?;
+        futures::pin_mut!(progress);
+
+        let mut status = None;
+        while let Some(p) = progress.next().await {
+            status = Some(p.status);
+        }
+
+        match status {
+            Some(TransferStatus::Complete) => Ok(()),
+            Some(TransferStatus::Error) => {
+                Err(Error { kind: ErrorKind::Failed, message: "OBEX transfer failed".to_string() })
+            }
+            _ => Err(Error { kind: ErrorKind::Failed, message: "OBEX transfer was removed".to_string() }),
+        }
+    }
+}
+
+define_properties!(
+    Transfer,
+    /// OBEX transfer property.
+    pub TransferProperty => {
+        /// Transfer status.
+        property(
+            Status, TransferStatus,
+            dbus: (TRANSFER_INTERFACE, "Status", String, MANDATORY),
+            get: (status, v => { parse_value(v)? }),
+        );
+
+        /// Name of the transferred object.
+        property(
+            Name, String,
+            dbus: (TRANSFER_INTERFACE, "Name", String, OPTIONAL),
+            get: (name, v => {v.to_owned()}),
+        );
+
+        /// MIME type of the transferred object.
+        property(
+            Type, String,
+            dbus: (TRANSFER_INTERFACE, "Type", String, OPTIONAL),
+            get: (object_type, v => {v.to_owned()}),
+        );
+
+        /// Modification time of the transferred object as Unix timestamp.
+        property(
+            Time, u64,
+            dbus: (TRANSFER_INTERFACE, "Time", u64, OPTIONAL),
+            get: (time, v => {v.to_owned()}),
+        );
+
+        /// Size of the transferred object in bytes.
+        property(
+            Size, u64,
+            dbus: (TRANSFER_INTERFACE, "Size", u64, OPTIONAL),
+            get: (size, v => {v.to_owned()}),
+        );
+
+        /// Number of bytes transferred so far.
+        property(
+            Transferred, u64,
+            dbus: (TRANSFER_INTERFACE, "Transferred", u64, OPTIONAL),
+            get: (transferred, v => {v.to_owned()}),
+        );
+
+        /// Local file the object is read from or written to.
+        property(
+            Filename, PathBuf,
+            dbus: (TRANSFER_INTERFACE, "Filename", String, OPTIONAL),
+            get: (filename, v => {PathBuf::from(v)}),
+        );
+    }
+);
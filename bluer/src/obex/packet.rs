@@ -0,0 +1,1003 @@
+//! OBEX packets and headers.
+
+use std::{
+    convert::TryInto,
+    fmt,
+    io::{Error, ErrorKind, Result},
+};
+
+/// OBEX protocol version 1.0.
+pub(crate) const VERSION: u8 = 0x10;
+
+/// Minimum maximum packet length every OBEX implementation must support.
+pub const MIN_PACKET_LEN: u16 = 255;
+
+/// Largest possible OBEX packet length.
+pub const MAX_PACKET_LEN: u16 = 0xffff;
+
+/// Final bit of request opcodes and response codes.
+const FINAL: u8 = 0x80;
+
+pub(crate) fn invalid_data(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid_data("OBEX packet truncated"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Reads the packet length from the first three bytes of an OBEX packet.
+///
+/// Returns `None` if less than three bytes are available.
+pub(crate) fn packet_len(buf: &[u8]) -> Option<u16> {
+    match buf {
+        [_, hi, lo, ..] => Some(u16::from_be_bytes([*hi, *lo])),
+        _ => None,
+    }
+}
+
+/// Checks the length field of a received packet and returns its contents after it.
+fn packet_body(buf: &[u8]) -> Result<Reader<'_>> {
+    match packet_len(buf) {
+        Some(len) if len as usize == buf.len() => Ok(Reader(&buf[3..])),
+        Some(_) => Err(invalid_data("OBEX packet length mismatch")),
+        None => Err(invalid_data("OBEX packet truncated")),
+    }
+}
+
+/// Writes the packet length into the first three bytes of an encoded packet.
+fn finish_packet(mut buf: Vec<u8>) -> Result<Vec<u8>> {
+    let len: u16 =
+        buf.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "OBEX packet too long"))?;
+    buf[1..3].copy_from_slice(&len.to_be_bytes());
+    Ok(buf)
+}
+
+/// Encodes the length field of a header with a variable length value.
+fn header_len(data_len: usize) -> Result<[u8; 2]> {
+    let len: u16 =
+        (3 + data_len).try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "OBEX header too long"))?;
+    Ok(len.to_be_bytes())
+}
+
+/// Encodes text as null-terminated UTF-16.
+fn unicode_bytes(v: &str) -> Vec<u8> {
+    let mut data: Vec<u8> = v.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+    if !data.is_empty() {
+        data.extend_from_slice(&[0, 0]);
+    }
+    data
+}
+
+// ===========================================================================================
+// Headers
+// ===========================================================================================
+
+/// Header identifiers.
+pub(crate) mod header_id {
+    /// Number of objects.
+    pub const COUNT: u8 = 0xc0;
+    /// Name of the object.
+    pub const NAME: u8 = 0x01;
+    /// MIME type of the object.
+    pub const TYPE: u8 = 0x42;
+    /// Length of the object in bytes.
+    pub const LENGTH: u8 = 0xc3;
+    /// Time in ISO 8601 format.
+    pub const TIME: u8 = 0x44;
+    /// Description of the object.
+    pub const DESCRIPTION: u8 = 0x05;
+    /// Target service of the connection.
+    pub const TARGET: u8 = 0x46;
+    /// Chunk of the object body.
+    pub const BODY: u8 = 0x48;
+    /// Final chunk of the object body.
+    pub const END_OF_BODY: u8 = 0x49;
+    /// Service that accepted the connection.
+    pub const WHO: u8 = 0x4a;
+    /// Connection identifier.
+    pub const CONNECTION_ID: u8 = 0xcb;
+    /// Application parameters.
+    pub const APP_PARAMETERS: u8 = 0x4c;
+    /// Action to perform.
+    pub const ACTION_ID: u8 = 0x94;
+    /// Destination name of an action.
+    pub const DEST_NAME: u8 = 0x15;
+    /// Permissions of the object.
+    pub const PERMISSIONS: u8 = 0xd6;
+    /// Single response mode.
+    pub const SRM: u8 = 0x97;
+    /// Single response mode parameters.
+    pub const SRM_PARAMETERS: u8 = 0x98;
+}
+
+/// Encoding of a header value, given by the upper two bits of its identifier.
+const ENCODING_MASK: u8 = 0xc0;
+const ENCODING_UNICODE: u8 = 0x00;
+const ENCODING_BYTES: u8 = 0x40;
+const ENCODING_U8: u8 = 0x80;
+const ENCODING_U32: u8 = 0xc0;
+
+/// Single response mode (SRM) setting.
+///
+/// Single response mode is only available for OBEX over L2CAP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Srm {
+    /// Disable single response mode.
+    Disable,
+    /// Enable single response mode.
+    Enable,
+    /// Single response mode is supported.
+    Supported,
+}
+
+impl Srm {
+    fn from_u8(v: u8) -> Result<Self> {
+        match v {
+            0x00 => Ok(Self::Disable),
+            0x01 => Ok(Self::Enable),
+            0x02 => Ok(Self::Supported),
+            _ => Err(invalid_data("invalid OBEX SRM value")),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Disable => 0x00,
+            Self::Enable => 0x01,
+            Self::Supported => 0x02,
+        }
+    }
+}
+
+/// Single response mode parameters.
+///
+/// These allow the receiving side to throttle the sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SrmParameters {
+    /// Request an additional packet.
+    AdditionalPacket,
+    /// Wait for the next packet before sending.
+    Wait,
+    /// Request an additional packet and wait for the next packet.
+    AdditionalPacketAndWait,
+}
+
+impl SrmParameters {
+    fn from_u8(v: u8) -> Result<Self> {
+        match v {
+            0x00 => Ok(Self::AdditionalPacket),
+            0x01 => Ok(Self::Wait),
+            0x02 => Ok(Self::AdditionalPacketAndWait),
+            _ => Err(invalid_data("invalid OBEX SRM parameters value")),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::AdditionalPacket => 0x00,
+            Self::Wait => 0x01,
+            Self::AdditionalPacketAndWait => 0x02,
+        }
+    }
+
+    /// Whether the other side is asked to wait.
+    pub fn is_wait(self) -> bool {
+        matches!(self, Self::Wait | Self::AdditionalPacketAndWait)
+    }
+}
+
+/// Value of a header with an identifier not known to this library.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeaderValue {
+    /// Unicode text.
+    Unicode(String),
+    /// Byte sequence.
+    Bytes(Vec<u8>),
+    /// Single byte.
+    U8(u8),
+    /// Four byte integer.
+    U32(u32),
+}
+
+/// OBEX header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Header {
+    /// Number of objects.
+    Count(u32),
+    /// Name of the object.
+    Name(String),
+    /// MIME type of the object, for example `x-obex/folder-listing`.
+    Type(String),
+    /// Length of the object in bytes.
+    Length(u32),
+    /// Time in ISO 8601 format, for example `20230131T120000Z`.
+    Time(String),
+    /// Description of the object.
+    Description(String),
+    /// Target service of the connection, usually a UUID.
+    Target(Vec<u8>),
+    /// Chunk of the object body.
+    Body(Vec<u8>),
+    /// Final chunk of the object body.
+    EndOfBody(Vec<u8>),
+    /// Service that accepted the connection.
+    Who(Vec<u8>),
+    /// Connection identifier.
+    ConnectionId(u32),
+    /// Application parameters.
+    AppParameters(Vec<u8>),
+    /// Destination name of an action.
+    DestName(String),
+    /// Permissions of the object.
+    Permissions(u32),
+    /// Action to perform.
+    ActionId(u8),
+    /// Single response mode.
+    Srm(Srm),
+    /// Single response mode parameters.
+    SrmParameters(SrmParameters),
+    /// Other header.
+    ///
+    /// The encoding of the value must match the upper two bits of the identifier.
+    Other {
+        /// Header identifier.
+        id: u8,
+        /// Header value.
+        value: HeaderValue,
+    },
+}
+
+impl Header {
+    /// Header identifier.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Count(_) => header_id::COUNT,
+            Self::Name(_) => header_id::NAME,
+            Self::Type(_) => header_id::TYPE,
+            Self::Length(_) => header_id::LENGTH,
+            Self::Time(_) => header_id::TIME,
+            Self::Description(_) => header_id::DESCRIPTION,
+            Self::Target(_) => header_id::TARGET,
+            Self::Body(_) => header_id::BODY,
+            Self::EndOfBody(_) => header_id::END_OF_BODY,
+            Self::Who(_) => header_id::WHO,
+            Self::ConnectionId(_) => header_id::CONNECTION_ID,
+            Self::AppParameters(_) => header_id::APP_PARAMETERS,
+            Self::DestName(_) => header_id::DEST_NAME,
+            Self::Permissions(_) => header_id::PERMISSIONS,
+            Self::ActionId(_) => header_id::ACTION_ID,
+            Self::Srm(_) => header_id::SRM,
+            Self::SrmParameters(_) => header_id::SRM_PARAMETERS,
+            Self::Other { id, .. } => *id,
+        }
+    }
+
+    /// Header value.
+    pub fn value(&self) -> HeaderValue {
+        match self {
+            Self::Name(v) | Self::Description(v) | Self::DestName(v) => HeaderValue::Unicode(v.clone()),
+            Self::Type(v) => {
+                let mut bytes = v.as_bytes().to_vec();
+                bytes.push(0);
+                HeaderValue::Bytes(bytes)
+            }
+            Self::Time(v) => HeaderValue::Bytes(v.as_bytes().to_vec()),
+            Self::Target(v) | Self::Body(v) | Self::EndOfBody(v) | Self::Who(v) | Self::AppParameters(v) => {
+                HeaderValue::Bytes(v.clone())
+            }
+            Self::Count(v) | Self::Length(v) | Self::ConnectionId(v) | Self::Permissions(v) => {
+                HeaderValue::U32(*v)
+            }
+            Self::ActionId(v) => HeaderValue::U8(*v),
+            Self::Srm(v) => HeaderValue::U8(v.to_u8()),
+            Self::SrmParameters(v) => HeaderValue::U8(v.to_u8()),
+            Self::Other { value, .. } => value.clone(),
+        }
+    }
+
+    /// Creates a header from its identifier and value.
+    pub fn from_value(id: u8, value: HeaderValue) -> Result<Self> {
+        let expected = match &value {
+            HeaderValue::Unicode(_) => ENCODING_UNICODE,
+            HeaderValue::Bytes(_) => ENCODING_BYTES,
+            HeaderValue::U8(_) => ENCODING_U8,
+            HeaderValue::U32(_) => ENCODING_U32,
+        };
+        if id & ENCODING_MASK != expected {
+            return Err(Error::new(ErrorKind::InvalidInput, "OBEX header value does not match its identifier"));
+        }
+
+        let text = |bytes: Vec<u8>| {
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            String::from_utf8(bytes[..end].to_vec()).map_err(|_| invalid_data("invalid OBEX header text"))
+        };
+
+        Ok(match (id, value) {
+            (header_id::COUNT, HeaderValue::U32(v)) => Self::Count(v),
+            (header_id::NAME, HeaderValue::Unicode(v)) => Self::Name(v),
+            (header_id::TYPE, HeaderValue::Bytes(v)) => Self::Type(text(v)?),
+            (header_id::LENGTH, HeaderValue::U32(v)) => Self::Length(v),
+            (header_id::TIME, HeaderValue::Bytes(v)) => Self::Time(text(v)?),
+            (header_id::DESCRIPTION, HeaderValue::Unicode(v)) => Self::Description(v),
+            (header_id::TARGET, HeaderValue::Bytes(v)) => Self::Target(v),
+            (header_id::BODY, HeaderValue::Bytes(v)) => Self::Body(v),
+            (header_id::END_OF_BODY, HeaderValue::Bytes(v)) => Self::EndOfBody(v),
+            (header_id::WHO, HeaderValue::Bytes(v)) => Self::Who(v),
+            (header_id::CONNECTION_ID, HeaderValue::U32(v)) => Self::ConnectionId(v),
+            (header_id::APP_PARAMETERS, HeaderValue::Bytes(v)) => Self::AppParameters(v),
+            (header_id::DEST_NAME, HeaderValue::Unicode(v)) => Self::DestName(v),
+            (header_id::PERMISSIONS, HeaderValue::U32(v)) => Self::Permissions(v),
+            (header_id::ACTION_ID, HeaderValue::U8(v)) => Self::ActionId(v),
+            (header_id::SRM, HeaderValue::U8(v)) => Self::Srm(Srm::from_u8(v)?),
+            (header_id::SRM_PARAMETERS, HeaderValue::U8(v)) => Self::SrmParameters(SrmParameters::from_u8(v)?),
+            (id, value) => Self::Other { id, value },
+        })
+    }
+
+    /// Data of a [Body](Self::Body) or [EndOfBody](Self::EndOfBody) header.
+    pub fn body(&self) -> Option<&[u8]> {
+        match self {
+            Self::Body(v) | Self::EndOfBody(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Encodes the header and appends it to the buffer.
+    ///
+    /// Fails if the encoded header exceeds the maximum header length of 65535 bytes.
+    pub fn write(&self, buf: &mut Vec<u8>) -> Result<()> {
+        match self.value() {
+            HeaderValue::Unicode(v) => {
+                let data = unicode_bytes(&v);
+                let len = header_len(data.len())?;
+                buf.push(self.id());
+                buf.extend_from_slice(&len);
+                buf.extend_from_slice(&data);
+            }
+            HeaderValue::Bytes(v) => {
+                let len = header_len(v.len())?;
+                buf.push(self.id());
+                buf.extend_from_slice(&len);
+                buf.extend_from_slice(&v);
+            }
+            HeaderValue::U8(v) => buf.extend_from_slice(&[self.id(), v]),
+            HeaderValue::U32(v) => {
+                buf.push(self.id());
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    /// Length of the encoded header in bytes.
+    pub fn encoded_len(&self) -> usize {
+        match self.value() {
+            HeaderValue::Unicode(v) => 3 + unicode_bytes(&v).len(),
+            HeaderValue::Bytes(v) => 3 + v.len(),
+            HeaderValue::U8(_) => 2,
+            HeaderValue::U32(_) => 5,
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let id = reader.u8()?;
+        let value = match id & ENCODING_MASK {
+            ENCODING_UNICODE => {
+                let len = reader.u16()?;
+                let data = reader.bytes(
+                    (len as usize).checked_sub(3).ok_or_else(|| invalid_data("invalid OBEX header length"))?,
+                )?;
+                if data.len() % 2 != 0 {
+                    return Err(invalid_data("invalid OBEX unicode header"));
+                }
+                let chars: Vec<u16> = data.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                let end = chars.iter().position(|&c| c == 0).unwrap_or(chars.len());
+                HeaderValue::Unicode(
+                    String::from_utf16(&chars[..end]).map_err(|_| invalid_data("invalid OBEX unicode header"))?,
+                )
+            }
+            ENCODING_BYTES => {
+                let len = reader.u16()?;
+                let data = reader.bytes(
+                    (len as usize).checked_sub(3).ok_or_else(|| invalid_data("invalid OBEX header length"))?,
+                )?;
+                HeaderValue::Bytes(data.to_vec())
+            }
+            ENCODING_U8 => HeaderValue::U8(reader.u8()?),
+            _ => HeaderValue::U32(reader.u32()?),
+        };
+        Self::from_value(id, value)
+    }
+
+    fn read_all(reader: &mut Reader) -> Result<Vec<Self>> {
+        let mut headers = Vec::new();
+        while !reader.is_empty() {
+            headers.push(Self::read(reader)?);
+        }
+        Ok(headers)
+    }
+}
+
+/// Functions for accessing a list of headers.
+pub trait Headers {
+    /// The headers.
+    fn headers(&self) -> &[Header];
+
+    /// First header with the specified identifier.
+    fn header(&self, id: u8) -> Option<&Header> {
+        self.headers().iter().find(|h| h.id() == id)
+    }
+
+    /// Value of the [Name](Header::Name) header.
+    fn name(&self) -> Option<&str> {
+        self.headers().iter().find_map(|h| match h {
+            Header::Name(v) => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Value of the [Type](Header::Type) header.
+    fn object_type(&self) -> Option<&str> {
+        self.headers().iter().find_map(|h| match h {
+            Header::Type(v) => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Value of the [ConnectionId](Header::ConnectionId) header.
+    fn connection_id(&self) -> Option<u32> {
+        self.headers().iter().find_map(|h| match h {
+            Header::ConnectionId(v) => Some(*v),
+            _ => None,
+        })
+    }
+
+    /// Value of the [Srm](Header::Srm) header.
+    fn srm(&self) -> Option<Srm> {
+        self.headers().iter().find_map(|h| match h {
+            Header::Srm(v) => Some(*v),
+            _ => None,
+        })
+    }
+
+    /// Value of the [SrmParameters](Header::SrmParameters) header.
+    fn srm_parameters(&self) -> Option<SrmParameters> {
+        self.headers().iter().find_map(|h| match h {
+            Header::SrmParameters(v) => Some(*v),
+            _ => None,
+        })
+    }
+
+    /// Whether the headers contain a [Body](Header::Body) or [EndOfBody](Header::EndOfBody) header.
+    fn has_body(&self) -> bool {
+        self.headers().iter().any(|h| h.body().is_some())
+    }
+
+    /// Whether the headers contain an [EndOfBody](Header::EndOfBody) header.
+    fn has_end_of_body(&self) -> bool {
+        self.headers().iter().any(|h| matches!(h, Header::EndOfBody(_)))
+    }
+}
+
+impl Headers for [Header] {
+    fn headers(&self) -> &[Header] {
+        self
+    }
+}
+
+impl Headers for Vec<Header> {
+    fn headers(&self) -> &[Header] {
+        self
+    }
+}
+
+// ===========================================================================================
+// Requests
+// ===========================================================================================
+
+/// Parameters of a connect request or response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectParams {
+    /// OBEX protocol version.
+    pub version: u8,
+    /// Connect flags.
+    pub flags: u8,
+    /// Maximum packet length the sender can receive.
+    pub max_packet_len: u16,
+}
+
+impl Default for ConnectParams {
+    fn default() -> Self {
+        Self { version: VERSION, flags: 0, max_packet_len: MAX_PACKET_LEN }
+    }
+}
+
+impl ConnectParams {
+    fn read(reader: &mut Reader) -> Result<Self> {
+        Ok(Self { version: reader.u8()?, flags: reader.u8()?, max_packet_len: reader.u16()? })
+    }
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.push(self.version);
+        buf.push(self.flags);
+        buf.extend_from_slice(&self.max_packet_len.to_be_bytes());
+    }
+}
+
+/// Flags of a set path request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetPathFlags {
+    /// Change to the parent folder before applying the name.
+    pub parent: bool,
+    /// Do not create the folder if it does not exist.
+    pub no_create: bool,
+}
+
+impl SetPathFlags {
+    fn from_u8(v: u8) -> Self {
+        Self { parent: v & 0x01 != 0, no_create: v & 0x02 != 0 }
+    }
+
+    fn to_u8(self) -> u8 {
+        (self.parent as u8) | (self.no_create as u8) << 1
+    }
+}
+
+/// Request opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Opcode {
+    /// Establish a connection.
+    Connect(ConnectParams),
+    /// Terminate the connection.
+    Disconnect,
+    /// Send an object.
+    Put,
+    /// Request an object.
+    Get,
+    /// Change the current folder.
+    SetPath(SetPathFlags),
+    /// Perform an action on an object.
+    Action,
+    /// Manage a reliable session.
+    Session,
+    /// Abort the current operation.
+    Abort,
+}
+
+impl Opcode {
+    const CONNECT: u8 = 0x00;
+    const DISCONNECT: u8 = 0x01;
+    const PUT: u8 = 0x02;
+    const GET: u8 = 0x03;
+    const SET_PATH: u8 = 0x05;
+    const ACTION: u8 = 0x06;
+    const SESSION: u8 = 0x07;
+    const ABORT: u8 = 0x7f;
+
+    fn code(&self) -> u8 {
+        match self {
+            Self::Connect(_) => Self::CONNECT,
+            Self::Disconnect => Self::DISCONNECT,
+            Self::Put => Self::PUT,
+            Self::Get => Self::GET,
+            Self::SetPath(_) => Self::SET_PATH,
+            Self::Action => Self::ACTION,
+            Self::Session => Self::SESSION,
+            Self::Abort => Self::ABORT,
+        }
+    }
+}
+
+/// OBEX request packet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Request {
+    /// Opcode.
+    pub opcode: Opcode,
+    /// Whether this is the final packet of the request.
+    ///
+    /// Only [Put](Opcode::Put) and [Get](Opcode::Get) requests can span multiple packets.
+    pub is_final: bool,
+    /// Headers.
+    pub headers: Vec<Header>,
+}
+
+impl Request {
+    /// Creates a final request without headers.
+    pub fn new(opcode: Opcode) -> Self {
+        Self { opcode, is_final: true, headers: Vec::new() }
+    }
+
+    /// Appends a header.
+    pub fn with_header(mut self, header: Header) -> Self {
+        self.headers.push(header);
+        self
+    }
+
+    /// Encodes the request.
+    ///
+    /// Fails if the encoded request exceeds the maximum packet length.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let is_final = match self.opcode {
+            Opcode::Put | Opcode::Get => self.is_final,
+            _ => true,
+        };
+        let mut buf = vec![self.opcode.code() | if is_final { FINAL } else { 0 }, 0, 0];
+        match &self.opcode {
+            Opcode::Connect(params) => params.write(&mut buf),
+            Opcode::SetPath(flags) => buf.extend_from_slice(&[flags.to_u8(), 0]),
+            _ => (),
+        }
+        for header in &self.headers {
+            header.write(&mut buf)?;
+        }
+        finish_packet(buf)
+    }
+
+    /// Decodes a request.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        let mut reader = packet_body(buf)?;
+        let code = buf[0];
+        let opcode = match code & !FINAL {
+            Opcode::CONNECT => Opcode::Connect(ConnectParams::read(&mut reader)?),
+            Opcode::DISCONNECT => Opcode::Disconnect,
+            Opcode::PUT => Opcode::Put,
+            Opcode::GET => Opcode::Get,
+            Opcode::SET_PATH => {
+                let flags = SetPathFlags::from_u8(reader.u8()?);
+                let _constants = reader.u8()?;
+                Opcode::SetPath(flags)
+            }
+            Opcode::ACTION => Opcode::Action,
+            Opcode::SESSION => Opcode::Session,
+            Opcode::ABORT => Opcode::Abort,
+            _ => return Err(invalid_data("unknown OBEX opcode")),
+        };
+        Ok(Self { opcode, is_final: code & FINAL != 0, headers: Header::read_all(&mut reader)? })
+    }
+}
+
+impl Headers for Request {
+    fn headers(&self) -> &[Header] {
+        &self.headers
+    }
+}
+
+// ===========================================================================================
+// Responses
+// ===========================================================================================
+
+/// OBEX response code.
+///
+/// This is returned as the inner error of an [std::io::Error] when
+/// a request fails.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResponseCode(pub u8);
+
+impl ResponseCode {
+    /// Continue with the next packet.
+    pub const CONTINUE: Self = Self(0x10);
+    /// Success.
+    pub const SUCCESS: Self = Self(0x20);
+    /// Object created.
+    pub const CREATED: Self = Self(0x21);
+    /// Request accepted.
+    pub const ACCEPTED: Self = Self(0x22);
+    /// Partial content.
+    pub const PARTIAL_CONTENT: Self = Self(0x26);
+    /// Bad request.
+    pub const BAD_REQUEST: Self = Self(0x40);
+    /// Unauthorized.
+    pub const UNAUTHORIZED: Self = Self(0x41);
+    /// Forbidden.
+    pub const FORBIDDEN: Self = Self(0x43);
+    /// Object not found.
+    pub const NOT_FOUND: Self = Self(0x44);
+    /// Method not allowed.
+    pub const METHOD_NOT_ALLOWED: Self = Self(0x45);
+    /// Not acceptable.
+    pub const NOT_ACCEPTABLE: Self = Self(0x46);
+    /// Request timed out.
+    pub const REQUEST_TIMEOUT: Self = Self(0x48);
+    /// Conflict.
+    pub const CONFLICT: Self = Self(0x49);
+    /// Length required.
+    pub const LENGTH_REQUIRED: Self = Self(0x4b);
+    /// Precondition failed.
+    pub const PRECONDITION_FAILED: Self = Self(0x4c);
+    /// Object too large.
+    pub const ENTITY_TOO_LARGE: Self = Self(0x4d);
+    /// Unsupported media type.
+    pub const UNSUPPORTED_MEDIA_TYPE: Self = Self(0x4f);
+    /// Internal server error.
+    pub const INTERNAL_SERVER_ERROR: Self = Self(0x50);
+    /// Not implemented.
+    pub const NOT_IMPLEMENTED: Self = Self(0x51);
+    /// Service unavailable.
+    pub const SERVICE_UNAVAILABLE: Self = Self(0x53);
+    /// Database full.
+    pub const DATABASE_FULL: Self = Self(0x60);
+    /// Database locked.
+    pub const DATABASE_LOCKED: Self = Self(0x61);
+
+    /// Whether the code indicates success.
+    pub fn is_success(self) -> bool {
+        (0x20..0x30).contains(&self.0)
+    }
+}
+
+impl fmt::Debug for ResponseCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ResponseCode(0x{:02x})", self.0)
+    }
+}
+
+impl fmt::Display for ResponseCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            Self::CONTINUE => "continue",
+            Self::SUCCESS => "success",
+            Self::CREATED => "created",
+            Self::ACCEPTED => "accepted",
+            Self::PARTIAL_CONTENT => "partial content",
+            Self::BAD_REQUEST => "bad request",
+            Self::UNAUTHORIZED => "unauthorized",
+            Self::FORBIDDEN => "forbidden",
+            Self::NOT_FOUND => "not found",
+            Self::METHOD_NOT_ALLOWED => "method not allowed",
+            Self::NOT_ACCEPTABLE => "not acceptable",
+            Self::REQUEST_TIMEOUT => "request timeout",
+            Self::CONFLICT => "conflict",
+            Self::LENGTH_REQUIRED => "length required",
+            Self::PRECONDITION_FAILED => "precondition failed",
+            Self::ENTITY_TOO_LARGE => "object too large",
+            Self::UNSUPPORTED_MEDIA_TYPE => "unsupported media type",
+            Self::INTERNAL_SERVER_ERROR => "internal server error",
+            Self::NOT_IMPLEMENTED => "not implemented",
+            Self::SERVICE_UNAVAILABLE => "service unavailable",
+            Self::DATABASE_FULL => "database full",
+            Self::DATABASE_LOCKED => "database locked",
+            _ => return write!(f, "OBEX response 0x{:02x}", self.0),
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for ResponseCode {}
+
+impl From<ResponseCode> for Error {
+    fn from(code: ResponseCode) -> Self {
+        let kind = match code {
+            ResponseCode::UNAUTHORIZED | ResponseCode::FORBIDDEN => ErrorKind::PermissionDenied,
+            ResponseCode::NOT_FOUND => ErrorKind::NotFound,
+            ResponseCode::NOT_IMPLEMENTED => ErrorKind::Unsupported,
+            ResponseCode::REQUEST_TIMEOUT => ErrorKind::TimedOut,
+            ResponseCode::BAD_REQUEST => ErrorKind::InvalidInput,
+            _ => ErrorKind::Other,
+        };
+        Error::new(kind, code)
+    }
+}
+
+/// OBEX response packet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    /// Response code.
+    pub code: ResponseCode,
+    /// Connection parameters, only present in the response to a connect request.
+    pub connect: Option<ConnectParams>,
+    /// Headers.
+    pub headers: Vec<Header>,
+}
+
+impl Response {
+    /// Creates a response without headers.
+    pub fn new(code: ResponseCode) -> Self {
+        Self { code, connect: None, headers: Vec::new() }
+    }
+
+    /// Appends a header.
+    pub fn with_header(mut self, header: Header) -> Self {
+        self.headers.push(header);
+        self
+    }
+
+    /// Returns an error if the response code does not indicate success.
+    pub fn check(self) -> Result<Self> {
+        if self.code.is_success() {
+            Ok(self)
+        } else {
+            Err(self.code.into())
+        }
+    }
+
+    /// Encodes the response.
+    ///
+    /// Fails if the encoded response exceeds the maximum packet length.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![self.code.0 | FINAL, 0, 0];
+        if let Some(params) = &self.connect {
+            params.write(&mut buf);
+        }
+        for header in &self.headers {
+            header.write(&mut buf)?;
+        }
+        finish_packet(buf)
+    }
+
+    /// Decodes a response.
+    ///
+    /// Since the response to a connect request carries additional fields,
+    /// `connect` must be true if and only if the response belongs to a connect request.
+    pub fn from_bytes(buf: &[u8], connect: bool) -> Result<Self> {
+        let mut reader = packet_body(buf)?;
+        let code = ResponseCode(buf[0] & !FINAL);
+        let connect = if connect { Some(ConnectParams::read(&mut reader)?) } else { None };
+        Ok(Self { code, connect, headers: Header::read_all(&mut reader)? })
+    }
+}
+
+impl Headers for Response {
+    fn headers(&self) -> &[Header] {
+        &self.headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ConnectParams, Header, HeaderValue, Opcode, Request, Response, ResponseCode, SetPathFlags, Srm,
+        SrmParameters,
+    };
+
+    #[test]
+    fn connect_request() {
+        let request =
+            Request::new(Opcode::Connect(ConnectParams { max_packet_len: 0x2000, ..Default::default() }))
+                .with_header(Header::Target(vec![0x01, 0x02]));
+        let bytes = [0x80, 0x00, 0x0c, 0x10, 0x00, 0x20, 0x00, 0x46, 0x00, 0x05, 0x01, 0x02];
+        assert_eq!(request.to_bytes().unwrap(), bytes);
+        assert_eq!(Request::from_bytes(&bytes).unwrap(), request);
+    }
+
+    #[test]
+    fn put_request() {
+        let request = Request {
+            opcode: Opcode::Put,
+            is_final: false,
+            headers: vec![Header::Name("a".into()), Header::Length(3), Header::Body(vec![1, 2, 3])],
+        };
+        let bytes = [
+            0x02, 0x00, 0x15, 0x01, 0x00, 0x07, 0x00, 0x61, 0x00, 0x00, 0xc3, 0x00, 0x00, 0x00, 0x03, 0x48, 0x00,
+            0x06, 0x01, 0x02, 0x03,
+        ];
+        assert_eq!(request.to_bytes().unwrap(), bytes);
+        assert_eq!(Request::from_bytes(&bytes).unwrap(), request);
+    }
+
+    #[test]
+    fn set_path_request() {
+        let request = Request::new(Opcode::SetPath(SetPathFlags { parent: true, no_create: false }));
+        let bytes = [0x85, 0x00, 0x05, 0x01, 0x00];
+        assert_eq!(request.to_bytes().unwrap(), bytes);
+        assert_eq!(Request::from_bytes(&bytes).unwrap(), request);
+    }
+
+    #[test]
+    fn connect_response() {
+        let response = Response {
+            code: ResponseCode::SUCCESS,
+            connect: Some(ConnectParams::default()),
+            headers: vec![Header::ConnectionId(1)],
+        };
+        let bytes = [0xa0, 0x00, 0x0c, 0x10, 0x00, 0xff, 0xff, 0xcb, 0x00, 0x00, 0x00, 0x01];
+        assert_eq!(response.to_bytes().unwrap(), bytes);
+        assert_eq!(Response::from_bytes(&bytes, true).unwrap(), response);
+        assert!(Response::from_bytes(&[0xa0, 0x00, 0x03], true).is_err());
+    }
+
+    #[test]
+    fn headers_round_trip() {
+        let response = Response::new(ResponseCode::CONTINUE)
+            .with_header(Header::Name(String::new()))
+            .with_header(Header::Type("text/plain".into()))
+            .with_header(Header::Time("20230131T120000Z".into()))
+            .with_header(Header::Description("ä€".into()))
+            .with_header(Header::EndOfBody(Vec::new()))
+            .with_header(Header::Srm(Srm::Enable))
+            .with_header(Header::SrmParameters(SrmParameters::Wait))
+            .with_header(Header::Other { id: 0x4d, value: HeaderValue::Bytes(vec![0xaa]) });
+        let bytes = response.to_bytes().unwrap();
+        assert_eq!(Response::from_bytes(&bytes, false).unwrap(), response);
+        assert_eq!(bytes.len(), 3 + response.headers.iter().map(|h| h.encoded_len()).sum::<usize>());
+    }
+
+    #[test]
+    fn header_encoding() {
+        for (header, bytes) in [
+            (Header::Name(String::new()), &[0x01, 0x00, 0x03][..]),
+            (Header::Type("a".into()), &[0x42, 0x00, 0x05, 0x61, 0x00]),
+            (Header::ActionId(2), &[0x94, 0x02]),
+            (Header::Count(0x01020304), &[0xc0, 0x01, 0x02, 0x03, 0x04]),
+        ] {
+            let mut buf = Vec::new();
+            header.write(&mut buf).unwrap();
+            assert_eq!(buf, bytes);
+            assert_eq!(header.encoded_len(), bytes.len());
+        }
+    }
+
+    #[test]
+    fn from_value_mismatch() {
+        assert!(Header::from_value(0x01, HeaderValue::U8(0)).is_err());
+        assert_eq!(Header::from_value(0x48, HeaderValue::Bytes(vec![1])).unwrap(), Header::Body(vec![1]));
+    }
+
+    #[test]
+    fn decode_invalid() {
+        for bytes in [
+            &[0x82][..],
+            &[0x82, 0x00, 0x04],
+            &[0x84, 0x00, 0x03],
+            &[0x82, 0x00, 0x06, 0x48, 0x00, 0x02],
+            &[0x82, 0x00, 0x06, 0x48, 0x00, 0x04],
+            &[0x82, 0x00, 0x05, 0x97, 0x05],
+            &[0x82, 0x00, 0x06, 0x01, 0x00, 0x03, 0x00],
+        ] {
+            assert!(Request::from_bytes(bytes).is_err(), "{:x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn too_long() {
+        let mut buf = Vec::new();
+        assert!(Header::Body(vec![0; 0x10000]).write(&mut buf).is_err());
+        assert!(buf.is_empty());
+
+        let request = Request::new(Opcode::Put)
+            .with_header(Header::Body(vec![0; 0x8000]))
+            .with_header(Header::EndOfBody(vec![0; 0x8000]));
+        assert!(request.to_bytes().is_err());
+        assert!(Response::new(ResponseCode::SUCCESS)
+            .with_header(Header::Body(vec![0; 0xfff9]))
+            .to_bytes()
+            .is_ok());
+    }
+}
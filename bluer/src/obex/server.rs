@@ -0,0 +1,460 @@
+//! Native OBEX server.
+
+use futures::future::BoxFuture;
+use std::{
+    io::{ErrorKind, Result},
+    mem::take,
+};
+
+use super::{
+    client::body_space,
+    packet::{Header, Headers, Opcode, Request, Response, ResponseCode, SetPathFlags, Srm, MIN_PACKET_LEN},
+    transport::Transport,
+    ConnectParams,
+};
+
+/// Result of a service operation.
+///
+/// An error is sent to the client as response code.
+pub type ServiceResult<T> = std::result::Result<T, ResponseCode>;
+
+/// OBEX service handling the requests received by a [Server].
+///
+/// Each function receives the headers of the request, excluding body and
+/// single response mode headers, and returns the headers of the response.
+/// Operations that are not implemented are rejected with
+/// [NOT_IMPLEMENTED](ResponseCode::NOT_IMPLEMENTED).
+pub trait Service: Send {
+    /// A client connects.
+    ///
+    /// If the request contains a [Target](Header::Target) header, the server
+    /// assigns a connection identifier and adds the [Who](Header::Who) and
+    /// [ConnectionId](Header::ConnectionId) headers to the response.
+    fn connect<'a>(&'a mut self, headers: &'a [Header]) -> BoxFuture<'a, ServiceResult<Vec<Header>>> {
+        let _ = headers;
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    /// The client disconnects.
+    fn disconnect<'a>(&'a mut self, headers: &'a [Header]) -> BoxFuture<'a, ()> {
+        let _ = headers;
+        Box::pin(async {})
+    }
+
+    /// The client sends an object.
+    fn put<'a>(&'a mut self, headers: &'a [Header], body: Vec<u8>) -> BoxFuture<'a, ServiceResult<Vec<Header>>> {
+        let _ = (headers, body);
+        Box::pin(async { Err(ResponseCode::NOT_IMPLEMENTED) })
+    }
+
+    /// The client deletes an object.
+    fn delete<'a>(&'a mut self, headers: &'a [Header]) -> BoxFuture<'a, ServiceResult<Vec<Header>>> {
+        let _ = headers;
+        Box::pin(async { Err(ResponseCode::NOT_IMPLEMENTED) })
+    }
+
+    /// The client requests an object.
+    ///
+    /// Returns the response headers and the object contents.
+    fn get<'a>(&'a mut self, headers: &'a [Header]) -> BoxFuture<'a, ServiceResult<(Vec<Header>, Vec<u8>)>> {
+        let _ = headers;
+        Box::pin(async { Err(ResponseCode::NOT_IMPLEMENTED) })
+    }
+
+    /// The client changes the current folder.
+    fn set_path<'a>(
+        &'a mut self, flags: SetPathFlags, headers: &'a [Header],
+    ) -> BoxFuture<'a, ServiceResult<Vec<Header>>> {
+        let _ = (flags, headers);
+        Box::pin(async { Err(ResponseCode::NOT_IMPLEMENTED) })
+    }
+}
+
+/// Default limit of the total length of the headers and body of a request.
+pub const DEFAULT_MAX_REQUEST_LEN: usize = 16 * 1024 * 1024;
+
+/// Outcome of receiving the remaining packets of an operation.
+enum Received {
+    Request(Request),
+    Aborted,
+}
+
+/// OBEX server.
+///
+/// The server receives requests over a [Transport] from an OBEX client
+/// and dispatches them to a [Service].
+/// Single response mode (SRM) is used if the transport and the client support it.
+///
+/// Malformed requests are answered with [BAD_REQUEST](ResponseCode::BAD_REQUEST).
+/// Requests whose headers and body exceed the [maximum request length](Self::set_max_request_len)
+/// are answered with [ENTITY_TOO_LARGE](ResponseCode::ENTITY_TOO_LARGE) without
+/// being passed to the service.
+#[derive(Debug)]
+pub struct Server<T, S> {
+    transport: T,
+    service: S,
+    max_packet_len: u16,
+    max_request_len: usize,
+    connection_id: Option<u32>,
+    next_connection_id: u32,
+}
+
+impl<T: Transport, S: Service> Server<T, S> {
+    /// Creates a new server using the specified transport and service.
+    pub fn new(transport: T, service: S) -> Self {
+        Self {
+            transport,
+            service,
+            max_packet_len: MIN_PACKET_LEN,
+            max_request_len: DEFAULT_MAX_REQUEST_LEN,
+            connection_id: None,
+            next_connection_id: 1,
+        }
+    }
+
+    /// Maximum total length of the headers and body of a request in bytes.
+    ///
+    /// This limits the memory used for buffering a request that spans multiple packets.
+    pub fn max_request_len(&self) -> usize {
+        self.max_request_len
+    }
+
+    /// Sets the maximum total length of the headers and body of a request in bytes.
+    ///
+    /// The default is [DEFAULT_MAX_REQUEST_LEN].
+    pub fn set_max_request_len(&mut self, max_request_len: usize) {
+        self.max_request_len = max_request_len;
+    }
+
+    /// The service.
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+
+    /// Mutable reference to the service.
+    pub fn service_mut(&mut self) -> &mut S {
+        &mut self.service
+    }
+
+    /// Consumes the server and returns the transport and service.
+    pub fn into_inner(self) -> (T, S) {
+        (self.transport, self.service)
+    }
+
+    /// Serves requests until the client disconnects or closes the transport.
+    pub async fn run(&mut self) -> Result<()> {
+        loop {
+            let request = match self.recv_request().await {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            };
+
+            if let (Some(id), Some(req_id)) = (self.connection_id, request.connection_id()) {
+                if id != req_id {
+                    self.respond(Response::new(ResponseCode::SERVICE_UNAVAILABLE)).await?;
+                    continue;
+                }
+            }
+
+            match request.opcode {
+                Opcode::Connect(params) => self.handle_connect(params, request).await?,
+                Opcode::Disconnect => {
+                    self.service.disconnect(&service_headers(request.headers)).await;
+                    self.connection_id = None;
+                    self.respond(Response::new(ResponseCode::SUCCESS)).await?;
+                    return Ok(());
+                }
+                Opcode::Put => self.handle_put(request).await?,
+                Opcode::Get => self.handle_get(request).await?,
+                Opcode::SetPath(flags) => {
+                    let result = self.service.set_path(flags, &service_headers(request.headers)).await;
+                    self.respond(result_response(result)).await?;
+                }
+                Opcode::Abort => self.respond(Response::new(ResponseCode::SUCCESS)).await?,
+                _ => self.respond(Response::new(ResponseCode::NOT_IMPLEMENTED)).await?,
+            }
+        }
+    }
+
+    async fn respond(&mut self, response: Response) -> Result<()> {
+        self.transport.send_packet(&response.to_bytes()?).await
+    }
+
+    /// Receives a request packet.
+    ///
+    /// A malformed packet is answered with [BAD_REQUEST](ResponseCode::BAD_REQUEST)
+    /// and `None` is returned.
+    async fn recv_request(&mut self) -> Result<Option<Request>> {
+        let buf = self.transport.recv_packet().await?;
+        match Request::from_bytes(&buf) {
+            Ok(request) => Ok(Some(request)),
+            Err(err) => {
+                log::debug!("Received malformed OBEX request: {}", err);
+                self.respond(Response::new(ResponseCode::BAD_REQUEST)).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Receives the next packet of a multi-packet operation.
+    async fn recv_next(&mut self, opcode: Opcode) -> Result<Received> {
+        let request = match self.recv_request().await? {
+            Some(request) => request,
+            None => return Ok(Received::Aborted),
+        };
+        if request.opcode == Opcode::Abort {
+            self.respond(Response::new(ResponseCode::SUCCESS)).await?;
+            return Ok(Received::Aborted);
+        }
+        if request.opcode != opcode {
+            self.respond(Response::new(ResponseCode::BAD_REQUEST)).await?;
+            return Ok(Received::Aborted);
+        }
+        Ok(Received::Request(request))
+    }
+
+    async fn handle_connect(&mut self, params: ConnectParams, request: Request) -> Result<()> {
+        let own = ConnectParams { max_packet_len: self.transport.max_packet_len(), ..Default::default() };
+        self.max_packet_len = own.max_packet_len.min(params.max_packet_len.max(MIN_PACKET_LEN));
+
+        let target = request.headers.iter().find_map(|h| match h {
+            Header::Target(target) => Some(target.clone()),
+            _ => None,
+        });
+        let mut response = match self.service.connect(&service_headers(request.headers)).await {
+            Ok(headers) => Response { code: ResponseCode::SUCCESS, connect: Some(own), headers },
+            Err(code) => Response { code, connect: Some(own), headers: Vec::new() },
+        };
+
+        if let (true, Some(target)) = (response.code.is_success(), target) {
+            let id = self.next_connection_id;
+            self.next_connection_id = self.next_connection_id.wrapping_add(1).max(1);
+            self.connection_id = Some(id);
+            response.headers.insert(0, Header::ConnectionId(id));
+            response.headers.push(Header::Who(target));
+        }
+
+        self.respond(response).await
+    }
+
+    async fn handle_put(&mut self, mut request: Request) -> Result<()> {
+        let srm = self.transport.supports_srm() && request.srm() == Some(Srm::Enable);
+        let mut headers = Vec::new();
+        let mut body = Vec::new();
+        let mut len = 0;
+        let mut has_body = false;
+        let mut first = true;
+
+        loop {
+            has_body |= request.has_body();
+            len += request_len(&request.headers);
+            if len > self.max_request_len {
+                // In single response mode the client does not wait for our response,
+                // thus the remaining packets must be discarded.
+                if !srm || request.is_final {
+                    return self.respond(Response::new(ResponseCode::ENTITY_TOO_LARGE)).await;
+                }
+                request.headers.clear();
+            }
+            for header in take(&mut request.headers) {
+                match header {
+                    Header::Body(data) | Header::EndOfBody(data) => body.extend(data),
+                    Header::Srm(_) | Header::SrmParameters(_) => (),
+                    other => headers.push(other),
+                }
+            }
+            if request.is_final {
+                break;
+            }
+
+            // In single response mode only the first packet is acknowledged.
+            if first || !srm {
+                let mut response = Response::new(ResponseCode::CONTINUE);
+                if first && srm {
+                    response.headers.push(Header::Srm(Srm::Enable));
+                }
+                self.respond(response).await?;
+            }
+            first = false;
+
+            request = match self.recv_next(Opcode::Put).await? {
+                Received::Request(request) => request,
+                Received::Aborted => return Ok(()),
+            };
+        }
+
+        let headers = service_headers(headers);
+        let result =
+            if has_body { self.service.put(&headers, body).await } else { self.service.delete(&headers).await };
+        self.respond(result_response(result)).await
+    }
+
+    async fn handle_get(&mut self, mut request: Request) -> Result<()> {
+        let mut srm = false;
+        let mut wait;
+        let mut headers = Vec::new();
+        let mut len = 0;
+
+        // Receive all request packets.
+        loop {
+            srm |= self.transport.supports_srm() && request.srm() == Some(Srm::Enable);
+            wait = request.srm_parameters().map(|p| p.is_wait()).unwrap_or_default();
+            len += request_len(&request.headers);
+            if len > self.max_request_len {
+                return self.respond(Response::new(ResponseCode::ENTITY_TOO_LARGE)).await;
+            }
+            headers.extend(take(&mut request.headers));
+            if request.is_final {
+                break;
+            }
+            self.respond(Response::new(ResponseCode::CONTINUE)).await?;
+            request = match self.recv_next(Opcode::Get).await? {
+                Received::Request(request) => request,
+                Received::Aborted => return Ok(()),
+            };
+        }
+
+        let (mut headers, body) = match self.service.get(&service_headers(headers)).await {
+            Ok(result) => result,
+            Err(code) => return self.respond(Response::new(code)).await,
+        };
+        if srm {
+            headers.push(Header::Srm(Srm::Enable));
+        }
+
+        // Send object in response packets.
+        let mut rest = &body[..];
+        loop {
+            let mut response = Response::new(ResponseCode::CONTINUE);
+            response.headers = take(&mut headers);
+            let space = body_space(response.to_bytes()?.len(), self.max_packet_len)?;
+            if rest.len() <= space {
+                response.code = ResponseCode::SUCCESS;
+                response.headers.push(Header::EndOfBody(rest.to_vec()));
+                return self.respond(response).await;
+            }
+            response.headers.push(Header::Body(rest[..space].to_vec()));
+            rest = &rest[space..];
+            self.respond(response).await?;
+
+            // In single response mode the client only sends requests when it wants us to wait.
+            if !srm || wait {
+                request = match self.recv_next(Opcode::Get).await? {
+                    Received::Request(request) => request,
+                    Received::Aborted => return Ok(()),
+                };
+                wait = srm && request.srm_parameters().map(|p| p.is_wait()).unwrap_or_default();
+            }
+        }
+    }
+}
+
+/// Length of the headers of a request packet counted towards the maximum request length.
+fn request_len(headers: &[Header]) -> usize {
+    headers.iter().map(|h| h.encoded_len()).sum()
+}
+
+/// Removes headers that are handled by the server itself.
+fn service_headers(headers: Vec<Header>) -> Vec<Header> {
+    headers
+        .into_iter()
+        .filter(|h| {
+            !matches!(
+                h,
+                Header::ConnectionId(_)
+                    | Header::Body(_)
+                    | Header::EndOfBody(_)
+                    | Header::Srm(_)
+                    | Header::SrmParameters(_)
+            )
+        })
+        .collect()
+}
+
+/// Converts the result of a service operation into a response.
+fn result_response(result: ServiceResult<Vec<Header>>) -> Response {
+    match result {
+        Ok(headers) => Response { code: ResponseCode::SUCCESS, connect: None, headers },
+        Err(code) => Response::new(code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, future::BoxFuture};
+    use std::{
+        collections::VecDeque,
+        io::{Error, ErrorKind, Result},
+    };
+
+    use super::{Server, Service, ServiceResult};
+    use crate::obex::{Header, Opcode, Request, Response, ResponseCode, Transport};
+
+    #[derive(Default)]
+    struct MockTransport {
+        rx: VecDeque<Vec<u8>>,
+        tx: Vec<Vec<u8>>,
+    }
+
+    impl Transport for MockTransport {
+        fn send_packet<'a>(&'a mut self, packet: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+            self.tx.push(packet.to_vec());
+            Box::pin(async { Ok(()) })
+        }
+
+        fn recv_packet(&mut self) -> BoxFuture<'_, Result<Vec<u8>>> {
+            let packet = self.rx.pop_front().ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "closed"));
+            Box::pin(async { packet })
+        }
+    }
+
+    #[derive(Default)]
+    struct Inbox(Vec<Vec<u8>>);
+
+    impl Service for Inbox {
+        fn put<'a>(
+            &'a mut self, _headers: &'a [Header], body: Vec<u8>,
+        ) -> BoxFuture<'a, ServiceResult<Vec<Header>>> {
+            self.0.push(body);
+            Box::pin(async { Ok(Vec::new()) })
+        }
+    }
+
+    fn serve(server: &mut Server<MockTransport, Inbox>, requests: &[Vec<u8>]) -> Vec<ResponseCode> {
+        server.transport.rx.extend(requests.iter().cloned());
+        block_on(server.run()).unwrap();
+        server.transport.tx.drain(..).map(|buf| Response::from_bytes(&buf, false).unwrap().code).collect()
+    }
+
+    fn put(body: &[u8], is_final: bool) -> Vec<u8> {
+        let header = if is_final { Header::EndOfBody(body.to_vec()) } else { Header::Body(body.to_vec()) };
+        Request { opcode: Opcode::Put, is_final, headers: vec![header] }.to_bytes().unwrap()
+    }
+
+    #[test]
+    fn malformed_request() {
+        let mut server = Server::new(MockTransport::default(), Inbox::default());
+        let codes = serve(&mut server, &[vec![0x82, 0x00, 0x06, 0x48, 0x00, 0x02], put(b"abc", true)]);
+        assert_eq!(codes, [ResponseCode::BAD_REQUEST, ResponseCode::SUCCESS]);
+        assert_eq!(server.service().0, [b"abc".to_vec()]);
+    }
+
+    #[test]
+    fn malformed_continuation() {
+        let mut server = Server::new(MockTransport::default(), Inbox::default());
+        let codes = serve(&mut server, &[put(b"ab", false), vec![0x02, 0x00, 0x04], put(b"c", true)]);
+        assert_eq!(codes, [ResponseCode::CONTINUE, ResponseCode::BAD_REQUEST, ResponseCode::SUCCESS]);
+        assert_eq!(server.service().0, [b"c".to_vec()]);
+    }
+
+    #[test]
+    fn request_too_large() {
+        let mut server = Server::new(MockTransport::default(), Inbox::default());
+        server.set_max_request_len(8);
+        let codes = serve(&mut server, &[put(b"abc", false), put(b"def", true), put(b"gh", true)]);
+        assert_eq!(codes, [ResponseCode::CONTINUE, ResponseCode::ENTITY_TOO_LARGE, ResponseCode::SUCCESS]);
+        assert_eq!(server.service().0, [b"gh".to_vec()]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Transports carrying OBEX packets.
+
+use futures::future::BoxFuture;
+use std::io::Result;
+
+use super::packet::MAX_PACKET_LEN;
+
+#[cfg(feature = "l2cap")]
+use super::packet::MIN_PACKET_LEN;
+#[cfg(any(feature = "l2cap", feature = "rfcomm"))]
+use super::packet::{invalid_data, packet_len};
+#[cfg(feature = "l2cap")]
+use std::io::{Error, ErrorKind};
+
+/// Transport carrying OBEX packets.
+///
+/// This is implemented for [RFCOMM streams](crate::rfcomm::Stream) and
+/// [L2CAP sequential packet sockets](crate::l2cap::SeqPacket).
+/// Implement it to run OBEX over other transports.
+pub trait Transport: Send {
+    /// Sends a complete OBEX packet.
+    fn send_packet<'a>(&'a mut self, packet: &'a [u8]) -> BoxFuture<'a, Result<()>>;
+
+    /// Receives a complete OBEX packet.
+    ///
+    /// Returns an error of kind [UnexpectedEof](std::io::ErrorKind::UnexpectedEof)
+    /// when the remote device has closed the connection.
+    fn recv_packet(&mut self) -> BoxFuture<'_, Result<Vec<u8>>>;
+
+    /// Maximum length of OBEX packets that can be sent and received.
+    fn max_packet_len(&self) -> u16 {
+        MAX_PACKET_LEN
+    }
+
+    /// Whether single response mode (SRM) can be used.
+    ///
+    /// This is only the case for OBEX over L2CAP.
+    fn supports_srm(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "rfcomm")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "obex", feature = "rfcomm"))))]
+impl Transport for crate::rfcomm::Stream {
+    fn send_packet<'a>(&'a mut self, packet: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        use tokio::io::AsyncWriteExt;
+        Box::pin(async move { self.write_all(packet).await })
+    }
+
+    fn recv_packet(&mut self) -> BoxFuture<'_, Result<Vec<u8>>> {
+        use tokio::io::AsyncReadExt;
+        Box::pin(async move {
+            let mut buf = vec![0; 3];
+            self.read_exact(&mut buf).await?;
+            let len = packet_len(&buf).unwrap() as usize;
+            if len < 3 {
+                return Err(invalid_data("invalid OBEX packet length"));
+            }
+            buf.resize(len, 0);
+            self.read_exact(&mut buf[3..]).await?;
+            Ok(buf)
+        })
+    }
+}
+
+#[cfg(feature = "l2cap")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "obex", feature = "l2cap"))))]
+impl Transport for crate::l2cap::SeqPacket {
+    fn send_packet<'a>(&'a mut self, packet: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let n = crate::l2cap::SeqPacket::send(self, packet).await?;
+            if n != packet.len() {
+                return Err(Error::new(ErrorKind::WriteZero, "OBEX packet partially sent"));
+            }
+            Ok(())
+        })
+    }
+
+    fn recv_packet(&mut self) -> BoxFuture<'_, Result<Vec<u8>>> {
+        Box::pin(async move {
+            let mut buf = vec![0; self.recv_mtu()?];
+            let n = self.recv(&mut buf).await?;
+            if n == 0 {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "connection closed"));
+            }
+            buf.truncate(n);
+            if packet_len(&buf) != Some(n as u16) {
+                return Err(invalid_data("OBEX packet length mismatch"));
+            }
+            Ok(buf)
+        })
+    }
+
+    /// The smaller of the send and receive MTUs.
+    ///
+    /// This may be less than [MIN_PACKET_LEN] if the channel was configured with a small MTU.
+    fn max_packet_len(&self) -> u16 {
+        match (self.send_mtu(), self.recv_mtu()) {
+            (Ok(send), Ok(recv)) => send.min(recv).min(MAX_PACKET_LEN as usize) as u16,
+            _ => MIN_PACKET_LEN,
+        }
+    }
+
+    fn supports_srm(&self) -> bool {
+        true
+    }
+}
@@ -114,8 +114,8 @@ impl FromStr for SocketAddr {
 impl sock::SysSockAddr for SocketAddr {
     type SysSockAddr = sockaddr_rc;
 
-    fn into_sys_sock_addr(self) -> Self::SysSockAddr {
-        sockaddr_rc { rc_family: AF_BLUETOOTH as _, rc_bdaddr: self.addr.into(), rc_channel: self.channel }
+    fn into_sys_sock_addr(self) -> Result<Self::SysSockAddr> {
+        Ok(sockaddr_rc { rc_family: AF_BLUETOOTH as _, rc_bdaddr: self.addr.into(), rc_channel: self.channel })
     }
 
     fn try_from_sys_sock_addr(saddr: Self::SysSockAddr) -> Result<Self> {
@@ -349,6 +349,8 @@ impl Socket {
     }
 
     sock_priv!();
+    shutdown_sock_priv!();
+    stream_sock_priv!();
 }
 
 impl AsRawFd for Socket {
@@ -21,12 +21,12 @@ use tokio_stream::wrappers::ReceiverStream;
 use uuid::Uuid;
 
 use super::{Socket, Stream};
-use crate::{method_call, read_dict, Address, Device, Result, SessionInner, ERR_PREFIX, SERVICE_NAME, TIMEOUT};
+use crate::{method_call, read_dict, Address, Device, Result, SessionInner, ERR_PREFIX, TIMEOUT};
 
 pub(crate) const MANAGER_INTERFACE: &str = "org.bluez.ProfileManager1";
 pub(crate) const MANAGER_PATH: &str = "/org/bluez";
 pub(crate) const PROFILE_INTERFACE: &str = "org.bluez.Profile1";
-pub(crate) const PROFILE_PREFIX: &str = publish_path!("profile/");
+pub(crate) const PROFILE_PREFIX: &str = "profile/";
 
 /// Error response from us to a Bluetooth profile request.
 #[cfg_attr(docsrs, doc(cfg(all(feature = "rfcomm", feature = "bluetoothd"))))]
@@ -129,6 +129,9 @@ pub struct Profile {
     /// channels when a remote device is connected.
     pub auto_connect: Option<bool>,
     /// Provide a manual SDP record.
+    ///
+    /// It must be in the XML format of the Bluetooth daemon, as produced by `sdp::Record::to_xml`
+    /// when the `sdp` crate feature is enabled.
     pub service_record: Option<String>,
     /// Profile version (for SDP record).
     pub version: Option<u16>,
@@ -333,7 +336,9 @@ impl RegisteredProfile {
     pub(crate) async fn register(
         self, inner: Arc<SessionInner>, profile: Profile, req_rx: mpsc::Receiver<ConnectRequest>,
     ) -> Result<ProfileHandle> {
-        let name = dbus::Path::new(format!("{}{}", PROFILE_PREFIX, Uuid::new_v4().as_simple())).unwrap();
+        let name =
+            dbus::Path::new(format!("{}{}", inner.publish_path(PROFILE_PREFIX), Uuid::new_v4().as_simple()))
+                .unwrap();
         log::trace!("Publishing profile at {}", &name);
 
         {
@@ -342,21 +347,21 @@ impl RegisteredProfile {
         }
 
         log::trace!("Registering profile at {}", &name);
-        let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, inner.connection.clone());
-        proxy
-            .method_call(
-                MANAGER_INTERFACE,
-                "RegisterProfile",
-                (name.clone(), profile.uuid.to_string(), profile.to_dict()),
-            )
+        let reg_name = name.clone();
+        inner
+            .register_with(name.clone(), MANAGER_PATH.into(), MANAGER_INTERFACE, "RegisterProfile", move || {
+                (reg_name.clone(), profile.uuid.to_string(), profile.to_dict())
+            })
             .await?;
 
         let (drop_tx, drop_rx) = oneshot::channel();
         let unreg_name = name.clone();
         tokio::spawn(async move {
             let _ = drop_rx.await;
+            inner.forget_registration(unreg_name.clone()).await;
 
             log::trace!("Unregistering profile at {}", &unreg_name);
+            let proxy = Proxy::new(inner.service_name.clone(), MANAGER_PATH, TIMEOUT, inner.connection.clone());
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterProfile", (unreg_name.clone(),)).await;
 
@@ -0,0 +1,558 @@
+//! Synchronous connection-oriented (SCO) sockets.
+//!
+//! SCO sockets carry the voice audio of the hands-free (HFP) and
+//! headset (HSP) profiles over classic Bluetooth (BR/EDR).
+//! The control channel of these profiles is an RFCOMM connection, which can be
+//! established using [an RFCOMM profile](crate::rfcomm::Profile).
+//!
+//! To receive an incoming voice connection with a [voice setting](Voice) other than
+//! the default, enable [deferred setup](Socket::set_defer_setup) on the listening socket,
+//! set the voice setting on the accepted [SeqPacket] and then start receiving from it.
+//!
+
+use crate::{
+    sock::{self, OwnedFd},
+    sys::{
+        bt_voice, sockaddr_sco, BTPROTO_SCO, BT_DEFER_SETUP, BT_PHY, BT_PKT_STATUS, BT_SCM_PKT_STATUS, BT_VOICE,
+        BT_VOICE_CVSD_16BIT, BT_VOICE_TRANSPARENT, SCO_CONNINFO, SCO_OPTIONS, SOL_SCO,
+    },
+    Address,
+};
+use futures::ready;
+use libc::{
+    AF_BLUETOOTH, EAGAIN, EINPROGRESS, SHUT_RD, SHUT_RDWR, SHUT_WR, SOCK_SEQPACKET, SOL_BLUETOOTH, SOL_SOCKET,
+    SO_ERROR, SO_RCVBUF, TIOCINQ, TIOCOUTQ,
+};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::{
+    convert::TryInto,
+    fmt,
+    io::{Error, ErrorKind, Result},
+    net::Shutdown,
+    os::{
+        raw::c_int,
+        unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    },
+    task::{Context, Poll},
+};
+use tokio::io::{unix::AsyncFd, ReadBuf};
+
+pub use crate::sys::{sco_conninfo as ConnInfo, sco_options as Opts};
+
+/// Possible bit values for the [PHY socket option](Socket::phy).
+pub mod phy {
+    pub use crate::sys::{
+        BR1M1SLOT, BR1M3SLOT, BR1M5SLOT, EDR2M1SLOT, EDR2M3SLOT, EDR2M5SLOT, EDR3M1SLOT, EDR3M3SLOT, EDR3M5SLOT,
+    };
+}
+
+/// An SCO socket address.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SocketAddr {
+    /// Device address.
+    ///
+    /// When listening or binding, specify [Address::any] for any local adapter address.
+    pub addr: Address,
+}
+
+impl SocketAddr {
+    /// Creates a new SCO socket address.
+    pub const fn new(addr: Address) -> Self {
+        Self { addr }
+    }
+
+    /// When specified to [Socket::bind] binds to any local adapter address.
+    pub const fn any() -> Self {
+        Self { addr: Address::any() }
+    }
+}
+
+impl fmt::Display for SocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.addr)
+    }
+}
+
+impl sock::SysSockAddr for SocketAddr {
+    type SysSockAddr = sockaddr_sco;
+
+    fn into_sys_sock_addr(self) -> Result<Self::SysSockAddr> {
+        Ok(sockaddr_sco { sco_family: AF_BLUETOOTH as _, sco_bdaddr: self.addr.into() })
+    }
+
+    fn try_from_sys_sock_addr(saddr: Self::SysSockAddr) -> Result<Self> {
+        if saddr.sco_family != AF_BLUETOOTH as _ {
+            return Err(Error::new(ErrorKind::InvalidInput, "sockaddr_sco::sco_family is not AF_BLUETOOTH"));
+        }
+        Ok(Self { addr: Address::from(saddr.sco_bdaddr) })
+    }
+}
+
+/// SCO voice setting.
+///
+/// This determines how the controller encodes the voice data
+/// passed through the socket.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Voice {
+    /// 16-bit linear PCM samples, encoded to CVSD by the controller.
+    ///
+    /// This is the default.
+    Cvsd16Bit = BT_VOICE_CVSD_16BIT as _,
+    /// Transparent data, passed unmodified to the remote device.
+    ///
+    /// This is used for wide-band speech (mSBC) and other codecs that
+    /// are encoded by the host.
+    Transparent = BT_VOICE_TRANSPARENT as _,
+}
+
+impl Default for Voice {
+    fn default() -> Self {
+        Self::Cvsd16Bit
+    }
+}
+
+/// Status of a received SCO packet, as reported by the controller.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PacketStatus {
+    /// Correctly received data.
+    Correct = 0x00,
+    /// Possibly invalid data.
+    PossiblyInvalid = 0x01,
+    /// No data received.
+    NoData = 0x02,
+    /// Data partially lost.
+    PartiallyLost = 0x03,
+}
+
+/// An SCO socket that has not yet been converted to a [Listener] or [SeqPacket].
+///
+/// The primary use of this is to configure the socket before connecting or listening.
+pub struct Socket {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Socket").field("fd", &self.fd.as_raw_fd()).finish()
+    }
+}
+
+impl Socket {
+    /// Creates a new socket of sequential packet type.
+    pub fn new() -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(sock::socket(AF_BLUETOOTH, SOCK_SEQPACKET, BTPROTO_SCO)?)? })
+    }
+
+    /// Convert the socket into a [Listener].
+    ///
+    /// `backlog` defines the maximum number of pending connections are queued by the operating system
+    /// at any given time.
+    pub fn listen(self, backlog: u32) -> Result<Listener> {
+        sock::listen(
+            self.fd.get_ref(),
+            backlog.try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid backlog"))?,
+        )?;
+        Ok(Listener { socket: self })
+    }
+
+    /// Establish a voice connection with a peer at the specified socket address.
+    pub async fn connect(self, sa: SocketAddr) -> Result<SeqPacket> {
+        self.connect_priv(sa).await?;
+        Ok(SeqPacket { socket: self })
+    }
+
+    /// Bind the socket to the given address.
+    pub fn bind(&self, sa: SocketAddr) -> Result<()> {
+        sock::bind(self.fd.get_ref(), sa)
+    }
+
+    /// Get the local address of this socket.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        sock::getsockname(self.fd.get_ref())
+    }
+
+    /// Get the peer address of this socket.
+    fn peer_addr_priv(&self) -> Result<SocketAddr> {
+        sock::getpeername(self.fd.get_ref())
+    }
+
+    /// Get voice setting.
+    ///
+    /// This corresponds to the `BT_VOICE` socket option.
+    pub fn voice(&self) -> Result<Voice> {
+        let value: bt_voice = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_VOICE)?;
+        Voice::from_u16(value.setting).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid voice setting"))
+    }
+
+    /// Set voice setting.
+    ///
+    /// This must be set before connecting or, for an incoming connection
+    /// with [deferred setup](Self::set_defer_setup), before receiving from it.
+    ///
+    /// This corresponds to the `BT_VOICE` socket option.
+    pub fn set_voice(&self, voice: Voice) -> Result<()> {
+        let value = bt_voice { setting: voice as _ };
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_VOICE, &value)
+    }
+
+    /// Get whether deferred setup of incoming connections is enabled.
+    ///
+    /// This corresponds to the `BT_DEFER_SETUP` socket option.
+    pub fn is_defer_setup(&self) -> Result<bool> {
+        let value: u32 = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_DEFER_SETUP)?;
+        Ok(value != 0)
+    }
+
+    /// Set whether deferred setup of incoming connections is enabled.
+    ///
+    /// When enabled on a listening socket, incoming connections are accepted
+    /// without being confirmed.
+    /// The connection is confirmed once the accepted socket is first received from
+    /// and rejected when it is closed beforehand.
+    ///
+    /// This corresponds to the `BT_DEFER_SETUP` socket option.
+    pub fn set_defer_setup(&self, defer_setup: bool) -> Result<()> {
+        let value = u32::from(defer_setup);
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_DEFER_SETUP, &value)
+    }
+
+    /// Get whether the status of received packets is reported.
+    ///
+    /// This corresponds to the `BT_PKT_STATUS` socket option.
+    pub fn is_pkt_status(&self) -> Result<bool> {
+        let value: c_int = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_PKT_STATUS)?;
+        Ok(value != 0)
+    }
+
+    /// Set whether the status of received packets is reported.
+    ///
+    /// When enabled, [SeqPacket::recv_with_status] returns the [PacketStatus]
+    /// of each received packet.
+    ///
+    /// This corresponds to the `BT_PKT_STATUS` socket option.
+    pub fn set_pkt_status(&self, pkt_status: bool) -> Result<()> {
+        let value = c_int::from(pkt_status);
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_PKT_STATUS, &value)
+    }
+
+    /// Get maximum transmission unit (MTU).
+    ///
+    /// This is available once the socket is connected and applies to
+    /// both sending and receiving.
+    ///
+    /// This corresponds to [Opts::mtu].
+    pub fn mtu(&self) -> Result<u16> {
+        Ok(self.sco_opts()?.mtu)
+    }
+
+    /// Gets the raw SCO socket options.
+    ///
+    /// This corresponds to the `SCO_OPTIONS` socket option.
+    pub fn sco_opts(&self) -> Result<Opts> {
+        sock::getsockopt(self.fd.get_ref(), SOL_SCO, SCO_OPTIONS)
+    }
+
+    /// Gets the SCO socket connection information.
+    ///
+    /// This corresponds to the `SCO_CONNINFO` socket option.
+    pub fn conn_info(&self) -> Result<ConnInfo> {
+        sock::getsockopt(self.fd.get_ref(), SOL_SCO, SCO_CONNINFO)
+    }
+
+    /// Gets the supported PHYs bit field.
+    ///
+    /// Possible values are defined in the [phy] module.
+    /// This corresponds to the `BT_PHY` socket option.
+    pub fn phy(&self) -> Result<i32> {
+        sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_PHY)
+    }
+
+    /// Gets the maximum socket receive buffer in bytes.
+    ///
+    /// This corresponds to the `SO_RCVBUF` socket option.
+    pub fn recv_buffer(&self) -> Result<i32> {
+        sock::getsockopt(self.fd.get_ref(), SOL_SOCKET, SO_RCVBUF)
+    }
+
+    /// Sets the maximum socket receive buffer in bytes.
+    ///
+    /// This corresponds to the `SO_RCVBUF` socket option.
+    pub fn set_recv_buffer(&self, recv_buffer: i32) -> Result<()> {
+        sock::setsockopt(self.fd.get_ref(), SOL_SOCKET, SO_RCVBUF, &recv_buffer)
+    }
+
+    /// Get the number of bytes in the input buffer.
+    ///
+    /// This corresponds to the `TIOCINQ` IOCTL.
+    pub fn input_buffer(&self) -> Result<u32> {
+        let value: c_int = sock::ioctl_read(self.fd.get_ref(), TIOCINQ)?;
+        Ok(value as _)
+    }
+
+    /// Get the number of bytes in the output buffer.
+    ///
+    /// This corresponds to the `TIOCOUTQ` IOCTL.
+    pub fn output_buffer(&self) -> Result<u32> {
+        let value: c_int = sock::ioctl_read(self.fd.get_ref(), TIOCOUTQ)?;
+        Ok(value as _)
+    }
+
+    /// Constructs a new [Socket] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(OwnedFd::new(fd))? })
+    }
+
+    fn from_owned_fd(fd: OwnedFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(fd)? })
+    }
+
+    async fn recv_with_status_priv(&self, buf: &mut [u8]) -> Result<(usize, Option<u8>)> {
+        let mut buf = ReadBuf::new(buf);
+        loop {
+            let mut guard = self.fd.readable().await?;
+            match guard.try_io(|inner| {
+                sock::recv_with_cmsg(inner.get_ref(), &mut buf, 0, SOL_BLUETOOTH, BT_SCM_PKT_STATUS)
+            }) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    sock_priv!();
+    shutdown_sock_priv!();
+}
+
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for Socket {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_inner().into_raw_fd()
+    }
+}
+
+impl FromRawFd for Socket {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Socket::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// An SCO socket server, listening for [SeqPacket] connections.
+#[derive(Debug)]
+pub struct Listener {
+    socket: Socket,
+}
+
+impl Listener {
+    /// Creates a new Listener, which will be bound to the specified socket address.
+    ///
+    /// Specify [SocketAddr::any] for any local adapter address.
+    ///
+    /// Use [Socket::new], [Socket::bind] and [Socket::listen] instead if you need to
+    /// enable [deferred setup](Socket::set_defer_setup).
+    pub async fn bind(sa: SocketAddr) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(sa)?;
+        socket.listen(1)
+    }
+
+    /// Accepts a new incoming connection from this listener.
+    pub async fn accept(&self) -> Result<(SeqPacket, SocketAddr)> {
+        let (socket, sa) = self.socket.accept_priv().await?;
+        Ok((SeqPacket { socket }, sa))
+    }
+
+    /// Polls to accept a new incoming connection to this listener.
+    pub fn poll_accept(&self, cx: &mut Context) -> Poll<Result<(SeqPacket, SocketAddr)>> {
+        let (socket, sa) = ready!(self.socket.poll_accept_priv(cx))?;
+        Poll::Ready(Ok((SeqPacket { socket }, sa)))
+    }
+
+    /// Constructs a new [Listener] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)? })
+    }
+}
+
+impl AsRef<Socket> for Listener {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for Listener {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Listener::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// An SCO voice connection, transferring packets of voice data.
+#[derive(Debug)]
+pub struct SeqPacket {
+    socket: Socket,
+}
+
+impl SeqPacket {
+    /// Establish a voice connection with a peer at the specified socket address.
+    ///
+    /// Uses any local Bluetooth adapter and the default [voice setting](Voice).
+    pub async fn connect(addr: SocketAddr) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(SocketAddr::any())?;
+        socket.connect(addr).await
+    }
+
+    /// Gets the peer address of this connection.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.socket.peer_addr_priv()
+    }
+
+    /// Sends a packet.
+    ///
+    /// The packet length must not exceed the [Self::send_mtu].
+    pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+        self.socket.send_priv(buf).await
+    }
+
+    /// Attempts to send a packet.
+    ///
+    /// The packet length must not exceed the [Self::send_mtu].
+    pub fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize>> {
+        self.socket.poll_send_priv(cx, buf)
+    }
+
+    /// Receives a packet.
+    ///
+    /// The provided buffer must be of length [Self::recv_mtu], otherwise
+    /// the packet may be truncated.
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        self.socket.recv_priv(buf).await
+    }
+
+    /// Attempts to receive a packet.
+    ///
+    /// The provided buffer must be of length [Self::recv_mtu], otherwise
+    /// the packet may be truncated.
+    pub fn poll_recv(&self, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<()>> {
+        self.socket.poll_recv_priv(cx, buf)
+    }
+
+    /// Receives a packet together with its status.
+    ///
+    /// The status is only available if [packet status reporting](Socket::set_pkt_status)
+    /// has been enabled on the socket, otherwise `None` is returned.
+    ///
+    /// The provided buffer must be of length [Self::recv_mtu], otherwise
+    /// the packet may be truncated.
+    pub async fn recv_with_status(&self, buf: &mut [u8]) -> Result<(usize, Option<PacketStatus>)> {
+        let (n, status) = self.socket.recv_with_status_priv(buf).await?;
+        let status = match status {
+            Some(status) => Some(
+                PacketStatus::from_u8(status)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid packet status"))?,
+            ),
+            None => None,
+        };
+        Ok((n, status))
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        self.socket.shutdown_priv(how)
+    }
+
+    /// Maximum transmission unit (MTU) for sending.
+    pub fn send_mtu(&self) -> Result<usize> {
+        self.socket.mtu().map(|v| v.into())
+    }
+
+    /// Maximum transmission unit (MTU) for receiving.
+    pub fn recv_mtu(&self) -> Result<usize> {
+        self.socket.mtu().map(|v| v.into())
+    }
+
+    /// Constructs a new [SeqPacket] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)? })
+    }
+}
+
+impl AsRef<Socket> for SeqPacket {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for SeqPacket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for SeqPacket {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [SeqPacket::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
@@ -0,0 +1,965 @@
+//! Service discovery protocol (SDP) records and client.
+//!
+//! An SDP [Record] consists of attributes, each holding a [DataElement].
+//! Records can be built and then serialized into the XML format of the Bluetooth daemon
+//! using [Record::to_xml], for example to provide the `service_record` of
+//! an [RFCOMM profile](crate::rfcomm::Profile).
+//!
+//! A [Client] queries the SDP server of a remote device for its service records
+//! over an [L2CAP](crate::l2cap) connection.
+//! This does not require a running Bluetooth daemon.
+//!
+
+use crate::{
+    l2cap::{SeqPacket, SocketAddr},
+    Address, AddressType, Uuid, UuidExt,
+};
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    fmt,
+    fmt::Write,
+    io::{Error, ErrorKind, Result},
+    ops::RangeInclusive,
+};
+
+/// Protocol service multiplexor (PSM) of the SDP server.
+pub const PSM: u16 = 0x0001;
+
+/// Maximum number of UUIDs in a service search pattern.
+pub const MAX_SEARCH_UUIDS: usize = 12;
+
+/// Attribute identifiers.
+pub mod attr {
+    /// Service record handle.
+    pub const SERVICE_RECORD_HANDLE: u16 = 0x0000;
+    /// Service class ID list.
+    pub const SERVICE_CLASS_ID_LIST: u16 = 0x0001;
+    /// Service record state.
+    pub const SERVICE_RECORD_STATE: u16 = 0x0002;
+    /// Service ID.
+    pub const SERVICE_ID: u16 = 0x0003;
+    /// Protocol descriptor list.
+    pub const PROTOCOL_DESCRIPTOR_LIST: u16 = 0x0004;
+    /// Browse group list.
+    pub const BROWSE_GROUP_LIST: u16 = 0x0005;
+    /// Language base attribute ID list.
+    pub const LANGUAGE_BASE_ATTRIBUTE_ID_LIST: u16 = 0x0006;
+    /// Service info time to live.
+    pub const SERVICE_INFO_TIME_TO_LIVE: u16 = 0x0007;
+    /// Service availability.
+    pub const SERVICE_AVAILABILITY: u16 = 0x0008;
+    /// Bluetooth profile descriptor list.
+    pub const BLUETOOTH_PROFILE_DESCRIPTOR_LIST: u16 = 0x0009;
+    /// Documentation URL.
+    pub const DOCUMENTATION_URL: u16 = 0x000a;
+    /// Client executable URL.
+    pub const CLIENT_EXECUTABLE_URL: u16 = 0x000b;
+    /// Icon URL.
+    pub const ICON_URL: u16 = 0x000c;
+    /// Additional protocol descriptor lists.
+    pub const ADDITIONAL_PROTOCOL_DESCRIPTOR_LISTS: u16 = 0x000d;
+    /// Service name in the primary language.
+    pub const SERVICE_NAME: u16 = 0x0100;
+    /// Service description in the primary language.
+    pub const SERVICE_DESCRIPTION: u16 = 0x0101;
+    /// Provider name in the primary language.
+    pub const PROVIDER_NAME: u16 = 0x0102;
+    /// L2CAP PSM of the generic object exchange profile (GOEP).
+    pub const GOEP_L2CAP_PSM: u16 = 0x0200;
+}
+
+/// Protocol and browse group UUIDs.
+pub mod uuid {
+    use crate::Uuid;
+
+    /// SDP protocol.
+    pub const SDP: Uuid = Uuid::from_u128(0x00000001_0000_1000_8000_00805f9b34fb);
+    /// RFCOMM protocol.
+    pub const RFCOMM: Uuid = Uuid::from_u128(0x00000003_0000_1000_8000_00805f9b34fb);
+    /// OBEX protocol.
+    pub const OBEX: Uuid = Uuid::from_u128(0x00000008_0000_1000_8000_00805f9b34fb);
+    /// BNEP protocol.
+    pub const BNEP: Uuid = Uuid::from_u128(0x0000000f_0000_1000_8000_00805f9b34fb);
+    /// L2CAP protocol.
+    pub const L2CAP: Uuid = Uuid::from_u128(0x00000100_0000_1000_8000_00805f9b34fb);
+    /// Public browse root.
+    pub const PUBLIC_BROWSE_ROOT: Uuid = Uuid::from_u128(0x00001002_0000_1000_8000_00805f9b34fb);
+}
+
+const TYPE_NIL: u8 = 0;
+const TYPE_UINT: u8 = 1;
+const TYPE_INT: u8 = 2;
+const TYPE_UUID: u8 = 3;
+const TYPE_TEXT: u8 = 4;
+const TYPE_BOOL: u8 = 5;
+const TYPE_SEQUENCE: u8 = 6;
+const TYPE_ALTERNATIVE: u8 = 7;
+const TYPE_URL: u8 = 8;
+
+const PDU_ERROR_RSP: u8 = 0x01;
+const PDU_SERVICE_SEARCH_ATTR_REQ: u8 = 0x06;
+const PDU_SERVICE_SEARCH_ATTR_RSP: u8 = 0x07;
+
+/// Size of the PDU header.
+const PDU_HDR_SIZE: usize = 5;
+
+/// Maximum length of the continuation state.
+const MAX_CONTINUATION_LEN: usize = 16;
+
+fn invalid_data(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// SDP data element.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataElement {
+    /// Nil.
+    Nil,
+    /// Boolean.
+    Bool(bool),
+    /// 8-bit unsigned integer.
+    U8(u8),
+    /// 16-bit unsigned integer.
+    U16(u16),
+    /// 32-bit unsigned integer.
+    U32(u32),
+    /// 64-bit unsigned integer.
+    U64(u64),
+    /// 128-bit unsigned integer.
+    U128(u128),
+    /// 8-bit signed integer.
+    I8(i8),
+    /// 16-bit signed integer.
+    I16(i16),
+    /// 32-bit signed integer.
+    I32(i32),
+    /// 64-bit signed integer.
+    I64(i64),
+    /// 128-bit signed integer.
+    I128(i128),
+    /// UUID.
+    ///
+    /// It is encoded in its 16-bit or 32-bit short form if possible.
+    Uuid(Uuid),
+    /// Text string.
+    Text(String),
+    /// URL.
+    Url(String),
+    /// Sequence of data elements.
+    Sequence(Vec<DataElement>),
+    /// Alternative of data elements, from which one is to be selected.
+    Alternative(Vec<DataElement>),
+}
+
+impl DataElement {
+    /// Creates a sequence from the specified data elements.
+    pub fn seq<I, T>(elements: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<DataElement>,
+    {
+        Self::Sequence(elements.into_iter().map(Into::into).collect())
+    }
+
+    /// The value if this is an unsigned integer of at most 64 bits.
+    pub fn as_uint(&self) -> Option<u64> {
+        match self {
+            Self::U8(v) => Some((*v).into()),
+            Self::U16(v) => Some((*v).into()),
+            Self::U32(v) => Some((*v).into()),
+            Self::U64(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// The UUID if this is a UUID.
+    pub fn as_uuid(&self) -> Option<Uuid> {
+        match self {
+            Self::Uuid(uuid) => Some(*uuid),
+            _ => None,
+        }
+    }
+
+    /// The string if this is a text string or URL.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Text(s) | Self::Url(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The contained elements if this is a sequence or alternative.
+    pub fn as_seq(&self) -> Option<&[DataElement]> {
+        match self {
+            Self::Sequence(elems) | Self::Alternative(elems) => Some(elems),
+            _ => None,
+        }
+    }
+
+    /// Encodes the data element into its binary representation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf);
+        buf
+    }
+
+    /// Decodes a data element from its binary representation.
+    ///
+    /// The buffer must contain exactly one data element.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        let mut r = Reader(buf);
+        let elem = Self::decode(&mut r, 0)?;
+        if !r.0.is_empty() {
+            return Err(invalid_data("trailing data after SDP data element"));
+        }
+        Ok(elem)
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        fn fixed(buf: &mut Vec<u8>, ty: u8, data: &[u8]) {
+            let size_idx = match data.len() {
+                1 => 0,
+                2 => 1,
+                4 => 2,
+                8 => 3,
+                16 => 4,
+                _ => unreachable!(),
+            };
+            buf.push(ty << 3 | size_idx);
+            buf.extend_from_slice(data);
+        }
+
+        fn variable(buf: &mut Vec<u8>, ty: u8, data: &[u8]) {
+            match data.len() {
+                len if len <= u8::MAX as usize => {
+                    buf.push(ty << 3 | 5);
+                    buf.push(len as u8);
+                }
+                len if len <= u16::MAX as usize => {
+                    buf.push(ty << 3 | 6);
+                    buf.extend_from_slice(&(len as u16).to_be_bytes());
+                }
+                len => {
+                    buf.push(ty << 3 | 7);
+                    buf.extend_from_slice(&(len as u32).to_be_bytes());
+                }
+            }
+            buf.extend_from_slice(data);
+        }
+
+        match self {
+            Self::Nil => buf.push(TYPE_NIL << 3),
+            Self::Bool(v) => fixed(buf, TYPE_BOOL, &[(*v).into()]),
+            Self::U8(v) => fixed(buf, TYPE_UINT, &v.to_be_bytes()),
+            Self::U16(v) => fixed(buf, TYPE_UINT, &v.to_be_bytes()),
+            Self::U32(v) => fixed(buf, TYPE_UINT, &v.to_be_bytes()),
+            Self::U64(v) => fixed(buf, TYPE_UINT, &v.to_be_bytes()),
+            Self::U128(v) => fixed(buf, TYPE_UINT, &v.to_be_bytes()),
+            Self::I8(v) => fixed(buf, TYPE_INT, &v.to_be_bytes()),
+            Self::I16(v) => fixed(buf, TYPE_INT, &v.to_be_bytes()),
+            Self::I32(v) => fixed(buf, TYPE_INT, &v.to_be_bytes()),
+            Self::I64(v) => fixed(buf, TYPE_INT, &v.to_be_bytes()),
+            Self::I128(v) => fixed(buf, TYPE_INT, &v.to_be_bytes()),
+            Self::Uuid(uuid) => match (uuid.as_u16(), uuid.as_u32()) {
+                (Some(v), _) => fixed(buf, TYPE_UUID, &v.to_be_bytes()),
+                (None, Some(v)) => fixed(buf, TYPE_UUID, &v.to_be_bytes()),
+                (None, None) => fixed(buf, TYPE_UUID, uuid.as_bytes()),
+            },
+            Self::Text(s) => variable(buf, TYPE_TEXT, s.as_bytes()),
+            Self::Url(s) => variable(buf, TYPE_URL, s.as_bytes()),
+            Self::Sequence(elems) | Self::Alternative(elems) => {
+                let mut data = Vec::new();
+                for elem in elems {
+                    elem.encode(&mut data);
+                }
+                let ty = if matches!(self, Self::Sequence(_)) { TYPE_SEQUENCE } else { TYPE_ALTERNATIVE };
+                variable(buf, ty, &data);
+            }
+        }
+    }
+
+    fn decode(r: &mut Reader, depth: usize) -> Result<Self> {
+        if depth > 32 {
+            return Err(invalid_data("SDP data element nested too deeply"));
+        }
+
+        let hdr = r.u8()?;
+        let ty = hdr >> 3;
+        let size_idx = hdr & 0x07;
+        let len = match size_idx {
+            _ if ty == TYPE_NIL => 0,
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            3 => 8,
+            4 => 16,
+            5 => r.u8()?.into(),
+            6 => r.u16()?.into(),
+            _ => r.u32()? as usize,
+        };
+        let data = r.bytes(len)?;
+
+        let text = |data: &[u8]| {
+            let len = data.iter().rposition(|&b| b != 0).map(|p| p + 1).unwrap_or_default();
+            String::from_utf8_lossy(&data[..len]).to_string()
+        };
+
+        let elem = match (ty, data.len()) {
+            (TYPE_NIL, _) => Self::Nil,
+            (TYPE_BOOL, 1) => Self::Bool(data[0] != 0),
+            (TYPE_UINT, 1) => Self::U8(data[0]),
+            (TYPE_UINT, 2) => Self::U16(u16::from_be_bytes(data.try_into().unwrap())),
+            (TYPE_UINT, 4) => Self::U32(u32::from_be_bytes(data.try_into().unwrap())),
+            (TYPE_UINT, 8) => Self::U64(u64::from_be_bytes(data.try_into().unwrap())),
+            (TYPE_UINT, 16) => Self::U128(u128::from_be_bytes(data.try_into().unwrap())),
+            (TYPE_INT, 1) => Self::I8(data[0] as i8),
+            (TYPE_INT, 2) => Self::I16(i16::from_be_bytes(data.try_into().unwrap())),
+            (TYPE_INT, 4) => Self::I32(i32::from_be_bytes(data.try_into().unwrap())),
+            (TYPE_INT, 8) => Self::I64(i64::from_be_bytes(data.try_into().unwrap())),
+            (TYPE_INT, 16) => Self::I128(i128::from_be_bytes(data.try_into().unwrap())),
+            (TYPE_UUID, 2) => Self::Uuid(Uuid::from_u16(u16::from_be_bytes(data.try_into().unwrap()))),
+            (TYPE_UUID, 4) => Self::Uuid(Uuid::from_u32(u32::from_be_bytes(data.try_into().unwrap()))),
+            (TYPE_UUID, 16) => Self::Uuid(Uuid::from_u128(u128::from_be_bytes(data.try_into().unwrap()))),
+            (TYPE_TEXT, _) if size_idx >= 5 => Self::Text(text(data)),
+            (TYPE_URL, _) if size_idx >= 5 => Self::Url(text(data)),
+            (TYPE_SEQUENCE | TYPE_ALTERNATIVE, _) if size_idx >= 5 => {
+                let mut inner = Reader(data);
+                let mut elems = Vec::new();
+                while !inner.0.is_empty() {
+                    elems.push(Self::decode(&mut inner, depth + 1)?);
+                }
+                if ty == TYPE_SEQUENCE {
+                    Self::Sequence(elems)
+                } else {
+                    Self::Alternative(elems)
+                }
+            }
+            _ => return Err(invalid_data("invalid SDP data element")),
+        };
+        Ok(elem)
+    }
+
+    fn write_xml(&self, out: &mut String, indent: usize) {
+        let pad = " ".repeat(indent);
+        let _ = match self {
+            Self::Nil => writeln!(out, "{}<nil />", pad),
+            Self::Bool(v) => writeln!(out, "{}<boolean value=\"{}\" />", pad, v),
+            Self::U8(v) => writeln!(out, "{}<uint8 value=\"0x{:02x}\" />", pad, v),
+            Self::U16(v) => writeln!(out, "{}<uint16 value=\"0x{:04x}\" />", pad, v),
+            Self::U32(v) => writeln!(out, "{}<uint32 value=\"0x{:08x}\" />", pad, v),
+            Self::U64(v) => writeln!(out, "{}<uint64 value=\"0x{:016x}\" />", pad, v),
+            Self::U128(v) => writeln!(out, "{}<uint128 value=\"{:032x}\" />", pad, v),
+            Self::I8(v) => writeln!(out, "{}<int8 value=\"{}\" />", pad, v),
+            Self::I16(v) => writeln!(out, "{}<int16 value=\"{}\" />", pad, v),
+            Self::I32(v) => writeln!(out, "{}<int32 value=\"{}\" />", pad, v),
+            Self::I64(v) => writeln!(out, "{}<int64 value=\"{}\" />", pad, v),
+            Self::I128(v) => writeln!(out, "{}<int128 value=\"{:032x}\" />", pad, v),
+            Self::Uuid(uuid) => match (uuid.as_u16(), uuid.as_u32()) {
+                (Some(v), _) => writeln!(out, "{}<uuid value=\"0x{:04x}\" />", pad, v),
+                (None, Some(v)) => writeln!(out, "{}<uuid value=\"0x{:08x}\" />", pad, v),
+                (None, None) => writeln!(out, "{}<uuid value=\"{}\" />", pad, uuid),
+            },
+            Self::Text(s) if s.chars().any(|c| c.is_control()) => {
+                let hex: String = s.bytes().map(|b| format!("{:02x}", b)).collect();
+                writeln!(out, "{}<text encoding=\"hex\" value=\"{}\" />", pad, hex)
+            }
+            Self::Text(s) => writeln!(out, "{}<text value=\"{}\" />", pad, xml_escape(s)),
+            Self::Url(s) => writeln!(out, "{}<url value=\"{}\" />", pad, xml_escape(s)),
+            Self::Sequence(elems) | Self::Alternative(elems) => {
+                let tag = if matches!(self, Self::Sequence(_)) { "sequence" } else { "alternate" };
+                let _ = writeln!(out, "{}<{}>", pad, tag);
+                for elem in elems {
+                    elem.write_xml(out, indent + 2);
+                }
+                writeln!(out, "{}</{}>", pad, tag)
+            }
+        };
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+macro_rules! impl_from {
+    ($t:ty, $variant:ident) => {
+        impl From<$t> for DataElement {
+            fn from(v: $t) -> Self {
+                Self::$variant(v)
+            }
+        }
+    };
+}
+
+impl_from!(bool, Bool);
+impl_from!(u8, U8);
+impl_from!(u16, U16);
+impl_from!(u32, U32);
+impl_from!(u64, U64);
+impl_from!(u128, U128);
+impl_from!(i8, I8);
+impl_from!(i16, I16);
+impl_from!(i32, I32);
+impl_from!(i64, I64);
+impl_from!(i128, I128);
+impl_from!(Uuid, Uuid);
+impl_from!(String, Text);
+impl_from!(Vec<DataElement>, Sequence);
+
+impl From<&str> for DataElement {
+    fn from(s: &str) -> Self {
+        Self::Text(s.to_string())
+    }
+}
+
+/// SDP service record.
+///
+/// The service record handle is assigned by the SDP server and thus
+/// should not be set when registering a record.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record {
+    /// Attributes by their identifier.
+    ///
+    /// See the [attr] module for common attribute identifiers.
+    pub attributes: BTreeMap<u16, DataElement>,
+}
+
+impl Record {
+    /// Creates an empty record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets an attribute, replacing the previous value.
+    pub fn with(mut self, id: u16, value: impl Into<DataElement>) -> Self {
+        self.attributes.insert(id, value.into());
+        self
+    }
+
+    /// Sets the service class ID list.
+    pub fn with_service_classes(self, classes: &[Uuid]) -> Self {
+        self.with(attr::SERVICE_CLASS_ID_LIST, DataElement::seq(classes.iter().copied()))
+    }
+
+    /// Sets the protocol descriptor list to L2CAP using the specified PSM.
+    pub fn with_l2cap(self, psm: u16) -> Self {
+        self.with(
+            attr::PROTOCOL_DESCRIPTOR_LIST,
+            DataElement::seq([DataElement::seq([DataElement::Uuid(uuid::L2CAP), DataElement::U16(psm)])]),
+        )
+    }
+
+    /// Sets the protocol descriptor list to RFCOMM over L2CAP using the specified channel.
+    pub fn with_rfcomm(self, channel: u8) -> Self {
+        self.with(
+            attr::PROTOCOL_DESCRIPTOR_LIST,
+            DataElement::seq([
+                DataElement::seq([uuid::L2CAP]),
+                DataElement::seq([DataElement::Uuid(uuid::RFCOMM), DataElement::U8(channel)]),
+            ]),
+        )
+    }
+
+    /// Adds the record to the public browse group, making it discoverable by browsing.
+    pub fn with_public_browse_group(self) -> Self {
+        self.with(attr::BROWSE_GROUP_LIST, DataElement::seq([uuid::PUBLIC_BROWSE_ROOT]))
+    }
+
+    /// Sets the Bluetooth profile descriptor list to the specified profile and version.
+    ///
+    /// The version is encoded as `0xMMmm` where `MM` is the major and `mm` the minor version.
+    pub fn with_profile(self, profile: Uuid, version: u16) -> Self {
+        self.with(
+            attr::BLUETOOTH_PROFILE_DESCRIPTOR_LIST,
+            DataElement::seq([DataElement::seq([DataElement::Uuid(profile), DataElement::U16(version)])]),
+        )
+    }
+
+    /// Sets the service name in the primary language.
+    pub fn with_service_name(self, name: &str) -> Self {
+        self.with(attr::SERVICE_NAME, name)
+    }
+
+    /// Gets the value of an attribute.
+    pub fn get(&self, id: u16) -> Option<&DataElement> {
+        self.attributes.get(&id)
+    }
+
+    /// Service record handle.
+    pub fn handle(&self) -> Option<u32> {
+        match self.get(attr::SERVICE_RECORD_HANDLE)? {
+            DataElement::U32(handle) => Some(*handle),
+            _ => None,
+        }
+    }
+
+    /// Service classes.
+    pub fn service_classes(&self) -> Vec<Uuid> {
+        self.get(attr::SERVICE_CLASS_ID_LIST)
+            .and_then(|v| v.as_seq())
+            .map(|elems| elems.iter().filter_map(|e| e.as_uuid()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Protocols of the protocol descriptor list, each with its UUID and parameters.
+    pub fn protocols(&self) -> Vec<(Uuid, Vec<DataElement>)> {
+        let descs = match self.get(attr::PROTOCOL_DESCRIPTOR_LIST) {
+            Some(DataElement::Alternative(alts)) => alts.first().and_then(|v| v.as_seq()).unwrap_or_default(),
+            Some(DataElement::Sequence(descs)) => descs,
+            _ => &[],
+        };
+        descs
+            .iter()
+            .filter_map(|desc| {
+                let (uuid, params) = desc.as_seq()?.split_first()?;
+                Some((uuid.as_uuid()?, params.to_vec()))
+            })
+            .collect()
+    }
+
+    fn protocol_param(&self, protocol: Uuid) -> Option<u64> {
+        let (_, params) = self.protocols().into_iter().find(|(uuid, _)| *uuid == protocol)?;
+        params.first()?.as_uint()
+    }
+
+    /// L2CAP PSM from the protocol descriptor list.
+    pub fn l2cap_psm(&self) -> Option<u16> {
+        self.protocol_param(uuid::L2CAP)?.try_into().ok()
+    }
+
+    /// RFCOMM channel from the protocol descriptor list.
+    pub fn rfcomm_channel(&self) -> Option<u8> {
+        self.protocol_param(uuid::RFCOMM)?.try_into().ok()
+    }
+
+    /// Profiles and their versions from the Bluetooth profile descriptor list.
+    pub fn profiles(&self) -> Vec<(Uuid, u16)> {
+        self.get(attr::BLUETOOTH_PROFILE_DESCRIPTOR_LIST)
+            .and_then(|v| v.as_seq())
+            .map(|descs| {
+                descs
+                    .iter()
+                    .filter_map(|desc| match desc.as_seq()? {
+                        [DataElement::Uuid(uuid), DataElement::U16(version)] => Some((*uuid, *version)),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Service name in the primary language.
+    pub fn service_name(&self) -> Option<&str> {
+        self.get(attr::SERVICE_NAME)?.as_str()
+    }
+
+    /// Serializes the record into the XML format used by the Bluetooth daemon.
+    pub fn to_xml(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n");
+        out.push_str("<record>\n");
+        for (id, value) in &self.attributes {
+            let _ = writeln!(out, "  <attribute id=\"0x{:04x}\">", id);
+            value.write_xml(&mut out, 4);
+            out.push_str("  </attribute>\n");
+        }
+        out.push_str("</record>\n");
+        out
+    }
+
+    /// Converts the record into a sequence of attribute identifier and value pairs,
+    /// as transferred by the SDP protocol.
+    pub fn to_data_element(&self) -> DataElement {
+        DataElement::Sequence(
+            self.attributes.iter().flat_map(|(id, value)| [DataElement::U16(*id), value.clone()]).collect(),
+        )
+    }
+
+    /// Parses a record from a sequence of attribute identifier and value pairs.
+    pub fn from_data_element(elem: &DataElement) -> Result<Self> {
+        let elems = match elem {
+            DataElement::Sequence(elems) if elems.len() % 2 == 0 => elems,
+            _ => return Err(invalid_data("invalid SDP attribute list")),
+        };
+        let mut attributes = BTreeMap::new();
+        for pair in elems.chunks(2) {
+            match &pair[0] {
+                DataElement::U16(id) => attributes.insert(*id, pair[1].clone()),
+                _ => return Err(invalid_data("invalid SDP attribute identifier")),
+            };
+        }
+        Ok(Self { attributes })
+    }
+}
+
+/// Error code returned by an SDP server.
+///
+/// This is returned as the inner error of an [std::io::Error].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorCode(pub u16);
+
+impl ErrorCode {
+    /// Invalid or unsupported SDP version.
+    pub const INVALID_VERSION: Self = Self(0x0001);
+    /// Invalid service record handle.
+    pub const INVALID_RECORD_HANDLE: Self = Self(0x0002);
+    /// Invalid request syntax.
+    pub const INVALID_SYNTAX: Self = Self(0x0003);
+    /// Invalid PDU size.
+    pub const INVALID_PDU_SIZE: Self = Self(0x0004);
+    /// Invalid continuation state.
+    pub const INVALID_CONTINUATION_STATE: Self = Self(0x0005);
+    /// Insufficient resources to satisfy request.
+    pub const INSUFFICIENT_RESOURCES: Self = Self(0x0006);
+}
+
+impl fmt::Debug for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ErrorCode(0x{:04x})", self.0)
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            Self::INVALID_VERSION => "invalid SDP version",
+            Self::INVALID_RECORD_HANDLE => "invalid service record handle",
+            Self::INVALID_SYNTAX => "invalid request syntax",
+            Self::INVALID_PDU_SIZE => "invalid PDU size",
+            Self::INVALID_CONTINUATION_STATE => "invalid continuation state",
+            Self::INSUFFICIENT_RESOURCES => "insufficient resources",
+            _ => return write!(f, "SDP error 0x{:04x}", self.0),
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for ErrorCode {}
+
+impl From<ErrorCode> for Error {
+    fn from(code: ErrorCode) -> Self {
+        Error::new(ErrorKind::Other, code)
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid_data("SDP data truncated"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+/// SDP client querying the service records of a remote device.
+#[derive(Debug)]
+pub struct Client {
+    socket: SeqPacket,
+    transaction_id: u16,
+}
+
+impl Client {
+    /// Connects to the SDP server of the specified remote device using classic Bluetooth (BR/EDR).
+    pub async fn connect(addr: Address) -> Result<Self> {
+        let socket = SeqPacket::connect(SocketAddr::new(addr, AddressType::BrEdr, PSM)).await?;
+        Ok(Self { socket, transaction_id: 0 })
+    }
+
+    /// The underlying L2CAP connection.
+    pub fn socket(&self) -> &SeqPacket {
+        &self.socket
+    }
+
+    /// Searches for service records matching all UUIDs of the pattern and returns
+    /// the specified attributes of them.
+    ///
+    /// At most [MAX_SEARCH_UUIDS] can be specified in the pattern.
+    /// The attributes are specified as ranges of attribute identifiers;
+    /// use `0x0000..=0xffff` to retrieve all attributes.
+    pub async fn search_attributes(
+        &mut self, pattern: &[Uuid], attributes: &[RangeInclusive<u16>],
+    ) -> Result<Vec<Record>> {
+        if pattern.is_empty() || pattern.len() > MAX_SEARCH_UUIDS {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid number of UUIDs in search pattern"));
+        }
+
+        let mut params = DataElement::seq(pattern.iter().copied()).to_bytes();
+        params.extend_from_slice(&u16::MAX.to_be_bytes());
+        params.extend(
+            DataElement::seq(attributes.iter().map(|range| {
+                if range.start() == range.end() {
+                    DataElement::U16(*range.start())
+                } else {
+                    DataElement::U32(u32::from(*range.start()) << 16 | u32::from(*range.end()))
+                }
+            }))
+            .to_bytes(),
+        );
+
+        let mut lists = Vec::new();
+        let mut continuation = Vec::new();
+        loop {
+            let mut req = params.clone();
+            req.push(continuation.len() as u8);
+            req.extend_from_slice(&continuation);
+
+            let rsp = self.transact(PDU_SERVICE_SEARCH_ATTR_REQ, &req, PDU_SERVICE_SEARCH_ATTR_RSP).await?;
+            let mut r = Reader(&rsp);
+            let count = r.u16()?;
+            lists.extend_from_slice(r.bytes(count.into())?);
+            let cont_len = r.u8()?;
+            if usize::from(cont_len) > MAX_CONTINUATION_LEN {
+                return Err(invalid_data("SDP continuation state too long"));
+            }
+            continuation = r.bytes(cont_len.into())?.to_vec();
+            if continuation.is_empty() {
+                break;
+            }
+        }
+
+        match DataElement::from_bytes(&lists)? {
+            DataElement::Sequence(records) => records.iter().map(Record::from_data_element).collect(),
+            _ => Err(invalid_data("invalid SDP attribute lists")),
+        }
+    }
+
+    /// Searches for service records matching all UUIDs of the pattern and returns
+    /// all their attributes.
+    pub async fn search(&mut self, pattern: &[Uuid]) -> Result<Vec<Record>> {
+        self.search_attributes(pattern, &[0x0000..=0xffff]).await
+    }
+
+    /// Returns all records of the public browse group.
+    pub async fn browse(&mut self) -> Result<Vec<Record>> {
+        self.search(&[uuid::PUBLIC_BROWSE_ROOT]).await
+    }
+
+    async fn transact(&mut self, pdu_id: u8, params: &[u8], rsp_pdu_id: u8) -> Result<Vec<u8>> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let len: u16 =
+            params.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "SDP request too long"))?;
+
+        let mut req = Vec::with_capacity(PDU_HDR_SIZE + params.len());
+        req.push(pdu_id);
+        req.extend_from_slice(&self.transaction_id.to_be_bytes());
+        req.extend_from_slice(&len.to_be_bytes());
+        req.extend_from_slice(params);
+        self.socket.send(&req).await?;
+
+        let mut buf = vec![0; self.socket.recv_mtu()?.max(PDU_HDR_SIZE)];
+        let n = self.socket.recv(&mut buf).await?;
+        let mut r = Reader(&buf[..n]);
+        let id = r.u8()?;
+        let tid = r.u16()?;
+        let len = r.u16()?;
+        let params = r.bytes(len.into())?;
+
+        if tid != self.transaction_id {
+            return Err(invalid_data("SDP transaction identifier mismatch"));
+        }
+        match id {
+            PDU_ERROR_RSP => Err(ErrorCode(Reader(params).u16()?).into()),
+            id if id == rsp_pdu_id => Ok(params.to_vec()),
+            _ => Err(invalid_data("unexpected SDP response")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{attr, uuid, DataElement, Record};
+    use crate::{Uuid, UuidExt};
+
+    const SERIAL_PORT: Uuid = Uuid::from_u128(0x00001101_0000_1000_8000_00805f9b34fb);
+
+    #[test]
+    fn encode() {
+        let vectors: Vec<(DataElement, Vec<u8>)> = vec![
+            (DataElement::Nil, vec![0x00]),
+            (DataElement::Bool(true), vec![0x28, 0x01]),
+            (DataElement::U8(0x12), vec![0x08, 0x12]),
+            (DataElement::U16(0x1234), vec![0x09, 0x12, 0x34]),
+            (DataElement::U32(0x12345678), vec![0x0a, 0x12, 0x34, 0x56, 0x78]),
+            (DataElement::I8(-1), vec![0x10, 0xff]),
+            (DataElement::I16(-2), vec![0x11, 0xff, 0xfe]),
+            (DataElement::Uuid(SERIAL_PORT), vec![0x19, 0x11, 0x01]),
+            (DataElement::Uuid(Uuid::from_u32(0x12345678)), vec![0x1a, 0x12, 0x34, 0x56, 0x78]),
+            (DataElement::Text("abc".into()), vec![0x25, 0x03, b'a', b'b', b'c']),
+            (DataElement::Url("x".into()), vec![0x45, 0x01, b'x']),
+            (
+                DataElement::seq([DataElement::U8(1), DataElement::U16(2)]),
+                vec![0x35, 0x05, 0x08, 0x01, 0x09, 0x00, 0x02],
+            ),
+            (DataElement::Alternative(vec![]), vec![0x3d, 0x00]),
+        ];
+        for (elem, bytes) in vectors {
+            assert_eq!(elem.to_bytes(), bytes, "{:?}", elem);
+            assert_eq!(DataElement::from_bytes(&bytes).unwrap(), elem);
+        }
+    }
+
+    #[test]
+    fn uuid128() {
+        let uuid = Uuid::from_u128(0x12345678_9abc_def0_1234_56789abcdef0);
+        let bytes = DataElement::Uuid(uuid).to_bytes();
+        assert_eq!(bytes[0], 0x1c);
+        assert_eq!(&bytes[1..], uuid.as_bytes());
+        assert_eq!(DataElement::from_bytes(&bytes).unwrap(), DataElement::Uuid(uuid));
+    }
+
+    #[test]
+    fn long_text() {
+        let text = "a".repeat(300);
+        let bytes = DataElement::Text(text.clone()).to_bytes();
+        assert_eq!(&bytes[..3], [0x26, 0x01, 0x2c]);
+        assert_eq!(bytes.len(), 3 + 300);
+        assert_eq!(DataElement::from_bytes(&bytes).unwrap(), DataElement::Text(text));
+    }
+
+    #[test]
+    fn round_trip() {
+        let elem = DataElement::seq([
+            DataElement::U64(u64::MAX),
+            DataElement::U128(u128::MAX),
+            DataElement::I32(i32::MIN),
+            DataElement::I64(-3),
+            DataElement::I128(i128::MIN),
+            DataElement::Alternative(vec![DataElement::Nil, DataElement::Bool(false)]),
+            DataElement::seq([uuid::L2CAP, uuid::RFCOMM]),
+        ]);
+        assert_eq!(DataElement::from_bytes(&elem.to_bytes()).unwrap(), elem);
+    }
+
+    #[test]
+    fn decode_text_nul_terminated() {
+        assert_eq!(
+            DataElement::from_bytes(&[0x25, 0x04, b'a', b'b', 0x00, 0x00]).unwrap(),
+            DataElement::Text("ab".into())
+        );
+        assert_eq!(
+            DataElement::from_bytes(&[0x27, 0x00, 0x00, 0x00, 0x01, b'a']).unwrap(),
+            DataElement::Text("a".into())
+        );
+    }
+
+    #[test]
+    fn decode_invalid() {
+        for bytes in [
+            &[][..],
+            &[0x09, 0x12],
+            &[0x08, 0x01, 0x02],
+            &[0x29, 0x00, 0x01],
+            &[0x1b, 0, 0, 0, 0, 0, 0, 0, 0],
+            &[0x20, b'a'],
+            &[0x35, 0x03, 0x09, 0x00],
+            &[0x25, 0x05, b'a'],
+        ] {
+            assert!(DataElement::from_bytes(bytes).is_err(), "{:x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn decode_nesting() {
+        let nested = |depth| (0..depth).fold(DataElement::Nil, |elem, _| DataElement::seq([elem]));
+        assert!(DataElement::from_bytes(&nested(10).to_bytes()).is_ok());
+        assert!(DataElement::from_bytes(&nested(40).to_bytes()).is_err());
+    }
+
+    #[test]
+    fn record() {
+        let record = Record::new()
+            .with(attr::SERVICE_RECORD_HANDLE, 0x00010001u32)
+            .with_service_classes(&[SERIAL_PORT])
+            .with_rfcomm(3)
+            .with_profile(SERIAL_PORT, 0x0102)
+            .with_public_browse_group()
+            .with_service_name("Serial");
+
+        let bytes = record.to_data_element().to_bytes();
+        let parsed = Record::from_data_element(&DataElement::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(parsed, record);
+
+        assert_eq!(parsed.handle(), Some(0x00010001));
+        assert_eq!(parsed.service_classes(), vec![SERIAL_PORT]);
+        assert_eq!(parsed.rfcomm_channel(), Some(3));
+        assert_eq!(parsed.l2cap_psm(), None);
+        assert_eq!(parsed.profiles(), vec![(SERIAL_PORT, 0x0102)]);
+        assert_eq!(parsed.service_name(), Some("Serial"));
+        assert_eq!(Record::new().with_l2cap(0x1001).l2cap_psm(), Some(0x1001));
+    }
+
+    #[test]
+    fn record_invalid() {
+        assert!(Record::from_data_element(&DataElement::U16(0)).is_err());
+        assert!(Record::from_data_element(&DataElement::seq([DataElement::U16(0)])).is_err());
+        assert!(Record::from_data_element(&DataElement::seq([DataElement::U8(0), DataElement::Nil])).is_err());
+    }
+
+    #[test]
+    fn to_xml() {
+        let record = Record::new().with_rfcomm(1).with_service_name("a<b");
+        assert_eq!(
+            record.to_xml(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n\
+             <record>\n  \
+               <attribute id=\"0x0004\">\n    \
+                 <sequence>\n      \
+                   <sequence>\n        \
+                     <uuid value=\"0x0100\" />\n      \
+                   </sequence>\n      \
+                   <sequence>\n        \
+                     <uuid value=\"0x0003\" />\n        \
+                     <uint8 value=\"0x01\" />\n      \
+                   </sequence>\n    \
+                 </sequence>\n  \
+               </attribute>\n  \
+               <attribute id=\"0x0100\">\n    \
+                 <text value=\"a&lt;b\" />\n  \
+               </attribute>\n\
+             </record>\n"
+        );
+    }
+
+    #[test]
+    fn to_xml_128() {
+        let mut out = String::new();
+        DataElement::U128(0x0102).write_xml(&mut out, 0);
+        DataElement::I128(-1).write_xml(&mut out, 2);
+        assert_eq!(
+            out,
+            "<uint128 value=\"00000000000000000000000000000102\" />\n  \
+             <int128 value=\"ffffffffffffffffffffffffffffffff\" />\n"
+        );
+    }
+}
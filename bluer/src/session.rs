@@ -1,13 +1,15 @@
 //! Bluetooth session.
 
 use dbus::{
-    arg::Variant,
+    arg::{AppendAll, IterAppend, PropMap, RefArg, Variant},
+    channel::Channel,
     message::MatchRule,
     nonblock::{
         stdintf::org_freedesktop_dbus::{
-            ObjectManagerInterfacesAdded, ObjectManagerInterfacesRemoved, PropertiesPropertiesChanged,
+            ObjectManager, ObjectManagerInterfacesAdded, ObjectManagerInterfacesRemoved,
+            PropertiesPropertiesChanged,
         },
-        SyncConnection,
+        Proxy, SyncConnection,
     },
     strings::BusName,
     Message,
@@ -16,12 +18,13 @@ use dbus_crossroads::{Crossroads, IfaceToken};
 use dbus_tokio::connection;
 use futures::{
     channel::{mpsc, oneshot},
+    future::BoxFuture,
     lock::Mutex,
-    Future, SinkExt, Stream, StreamExt,
+    stream, Future, FutureExt, SinkExt, Stream, StreamExt,
 };
-use lazy_static::lazy_static;
 use std::{
     collections::{HashMap, HashSet},
+    fmt,
     fmt::{Debug, Formatter},
     sync::{Arc, Weak},
 };
@@ -32,35 +35,208 @@ use tokio::{
 
 use crate::{
     adapter,
-    adv::Advertisement,
+    adv::RegisteredAdvertisement,
+    adv_monitor::RegisteredMonitor,
     agent::{Agent, AgentHandle, RegisteredAgent},
-    all_dbus_objects, gatt, parent_path, Adapter, Error, ErrorKind, InternalErrorKind, Result, SERVICE_NAME,
+    battery::RegisteredBattery,
+    dbus_objects_with_interface, gatt,
+    media::RegisteredEndpoint,
+    network, parent_path, Adapter, Error, ErrorKind, InternalErrorKind, Result, DEFAULT_PUBLISH_PREFIX,
+    SERVICE_NAME, TIMEOUT,
 };
 
 #[cfg(feature = "rfcomm")]
 use crate::rfcomm::{profile::RegisteredProfile, Profile, ProfileHandle};
 
+/// Opens a connection to the D-Bus bus with the specified address.
+pub(crate) fn connect_bus(
+    address: &str,
+) -> std::result::Result<(connection::IOResource<SyncConnection>, Arc<SyncConnection>), dbus::Error> {
+    let mut channel = Channel::open_private(address)?;
+    channel.register()?;
+    connection::from_channel(channel)
+}
+
 /// Terminate TX and terminated RX for single session.
 type SingleSessionTerm = (Weak<oneshot::Sender<()>>, oneshot::Receiver<()>);
 
+/// Function that registers a published object with the Bluetooth daemon.
+pub(crate) type RegisterFn = Box<dyn Fn(Arc<SessionInner>) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// Identifies a registration with the Bluetooth daemon.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum RegistrationKey {
+    /// Object published at the path.
+    Object(dbus::Path<'static>),
+    /// Network server for the role, registered with the adapter at the path.
+    ///
+    /// No object is published for a network server.
+    NetworkServer(dbus::Path<'static>, network::Role),
+}
+
+impl From<dbus::Path<'static>> for RegistrationKey {
+    fn from(path: dbus::Path<'static>) -> Self {
+        Self::Object(path)
+    }
+}
+
+impl fmt::Display for RegistrationKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Object(path) => write!(f, "{}", path),
+            Self::NetworkServer(adapter_path, role) => write!(f, "network server {} of {}", role, adapter_path),
+        }
+    }
+}
+
+/// Registration that is renewed when the Bluetooth daemon restarts.
+pub(crate) struct Registration {
+    /// Daemon object the registration is made with.
+    target: dbus::Path<'static>,
+    register_fn: RegisterFn,
+}
+
+/// D-Bus objects of the Bluetooth daemon with their interfaces and properties.
+pub(crate) type ObjectTree = HashMap<dbus::Path<'static>, HashMap<String, PropMap>>;
+
+/// Cache of the D-Bus objects of the Bluetooth daemon.
+///
+/// It is kept up to date by the event loop from the signals it receives.
+#[derive(Default)]
+pub(crate) struct ObjectCache {
+    objects: std::sync::Mutex<ObjectTree>,
+}
+
+impl ObjectCache {
+    /// Replaces the cached objects.
+    fn populate(&self, objects: ObjectTree) {
+        log::trace!("Populating object cache with {} objects", objects.len());
+        *self.objects.lock().unwrap() = objects;
+    }
+
+    /// Updates the cache from the specified signal.
+    fn update(&self, msg: &Message) {
+        use dbus::message::SignalArgs;
+        let mut objects = self.objects.lock().unwrap();
+
+        if let (
+            Some(object),
+            Some(PropertiesPropertiesChanged { interface_name, changed_properties, invalidated_properties }),
+        ) = (msg.path(), PropertiesPropertiesChanged::from_message(msg))
+        {
+            let object = object.into_static();
+            if let Some(props) = objects.get_mut(&object).and_then(|ifaces| ifaces.get_mut(&interface_name)) {
+                props.extend(changed_properties);
+                for name in invalidated_properties {
+                    props.remove(&name);
+                }
+            }
+        }
+
+        if let Some(ObjectManagerInterfacesAdded { object, interfaces }) =
+            ObjectManagerInterfacesAdded::from_message(msg)
+        {
+            objects.entry(object).or_default().extend(interfaces);
+        }
+
+        if let Some(ObjectManagerInterfacesRemoved { object, interfaces }) =
+            ObjectManagerInterfacesRemoved::from_message(msg)
+        {
+            if let Some(ifaces) = objects.get_mut(&object) {
+                for interface in interfaces {
+                    ifaces.remove(&interface);
+                }
+                if ifaces.is_empty() {
+                    objects.remove(&object);
+                }
+            }
+        }
+    }
+
+    /// Paths of all cached objects implementing the specified interface.
+    pub fn paths_with_interface(&self, interface: &str) -> Vec<dbus::Path<'static>> {
+        let objects = self.objects.lock().unwrap();
+        objects
+            .iter()
+            .filter(|(_, ifaces)| ifaces.contains_key(interface))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Gets a cached property value.
+    ///
+    /// Fails in the same way as querying a non-existent object or property over D-Bus.
+    pub fn property<R>(&self, path: &dbus::Path<'static>, interface: &str, name: &str) -> Result<Option<R>>
+    where
+        R: for<'b> dbus::arg::Get<'b> + 'static,
+    {
+        let objects = self.objects.lock().unwrap();
+        let props = match objects.get(path) {
+            Some(ifaces) => ifaces.get(interface),
+            None => return Err(Error::new(ErrorKind::NotFound)),
+        };
+        match props.and_then(|props| props.get(name)) {
+            Some(value) => {
+                // Convert the value by passing it through a D-Bus message.
+                let mut msg = Message::new_signal("/", "org.bluez.bluer", "Value").unwrap();
+                value.0.append(&mut IterAppend::new(&mut msg));
+                match msg.iter_init().get() {
+                    Some(value) => Ok(Some(value)),
+                    None => Err(Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue))),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Sets a cached property value.
+    pub fn set_property<T>(&self, path: &dbus::Path<'static>, interface: &str, name: &str, value: &T)
+    where
+        T: dbus::arg::Arg + dbus::arg::Append,
+    {
+        let mut objects = self.objects.lock().unwrap();
+        if let Some(props) = objects.get_mut(path).and_then(|ifaces| ifaces.get_mut(interface)) {
+            let mut msg = Message::new_signal("/", "org.bluez.bluer", "Value").unwrap();
+            msg.append_all((Variant(value),));
+            if let Ok(value) = msg.read1::<Variant<Box<dyn RefArg>>>() {
+                props.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
 /// Shared state of all objects in a Bluetooth session.
 pub(crate) struct SessionInner {
     pub connection: Arc<SyncConnection>,
+    pub service_name: String,
+    pub publish_prefix: String,
     pub crossroads: Mutex<Crossroads>,
-    pub le_advertisment_token: IfaceToken<Advertisement>,
+    pub le_advertisment_token: IfaceToken<Arc<RegisteredAdvertisement>>,
+    pub adv_monitor_token: IfaceToken<Arc<RegisteredMonitor>>,
     pub gatt_reg_service_token: IfaceToken<Arc<gatt::local::RegisteredService>>,
     pub gatt_reg_characteristic_token: IfaceToken<Arc<gatt::local::RegisteredCharacteristic>>,
     pub gatt_reg_characteristic_descriptor_token: IfaceToken<Arc<gatt::local::RegisteredDescriptor>>,
     pub gatt_profile_token: IfaceToken<gatt::local::Profile>,
     pub agent_token: IfaceToken<Arc<RegisteredAgent>>,
+    pub battery_provider_token: IfaceToken<Arc<RegisteredBattery>>,
+    pub media_endpoint_token: IfaceToken<Arc<RegisteredEndpoint>>,
     #[cfg(feature = "rfcomm")]
     pub profile_token: IfaceToken<Arc<RegisteredProfile>>,
     pub single_sessions: Mutex<HashMap<dbus::Path<'static>, SingleSessionTerm>>,
+    pub discovery_filters: Mutex<HashMap<dbus::Path<'static>, adapter::DiscoveryFilters>>,
+    pub registrations: Mutex<HashMap<RegistrationKey, Registration>>,
+    pub daemon_restart_subs: Mutex<Vec<mpsc::UnboundedSender<()>>>,
     pub event_sub_tx: mpsc::Sender<SubscriptionReq>,
-    dbus_task: JoinHandle<connection::IOResourceError>,
+    pub object_cache: Option<Arc<ObjectCache>>,
+    dbus_task: Option<JoinHandle<connection::IOResourceError>>,
 }
 
 impl SessionInner {
+    /// D-Bus path for an object published by this session below the specified path.
+    pub fn publish_path(&self, path: &str) -> String {
+        format!("{}/{}", &self.publish_prefix, path)
+    }
+
     pub async fn single_session(
         &self, path: &dbus::Path<'static>, start_fn: impl Future<Output = Result<()>>,
         stop_fn: impl Future<Output = ()> + Send + 'static,
@@ -104,14 +280,112 @@ impl SessionInner {
     pub async fn events(
         &self, path: dbus::Path<'static>, child_objects: bool,
     ) -> Result<mpsc::UnboundedReceiver<Event>> {
-        Event::subscribe(&mut self.event_sub_tx.clone(), path, child_objects).await
+        Event::subscribe(&mut self.event_sub_tx.clone(), path, child_objects, false).await
+    }
+
+    /// Subscribes to events for the specified path and property changes of its direct child objects.
+    pub async fn child_property_events(
+        &self, path: dbus::Path<'static>,
+    ) -> Result<mpsc::UnboundedReceiver<Event>> {
+        Event::subscribe(&mut self.event_sub_tx.clone(), path, false, true).await
+    }
+
+    /// Registers with the daemon object at `target` using the specified function.
+    ///
+    /// The registration is identified by `key`, usually the path of the registered object.
+    /// It is renewed when the Bluetooth daemon restarts until
+    /// [forget_registration](Self::forget_registration) is called.
+    pub async fn register(
+        self: &Arc<Self>, key: impl Into<RegistrationKey>, target: dbus::Path<'static>, register_fn: RegisterFn,
+    ) -> Result<()> {
+        register_fn(self.clone()).await?;
+        self.registrations.lock().await.insert(key.into(), Registration { target, register_fn });
+        Ok(())
+    }
+
+    /// Registers by calling the specified method of the daemon object at `target`
+    /// with the arguments returned by `args_fn`.
+    ///
+    /// See [register](Self::register) for details.
+    pub async fn register_with<A>(
+        self: &Arc<Self>, key: impl Into<RegistrationKey>, target: dbus::Path<'static>, interface: &'static str,
+        method: &'static str, args_fn: impl Fn() -> A + Send + Sync + 'static,
+    ) -> Result<()>
+    where
+        A: AppendAll + Send + 'static,
+    {
+        let reg_target = target.clone();
+        let register_fn: RegisterFn = Box::new(move |inner| {
+            let proxy =
+                Proxy::new(inner.service_name.clone(), reg_target.clone(), TIMEOUT, inner.connection.clone());
+            let args = args_fn();
+            async move {
+                proxy.method_call::<(), _, _, _>(interface, method, args).await?;
+                Ok(())
+            }
+            .boxed()
+        });
+        self.register(key, target, register_fn).await
+    }
+
+    /// Stops renewing the registration identified by `key`.
+    pub async fn forget_registration(&self, key: impl Into<RegistrationKey>) {
+        self.registrations.lock().await.remove(&key.into());
+    }
+
+    /// Notifies subscribers and renews all registrations after the Bluetooth daemon has been started.
+    async fn daemon_restarted(self: Arc<Self>) {
+        log::trace!("Bluetooth daemon {} has been started", &self.service_name);
+        self.daemon_restart_subs.lock().await.retain(|tx| tx.unbounded_send(()).is_ok());
+
+        let registrations = self.registrations.lock().await;
+        for (key, reg) in registrations.iter() {
+            tokio::spawn(self.clone().renew_registration(key.clone(), reg.target.clone()));
+        }
+    }
+
+    /// Renews the registration identified by `key`.
+    ///
+    /// If registration fails, because the daemon has not yet published the target
+    /// object, it is retried each time interfaces are added to the target object.
+    /// Since added objects are only reported to subscribers of their parent,
+    /// the parent of the target is watched.
+    async fn renew_registration(self: Arc<Self>, key: RegistrationKey, target: dbus::Path<'static>) {
+        let mut events = match self.events(parent_path(&target), true).await {
+            Ok(events) => events,
+            Err(_) => return,
+        };
+
+        loop {
+            let register = match self.registrations.lock().await.get(&key) {
+                Some(reg) => (reg.register_fn)(self.clone()),
+                None => return,
+            };
+            match register.await {
+                Ok(()) => {
+                    log::trace!("Renewed registration of {} with {}", &key, &target);
+                    return;
+                }
+                Err(err) => log::trace!("Renewing registration of {} with {} failed: {}", &key, &target, &err),
+            }
+
+            loop {
+                match events.next().await {
+                    Some(Event::ObjectAdded { object, .. }) if object == target => break,
+                    Some(_) => (),
+                    None => return,
+                }
+            }
+        }
     }
 }
 
 impl Drop for SessionInner {
     fn drop(&mut self) {
         // documentation for dbus_tokio::connection::IOResource indicates it is abortable
-        self.dbus_task.abort();
+        if let Some(dbus_task) = &self.dbus_task {
+            dbus_task.abort();
+        }
     }
 }
 
@@ -148,17 +422,192 @@ pub enum SessionEvent {
     AdapterAdded(String),
     /// Adapter removed.
     AdapterRemoved(String),
+    /// The Bluetooth daemon has been restarted.
+    ///
+    /// All advertisements, advertisement monitors, GATT applications, agents and profiles
+    /// that are still alive are registered again with the new daemon instance.
+    /// Adapters and devices must be enumerated again and device discovery must be
+    /// restarted, since the daemon lost its state.
+    DaemonRestarted,
+}
+
+/// D-Bus connection used by a session.
+#[derive(Clone)]
+enum SessionBus {
+    System,
+    Address(String),
+    Connection(Arc<SyncConnection>),
+}
+
+/// Builder for a [Bluetooth session](Session).
+///
+/// By default the session connects to the system D-Bus bus and
+/// talks to the Bluetooth daemon at the well-known name `org.bluez`.
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+#[derive(Clone)]
+pub struct SessionBuilder {
+    bus: SessionBus,
+    service_name: String,
+    publish_prefix: String,
+    object_cache: bool,
+}
+
+impl Debug for SessionBuilder {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let bus = match &self.bus {
+            SessionBus::System => "system".to_string(),
+            SessionBus::Address(address) => address.clone(),
+            SessionBus::Connection(connection) => connection.unique_name().to_string(),
+        };
+        f.debug_struct("SessionBuilder")
+            .field("bus", &bus)
+            .field("service_name", &self.service_name)
+            .field("publish_prefix", &self.publish_prefix)
+            .field("object_cache", &self.object_cache)
+            .finish()
+    }
+}
+
+impl Default for SessionBuilder {
+    fn default() -> Self {
+        Self {
+            bus: SessionBus::System,
+            service_name: SERVICE_NAME.to_string(),
+            publish_prefix: DEFAULT_PUBLISH_PREFIX.to_string(),
+            object_cache: false,
+        }
+    }
+}
+
+impl SessionBuilder {
+    /// Creates a new session builder using the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect to the D-Bus bus with the specified address instead of the system bus.
+    ///
+    /// The address must be a D-Bus server address, for example `unix:path=/run/dbus/test_bus`.
+    pub fn bus_address(mut self, address: impl Into<String>) -> Self {
+        self.bus = SessionBus::Address(address.into());
+        self
+    }
+
+    /// Use the specified existing D-Bus connection.
+    ///
+    /// The caller is responsible for driving the I/O resource of the connection.
+    /// All method calls received on the connection are handled by the session,
+    /// thus the connection should not be used to serve other D-Bus objects.
+    pub fn connection(mut self, connection: Arc<SyncConnection>) -> Self {
+        self.bus = SessionBus::Connection(connection);
+        self
+    }
+
+    /// D-Bus service name of the Bluetooth daemon.
+    ///
+    /// Defaults to `org.bluez`.
+    pub fn service_name(mut self, service_name: impl Into<String>) -> Self {
+        self.service_name = service_name.into();
+        self
+    }
+
+    /// D-Bus object path prefix for objects published by the session,
+    /// such as advertisements, agents, profiles and GATT applications.
+    ///
+    /// Defaults to `/org/bluez/bluer`.
+    pub fn publish_prefix(mut self, publish_prefix: impl Into<String>) -> Self {
+        self.publish_prefix = publish_prefix.into();
+        self
+    }
+
+    /// Keep a session-wide cache of all objects of the Bluetooth daemon.
+    ///
+    /// When enabled, enumerating adapters, devices and GATT attributes as well as
+    /// reading properties is served from the cache instead of querying the
+    /// Bluetooth daemon over D-Bus.
+    /// The cache is kept up to date from the change signals sent by the daemon.
+    /// This greatly reduces the CPU usage when many devices are present,
+    /// at the expense of holding all their properties in memory.
+    ///
+    /// Disabled by default.
+    pub fn object_cache(mut self, object_cache: bool) -> Self {
+        self.object_cache = object_cache;
+        self
+    }
+
+    /// Connects to D-Bus and creates the Bluetooth session.
+    pub async fn build(self) -> Result<Session> {
+        let Self { bus, service_name, publish_prefix, object_cache } = self;
+
+        if BusName::new(&*service_name).is_err() {
+            return Err(Error {
+                kind: ErrorKind::InvalidArguments,
+                message: format!("invalid D-Bus service name: {}", &service_name),
+            });
+        }
+        let publish_prefix = publish_prefix.trim_end_matches('/').to_string();
+        if dbus::Path::new(format!("{}/x", &publish_prefix)).is_err() {
+            return Err(Error {
+                kind: ErrorKind::InvalidArguments,
+                message: format!("invalid D-Bus object path prefix: {}", &publish_prefix),
+            });
+        }
+
+        let (connection, dbus_task) = match bus {
+            SessionBus::System => {
+                let (resource, connection) = spawn_blocking(connection::new_system_sync).await??;
+                (connection, Some(tokio::spawn(resource)))
+            }
+            SessionBus::Address(address) => {
+                let (resource, connection) = spawn_blocking(move || connect_bus(&address)).await??;
+                (connection, Some(tokio::spawn(resource)))
+            }
+            SessionBus::Connection(connection) => (connection, None),
+        };
+        if dbus_task.is_some() {
+            log::trace!("Connected to D-Bus with unique name {}", &connection.unique_name());
+        }
+
+        let object_cache = if object_cache { Some(Arc::new(ObjectCache::default())) } else { None };
+        Session::from_connection(connection, dbus_task, service_name, publish_prefix, object_cache).await
+    }
 }
 
 impl Session {
     /// Create a new Bluetooth session.
     ///
     /// This establishes a connection to the system Bluetooth daemon over D-Bus.
+    /// Use [SessionBuilder] for more control over the connection.
     pub async fn new() -> Result<Self> {
-        let (resource, connection) = spawn_blocking(connection::new_system_sync).await??;
-        let dbus_task = tokio::spawn(resource);
-        log::trace!("Connected to D-Bus with unique name {}", &connection.unique_name());
+        SessionBuilder::new().build().await
+    }
 
+    /// Create a new Bluetooth session on the D-Bus bus with the specified address.
+    ///
+    /// The address must be a D-Bus server address, for example `unix:path=/run/dbus/test_bus`.
+    /// This is useful for connecting to a [mock Bluetooth daemon](crate::mock) on a private bus.
+    pub async fn new_on_bus(address: &str) -> Result<Self> {
+        SessionBuilder::new().bus_address(address).build().await
+    }
+
+    /// Create a new Bluetooth session using the specified D-Bus connection.
+    ///
+    /// The caller is responsible for driving the I/O resource of the connection.
+    /// All method calls received on the connection are handled by the session,
+    /// thus the connection should not be used to serve other D-Bus objects.
+    pub async fn with_connection(connection: Arc<SyncConnection>) -> Result<Self> {
+        SessionBuilder::new().connection(connection).build().await
+    }
+
+    /// Creates a builder for a Bluetooth session.
+    pub fn builder() -> SessionBuilder {
+        SessionBuilder::new()
+    }
+
+    async fn from_connection(
+        connection: Arc<SyncConnection>, dbus_task: Option<JoinHandle<connection::IOResourceError>>,
+        service_name: String, publish_prefix: String, object_cache: Option<Arc<ObjectCache>>,
+    ) -> Result<Self> {
         let mut crossroads = Crossroads::new();
         crossroads.set_async_support(Some((
             connection.clone(),
@@ -167,7 +616,8 @@ impl Session {
             }),
         )));
 
-        let le_advertisment_token = Advertisement::register_interface(&mut crossroads);
+        let le_advertisment_token = RegisteredAdvertisement::register_interface(&mut crossroads);
+        let adv_monitor_token = RegisteredMonitor::register_interface(&mut crossroads);
         let gatt_service_token = gatt::local::RegisteredService::register_interface(&mut crossroads);
         let gatt_reg_characteristic_token =
             gatt::local::RegisteredCharacteristic::register_interface(&mut crossroads);
@@ -175,28 +625,61 @@ impl Session {
             gatt::local::RegisteredDescriptor::register_interface(&mut crossroads);
         let gatt_profile_token = gatt::local::Profile::register_interface(&mut crossroads);
         let agent_token = RegisteredAgent::register_interface(&mut crossroads);
+        let battery_provider_token = RegisteredBattery::register_interface(&mut crossroads);
+        let media_endpoint_token = RegisteredEndpoint::register_interface(&mut crossroads);
         #[cfg(feature = "rfcomm")]
         let profile_token = RegisteredProfile::register_interface(&mut crossroads);
 
         let (event_sub_tx, event_sub_rx) = mpsc::channel(1);
-        Event::handle_connection(connection.clone(), event_sub_rx).await?;
+        Event::handle_connection(connection.clone(), &service_name, object_cache.clone(), event_sub_rx).await?;
 
         let inner = Arc::new(SessionInner {
             connection: connection.clone(),
+            service_name,
+            publish_prefix,
             crossroads: Mutex::new(crossroads),
             le_advertisment_token,
+            adv_monitor_token,
             gatt_reg_service_token: gatt_service_token,
             gatt_reg_characteristic_token,
             gatt_reg_characteristic_descriptor_token,
             gatt_profile_token,
             agent_token,
+            battery_provider_token,
+            media_endpoint_token,
             #[cfg(feature = "rfcomm")]
             profile_token,
             single_sessions: Mutex::new(HashMap::new()),
+            discovery_filters: Mutex::new(HashMap::new()),
+            registrations: Mutex::new(HashMap::new()),
+            daemon_restart_subs: Mutex::new(Vec::new()),
             event_sub_tx,
+            object_cache,
             dbus_task,
         });
 
+        let noc_rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+            .with_sender("org.freedesktop.DBus")
+            .with_path("/org/freedesktop/DBus");
+        let noc_callback = connection.add_match(noc_rule).await?;
+        let noc_inner = Arc::downgrade(&inner);
+        tokio::spawn(async move {
+            let (_noc_callback, mut noc_stream) = noc_callback.msg_stream();
+            while let Some(msg) = noc_stream.next().await {
+                let noc_inner = match noc_inner.upgrade() {
+                    Some(inner) => inner,
+                    None => return,
+                };
+                let started = match msg.read3::<&str, &str, &str>() {
+                    Ok((name, _old_owner, new_owner)) => name == noc_inner.service_name && !new_owner.is_empty(),
+                    Err(_) => false,
+                };
+                if started {
+                    noc_inner.daemon_restarted().await;
+                }
+            }
+        });
+
         let mc_callback = connection.add_match(MatchRule::new_method_call()).await?;
         let mc_inner = Arc::downgrade(&inner);
         tokio::spawn(async move {
@@ -237,12 +720,9 @@ impl Session {
     /// Enumerate connected Bluetooth adapters and return their names.
     pub async fn adapter_names(&self) -> Result<Vec<String>> {
         let mut names = Vec::new();
-        for (path, interfaces) in all_dbus_objects(&self.inner.connection).await? {
-            match Adapter::parse_dbus_path(&path) {
-                Some(name) if interfaces.contains_key(adapter::INTERFACE) => {
-                    names.push(name.to_string());
-                }
-                _ => (),
+        for path in dbus_objects_with_interface(&self.inner, adapter::INTERFACE).await? {
+            if let Some(name) = Adapter::parse_dbus_path(&path) {
+                names.push(name.to_string());
             }
         }
         Ok(names)
@@ -288,8 +768,10 @@ impl Session {
         reg_profile.register(self.inner.clone(), profile, req_rx).await
     }
 
-    /// Stream adapter added and removed events.
+    /// Stream adapter added and removed events as well as Bluetooth daemon restarts.
     pub async fn events(&self) -> Result<impl Stream<Item = SessionEvent>> {
+        let (restart_tx, restart_rx) = mpsc::unbounded();
+        self.inner.daemon_restart_subs.lock().await.push(restart_tx);
         let obj_events = self.inner.events(adapter::PATH.into(), true).await?;
         let events = obj_events.filter_map(|evt| async move {
             match evt {
@@ -306,7 +788,7 @@ impl Session {
                 _ => None,
             }
         });
-        Ok(events)
+        Ok(stream::select(events, restart_rx.map(|()| SessionEvent::DaemonRestarted)))
     }
 }
 
@@ -343,6 +825,7 @@ impl Clone for Event {
 pub(crate) struct SubscriptionReq {
     path: dbus::Path<'static>,
     child_objects: bool,
+    child_properties: bool,
     tx: mpsc::UnboundedSender<Event>,
     ready_tx: oneshot::Sender<()>,
 }
@@ -350,13 +833,11 @@ pub(crate) struct SubscriptionReq {
 impl Event {
     /// Spawns a task that handles events for the specified connection.
     pub(crate) async fn handle_connection(
-        connection: Arc<SyncConnection>, mut sub_rx: mpsc::Receiver<SubscriptionReq>,
+        connection: Arc<SyncConnection>, service_name: &str, object_cache: Option<Arc<ObjectCache>>,
+        mut sub_rx: mpsc::Receiver<SubscriptionReq>,
     ) -> Result<()> {
         use dbus::message::SignalArgs;
-        lazy_static! {
-            static ref SERVICE_NAME_BUS: BusName<'static> = BusName::new(SERVICE_NAME).unwrap();
-            static ref SERVICE_NAME_REF: Option<&'static BusName<'static>> = Some(&SERVICE_NAME_BUS);
-        }
+        let service_name = BusName::new(service_name).map_err(|_| Error::new(ErrorKind::InvalidArguments))?;
 
         let (msg_tx, mut msg_rx) = mpsc::unbounded();
         let handle_msg = move |msg: Message| {
@@ -364,20 +845,38 @@ impl Event {
             true
         };
 
-        let rule_add = ObjectManagerInterfacesAdded::match_rule(*SERVICE_NAME_REF, None);
+        let rule_add = ObjectManagerInterfacesAdded::match_rule(Some(&service_name), None).static_clone();
         let msg_match_add = connection.add_match(rule_add).await?.msg_cb(handle_msg.clone());
 
-        let rule_removed = ObjectManagerInterfacesRemoved::match_rule(*SERVICE_NAME_REF, None);
+        let rule_removed = ObjectManagerInterfacesRemoved::match_rule(Some(&service_name), None).static_clone();
         let msg_match_removed = connection.add_match(rule_removed).await?.msg_cb(handle_msg.clone());
 
-        let rule_prop = PropertiesPropertiesChanged::match_rule(*SERVICE_NAME_REF, None);
+        let rule_prop = PropertiesPropertiesChanged::match_rule(Some(&service_name), None).static_clone();
         let msg_match_prop = connection.add_match(rule_prop).await?.msg_cb(handle_msg.clone());
 
+        // The cache is populated after the match rules have been added, so that no change is missed.
+        // Signals received before the object tree are already reflected in it and can be applied again.
+        let msg_match_owner = match &object_cache {
+            Some(object_cache) => {
+                let rule_owner = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+                    .with_sender("org.freedesktop.DBus")
+                    .with_path("/org/freedesktop/DBus");
+                let msg_match_owner = connection.add_match(rule_owner).await?.msg_cb(handle_msg.clone());
+                match Self::fetch_objects(&connection, &service_name).await {
+                    Ok(objects) => object_cache.populate(objects),
+                    Err(err) => log::trace!("Cannot populate object cache: {}", &err),
+                }
+                Some(msg_match_owner)
+            }
+            None => None,
+        };
+
         tokio::spawn(async move {
             log::trace!("Starting event loop for {}", &connection.unique_name());
 
             struct Subscription {
                 child_objects: bool,
+                child_properties: bool,
                 tx: mpsc::UnboundedSender<Event>,
             }
             let mut subs: HashMap<String, Vec<Subscription>> = HashMap::new();
@@ -387,43 +886,111 @@ impl Event {
                     msg_opt = msg_rx.next() => {
                         match msg_opt {
                             Some(msg) => {
+                                // Update object cache.
+                                if let Some(object_cache) = &object_cache {
+                                    let restarted = match msg.read3::<&str, &str, &str>() {
+                                        Ok((name, _old_owner, new_owner)) if msg.member().as_deref() == Some("NameOwnerChanged") => {
+                                            (name == &*service_name).then(|| !new_owner.is_empty())
+                                        }
+                                        _ => None,
+                                    };
+                                    match restarted {
+                                        Some(true) => match Self::fetch_objects(&connection, &service_name).await {
+                                            Ok(objects) => object_cache.populate(objects),
+                                            Err(err) => {
+                                                log::trace!("Cannot populate object cache: {}", &err);
+                                                object_cache.populate(ObjectTree::new());
+                                            }
+                                        },
+                                        Some(false) => object_cache.populate(ObjectTree::new()),
+                                        None => object_cache.update(&msg),
+                                    }
+                                }
+
                                 // Properties changed.
                                 if let (Some(object), Some(PropertiesPropertiesChanged { interface_name, changed_properties, .. })) =
                                     (msg.path(), PropertiesPropertiesChanged::from_message(&msg))
                                 {
+                                    let evt = Self::PropertiesChanged {
+                                        object: object.clone().into_static(),
+                                        interface: interface_name,
+                                        changed: changed_properties,
+                                    };
+
                                     // Check for direct path match for PropertiesChanged event.
                                     if let Some(path_subs) = subs.get_mut(&*object) {
-                                        let evt = Self::PropertiesChanged {
-                                            object: object.clone().into_static(),
-                                            interface: interface_name,
-                                            changed: changed_properties,
-                                        };
                                         log::trace!("Event: {:?}", &evt);
                                         path_subs.retain(|sub| sub.tx.unbounded_send(evt.clone()).is_ok());
                                         if path_subs.is_empty() {
                                             subs.remove(&*object);
                                         }
                                     }
+
+                                    // Check for parent path match for PropertiesChanged event.
+                                    let parent = parent_path(&object);
+                                    if let Some(parent_subs) = subs.get_mut(&*parent) {
+                                        if parent_subs.iter().any(|sub| sub.child_properties) {
+                                            log::trace!("Event for parent: {:?}", &evt);
+                                            parent_subs.retain(|sub| {
+                                                if sub.child_properties {
+                                                    sub.tx.unbounded_send(evt.clone()).is_ok()
+                                                } else {
+                                                    true
+                                                }
+                                            });
+                                            if parent_subs.is_empty() {
+                                                subs.remove(&*parent);
+                                            }
+                                        }
+                                    }
                                 }
 
                                 // Objects added.
                                 if let Some(ObjectManagerInterfacesAdded { object, interfaces }) =
                                     ObjectManagerInterfacesAdded::from_message(&msg)
                                 {
+                                    // Properties of interfaces added to an existing object are delivered
+                                    // as PropertiesChanged event.
+                                    if let Some(path_subs) = subs.get_mut(&*object) {
+                                        let prop_evts: Vec<_> = interfaces.iter().map(|(interface, props)| Self::PropertiesChanged {
+                                            object: object.clone(),
+                                            interface: interface.clone(),
+                                            changed: props.iter().map(|(k, v)| (k.clone(), Variant(v.0.box_clone()))).collect(),
+                                        }).collect();
+                                        log::trace!("Events: {:?}", &prop_evts);
+                                        path_subs.retain(|sub| prop_evts.iter().all(|evt| sub.tx.unbounded_send(evt.clone()).is_ok()));
+                                        if path_subs.is_empty() {
+                                            subs.remove(&*object);
+                                        }
+                                    }
+
                                     // Check for parent path match for ObjectAdded event.
                                     let parent = parent_path(&object);
                                     if let Some(parent_subs) = subs.get_mut(&*parent) {
+                                        // Initial properties of added object are delivered as PropertiesChanged event.
+                                        let prop_evts: Vec<_> = if parent_subs.iter().any(|sub| sub.child_properties) {
+                                            interfaces.iter().map(|(interface, props)| Self::PropertiesChanged {
+                                                object: object.clone(),
+                                                interface: interface.clone(),
+                                                changed: props.iter().map(|(k, v)| (k.clone(), Variant(v.0.box_clone()))).collect(),
+                                            }).collect()
+                                        } else {
+                                            Vec::new()
+                                        };
                                         let evt = Self::ObjectAdded {
                                             object,
                                             interfaces: interfaces.into_keys().collect(),
                                         };
                                         log::trace!("Event: {:?}", &evt);
                                         parent_subs.retain(|sub| {
+                                            let mut ok = true;
                                             if sub.child_objects {
-                                                sub.tx.unbounded_send(evt.clone()).is_ok()
-                                            } else {
-                                                true
+                                                ok &= sub.tx.unbounded_send(evt.clone()).is_ok();
                                             }
+                                            if sub.child_properties {
+                                                ok &= prop_evts.iter().all(|evt| sub.tx.unbounded_send(evt.clone()).is_ok());
+                                            }
+                                            ok
                                         });
                                         if parent_subs.is_empty() {
                                             subs.remove(&*parent);
@@ -464,12 +1031,13 @@ impl Event {
                     },
                     sub_opt = sub_rx.next() => {
                         match sub_opt {
-                            Some(SubscriptionReq { path, child_objects, tx, ready_tx }) => {
-                                log::trace!("Adding event subscription for {} with child_objects={:?}", &path, &child_objects);
+                            Some(SubscriptionReq { path, child_objects, child_properties, tx, ready_tx }) => {
+                                log::trace!("Adding event subscription for {} with child_objects={:?} and child_properties={:?}",
+                                    &path, &child_objects, &child_properties);
                                 let _ = ready_tx.send(());
                                 let path_subs = subs.entry(path.to_string()).or_default();
                                 path_subs.push(Subscription {
-                                    child_objects, tx
+                                    child_objects, child_properties, tx
                                 });
                             }
                             None => break,
@@ -481,26 +1049,148 @@ impl Event {
             let _ = connection.remove_match(msg_match_add.token()).await;
             let _ = connection.remove_match(msg_match_removed.token()).await;
             let _ = connection.remove_match(msg_match_prop.token()).await;
+            if let Some(msg_match_owner) = msg_match_owner {
+                let _ = connection.remove_match(msg_match_owner.token()).await;
+            }
             log::trace!("Terminated event loop for {}", &connection.unique_name());
         });
 
         Ok(())
     }
 
+    /// Fetches all D-Bus objects of the Bluetooth daemon.
+    async fn fetch_objects(connection: &SyncConnection, service_name: &BusName<'_>) -> Result<ObjectTree> {
+        let proxy = Proxy::new(service_name, "/", TIMEOUT, connection);
+        Ok(proxy.get_managed_objects().await?)
+    }
+
     /// Subscribe to D-Bus events for specified path.
     ///
     /// If `child_objects` is [true] events about *direct* child objects being added and removed
     /// will also be delivered.
+    ///
+    /// If `child_properties` is [true] property changes of *direct* child objects will also
+    /// be delivered.
+    /// The properties of an added child object are delivered as a property change event.
     pub(crate) async fn subscribe(
         sub_tx: &mut mpsc::Sender<SubscriptionReq>, path: dbus::Path<'static>, child_objects: bool,
+        child_properties: bool,
     ) -> Result<mpsc::UnboundedReceiver<Event>> {
         let (tx, rx) = mpsc::unbounded();
         let (ready_tx, ready_rx) = oneshot::channel();
         sub_tx
-            .send(SubscriptionReq { path, child_objects, tx, ready_tx })
+            .send(SubscriptionReq { path, child_objects, child_properties, tx, ready_tx })
             .await
             .map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?;
         ready_rx.await.map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?;
         Ok(rx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dbus::{
+        arg::{PropMap, RefArg, Variant},
+        message::SignalArgs,
+        nonblock::stdintf::org_freedesktop_dbus::{
+            ObjectManagerInterfacesAdded, ObjectManagerInterfacesRemoved, PropertiesPropertiesChanged,
+        },
+        Path,
+    };
+    use std::collections::HashMap;
+
+    use super::{ObjectCache, ObjectTree};
+    use crate::ErrorKind;
+
+    const INTERFACE: &str = "org.bluez.Device1";
+
+    fn path() -> Path<'static> {
+        Path::from("/org/bluez/hci0/dev_00_11_22_33_44_55")
+    }
+
+    fn props(entries: Vec<(&str, Box<dyn RefArg>)>) -> PropMap {
+        entries.into_iter().map(|(name, value)| (name.to_string(), Variant(value))).collect()
+    }
+
+    fn cache() -> ObjectCache {
+        let cache = ObjectCache::default();
+        let mut objects = ObjectTree::new();
+        objects.insert(
+            path(),
+            [(
+                INTERFACE.to_string(),
+                props(vec![("RSSI", Box::new(-60i16)), ("Name", Box::new("dev".to_string()))]),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        cache.populate(objects);
+        cache
+    }
+
+    #[test]
+    fn property() {
+        let cache = cache();
+        assert_eq!(cache.property::<i16>(&path(), INTERFACE, "RSSI").unwrap(), Some(-60));
+        assert_eq!(cache.property::<String>(&path(), INTERFACE, "Name").unwrap(), Some("dev".to_string()));
+        assert_eq!(cache.property::<i16>(&path(), INTERFACE, "TxPower").unwrap(), None);
+        assert_eq!(cache.property::<i16>(&path(), "org.bluez.Battery1", "Percentage").unwrap(), None);
+        assert_eq!(
+            cache.property::<i16>(&Path::from("/org/bluez/hci1"), INTERFACE, "RSSI").unwrap_err().kind,
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            cache.property::<String>(&path(), INTERFACE, "RSSI").unwrap_err().kind,
+            ErrorKind::Internal(crate::InternalErrorKind::InvalidValue)
+        );
+        assert_eq!(cache.paths_with_interface(INTERFACE), vec![path()]);
+        assert!(cache.paths_with_interface("org.bluez.Adapter1").is_empty());
+    }
+
+    #[test]
+    fn properties_changed() {
+        let cache = cache();
+        let ppc = PropertiesPropertiesChanged {
+            interface_name: INTERFACE.to_string(),
+            changed_properties: props(vec![("RSSI", Box::new(-70i16))]),
+            invalidated_properties: vec!["Name".to_string()],
+        };
+        cache.update(&ppc.to_emit_message(&path()));
+        assert_eq!(cache.property::<i16>(&path(), INTERFACE, "RSSI").unwrap(), Some(-70));
+        assert_eq!(cache.property::<String>(&path(), INTERFACE, "Name").unwrap(), None);
+    }
+
+    #[test]
+    fn interfaces_added_and_removed() {
+        let cache = cache();
+        let adapter_path = Path::from("/org/bluez/hci0");
+        let added = ObjectManagerInterfacesAdded {
+            object: adapter_path.clone(),
+            interfaces: [("org.bluez.Adapter1".to_string(), props(vec![("Powered", Box::new(true))]))]
+                .into_iter()
+                .collect::<HashMap<_, _>>(),
+        };
+        cache.update(&added.to_emit_message(&Path::from("/")));
+        assert_eq!(cache.property::<bool>(&adapter_path, "org.bluez.Adapter1", "Powered").unwrap(), Some(true));
+
+        let removed = ObjectManagerInterfacesRemoved {
+            object: adapter_path.clone(),
+            interfaces: vec!["org.bluez.Adapter1".to_string()],
+        };
+        cache.update(&removed.to_emit_message(&Path::from("/")));
+        assert_eq!(
+            cache.property::<bool>(&adapter_path, "org.bluez.Adapter1", "Powered").unwrap_err().kind,
+            ErrorKind::NotFound
+        );
+        assert_eq!(cache.property::<i16>(&path(), INTERFACE, "RSSI").unwrap(), Some(-60));
+    }
+
+    #[test]
+    fn set_property() {
+        let cache = cache();
+        cache.set_property(&path(), INTERFACE, "RSSI", &-50i16);
+        assert_eq!(cache.property::<i16>(&path(), INTERFACE, "RSSI").unwrap(), Some(-50));
+        cache.set_property(&Path::from("/org/bluez/hci1"), INTERFACE, "RSSI", &-50i16);
+        assert_eq!(cache.paths_with_interface(INTERFACE), vec![path()]);
+    }
+}
@@ -49,10 +49,26 @@ pub trait SysSockAddr: Sized {
     type SysSockAddr: Sized + 'static;
 
     /// Convert to system socket address.
-    fn into_sys_sock_addr(self) -> Self::SysSockAddr;
+    ///
+    /// Fails if the address cannot be represented as a system socket address.
+    fn into_sys_sock_addr(self) -> Result<Self::SysSockAddr>;
 
     /// Convert from system socket address.
     fn try_from_sys_sock_addr(addr: Self::SysSockAddr) -> Result<Self>;
+
+    /// Length of the system socket address passed to the kernel.
+    ///
+    /// Override this if only a prefix of the system socket address is used.
+    fn sys_sock_addr_len(&self) -> usize {
+        size_of::<Self::SysSockAddr>()
+    }
+
+    /// Whether the length of a system socket address returned by the kernel is valid.
+    ///
+    /// Bytes of the system socket address beyond the returned length are zero.
+    fn is_valid_sys_sock_addr_len(len: usize) -> bool {
+        len == size_of::<Self::SysSockAddr>()
+    }
 }
 
 /// Creates a socket of the specified type and returns its file descriptor.
@@ -71,15 +87,9 @@ pub fn bind<SA>(socket: &OwnedFd, sa: SA) -> Result<()>
 where
     SA: SysSockAddr,
 {
-    let addr: SA::SysSockAddr = sa.into_sys_sock_addr();
-    if unsafe {
-        libc::bind(
-            socket.as_raw_fd(),
-            &addr as *const _ as *const sockaddr,
-            size_of::<SA::SysSockAddr>() as socklen_t,
-        )
-    } == 0
-    {
+    let len = sa.sys_sock_addr_len();
+    let addr: SA::SysSockAddr = sa.into_sys_sock_addr()?;
+    if unsafe { libc::bind(socket.as_raw_fd(), &addr as *const _ as *const sockaddr, len as socklen_t) } == 0 {
         Ok(())
     } else {
         Err(Error::last_os_error())
@@ -91,14 +101,14 @@ pub fn getsockname<SA>(socket: &OwnedFd) -> Result<SA>
 where
     SA: SysSockAddr,
 {
-    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::uninit();
+    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::zeroed();
     let mut length = size_of::<SA::SysSockAddr>() as socklen_t;
 
     if unsafe { libc::getsockname(socket.as_raw_fd(), saddr.as_mut_ptr() as *mut _, &mut length) } == -1 {
         return Err(Error::last_os_error());
     };
 
-    if length != size_of::<SA::SysSockAddr>() as socklen_t {
+    if !SA::is_valid_sys_sock_addr_len(length as _) {
         return Err(Error::new(ErrorKind::InvalidInput, "invalid sockaddr length from getsockname"));
     }
     let saddr = unsafe { saddr.assume_init() };
@@ -106,18 +116,19 @@ where
 }
 
 /// Gets the address the socket is connected to.
+#[cfg(any(feature = "iso", feature = "l2cap", feature = "rfcomm", feature = "sco"))]
 pub fn getpeername<SA>(socket: &OwnedFd) -> Result<SA>
 where
     SA: SysSockAddr,
 {
-    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::uninit();
+    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::zeroed();
     let mut length = size_of::<SA::SysSockAddr>() as socklen_t;
 
     if unsafe { libc::getpeername(socket.as_raw_fd(), saddr.as_mut_ptr() as *mut _, &mut length) } == -1 {
         return Err(Error::last_os_error());
     };
 
-    if length != size_of::<SA::SysSockAddr>() as socklen_t {
+    if !SA::is_valid_sys_sock_addr_len(length as _) {
         return Err(Error::new(ErrorKind::InvalidInput, "invalid sockaddr length from getpeername"));
     }
     let saddr = unsafe { saddr.assume_init() };
@@ -125,6 +136,7 @@ where
 }
 
 /// Puts socket in listen mode.
+#[cfg(any(feature = "iso", feature = "l2cap", feature = "rfcomm", feature = "sco"))]
 pub fn listen(socket: &OwnedFd, backlog: i32) -> Result<()> {
     if unsafe { libc::listen(socket.as_raw_fd(), backlog) } == 0 {
         Ok(())
@@ -140,7 +152,7 @@ pub fn accept<SA>(socket: &OwnedFd) -> Result<(OwnedFd, SA)>
 where
     SA: SysSockAddr,
 {
-    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::uninit();
+    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::zeroed();
     let mut length = size_of::<SA::SysSockAddr>() as socklen_t;
 
     let fd = match unsafe {
@@ -150,7 +162,7 @@ where
         fd => unsafe { OwnedFd::new(fd) },
     };
 
-    if length != size_of::<SA::SysSockAddr>() as socklen_t {
+    if !SA::is_valid_sys_sock_addr_len(length as _) {
         return Err(Error::new(ErrorKind::InvalidInput, "invalid sockaddr length"));
     }
     let saddr = unsafe { saddr.assume_init() };
@@ -164,15 +176,9 @@ pub fn connect<SA>(socket: &OwnedFd, sa: SA) -> Result<()>
 where
     SA: SysSockAddr,
 {
-    let addr: SA::SysSockAddr = sa.into_sys_sock_addr();
-    if unsafe {
-        libc::connect(
-            socket.as_raw_fd(),
-            &addr as *const _ as *const sockaddr,
-            size_of::<SA::SysSockAddr>() as socklen_t,
-        )
-    } == 0
-    {
+    let len = sa.sys_sock_addr_len();
+    let addr: SA::SysSockAddr = sa.into_sys_sock_addr()?;
+    if unsafe { libc::connect(socket.as_raw_fd(), &addr as *const _ as *const sockaddr, len as socklen_t) } == 0 {
         Ok(())
     } else {
         Err(Error::last_os_error())
@@ -192,7 +198,8 @@ pub fn sendto<SA>(socket: &OwnedFd, buf: &[u8], flags: c_int, sa: SA) -> Result<
 where
     SA: SysSockAddr,
 {
-    let addr: SA::SysSockAddr = sa.into_sys_sock_addr();
+    let len = sa.sys_sock_addr_len();
+    let addr: SA::SysSockAddr = sa.into_sys_sock_addr()?;
     match unsafe {
         libc::sendto(
             socket.as_raw_fd(),
@@ -200,7 +207,7 @@ where
             buf.len(),
             flags,
             &addr as *const _ as *const sockaddr,
-            size_of::<SA::SysSockAddr>() as socklen_t,
+            len as socklen_t,
         )
     } {
         -1 => Err(Error::last_os_error()),
@@ -230,7 +237,7 @@ where
     SA: SysSockAddr,
 {
     let unfilled = unsafe { buf.unfilled_mut() };
-    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::uninit();
+    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::zeroed();
     let mut length = size_of::<SA::SysSockAddr>() as socklen_t;
     match unsafe {
         libc::recvfrom(
@@ -250,7 +257,7 @@ where
             }
             buf.advance(n);
 
-            if length != size_of::<SA::SysSockAddr>() as socklen_t {
+            if !SA::is_valid_sys_sock_addr_len(length as _) {
                 return Err(Error::new(ErrorKind::InvalidInput, "invalid sockaddr length"));
             }
             let saddr = unsafe { saddr.assume_init() };
@@ -261,7 +268,53 @@ where
     }
 }
 
+/// Receive from socket into buffer together with a control message
+/// of the specified level and type.
+#[cfg(any(all(feature = "hci", feature = "capture"), feature = "sco"))]
+pub fn recv_with_cmsg<T>(
+    socket: &OwnedFd, buf: &mut ReadBuf, flags: c_int, cmsg_level: c_int, cmsg_type: c_int,
+) -> Result<(usize, Option<T>)> {
+    let unfilled = unsafe { buf.unfilled_mut() };
+    let mut iov = libc::iovec { iov_base: unfilled.as_mut_ptr() as *mut _, iov_len: unfilled.len() };
+
+    let control_len = unsafe { libc::CMSG_SPACE(size_of::<T>() as _) } as usize;
+    let mut control = vec![0u64; (control_len + size_of::<u64>() - 1) / size_of::<u64>()];
+
+    let mut msg: libc::msghdr = unsafe { MaybeUninit::zeroed().assume_init() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut _;
+    msg.msg_controllen = control_len as _;
+
+    match unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, flags) } {
+        -1 => Err(Error::last_os_error()),
+        n => {
+            let n = n as usize;
+            unsafe {
+                buf.assume_init(n);
+            }
+            buf.advance(n);
+
+            let mut value = None;
+            let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+            while !cmsg.is_null() {
+                let hdr = unsafe { &*cmsg };
+                if hdr.cmsg_level == cmsg_level
+                    && hdr.cmsg_type == cmsg_type
+                    && hdr.cmsg_len as usize >= unsafe { libc::CMSG_LEN(size_of::<T>() as _) } as usize
+                {
+                    value = Some(unsafe { (libc::CMSG_DATA(cmsg) as *const T).read_unaligned() });
+                }
+                cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+            }
+
+            Ok((n, value))
+        }
+    }
+}
+
 /// Shut down part of a socket.
+#[cfg(any(feature = "iso", feature = "l2cap", feature = "rfcomm", feature = "sco"))]
 pub fn shutdown(socket: &OwnedFd, how: c_int) -> Result<()> {
     if unsafe { libc::shutdown(socket.as_raw_fd(), how) } == 0 {
         Ok(())
@@ -298,6 +351,7 @@ pub fn setsockopt<T>(socket: &OwnedFd, level: c_int, optname: i32, optval: &T) -
 }
 
 /// Perform an IOCTL that reads a single value.
+#[cfg(any(feature = "iso", feature = "l2cap", feature = "rfcomm", feature = "sco"))]
 pub fn ioctl_read<T>(socket: &OwnedFd, request: Ioctl) -> Result<T> {
     let mut value: MaybeUninit<T> = MaybeUninit::uninit();
     let ret = unsafe { libc::ioctl(socket.as_raw_fd(), request, value.as_mut_ptr()) };
@@ -321,6 +375,7 @@ pub fn ioctl_write<T>(socket: &OwnedFd, request: Ioctl, value: &T) -> Result<c_i
 /// Private socket implementation functions.
 macro_rules! sock_priv {
     () => {
+        #[allow(dead_code)]
         async fn accept_priv(&self) -> Result<(Self, SocketAddr)> {
             let (fd, sa) = loop {
                 let mut guard = self.fd.readable().await?;
@@ -334,6 +389,7 @@ macro_rules! sock_priv {
             Ok((socket, sa))
         }
 
+        #[allow(dead_code)]
         fn poll_accept_priv(&self, cx: &mut Context) -> Poll<Result<(Self, SocketAddr)>> {
             let (fd, sa) = loop {
                 let mut guard = ready!(self.fd.poll_read_ready(cx))?;
@@ -347,6 +403,7 @@ macro_rules! sock_priv {
             Poll::Ready(Ok((socket, sa)))
         }
 
+        #[allow(dead_code)]
         async fn connect_priv(&self, sa: SocketAddr) -> Result<()> {
             match sock::connect(self.fd.get_ref(), sa) {
                 Ok(()) => Ok(()),
@@ -392,22 +449,22 @@ macro_rules! sock_priv {
             }
         }
 
-        #[allow(dead_code)]
+        #[allow(dead_code, clippy::clone_on_copy)]
         async fn send_to_priv(&self, buf: &[u8], target: SocketAddr) -> Result<usize> {
             loop {
                 let mut guard = self.fd.writable().await?;
-                match guard.try_io(|inner| sock::sendto(inner.get_ref(), buf, 0, target)) {
+                match guard.try_io(|inner| sock::sendto(inner.get_ref(), buf, 0, target.clone())) {
                     Ok(result) => return result,
                     Err(_would_block) => continue,
                 }
             }
         }
 
-        #[allow(dead_code)]
+        #[allow(dead_code, clippy::clone_on_copy)]
         fn poll_send_to_priv(&self, cx: &mut Context, buf: &[u8], target: SocketAddr) -> Poll<Result<usize>> {
             loop {
                 let mut guard = ready!(self.fd.poll_write_ready(cx))?;
-                match guard.try_io(|inner| sock::sendto(inner.get_ref(), buf, 0, target)) {
+                match guard.try_io(|inner| sock::sendto(inner.get_ref(), buf, 0, target.clone())) {
                     Ok(result) => return Poll::Ready(result),
                     Err(_would_block) => continue,
                 }
@@ -458,7 +515,29 @@ macro_rules! sock_priv {
                 }
             }
         }
+    };
+}
 
+/// Private socket implementation function for sockets that can be shut down.
+#[cfg(any(feature = "iso", feature = "l2cap", feature = "rfcomm", feature = "sco"))]
+macro_rules! shutdown_sock_priv {
+    () => {
+        fn shutdown_priv(&self, how: Shutdown) -> Result<()> {
+            let how = match how {
+                Shutdown::Read => SHUT_RD,
+                Shutdown::Write => SHUT_WR,
+                Shutdown::Both => SHUT_RDWR,
+            };
+            sock::shutdown(self.fd.get_ref(), how)?;
+            Ok(())
+        }
+    };
+}
+
+/// Private socket implementation functions for stream sockets.
+#[cfg(any(feature = "l2cap", feature = "rfcomm"))]
+macro_rules! stream_sock_priv {
+    () => {
         async fn peek_priv(&self, buf: &mut [u8]) -> Result<usize> {
             let mut buf = ReadBuf::new(buf);
             loop {
@@ -485,16 +564,6 @@ macro_rules! sock_priv {
             Poll::Ready(Ok(()))
         }
 
-        fn shutdown_priv(&self, how: Shutdown) -> Result<()> {
-            let how = match how {
-                Shutdown::Read => SHUT_RD,
-                Shutdown::Write => SHUT_WR,
-                Shutdown::Both => SHUT_RDWR,
-            };
-            sock::shutdown(self.fd.get_ref(), how)?;
-            Ok(())
-        }
-
         fn poll_shutdown_priv(&self, _cx: &mut Context, how: Shutdown) -> Poll<Result<()>> {
             self.shutdown_priv(how)?;
             Poll::Ready(Ok(()))
@@ -0,0 +1,239 @@
+//! Tests using the mock Bluetooth daemon.
+//!
+//! These require the `dbus-daemon` binary.
+
+use bluer::{
+    adv::Advertisement,
+    agent::{Agent, ReqError},
+    mock::{Bluetoothd, Bus, PairingMethod},
+    AdapterEvent, AdapterProperty, Address, DeviceEvent, DeviceProperty, ErrorKind, Session,
+};
+use futures::{pin_mut, FutureExt, StreamExt};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::timeout;
+use uuid::Uuid;
+
+const ADAPTER_ADDRESS: Address = Address([0x00, 0x01, 0x02, 0x03, 0x04, 0x05]);
+const DEVICE_ADDRESS: Address = Address([0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b]);
+const SERVICE_UUID: Uuid = Uuid::from_u128(0x0000180f_0000_1000_8000_00805f9b34fb);
+const CHARACTERISTIC_UUID: Uuid = Uuid::from_u128(0x00002a19_0000_1000_8000_00805f9b34fb);
+
+#[tokio::test]
+async fn adapter_properties() -> bluer::Result<()> {
+    let bus = Bus::start()?;
+    let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+    bluetoothd.add_adapter("hci0", ADAPTER_ADDRESS);
+
+    let session = Session::new_on_bus(bus.address()).await?;
+    assert_eq!(session.adapter_names().await?, vec!["hci0".to_string()]);
+
+    let adapter = session.adapter("hci0")?;
+    assert_eq!(adapter.address().await?, ADAPTER_ADDRESS);
+    assert!(!adapter.is_powered().await?);
+    adapter.set_powered(true).await?;
+    assert!(adapter.is_powered().await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn device_properties() -> bluer::Result<()> {
+    let bus = Bus::start()?;
+    let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+    let mock_adapter = bluetoothd.add_adapter("hci0", ADAPTER_ADDRESS);
+    let mock_device = mock_adapter.add_device(DEVICE_ADDRESS);
+    mock_device.set_property("RSSI", -60i16);
+
+    let session = Session::new_on_bus(bus.address()).await?;
+    let adapter = session.adapter("hci0")?;
+    assert_eq!(adapter.device_addresses().await?, vec![DEVICE_ADDRESS]);
+
+    let device = adapter.device(DEVICE_ADDRESS)?;
+    assert_eq!(device.rssi().await?, Some(-60));
+    mock_device.set_property("RSSI", -70i16);
+    assert_eq!(device.rssi().await?, Some(-70));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gatt_read_write() -> bluer::Result<()> {
+    let bus = Bus::start()?;
+    let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+    let mock_adapter = bluetoothd.add_adapter("hci0", ADAPTER_ADDRESS);
+    let mock_device = mock_adapter.add_device(DEVICE_ADDRESS);
+    let mock_service = mock_device.add_service(SERVICE_UUID, true);
+    let mock_char = mock_service.add_characteristic(CHARACTERISTIC_UUID, &["read", "write"], vec![42]);
+
+    let session = Session::new_on_bus(bus.address()).await?;
+    let device = session.adapter("hci0")?.device(DEVICE_ADDRESS)?;
+    device.connect().await?;
+    assert!(device.is_connected().await?);
+    assert!(device.is_services_resolved().await?);
+
+    let services = device.services().await?;
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].uuid().await?, SERVICE_UUID);
+
+    let chars = services[0].characteristics().await?;
+    assert_eq!(chars.len(), 1);
+    assert_eq!(chars[0].uuid().await?, CHARACTERISTIC_UUID);
+    assert_eq!(chars[0].read().await?, vec![42]);
+
+    chars[0].write(&[1, 2, 3]).await?;
+    assert_eq!(mock_char.value(), vec![1, 2, 3]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn property_events() -> bluer::Result<()> {
+    let bus = Bus::start()?;
+    let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+    let mock_adapter = bluetoothd.add_adapter("hci0", ADAPTER_ADDRESS);
+    let mock_device = mock_adapter.add_device(DEVICE_ADDRESS);
+
+    let session = Session::new_on_bus(bus.address()).await?;
+    let adapter = session.adapter("hci0")?;
+    let device = adapter.device(DEVICE_ADDRESS)?;
+    let adapter_events = adapter.events().await?;
+    let device_events = device.events().await?;
+    pin_mut!(adapter_events, device_events);
+
+    mock_device.set_property("RSSI", -70i16);
+    let event = timeout(Duration::from_secs(5), device_events.next()).await.unwrap();
+    assert!(matches!(event, Some(DeviceEvent::PropertyChanged(DeviceProperty::Rssi(-70)))), "{:?}", event);
+
+    adapter.set_powered(true).await?;
+    let event = timeout(Duration::from_secs(5), adapter_events.next()).await.unwrap();
+    assert!(matches!(event, Some(AdapterEvent::PropertyChanged(AdapterProperty::Powered(true)))), "{:?}", event);
+
+    let other_address = Address([0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11]);
+    let other_device = mock_adapter.add_device(other_address);
+    let event = timeout(Duration::from_secs(5), adapter_events.next()).await.unwrap();
+    assert!(matches!(event, Some(AdapterEvent::DeviceAdded(addr)) if addr == other_address), "{:?}", event);
+
+    other_device.remove();
+    let event = timeout(Duration::from_secs(5), adapter_events.next()).await.unwrap();
+    assert!(matches!(event, Some(AdapterEvent::DeviceRemoved(addr)) if addr == other_address), "{:?}", event);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pairing_confirmation() -> bluer::Result<()> {
+    let bus = Bus::start()?;
+    let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+    let mock_adapter = bluetoothd.add_adapter("hci0", ADAPTER_ADDRESS);
+    let mock_device = mock_adapter.add_device(DEVICE_ADDRESS);
+    mock_device.set_pairing_method(PairingMethod::Confirmation(123456));
+
+    let session = Session::new_on_bus(bus.address()).await?;
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let agent_requests = requests.clone();
+    let agent = Agent {
+        request_confirmation: Some(Box::new(move |req| {
+            agent_requests.lock().unwrap().push((req.adapter, req.device, req.passkey));
+            async { Ok(()) }.boxed()
+        })),
+        ..Default::default()
+    };
+    let _agent_handle = session.register_agent(agent).await?;
+    assert!(bluetoothd.is_agent_registered());
+
+    let device = session.adapter("hci0")?.device(DEVICE_ADDRESS)?;
+    assert!(!device.is_paired().await?);
+    device.pair().await?;
+    assert!(device.is_paired().await?);
+    assert_eq!(*requests.lock().unwrap(), [("hci0".to_string(), DEVICE_ADDRESS, 123456)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pairing_passkey() -> bluer::Result<()> {
+    let bus = Bus::start()?;
+    let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+    let mock_adapter = bluetoothd.add_adapter("hci0", ADAPTER_ADDRESS);
+    let mock_device = mock_adapter.add_device(DEVICE_ADDRESS);
+
+    let session = Session::new_on_bus(bus.address()).await?;
+    let agent = Agent {
+        request_passkey: Some(Box::new(|req| {
+            async move {
+                if req.device == DEVICE_ADDRESS {
+                    Ok(1234)
+                } else {
+                    Err(ReqError::Rejected)
+                }
+            }
+            .boxed()
+        })),
+        ..Default::default()
+    };
+    let agent_handle = session.register_agent(agent).await?;
+    let device = session.adapter("hci0")?.device(DEVICE_ADDRESS)?;
+
+    mock_device.set_pairing_method(PairingMethod::Passkey(4321));
+    let err = device.pair().await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::AuthenticationFailed);
+    assert!(!device.is_paired().await?);
+
+    mock_device.set_pairing_method(PairingMethod::Passkey(1234));
+    device.pair().await?;
+    assert!(device.is_paired().await?);
+
+    drop(agent_handle);
+    timeout(Duration::from_secs(5), async {
+        while bluetoothd.is_agent_registered() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn daemon_restart() -> bluer::Result<()> {
+    let bus = Bus::start()?;
+    let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+    let mock_adapter = bluetoothd.add_adapter("hci0", ADAPTER_ADDRESS);
+
+    let session = Session::new_on_bus(bus.address()).await?;
+    let _agent_handle = session.register_agent(Agent::default()).await?;
+    let adapter = session.adapter("hci0")?;
+    let adv = Advertisement { local_name: Some("mock".to_string()), ..Default::default() };
+    let _adv_handle = adapter.advertise(adv).await?;
+    assert!(bluetoothd.is_agent_registered());
+    assert_eq!(mock_adapter.advertisements().len(), 1);
+
+    // The restarted daemon publishes the adapter after it has acquired its name,
+    // so that renewing the advertisement registration fails at first.
+    drop(mock_adapter);
+    drop(bluetoothd);
+    let bluetoothd = Bluetoothd::new_on_bus(bus.address()).await?;
+    timeout(Duration::from_secs(5), async {
+        while !bluetoothd.is_agent_registered() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_adapter = bluetoothd.add_adapter("hci0", ADAPTER_ADDRESS);
+    timeout(Duration::from_secs(5), async {
+        while mock_adapter.advertisements().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    Ok(())
+}